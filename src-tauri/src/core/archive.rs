@@ -0,0 +1,316 @@
+// core/archive.rs
+// アーカイブ（ZIP/CBZ）を仮想ディレクトリとして扱う - 展開せずにエントリを必要な分だけ読み込む
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, PoisonError};
+use std::time::SystemTime;
+use thiserror::Error;
+use zip::ZipArchive;
+
+use crate::core::image_format::{is_image_file, ImageFormat};
+use crate::core::image_probe::{self, ProbeError};
+use crate::utils::natural_cmp;
+
+/// アーカイブのパスとエントリ名の区切り（`/path/book.cbz!/page001.jpg`）
+pub const ENTRY_SEPARATOR: &str = "!/";
+
+/// 1つのエントリを展開する最大サイズ（壊れた、または悪意のあるアーカイブでの巨大な確保を防ぐ）
+pub const MAX_ENTRY_SIZE: u64 = 256 * 1024 * 1024;
+
+/// 開いたままにしておくアーカイブの最大数
+const MAX_OPEN_ARCHIVES: usize = 8;
+
+/// 開いたアーカイブのキャッシュ（中央ディレクトリの再解析を避けるため、最近使ったものから保持）
+static OPEN_ARCHIVES: LazyLock<Mutex<Vec<OpenArchive>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+type SharedArchive = Arc<Mutex<ZipArchive<BufReader<File>>>>;
+
+/// キャッシュされたアーカイブ
+struct OpenArchive {
+    path: PathBuf,
+    /// 開いた時点のファイルサイズと更新日時（変わっていれば開き直す）
+    stamp: (u64, Option<SystemTime>),
+    zip: SharedArchive,
+}
+
+/// アーカイブ操作のエラー型
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("Failed to read archive: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid archive: {0}")]
+    Zip(#[from] zip::result::ZipError),
+
+    #[error("Entry not found in archive: {0}")]
+    EntryNotFound(String),
+
+    #[error("Archive entry is too large: {0} (limit {1} bytes)")]
+    EntryTooLarge(String, u64),
+}
+
+/// アーカイブ内の画像エントリ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// アーカイブ内のパス（`/` 区切り）
+    pub name: String,
+    /// 展開後のサイズ（バイト）
+    pub size: u64,
+    /// 更新日時（ISO-8601。ZIPの日時はタイムゾーンを持たないためそのままUTCとして扱う）
+    pub modified: Option<String>,
+}
+
+/// エントリのプローブ結果
+#[derive(Debug)]
+pub struct EntryProbe {
+    /// エントリの情報
+    pub entry: ArchiveEntry,
+    /// シグネチャから判定した形式（判定できない場合は None）
+    pub format: Option<ImageFormat>,
+    /// 画像の寸法（幅, 高さ）。ヘッダーが読めない場合はエラー
+    pub dimensions: Result<(u32, u32), ProbeError>,
+}
+
+/// アーカイブファイル拡張子判定
+pub fn is_archive_file(file_name: &str) -> bool {
+    let lower_case = file_name.to_lowercase();
+    lower_case.ends_with(".zip") || lower_case.ends_with(".cbz")
+}
+
+/// パスがアーカイブファイルを指しているかどうか（拡張子のみで判定）
+pub fn is_archive_path(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(is_archive_file)
+}
+
+/// アーカイブ内のエントリを表す仮想パスを生成
+pub fn entry_path(archive: &str, entry: &str) -> String {
+    format!("{}{}{}", archive, ENTRY_SEPARATOR, entry)
+}
+
+/// 仮想パスをアーカイブのパスとエントリ名に分解（アーカイブ内のエントリでなければ None）
+pub fn split_entry_path(path: &str) -> Option<(&str, &str)> {
+    // ディレクトリ名に `!` を含む場合に備え、アーカイブの拡張子を持つ区切りを探す
+    path.match_indices(ENTRY_SEPARATOR)
+        .map(|(index, _)| (&path[..index], &path[index + ENTRY_SEPARATOR.len()..]))
+        .find(|(archive, entry)| !entry.is_empty() && is_archive_file(archive))
+}
+
+/// 実在するファイルのパスを取得（アーカイブ内のエントリであればアーカイブのパス）
+pub fn physical_path(path: &str) -> &Path {
+    match split_entry_path(path) {
+        Some((archive, _)) => Path::new(archive),
+        None => Path::new(path),
+    }
+}
+
+/// アーカイブ内の画像エントリを自然順（page2 < page10）で取得
+pub fn list_image_entries(archive: &Path) -> Result<Vec<ArchiveEntry>, ArchiveError> {
+    with_archive(archive, |zip| {
+        let mut entries = Vec::new();
+        for index in 0..zip.len() {
+            let file = zip.by_index_raw(index)?;
+            if file.is_dir() || !is_image_file(file.name()) {
+                continue;
+            }
+            entries.push(entry_info(&file));
+        }
+        entries.sort_by(|a, b| natural_cmp(&a.name, &b.name));
+        Ok(entries)
+    })
+}
+
+/// エントリの情報とヘッダーから読み取った寸法を取得（ピクセルは展開しない）
+pub fn probe_entry(archive: &Path, entry: &str) -> Result<EntryProbe, ArchiveError> {
+    with_archive(archive, |zip| {
+        let mut file = zip.by_name(entry).map_err(|e| entry_error(entry, e))?;
+        let (format, dimensions) = image_probe::read_header(&mut file, entry);
+        let entry = entry_info(&file);
+        Ok(EntryProbe { entry, format, dimensions })
+    })
+}
+
+/// エントリの内容をすべて読み込む（`MAX_ENTRY_SIZE` を超えるエントリはエラー）
+pub fn read_entry(archive: &Path, entry: &str) -> Result<Vec<u8>, ArchiveError> {
+    read_entry_limited(archive, entry, MAX_ENTRY_SIZE)
+}
+
+/// 上限を指定してエントリの内容をすべて読み込む
+fn read_entry_limited(archive: &Path, entry: &str, limit: u64) -> Result<Vec<u8>, ArchiveError> {
+    with_archive(archive, |zip| {
+        let file = zip.by_name(entry).map_err(|e| entry_error(entry, e))?;
+        // 中央ディレクトリのサイズは偽れるため、実際に展開した量でも確認する
+        if file.size() > limit {
+            return Err(ArchiveError::EntryTooLarge(entry.to_string(), limit));
+        }
+        let mut bytes = Vec::with_capacity(file.size() as usize);
+        file.take(limit + 1).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 > limit {
+            return Err(ArchiveError::EntryTooLarge(entry.to_string(), limit));
+        }
+        Ok(bytes)
+    })
+}
+
+/// エントリの先頭部分を読み込む（ヘッダーのメタデータを読むため、全体は展開しない）
+pub fn read_entry_prefix(archive: &Path, entry: &str, limit: u64) -> Result<Vec<u8>, ArchiveError> {
+    with_archive(archive, |zip| {
+        let file = zip.by_name(entry).map_err(|e| entry_error(entry, e))?;
+        let mut bytes = Vec::with_capacity(file.size().min(limit) as usize);
+        file.take(limit).read_to_end(&mut bytes)?;
+        Ok(bytes)
+    })
+}
+
+/// 仮想パスが指すエントリの内容を読み込む
+pub fn read_entry_path(path: &str) -> Result<Vec<u8>, ArchiveError> {
+    let (archive, entry) = split_entry_path(path)
+        .ok_or_else(|| ArchiveError::EntryNotFound(path.to_string()))?;
+    read_entry(Path::new(archive), entry)
+}
+
+/// キャッシュから（なければ開いて）アーカイブを取得して処理する
+///
+/// 同じアーカイブへの処理は直列化される。ファイルが置き換えられていれば開き直す。
+fn with_archive<T>(
+    archive: &Path,
+    f: impl FnOnce(&mut ZipArchive<BufReader<File>>) -> Result<T, ArchiveError>,
+) -> Result<T, ArchiveError> {
+    let zip = cached_archive(archive)?;
+    let mut zip = zip.lock().unwrap_or_else(PoisonError::into_inner);
+    f(&mut zip)
+}
+
+/// キャッシュされたアーカイブを取得（なければ開いてキャッシュに追加）
+fn cached_archive(archive: &Path) -> Result<SharedArchive, ArchiveError> {
+    let metadata = std::fs::metadata(archive)?;
+    let stamp = (metadata.len(), metadata.modified().ok());
+
+    {
+        let mut cache = OPEN_ARCHIVES.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(index) = cache.iter().position(|open| open.path == archive) {
+            let open = cache.remove(index);
+            if open.stamp == stamp {
+                let zip = Arc::clone(&open.zip);
+                cache.insert(0, open);
+                return Ok(zip);
+            }
+        }
+    }
+
+    // 中央ディレクトリの解析はキャッシュのロックを外して行う
+    let zip: SharedArchive = Arc::new(Mutex::new(open_archive(archive)?));
+    let mut cache = OPEN_ARCHIVES.lock().unwrap_or_else(PoisonError::into_inner);
+    cache.retain(|open| open.path != archive);
+    cache.insert(0, OpenArchive { path: archive.to_path_buf(), stamp, zip: Arc::clone(&zip) });
+    cache.truncate(MAX_OPEN_ARCHIVES);
+    Ok(zip)
+}
+
+/// アーカイブを開く（中央ディレクトリのみを読み込む）
+fn open_archive(archive: &Path) -> Result<ZipArchive<BufReader<File>>, ArchiveError> {
+    let file = File::open(archive)?;
+    Ok(ZipArchive::new(BufReader::new(file))?)
+}
+
+/// エントリ取得時のエラーを変換
+fn entry_error(entry: &str, error: zip::result::ZipError) -> ArchiveError {
+    match error {
+        zip::result::ZipError::FileNotFound => ArchiveError::EntryNotFound(entry.to_string()),
+        error => ArchiveError::Zip(error),
+    }
+}
+
+/// ZIPのエントリ情報を変換
+fn entry_info(file: &zip::read::ZipFile<'_>) -> ArchiveEntry {
+    ArchiveEntry {
+        name: file.name().to_string(),
+        size: file.size(),
+        modified: file.last_modified().map(|time| format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            time.year(),
+            time.month(),
+            time.day(),
+            time.hour(),
+            time.minute(),
+            time.second()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    /// テスト用のアーカイブを作成
+    fn create_archive(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, bytes) in entries {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(bytes).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_split_entry_path() {
+        assert_eq!(split_entry_path("/a/book.cbz!/page1.jpg"), Some(("/a/book.cbz", "page1.jpg")));
+        assert_eq!(split_entry_path("/a/wow!/book.zip!/ch1/p.png"), Some(("/a/wow!/book.zip", "ch1/p.png")));
+        assert_eq!(split_entry_path("/a/wow!/photo.jpg"), None);
+        assert_eq!(split_entry_path("/a/book.cbz"), None);
+        assert_eq!(physical_path("/a/book.cbz!/page1.jpg"), Path::new("/a/book.cbz"));
+        assert_eq!(entry_path("/a/book.cbz", "page1.jpg"), "/a/book.cbz!/page1.jpg");
+    }
+
+    #[test]
+    fn test_list_and_read_entries() {
+        let dir = std::env::temp_dir().join(format!("image-viewer-archive-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let archive = dir.join("book.cbz");
+        let gif = b"GIF89a\x02\x00\x03\x00\x00\x00\x00";
+        create_archive(&archive, &[
+            ("page10.gif", gif),
+            ("page2.gif", gif),
+            ("notes.txt", b"not an image"),
+            ("page1.gif", gif),
+        ]);
+
+        let names: Vec<String> = list_image_entries(&archive).unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, vec!["page1.gif", "page2.gif", "page10.gif"]);
+
+        let probe = probe_entry(&archive, "page2.gif").unwrap();
+        assert_eq!(probe.entry.size, gif.len() as u64);
+        assert_eq!(probe.format, Some(ImageFormat::Gif));
+        assert_eq!(probe.dimensions.unwrap(), (2, 3));
+
+        let path = entry_path(archive.to_str().unwrap(), "page10.gif");
+        assert_eq!(read_entry_path(&path).unwrap(), gif.to_vec());
+        assert!(matches!(read_entry(&archive, "missing.gif"), Err(ArchiveError::EntryNotFound(_))));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_entry_size_limit_and_reopen_after_change() {
+        let dir = std::env::temp_dir().join(format!("image-viewer-archive-limit-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let archive = dir.join("book.zip");
+        create_archive(&archive, &[("page1.gif", &[0u8; 64])]);
+
+        // 上限を超えるエントリは展開せずにエラーにする
+        assert!(matches!(read_entry_limited(&archive, "page1.gif", 63), Err(ArchiveError::EntryTooLarge(_, 63))));
+        assert_eq!(read_entry_limited(&archive, "page1.gif", 64).unwrap().len(), 64);
+
+        // キャッシュ済みのアーカイブが置き換えられたら開き直す
+        create_archive(&archive, &[("page1.gif", &[1u8; 8]), ("page2.gif", &[2u8; 8])]);
+        assert_eq!(read_entry(&archive, "page2.gif").unwrap(), vec![2u8; 8]);
+        assert_eq!(list_image_entries(&archive).unwrap().len(), 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
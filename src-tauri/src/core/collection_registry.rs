@@ -0,0 +1,550 @@
+// core/collection_registry.rs
+// 画像コレクションのレジストリ - 作成したコレクションをIDで保持する
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+
+use crate::core::geo::{LocationCluster, PlaceIndex};
+use crate::core::image_collection::{ImageCollection, ImageData, ImageFilter, ImageMetadata, ImageSort, ImageWarning};
+
+/// ページ取得時の上限件数
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// 登録されたコレクションの概要
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectionSummary {
+    /// コレクションID
+    pub id: String,
+    /// 派生元のコレクションID
+    pub parent_id: Option<String>,
+    /// 総画像数
+    pub total_images: usize,
+    /// 総サイズ（バイト）
+    pub total_size_bytes: u64,
+    /// 読み込み時の警告数
+    pub warning_count: usize,
+}
+
+/// メタデータのページ
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectionPage {
+    /// コレクションID
+    pub collection_id: String,
+    /// 開始位置
+    pub offset: usize,
+    /// コレクション全体の画像数
+    pub total: usize,
+    /// このページのメタデータ
+    pub items: Vec<ImageMetadata>,
+}
+
+/// レジストリのエントリ
+#[derive(Debug)]
+struct CollectionEntry {
+    /// コレクション本体
+    collection: Arc<ImageCollection>,
+    /// 派生元のコレクションID
+    parent_id: Option<String>,
+    /// 監視による更新元のリソース設定ID
+    source_config_id: Option<String>,
+    /// 派生元から順に適用したフィルタ（更新時に再適用する）
+    filters: Vec<ImageFilter>,
+    /// 派生時に適用したソート（更新時に再適用する）
+    sort: Option<ImageSort>,
+    /// RAW+JPEGの組を1つのエントリにまとめるかどうか（更新時に再適用する）
+    group_raw_pairs: bool,
+}
+
+/// 登録時に引き継ぐエントリの由来
+#[derive(Debug, Default)]
+struct EntrySource {
+    parent_id: Option<String>,
+    source_config_id: Option<String>,
+    filters: Vec<ImageFilter>,
+    sort: Option<ImageSort>,
+    group_raw_pairs: bool,
+}
+
+/// コレクションレジストリ
+#[derive(Debug)]
+pub struct CollectionRegistry {
+    /// コレクションID -> エントリ
+    collections: Mutex<HashMap<String, CollectionEntry>>,
+    /// ID採番用カウンター
+    next_id: AtomicU64,
+}
+
+impl Default for CollectionRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CollectionRegistry {
+    /// 新しいCollectionRegistryインスタンスを作成
+    pub fn new() -> Self {
+        Self {
+            collections: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// コレクションを登録してIDを返す
+    pub fn register(&self, collection: ImageCollection) -> Result<CollectionSummary, String> {
+        self.insert(collection, EntrySource::default())
+    }
+
+    /// リソース設定から作成したコレクションを登録（監視による更新の対象になる）
+    pub fn register_with_source(&self, collection: ImageCollection, config_id: &str) -> Result<CollectionSummary, String> {
+        let source = EntrySource {
+            source_config_id: Some(config_id.to_string()),
+            ..EntrySource::default()
+        };
+        self.insert(collection, source)
+    }
+
+    /// 登録済みコレクションの概要一覧を取得（ID順）
+    pub fn list(&self) -> Result<Vec<CollectionSummary>, String> {
+        let collections = self.collections.lock().map_err(|e| {
+            format!("Failed to lock collections: {}", e)
+        })?;
+
+        let mut summaries: Vec<CollectionSummary> = collections.iter()
+            .map(|(id, entry)| summarize(id, entry))
+            .collect();
+        summaries.sort_by_key(|summary| id_number(&summary.id));
+        Ok(summaries)
+    }
+
+    /// コレクションを取得
+    pub fn get(&self, collection_id: &str) -> Result<Arc<ImageCollection>, String> {
+        let collections = self.collections.lock().map_err(|e| {
+            format!("Failed to lock collections: {}", e)
+        })?;
+
+        collections.get(collection_id)
+            .map(|entry| Arc::clone(&entry.collection))
+            .ok_or_else(|| format!("Collection not found: {}", collection_id))
+    }
+
+    /// メタデータのページを取得
+    pub fn get_page(&self, collection_id: &str, offset: usize, limit: usize) -> Result<CollectionPage, String> {
+        let collection = self.get(collection_id)?;
+
+        Ok(CollectionPage {
+            collection_id: collection_id.to_string(),
+            offset,
+            total: collection.len(),
+            items: collection.get_metadata_page(offset, limit.min(MAX_PAGE_SIZE)),
+        })
+    }
+
+    /// インデックスで画像を読み込む
+    pub fn load_image_at(&self, collection_id: &str, index: usize) -> Result<ImageData, String> {
+        self.get(collection_id)?.load_image_at(index)
+    }
+
+    /// ランダムな画像を取得
+    pub fn get_random_images(&self, collection_id: &str, count: usize) -> Result<Vec<ImageData>, String> {
+        self.get(collection_id)?.get_random_images(count)
+    }
+
+    /// 撮影位置ごとのグループを取得（各グループの重心に最も近い地名を添える）
+    pub fn location_clusters(&self, collection_id: &str, cell_km: f64, places: &PlaceIndex) -> Result<Vec<LocationCluster>, String> {
+        if !(cell_km.is_finite() && cell_km > 0.0) {
+            return Err(format!("Invalid cluster cell size: {}", cell_km));
+        }
+        let mut clusters = self.get(collection_id)?.location_clusters(cell_km);
+        for cluster in &mut clusters {
+            cluster.place = places.nearest(cluster.latitude, cluster.longitude);
+        }
+        Ok(clusters)
+    }
+
+    /// フィルタ・ソートを適用した子コレクションを作成して登録
+    ///
+    /// `group_raw_pairs` を指定すると、フィルタ後のRAW+JPEGの組を1つのエントリにまとめる
+    /// （フィルタはまとめる前の個々のファイルに適用される）。親でまとめていれば子も引き継ぐ。
+    pub fn derive(
+        &self,
+        collection_id: &str,
+        filter: Option<&ImageFilter>,
+        sort: Option<&ImageSort>,
+        group_raw_pairs: bool,
+    ) -> Result<CollectionSummary, String> {
+        let (parent, mut source) = {
+            let collections = self.collections.lock().map_err(|e| {
+                format!("Failed to lock collections: {}", e)
+            })?;
+            let entry = collections.get(collection_id)
+                .ok_or_else(|| format!("Collection not found: {}", collection_id))?;
+            let source = EntrySource {
+                parent_id: Some(collection_id.to_string()),
+                source_config_id: entry.source_config_id.clone(),
+                filters: entry.filters.clone(),
+                sort: entry.sort,
+                group_raw_pairs: entry.group_raw_pairs || group_raw_pairs,
+            };
+            (Arc::clone(&entry.collection), source)
+        };
+
+        // フィルタはまとめる前の個々のファイルに適用する
+        let mut derived = parent.ungroup_pairs().filter(|meta| filter.is_none_or(|filter| filter.matches(meta)));
+        if source.group_raw_pairs {
+            derived = derived.group_raw_pairs();
+        }
+        if let Some(sort) = sort {
+            derived = derived.sort(|a, b| sort.compare(a, b));
+        }
+
+        // 更新時に再適用できるよう、親のフィルタ・ソートを引き継ぐ
+        source.filters.extend(filter.cloned());
+        if let Some(sort) = sort {
+            source.sort = Some(*sort);
+        }
+        self.insert(derived, source)
+    }
+
+    /// リソース設定の変更を、その設定から作成されたコレクションと派生コレクションに反映
+    ///
+    /// 更新されたコレクションのIDを返す。
+    pub fn apply_changes(
+        &self,
+        config_id: &str,
+        upserts: &[ImageMetadata],
+        warnings: &[ImageWarning],
+        removed: &[String],
+    ) -> Result<Vec<String>, String> {
+        let mut collections = self.collections.lock().map_err(|e| {
+            format!("Failed to lock collections: {}", e)
+        })?;
+
+        let mut updated = Vec::new();
+        for (id, entry) in collections.iter_mut() {
+            if entry.source_config_id.as_deref() != Some(config_id) {
+                continue;
+            }
+
+            // 派生コレクションでは条件に合わなくなった画像を取り除く
+            let (matching, unmatched): (Vec<ImageMetadata>, Vec<ImageMetadata>) = upserts.iter()
+                .cloned()
+                .partition(|metadata| entry.filters.iter().all(|filter| filter.matches(metadata)));
+            let mut removed_paths = removed.to_vec();
+            removed_paths.extend(unmatched.into_iter().map(|metadata| metadata.path));
+
+            let mut collection = if entry.group_raw_pairs {
+                entry.collection.ungroup_pairs()
+                    .apply_changes(&matching, warnings, &removed_paths)
+                    .group_raw_pairs()
+            } else {
+                entry.collection.apply_changes(&matching, warnings, &removed_paths)
+            };
+            if let Some(sort) = &entry.sort {
+                collection = collection.sort(|a, b| sort.compare(a, b));
+            }
+            entry.collection = Arc::new(collection);
+            updated.push(id.clone());
+        }
+
+        updated.sort_by_key(|id| id_number(id));
+        Ok(updated)
+    }
+
+    /// 削除・移動された画像をすべてのコレクションから取り除く
+    ///
+    /// 更新されたコレクションのIDを返す。
+    pub fn remove_paths(&self, removed: &[String]) -> Result<Vec<String>, String> {
+        let mut collections = self.collections.lock().map_err(|e| {
+            format!("Failed to lock collections: {}", e)
+        })?;
+
+        let mut updated = Vec::new();
+        for (id, entry) in collections.iter_mut() {
+            let contains = entry.collection.get_all_metadata().iter()
+                .flat_map(|metadata| std::iter::once(metadata).chain(&metadata.companions))
+                .any(|metadata| removed.contains(&metadata.path));
+            if !contains {
+                continue;
+            }
+
+            let collection = if entry.group_raw_pairs {
+                entry.collection.ungroup_pairs()
+                    .apply_changes(&[], &[], removed)
+                    .group_raw_pairs()
+            } else {
+                entry.collection.apply_changes(&[], &[], removed)
+            };
+            entry.collection = Arc::new(collection);
+            updated.push(id.clone());
+        }
+
+        updated.sort_by_key(|id| id_number(id));
+        Ok(updated)
+    }
+
+    /// 画像のメタデータの更新（色の分析結果など）を、その画像を含むすべてのコレクションに反映
+    ///
+    /// 画像を追加することはなく、派生コレクションでは条件に合わなくなった画像を取り除く。
+    /// 更新されたコレクションのIDを返す。
+    pub fn update_metadata(&self, updates: &[ImageMetadata]) -> Result<Vec<String>, String> {
+        let mut collections = self.collections.lock().map_err(|e| {
+            format!("Failed to lock collections: {}", e)
+        })?;
+
+        let mut updated = Vec::new();
+        for (id, entry) in collections.iter_mut() {
+            let ungrouped = entry.collection.ungroup_pairs();
+            let contained: HashSet<String> = ungrouped.get_all_metadata().into_iter()
+                .map(|metadata| metadata.path)
+                .collect();
+            let (matching, unmatched): (Vec<ImageMetadata>, Vec<ImageMetadata>) = updates.iter()
+                .filter(|metadata| contained.contains(&metadata.path))
+                .map(|metadata| ImageMetadata { companions: Vec::new(), ..metadata.clone() })
+                .partition(|metadata| entry.filters.iter().all(|filter| filter.matches(metadata)));
+            if matching.is_empty() && unmatched.is_empty() {
+                continue;
+            }
+
+            let removed: Vec<String> = unmatched.into_iter().map(|metadata| metadata.path).collect();
+            let mut collection = ungrouped.apply_changes(&matching, &[], &removed);
+            if entry.group_raw_pairs {
+                collection = collection.group_raw_pairs();
+            }
+            if let Some(sort) = &entry.sort {
+                collection = collection.sort(|a, b| sort.compare(a, b));
+            }
+            entry.collection = Arc::new(collection);
+            updated.push(id.clone());
+        }
+
+        updated.sort_by_key(|id| id_number(id));
+        Ok(updated)
+    }
+
+    /// コレクションを破棄（派生したコレクションは残る）
+    pub fn drop_collection(&self, collection_id: &str) -> Result<(), String> {
+        let mut collections = self.collections.lock().map_err(|e| {
+            format!("Failed to lock collections: {}", e)
+        })?;
+
+        collections.remove(collection_id)
+            .map(|_| ())
+            .ok_or_else(|| format!("Collection not found: {}", collection_id))
+    }
+
+    /// IDを採番してエントリを追加
+    fn insert(&self, collection: ImageCollection, source: EntrySource) -> Result<CollectionSummary, String> {
+        let id = format!("collection-{}", self.next_id.fetch_add(1, Ordering::SeqCst));
+        let entry = CollectionEntry {
+            collection: Arc::new(collection),
+            parent_id: source.parent_id,
+            source_config_id: source.source_config_id,
+            filters: source.filters,
+            sort: source.sort,
+            group_raw_pairs: source.group_raw_pairs,
+        };
+        let summary = summarize(&id, &entry);
+
+        let mut collections = self.collections.lock().map_err(|e| {
+            format!("Failed to lock collections: {}", e)
+        })?;
+        collections.insert(id, entry);
+
+        Ok(summary)
+    }
+}
+
+/// エントリから概要を生成
+fn summarize(id: &str, entry: &CollectionEntry) -> CollectionSummary {
+    let digest = entry.collection.get_digest();
+    CollectionSummary {
+        id: id.to_string(),
+        parent_id: entry.parent_id.clone(),
+        total_images: digest.total_images,
+        total_size_bytes: digest.total_size_bytes,
+        warning_count: entry.collection.get_warnings().len(),
+    }
+}
+
+/// IDの連番部分を取得（一覧の並び順用）
+fn id_number(id: &str) -> u64 {
+    id.rsplit('-')
+        .next()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::image_collection::ImageSortKey;
+    use crate::core::orientation::Rotation;
+
+    fn create_collection() -> ImageCollection {
+        let metadata = [("b.jpg", 2048), ("a.png", 1024), ("c.jpg", 512)]
+            .iter()
+            .map(|(name, size)| ImageMetadata {
+                path: format!("/path/to/{}", name),
+                file_name: name.to_string(),
+                file_size: *size,
+                format: None,
+                dimensions: None,
+                orientation: None,
+                rotation: Rotation::None,
+                display_dimensions: None,
+                gps: None,
+                palette: None,
+                date_created: None,
+                date_modified: None,
+                companions: Vec::new(),
+            })
+            .collect();
+        ImageCollection::new(metadata)
+    }
+
+    #[test]
+    fn test_register_page_and_drop() {
+        let registry = CollectionRegistry::new();
+        let summary = registry.register(create_collection()).unwrap();
+        assert_eq!(summary.total_images, 3);
+        assert_eq!(summary.total_size_bytes, 3584);
+
+        let page = registry.get_page(&summary.id, 1, 10).unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.items[0].file_name, "a.png");
+
+        assert!(registry.drop_collection(&summary.id).is_ok());
+        assert!(registry.get_page(&summary.id, 0, 10).is_err());
+        assert!(registry.drop_collection(&summary.id).is_err());
+    }
+
+    #[test]
+    fn test_summary_and_page_use_snake_case_keys() {
+        // フロントエンドの CollectionSummary / CollectionPage と同じキーで送る
+        let registry = CollectionRegistry::new();
+        let summary = registry.register(create_collection()).unwrap();
+        let json = serde_json::to_value(&summary).unwrap();
+        let mut keys: Vec<&str> = json.as_object().unwrap().keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["id", "parent_id", "total_images", "total_size_bytes", "warning_count"]);
+
+        let page = serde_json::to_value(registry.get_page(&summary.id, 0, 1).unwrap()).unwrap();
+        let mut keys: Vec<&str> = page.as_object().unwrap().keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["collection_id", "items", "offset", "total"]);
+    }
+
+    #[test]
+    fn test_derive_child_collection() {
+        let registry = CollectionRegistry::new();
+        let parent = registry.register(create_collection()).unwrap();
+
+        let filter = ImageFilter {
+            extensions: Some(vec!["jpg".to_string()]),
+            ..ImageFilter::default()
+        };
+        let sort = ImageSort { key: ImageSortKey::FileSize, descending: false, color: None };
+        let child = registry.derive(&parent.id, Some(&filter), Some(&sort), false).unwrap();
+        assert_eq!(child.parent_id.as_deref(), Some(parent.id.as_str()));
+        assert_eq!(child.total_images, 2);
+
+        let page = registry.get_page(&child.id, 0, 10).unwrap();
+        let names: Vec<&str> = page.items.iter().map(|m| m.file_name.as_str()).collect();
+        assert_eq!(names, vec!["c.jpg", "b.jpg"]);
+
+        // 親を破棄しても子は残る
+        registry.drop_collection(&parent.id).unwrap();
+        let ids: Vec<String> = registry.list().unwrap().into_iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![child.id]);
+    }
+
+    #[test]
+    fn test_apply_changes_to_watched_collections() {
+        let registry = CollectionRegistry::new();
+        let unrelated = registry.register(create_collection()).unwrap();
+        let parent = registry.register_with_source(create_collection(), "config-1").unwrap();
+
+        let filter = ImageFilter {
+            extensions: Some(vec!["jpg".to_string()]),
+            ..ImageFilter::default()
+        };
+        let sort = ImageSort { key: ImageSortKey::FileSize, descending: false, color: None };
+        let child = registry.derive(&parent.id, Some(&filter), Some(&sort), false).unwrap();
+
+        let new_image = |name: &str, size: u64| ImageMetadata {
+            path: format!("/path/to/{}", name),
+            file_name: name.to_string(),
+            file_size: size,
+            format: None,
+            dimensions: None,
+            orientation: None,
+            rotation: Rotation::None,
+            display_dimensions: None,
+            gps: None,
+            palette: None,
+            date_created: None,
+            date_modified: None,
+            companions: Vec::new(),
+        };
+        // d.jpg を追加、a.png を削除、c.jpg を更新
+        let upserts = vec![new_image("d.jpg", 4096), new_image("c.jpg", 8192)];
+        let removed = vec!["/path/to/a.png".to_string()];
+        let updated = registry.apply_changes("config-1", &upserts, &[], &removed).unwrap();
+        assert_eq!(updated, vec![parent.id.clone(), child.id.clone()]);
+
+        let names = |id: &str| -> Vec<String> {
+            registry.get_page(id, 0, 10).unwrap().items.into_iter().map(|m| m.file_name).collect()
+        };
+        assert_eq!(names(&parent.id), vec!["b.jpg", "c.jpg", "d.jpg"]);
+        // 派生コレクションはフィルタとソートが再適用される
+        assert_eq!(names(&child.id), vec!["b.jpg", "d.jpg", "c.jpg"]);
+        assert_eq!(names(&unrelated.id), vec!["b.jpg", "a.png", "c.jpg"]);
+    }
+
+    #[test]
+    fn test_update_metadata_reapplies_color_filter_and_sort() {
+        use crate::core::color_palette::{Color, ColorFilter, ColorPalette, PaletteColor};
+
+        let registry = CollectionRegistry::new();
+        let parent = registry.register(create_collection()).unwrap();
+        let filter = ImageFilter {
+            color: Some(ColorFilter { color: Color([0, 0, 255]), min_coverage: None, tolerance: None }),
+            ..ImageFilter::default()
+        };
+        let sort = ImageSort { key: ImageSortKey::Hue, descending: false, color: None };
+        let blue = registry.derive(&parent.id, Some(&filter), Some(&sort), false).unwrap();
+        // 代表色を求める前はどの画像も条件に合わない
+        assert_eq!(blue.total_images, 0);
+
+        let with_palette = |name: &str, color: [u8; 3]| {
+            let mut metadata = registry.get(&parent.id).unwrap().get_all_metadata()
+                .into_iter()
+                .find(|metadata| metadata.file_name == name)
+                .unwrap();
+            metadata.palette = Some(ColorPalette {
+                colors: vec![PaletteColor { color: Color(color), weight: 1.0 }],
+                brightness: Color(color).luma(),
+            });
+            metadata
+        };
+        let hue = ImageSort { key: ImageSortKey::Hue, descending: false, color: None };
+        let sorted = registry.derive(&parent.id, None, Some(&hue), false).unwrap();
+
+        let updates = vec![with_palette("a.png", [200, 20, 20]), with_palette("b.jpg", [20, 20, 200])];
+        let updated = registry.update_metadata(&updates).unwrap();
+        assert_eq!(updated, vec![parent.id.clone(), sorted.id.clone()]);
+
+        let names = |id: &str| -> Vec<String> {
+            registry.get_page(id, 0, 10).unwrap().items.into_iter().map(|m| m.file_name).collect()
+        };
+        // 色相順（赤→青）、代表色のない画像は末尾
+        assert_eq!(names(&sorted.id), vec!["a.png", "b.jpg", "c.jpg"]);
+        // 派生時に除外された画像は追加されないため、分析後に派生し直す
+        assert!(names(&blue.id).is_empty());
+        let blue = registry.derive(&parent.id, Some(&filter), Some(&sort), false).unwrap();
+        assert_eq!(names(&blue.id), vec!["b.jpg"]);
+    }
+}
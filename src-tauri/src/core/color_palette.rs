@@ -0,0 +1,267 @@
+// core/color_palette.rs
+// 色の分析 - サムネイルから代表色のパレット（メディアンカット）と平均輝度を求め、色による絞り込み・並べ替えに使う
+
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::core::image_collection::ImageWarning;
+
+/// パレットの色数の既定値
+pub const DEFAULT_PALETTE_SIZE: usize = 5;
+
+/// 同じ色とみなす距離の既定値（`Color::distance` の尺度で、0〜約765）
+pub const DEFAULT_COLOR_TOLERANCE: f32 = 120.0;
+
+/// 「ほとんどがその色」とみなす割合の既定値
+pub const DEFAULT_MIN_COVERAGE: f32 = 0.4;
+
+/// 色相を持つとみなす彩度の下限（これより低い色は無彩色として色相順の並べ替えで末尾に置く）
+const MIN_SATURATION: f32 = 0.2;
+
+/// 半透明以下の画素は背景とみなして数えない
+const MIN_ALPHA: u8 = 128;
+
+/// RGBの色（JSONでは "#rrggbb" として扱う）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Color(pub [u8; 3]);
+
+impl Color {
+    /// "#rrggbb" または "rrggbb" を解析
+    pub fn parse(value: &str) -> Option<Self> {
+        let hex = value.trim().trim_start_matches('#');
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+        Some(Color([channel(0)?, channel(2)?, channel(4)?]))
+    }
+
+    /// "#rrggbb" 形式の文字列
+    pub fn to_hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0[0], self.0[1], self.0[2])
+    }
+
+    /// 見た目の差に近い色の距離（赤の平均で重みを変えたRGBのユークリッド距離）
+    pub fn distance(self, other: Color) -> f32 {
+        let [r1, g1, b1] = self.0.map(f32::from);
+        let [r2, g2, b2] = other.0.map(f32::from);
+        let red_mean = (r1 + r2) / 2.0;
+        let (dr, dg, db) = (r1 - r2, g1 - g2, b1 - b2);
+        ((2.0 + red_mean / 256.0) * dr * dr + 4.0 * dg * dg + (2.0 + (255.0 - red_mean) / 256.0) * db * db).sqrt()
+    }
+
+    /// 色相（0〜360度）、彩度（0〜1）、明度（0〜1）
+    pub fn hsv(self) -> (f32, f32, f32) {
+        let [r, g, b] = self.0.map(|channel| channel as f32 / 255.0);
+        let max = r.max(g).max(b);
+        let delta = max - r.min(g).min(b);
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        let saturation = if max == 0.0 { 0.0 } else { delta / max };
+        (hue, saturation, max)
+    }
+
+    /// 輝度（0〜1、ITU-R BT.601）
+    pub fn luma(self) -> f32 {
+        let [r, g, b] = self.0.map(f32::from);
+        (0.299 * r + 0.587 * g + 0.114 * b) / 255.0
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Color::parse(&value).ok_or_else(|| format!("Invalid color (expected #rrggbb): {}", value))
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> Self {
+        color.to_hex()
+    }
+}
+
+/// パレットの1色
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PaletteColor {
+    pub color: Color,
+    /// 画像に占める割合（0〜1）
+    pub weight: f32,
+}
+
+/// 画像の代表色
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ColorPalette {
+    /// 代表色（割合の大きい順）
+    pub colors: Vec<PaletteColor>,
+    /// 平均輝度（0〜1）
+    pub brightness: f32,
+}
+
+impl ColorPalette {
+    /// 画像（サムネイルで十分）から代表色をメディアンカットで求める
+    pub fn extract(image: &DynamicImage, size: usize) -> Self {
+        let pixels: Vec<[u8; 3]> = image.to_rgba8()
+            .pixels()
+            .filter(|pixel| pixel[3] >= MIN_ALPHA)
+            .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+            .collect();
+        if pixels.is_empty() {
+            return Self::default();
+        }
+        let total = pixels.len() as f32;
+        let brightness = pixels.iter().map(|pixel| Color(*pixel).luma()).sum::<f32>() / total;
+
+        // 値の幅が最も大きいチャンネルで、幅が最も大きい箱を中央で分割していく
+        let mut boxes = vec![pixels];
+        while boxes.len() < size.max(1) {
+            let Some((index, channel)) = boxes.iter()
+                .enumerate()
+                .filter(|(_, pixels)| pixels.len() > 1)
+                .map(|(index, pixels)| {
+                    let (channel, range) = (0..3)
+                        .map(|channel| (channel, channel_range(pixels, channel)))
+                        .max_by_key(|(_, range)| *range)
+                        .unwrap_or((0, 0));
+                    (index, channel, range)
+                })
+                .filter(|(_, _, range)| *range > 0)
+                .max_by_key(|(_, _, range)| *range)
+                .map(|(index, channel, _)| (index, channel))
+            else {
+                break;
+            };
+            let mut pixels = boxes.swap_remove(index);
+            pixels.sort_unstable_by_key(|pixel| pixel[channel]);
+            let upper = pixels.split_off(pixels.len() / 2);
+            boxes.push(pixels);
+            boxes.push(upper);
+        }
+
+        // 中央での分割で同じ色が複数の箱に分かれることがあるため、平均色が同じ箱はまとめる
+        let mut colors: Vec<PaletteColor> = Vec::with_capacity(boxes.len());
+        for pixels in &boxes {
+            let sum = pixels.iter().fold([0u64; 3], |sum, pixel| {
+                [sum[0] + pixel[0] as u64, sum[1] + pixel[1] as u64, sum[2] + pixel[2] as u64]
+            });
+            let count = pixels.len() as u64;
+            let color = Color(sum.map(|channel| ((channel + count / 2) / count) as u8));
+            let weight = pixels.len() as f32 / total;
+            match colors.iter_mut().find(|existing| existing.color == color) {
+                Some(existing) => existing.weight += weight,
+                None => colors.push(PaletteColor { color, weight }),
+            }
+        }
+        colors.sort_by(|a, b| b.weight.total_cmp(&a.weight).then_with(|| a.color.0.cmp(&b.color.0)));
+        Self { colors, brightness }
+    }
+
+    /// 指定した色に近い代表色が画像に占める割合（0〜1）
+    pub fn coverage(&self, target: Color, tolerance: f32) -> f32 {
+        self.colors.iter()
+            .filter(|color| color.color.distance(target) <= tolerance)
+            .map(|color| color.weight)
+            .sum()
+    }
+
+    /// 指定した色からの距離（代表色の距離を割合で重み付けした平均）
+    pub fn distance_to(&self, target: Color) -> Option<f32> {
+        if self.colors.is_empty() {
+            return None;
+        }
+        let weight: f32 = self.colors.iter().map(|color| color.weight).sum();
+        Some(self.colors.iter().map(|color| color.color.distance(target) * color.weight).sum::<f32>() / weight)
+    }
+
+    /// 画像の色相（有彩色の代表色のうち最も割合の大きい色、無彩色の画像は None）
+    pub fn hue(&self) -> Option<f32> {
+        self.colors.iter()
+            .map(|color| color.color.hsv())
+            .find(|(_, saturation, _)| *saturation >= MIN_SATURATION)
+            .map(|(hue, _, _)| hue)
+    }
+}
+
+/// チャンネルの値の幅
+fn channel_range(pixels: &[[u8; 3]], channel: usize) -> u8 {
+    let (min, max) = pixels.iter().fold((u8::MAX, u8::MIN), |(min, max), pixel| {
+        (min.min(pixel[channel]), max.max(pixel[channel]))
+    });
+    max.saturating_sub(min)
+}
+
+/// 色による絞り込みの条件（「ほとんどが青い画像」など）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColorFilter {
+    pub color: Color,
+    /// 近い色が占める割合の下限（省略時は0.4）
+    #[serde(default)]
+    pub min_coverage: Option<f32>,
+    /// 近い色とみなす距離（省略時は120）
+    #[serde(default)]
+    pub tolerance: Option<f32>,
+}
+
+impl ColorFilter {
+    /// パレットが条件に一致するかを判定
+    pub fn matches(&self, palette: &ColorPalette) -> bool {
+        let tolerance = self.tolerance.unwrap_or(DEFAULT_COLOR_TOLERANCE);
+        palette.coverage(self.color, tolerance) >= self.min_coverage.unwrap_or(DEFAULT_MIN_COVERAGE)
+    }
+}
+
+/// 色の分析の結果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ColorAnalysis {
+    /// 代表色を求めた画像の数
+    pub analyzed: usize,
+    /// 代表色を求められなかった画像
+    pub warnings: Vec<ImageWarning>,
+    /// 代表色を反映したコレクションのID
+    pub updated_collections: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_extract_palette_and_match_colors() {
+        // 左3/4が青、右1/4が赤、下端1行は透明
+        let image = RgbaImage::from_fn(40, 21, |x, y| match (x, y) {
+            (_, 20) => Rgba([255, 255, 255, 0]),
+            (0..30, _) => Rgba([20, 40, 220, 255]),
+            _ => Rgba([230, 30, 30, 255]),
+        });
+        let palette = ColorPalette::extract(&DynamicImage::ImageRgba8(image), DEFAULT_PALETTE_SIZE);
+
+        // 単色の箱はそれ以上分割しない
+        assert_eq!(palette.colors.len(), 2);
+        assert_eq!(palette.colors[0].color, Color([20, 40, 220]));
+        assert!((palette.colors[0].weight - 0.75).abs() < 1e-6);
+        assert!((palette.brightness - (0.75 * Color([20, 40, 220]).luma() + 0.25 * Color([230, 30, 30]).luma())).abs() < 1e-4);
+
+        let blue = Color::parse("#0000ff").unwrap();
+        assert!(ColorFilter { color: blue, min_coverage: None, tolerance: None }.matches(&palette));
+        assert!(!ColorFilter { color: Color::parse("ff0000").unwrap(), min_coverage: None, tolerance: None }.matches(&palette));
+        assert!(palette.distance_to(blue).unwrap() < palette.distance_to(Color([255, 0, 0])).unwrap());
+
+        // 色相は最も割合の大きい有彩色
+        assert!((palette.hue().unwrap() - Color([20, 40, 220]).hsv().0).abs() < 1e-4);
+        assert_eq!(ColorPalette { colors: vec![PaletteColor { color: Color([128, 128, 128]), weight: 1.0 }], brightness: 0.5 }.hue(), None);
+
+        assert_eq!(serde_json::to_value(blue).unwrap(), "#0000ff");
+        assert!(serde_json::from_str::<Color>("\"#12345\"").is_err());
+    }
+}
@@ -0,0 +1,451 @@
+// core/config_library.rs
+// リソース設定ライブラリ - 名前付きのリソース設定を1つのファイルにまとめて管理する
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use thiserror::Error;
+
+use crate::core::config_schema::{self, ConfigError, ConfigIssue, VersionedConfig};
+use crate::core::resource_manager::ResourceConfig;
+
+/// 設定ライブラリのエラー
+#[derive(Debug, Error, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConfigLibraryError {
+    #[error("Config not found: {id}")]
+    NotFound { id: String },
+    #[error("Config ID already exists: {id}")]
+    DuplicateId { id: String },
+    #[error("{error}")]
+    Invalid { error: ConfigError },
+    #[error("Failed to save config library: {message}")]
+    Io { message: String },
+    #[error("Config library is read-only because it could not be loaded: {error}")]
+    ReadOnly { error: ConfigError },
+}
+
+impl From<ConfigError> for ConfigLibraryError {
+    fn from(error: ConfigError) -> Self {
+        Self::Invalid { error }
+    }
+}
+
+/// 設定ライブラリの内容（ファイルの形式と同じ）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConfigListing {
+    /// 既定の設定ID
+    #[serde(default)]
+    pub default_id: Option<String>,
+    /// 登録された設定（登録順）
+    #[serde(default)]
+    pub configs: Vec<ResourceConfig>,
+}
+
+impl VersionedConfig for ConfigListing {
+    const KIND: &'static str = "resource_config_library";
+    const VERSION: u32 = 1;
+
+    fn migrate(from: u32, mut data: JsonValue) -> Result<JsonValue, String> {
+        match from {
+            // バージョン0: エンベロープのないライブラリ（各設定も同じバージョンとして移行する）
+            0 => {
+                if let Some(configs) = data.get_mut("configs").and_then(|c| c.as_array_mut()) {
+                    for config in configs.iter_mut() {
+                        *config = ResourceConfig::migrate(0, config.take())?;
+                    }
+                }
+                Ok(data)
+            },
+            _ => Err(format!("Unknown config library version: {}", from)),
+        }
+    }
+
+    fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        let mut seen = HashSet::new();
+        for (index, config) in self.configs.iter().enumerate() {
+            let prefix = format!("configs[{}]", index);
+            issues.extend(config.validate().into_iter().map(|issue| issue.under(&prefix)));
+            if !seen.insert(config.id.as_str()) {
+                issues.push(ConfigIssue::DuplicateId { path: format!("{}.id", prefix), id: config.id.clone() });
+            }
+        }
+        if let Some(default_id) = &self.default_id {
+            if !seen.contains(default_id.as_str()) {
+                issues.push(ConfigIssue::DefaultNotFound { path: "default_id".to_string(), id: default_id.clone() });
+            }
+        }
+        issues
+    }
+}
+
+/// リソース設定ライブラリ
+#[derive(Debug)]
+pub struct ConfigLibrary {
+    /// 保存先ファイルのパス
+    path: PathBuf,
+    listing: Mutex<ConfigListing>,
+    /// 読み込めなかった理由（ある場合はファイルを上書きしないよう変更を拒否する）
+    load_error: Option<ConfigError>,
+}
+
+impl ConfigLibrary {
+    /// 設定ライブラリを開く
+    ///
+    /// ファイルが存在しない場合は空のライブラリを作成する。古い形式は読み込み時に移行する。
+    /// JSONとして壊れている場合は元のファイルを `.corrupt` として退避してから空のライブラリで始める。
+    /// 新しいバージョンのファイルや検証に失敗したファイルは、内容を失わないよう読み取り専用で開く
+    /// （読める範囲で一覧を返し、変更は `ConfigLibraryError::ReadOnly` で拒否する）。
+    pub fn open(path: PathBuf) -> Self {
+        let (listing, load_error) = match fs::read_to_string(&path) {
+            Ok(text) => match config_schema::from_json::<ConfigListing>(&text) {
+                Ok(loaded) => (loaded.config, None),
+                Err(e @ ConfigError::Syntax { .. }) => {
+                    log::warn!("Failed to parse config library {}: {}", path.display(), e);
+                    let backup = path.with_extension("json.corrupt");
+                    if let Err(e) = fs::rename(&path, &backup) {
+                        log::warn!("Failed to back up config library: {}", e);
+                    }
+                    (ConfigListing::default(), None)
+                },
+                Err(e) => {
+                    log::warn!("Opening config library {} read-only: {}", path.display(), e);
+                    (load_lenient(&text).unwrap_or_default(), Some(e))
+                },
+            },
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Failed to read config library {}: {}", path.display(), e);
+                }
+                (ConfigListing::default(), None)
+            }
+        };
+
+        Self {
+            path,
+            listing: Mutex::new(listing),
+            load_error,
+        }
+    }
+
+    /// 読み込めなかった理由（読み取り専用で開いている場合）
+    pub fn load_error(&self) -> Option<&ConfigError> {
+        self.load_error.as_ref()
+    }
+
+    /// 保存先ファイルのパスを取得
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 設定の一覧を取得
+    pub fn list(&self) -> ConfigListing {
+        self.listing.lock()
+            .map(|listing| listing.clone())
+            .unwrap_or_default()
+    }
+
+    /// 設定を取得
+    pub fn get(&self, config_id: &str) -> Option<ResourceConfig> {
+        let listing = self.listing.lock().ok()?;
+        listing.configs.iter()
+            .find(|config| config.id == config_id)
+            .cloned()
+    }
+
+    /// 既定の設定を取得
+    pub fn default_config(&self) -> Option<ResourceConfig> {
+        let default_id = self.listing.lock().ok()?.default_id.clone()?;
+        self.get(&default_id)
+    }
+
+    /// 設定を追加（IDが空の場合は採番する。最初の設定は既定になる）
+    pub fn create(&self, mut config: ResourceConfig) -> Result<ResourceConfig, ConfigLibraryError> {
+        self.modify(|listing| {
+            if config.id.trim().is_empty() {
+                config.id = unique_id(listing, "config");
+            }
+            validate(&config)?;
+            if listing.configs.iter().any(|existing| existing.id == config.id) {
+                return Err(ConfigLibraryError::DuplicateId { id: config.id.clone() });
+            }
+            if listing.default_id.is_none() {
+                listing.default_id = Some(config.id.clone());
+            }
+            listing.configs.push(config.clone());
+            Ok(config)
+        })
+    }
+
+    /// 既存の設定を置き換え
+    pub fn update(&self, config: ResourceConfig) -> Result<ResourceConfig, ConfigLibraryError> {
+        validate(&config)?;
+        self.modify(|listing| {
+            let existing = listing.configs.iter_mut()
+                .find(|existing| existing.id == config.id)
+                .ok_or_else(|| ConfigLibraryError::NotFound { id: config.id.clone() })?;
+            *existing = config.clone();
+            Ok(config)
+        })
+    }
+
+    /// 設定を複製（名前を省略した場合は元の名前から付ける）
+    pub fn duplicate(&self, config_id: &str, name: Option<String>) -> Result<ResourceConfig, ConfigLibraryError> {
+        self.modify(|listing| {
+            let source = listing.configs.iter()
+                .find(|config| config.id == config_id)
+                .ok_or_else(|| ConfigLibraryError::NotFound { id: config_id.to_string() })?;
+
+            let mut copy = source.clone();
+            copy.name = name.unwrap_or_else(|| format!("{} のコピー", source.name));
+            copy.id = unique_id(listing, &format!("{}-copy", config_id));
+            validate(&copy)?;
+            listing.configs.push(copy.clone());
+            Ok(copy)
+        })
+    }
+
+    /// 設定を削除（既定の設定を削除した場合は先頭の設定が既定になる）
+    pub fn delete(&self, config_id: &str) -> Result<(), ConfigLibraryError> {
+        self.modify(|listing| {
+            let index = listing.configs.iter()
+                .position(|config| config.id == config_id)
+                .ok_or_else(|| ConfigLibraryError::NotFound { id: config_id.to_string() })?;
+            listing.configs.remove(index);
+            if listing.default_id.as_deref() == Some(config_id) {
+                listing.default_id = listing.configs.first().map(|config| config.id.clone());
+            }
+            Ok(())
+        })
+    }
+
+    /// 既定の設定を変更
+    pub fn set_default(&self, config_id: &str) -> Result<(), ConfigLibraryError> {
+        self.modify(|listing| {
+            if !listing.configs.iter().any(|config| config.id == config_id) {
+                return Err(ConfigLibraryError::NotFound { id: config_id.to_string() });
+            }
+            listing.default_id = Some(config_id.to_string());
+            Ok(())
+        })
+    }
+
+    /// 一覧を変更して保存（変更または保存に失敗した場合は元に戻す）
+    fn modify<T, F>(&self, change: F) -> Result<T, ConfigLibraryError>
+    where
+        F: FnOnce(&mut ConfigListing) -> Result<T, ConfigLibraryError>,
+    {
+        if let Some(error) = &self.load_error {
+            return Err(ConfigLibraryError::ReadOnly { error: error.clone() });
+        }
+        let mut listing = self.listing.lock().map_err(|e| {
+            ConfigLibraryError::Io { message: format!("Failed to lock config library: {}", e) }
+        })?;
+
+        let mut updated = listing.clone();
+        let result = change(&mut updated)?;
+        save_listing(&self.path, &updated)?;
+        *listing = updated;
+        Ok(result)
+    }
+}
+
+/// 設定の内容を検証
+fn validate(config: &ResourceConfig) -> Result<(), ConfigLibraryError> {
+    let issues = config.validate();
+    if !issues.is_empty() {
+        return Err(ConfigError::Invalid { issues }.into());
+    }
+    Ok(())
+}
+
+/// 検証に通らなかった一覧を、型に合う範囲で読み込む（読み取り専用で表示するため）
+fn load_lenient(text: &str) -> Option<ConfigListing> {
+    let value = serde_json::from_str(text).ok()?;
+    let (data, _) = config_schema::migrate_value(
+        value,
+        ConfigListing::KIND,
+        ConfigListing::VERSION,
+        ConfigListing::migrate,
+    ).ok()?;
+    serde_json::from_value(data).ok()
+}
+
+/// 使われていないIDを生成（`base`、`base-2`、`base-3` ...）
+fn unique_id(listing: &ConfigListing, base: &str) -> String {
+    let taken = |id: &str| listing.configs.iter().any(|config| config.id == id);
+    if !taken(base) {
+        return base.to_string();
+    }
+    (2..)
+        .map(|n| format!("{}-{}", base, n))
+        .find(|id| !taken(id))
+        .unwrap_or_else(|| base.to_string())
+}
+
+/// 一覧をバージョン付きでファイルに保存（一時ファイル経由で置き換え）
+fn save_listing(path: &Path, listing: &ConfigListing) -> Result<(), ConfigLibraryError> {
+    let io_error = |e: std::io::Error| ConfigLibraryError::Io { message: e.to_string() };
+    let json = config_schema::to_json(listing)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(io_error)?;
+    }
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, json).map_err(io_error)?;
+    fs::rename(&temp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        io_error(e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::resource_manager::{ResourceFilter, ScanOptions};
+
+    fn library_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("image-viewer-configs-{}-{}", name, std::process::id()))
+            .join("resource_configs.json")
+    }
+
+    fn create_config(id: &str, name: &str) -> ResourceConfig {
+        ResourceConfig {
+            id: id.to_string(),
+            name: name.to_string(),
+            filters: ResourceFilter {
+                include: vec!["~/Pictures".to_string()],
+                exclude: Vec::new(),
+            },
+            scan: ScanOptions::default(),
+            watch: false,
+        }
+    }
+
+    #[test]
+    fn test_create_duplicate_and_delete() {
+        let path = library_path("crud");
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+        let library = ConfigLibrary::open(path.clone());
+
+        let photos = library.create(create_config("photos", "Photos")).unwrap();
+        let generated = library.create(create_config("", "Wallpapers")).unwrap();
+        assert_eq!(generated.id, "config");
+        assert_eq!(library.create(create_config("photos", "Again")), Err(ConfigLibraryError::DuplicateId { id: "photos".to_string() }));
+        let issues = vec![ConfigIssue::EmptyField { path: "name".to_string() }];
+        assert_eq!(library.create(create_config("blank", " ")), Err(ConfigError::Invalid { issues }.into()));
+
+        // 最初に追加した設定が既定になる
+        assert_eq!(library.default_config().unwrap().id, photos.id);
+
+        let copy = library.duplicate("photos", None).unwrap();
+        assert_eq!(copy.id, "photos-copy");
+        assert_eq!(copy.name, "Photos のコピー");
+        assert_eq!(library.duplicate("photos", None).unwrap().id, "photos-copy-2");
+
+        let mut renamed = copy.clone();
+        renamed.name = "Renamed".to_string();
+        library.update(renamed).unwrap();
+        assert_eq!(library.get("photos-copy").unwrap().name, "Renamed");
+        assert!(library.update(create_config("missing", "Missing")).is_err());
+
+        // 既定の設定を削除すると先頭の設定が既定になる
+        library.set_default("photos-copy").unwrap();
+        library.delete("photos-copy").unwrap();
+        assert_eq!(library.list().default_id.as_deref(), Some("photos"));
+
+        // 保存した内容を読み直せる
+        let reopened = ConfigLibrary::open(path.clone());
+        let ids: Vec<String> = reopened.list().configs.into_iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec!["photos", "config", "photos-copy-2"]);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_legacy_library_is_migrated() {
+        let path = library_path("legacy");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, r#"{"default_id": "a", "configs": [
+            {"id": "a", "name": "A", "filters": {"include": ["/a"], "exclude": []}}
+        ]}"#).unwrap();
+
+        let library = ConfigLibrary::open(path.clone());
+        assert_eq!(library.default_config().unwrap().id, "a");
+
+        // 変更時には現在のバージョンで書き出される
+        library.set_default("a").unwrap();
+        let saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["kind"], ConfigListing::KIND);
+        assert_eq!(saved["version"], ConfigListing::VERSION);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_duplicate_ids_are_reported() {
+        let listing = ConfigListing {
+            default_id: Some("missing".to_string()),
+            configs: vec![create_config("a", "A"), create_config("a", "B")],
+        };
+        assert_eq!(listing.validate(), vec![
+            ConfigIssue::DuplicateId { path: "configs[1].id".to_string(), id: "a".to_string() },
+            ConfigIssue::DefaultNotFound { path: "default_id".to_string(), id: "missing".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn test_corrupt_library_is_backed_up() {
+        let path = library_path("corrupt");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "{ broken").unwrap();
+
+        let library = ConfigLibrary::open(path.clone());
+        assert!(library.list().configs.is_empty());
+        assert!(path.with_extension("json.corrupt").exists());
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_unloadable_library_is_opened_read_only() {
+        let path = library_path("read-only");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        // 新しいバージョンで書かれたファイル（ダウングレード）
+        let newer = format!(
+            r#"{{"kind": "{}", "version": {}, "data": {{"configs": []}}}}"#,
+            ConfigListing::KIND,
+            ConfigListing::VERSION + 1,
+        );
+        fs::write(&path, &newer).unwrap();
+        let library = ConfigLibrary::open(path.clone());
+        assert!(matches!(library.load_error(), Some(ConfigError::UnsupportedVersion { .. })));
+        assert!(matches!(library.create(create_config("a", "A")), Err(ConfigLibraryError::ReadOnly { .. })));
+        assert_eq!(fs::read_to_string(&path).unwrap(), newer);
+        assert!(!path.with_extension("json.corrupt").exists());
+
+        // 新しいビルドが追加したフィールドを含むファイルは、読める範囲で一覧を返す
+        let unknown = format!(
+            r#"{{"kind": "{}", "version": {}, "data": {{"default_id": "a", "pinned": true, "configs": [
+                {{"id": "a", "name": "A", "filters": {{"include": ["/a"], "exclude": []}}}}
+            ]}}}}"#,
+            ConfigListing::KIND,
+            ConfigListing::VERSION,
+        );
+        fs::write(&path, &unknown).unwrap();
+        let library = ConfigLibrary::open(path.clone());
+        assert!(matches!(library.load_error(), Some(ConfigError::Invalid { .. })));
+        assert_eq!(library.default_config().unwrap().id, "a");
+        assert!(library.delete("a").is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), unknown);
+        assert!(!path.with_extension("json.corrupt").exists());
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
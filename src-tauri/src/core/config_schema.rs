@@ -0,0 +1,257 @@
+// core/config_schema.rs
+// 設定ファイルのスキーマ管理 - バージョン付きエンベロープ、移行チェーン、構造化された検証エラー
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use thiserror::Error;
+
+/// 設定の検証で見つかった問題
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Error)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConfigIssue {
+    #[error("Unknown field: {path}")]
+    UnknownField { path: String },
+    #[error("Field must not be empty: {path}")]
+    EmptyField { path: String },
+    #[error("Include list is empty: {path}")]
+    EmptyIncludeList { path: String },
+    #[error("Duplicate config ID '{id}': {path}")]
+    DuplicateId { path: String, id: String },
+    #[error("Default config not found '{id}': {path}")]
+    DefaultNotFound { path: String, id: String },
+    #[error("Invalid value: {message}")]
+    InvalidValue { path: String, message: String },
+}
+
+impl ConfigIssue {
+    /// 親要素のパスを前に付けた問題を返す
+    pub fn under(self, prefix: &str) -> Self {
+        let join = |path: String| if path.is_empty() { prefix.to_string() } else { format!("{}.{}", prefix, path) };
+        match self {
+            Self::UnknownField { path } => Self::UnknownField { path: join(path) },
+            Self::EmptyField { path } => Self::EmptyField { path: join(path) },
+            Self::EmptyIncludeList { path } => Self::EmptyIncludeList { path: join(path) },
+            Self::DuplicateId { path, id } => Self::DuplicateId { path: join(path), id },
+            Self::DefaultNotFound { path, id } => Self::DefaultNotFound { path: join(path), id },
+            Self::InvalidValue { path, message } => Self::InvalidValue { path: join(path), message },
+        }
+    }
+}
+
+/// 設定の読み込みエラー
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Error)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConfigError {
+    #[error("Failed to access config file: {message}")]
+    Io { message: String },
+    #[error("Invalid JSON at line {line}, column {column}: {message}")]
+    Syntax { message: String, line: usize, column: usize },
+    #[error("Expected {expected} config but found {found}")]
+    KindMismatch { expected: String, found: String },
+    #[error("Config version {found} is newer than supported version {supported}")]
+    UnsupportedVersion { found: u32, supported: u32 },
+    #[error("Failed to migrate config from version {from}: {message}")]
+    Migration { from: u32, message: String },
+    #[error("Config validation failed: {}", format_issues(.issues))]
+    Invalid { issues: Vec<ConfigIssue> },
+}
+
+/// バージョン付きエンベロープ
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigEnvelope {
+    /// 設定の種類
+    pub kind: String,
+    /// 形式バージョン
+    pub version: u32,
+    /// 設定本体
+    pub data: JsonValue,
+}
+
+/// バージョン管理される設定
+///
+/// エンベロープのない古いファイルはバージョン0として扱い、`migrate` で1つずつ現在のバージョンまで移行する。
+pub trait VersionedConfig: Serialize + DeserializeOwned {
+    /// エンベロープに記録する設定の種類
+    const KIND: &'static str;
+    /// 現在の形式バージョン
+    const VERSION: u32;
+
+    /// `from` バージョンの内容を1つ上のバージョンへ移行
+    fn migrate(from: u32, data: JsonValue) -> Result<JsonValue, String>;
+
+    /// 内容を検証
+    fn validate(&self) -> Vec<ConfigIssue> {
+        Vec::new()
+    }
+}
+
+/// 読み込んだ設定
+#[derive(Debug, Clone)]
+pub struct LoadedConfig<T> {
+    /// 設定本体
+    pub config: T,
+    /// ファイルに記録されていたバージョン（現在のバージョンより古ければ移行済み）
+    pub source_version: u32,
+}
+
+/// 設定をエンベロープ付きのJSON文字列に変換
+pub fn to_json<T: VersionedConfig>(config: &T) -> Result<String, ConfigError> {
+    let data = serde_json::to_value(config).map_err(|e| ConfigError::Io { message: e.to_string() })?;
+    let envelope = ConfigEnvelope {
+        kind: T::KIND.to_string(),
+        version: T::VERSION,
+        data,
+    };
+    serde_json::to_string_pretty(&envelope).map_err(|e| ConfigError::Io { message: e.to_string() })
+}
+
+/// JSON文字列から設定を読み込む（必要に応じて移行し、検証する）
+pub fn from_json<T: VersionedConfig>(text: &str) -> Result<LoadedConfig<T>, ConfigError> {
+    let value: JsonValue = serde_json::from_str(text).map_err(|e| ConfigError::Syntax {
+        message: e.to_string(),
+        line: e.line(),
+        column: e.column(),
+    })?;
+    let (data, source_version) = migrate_value(value, T::KIND, T::VERSION, T::migrate)?;
+
+    let config: T = serde_json::from_value(data.clone()).map_err(|e| ConfigError::Invalid {
+        issues: vec![ConfigIssue::InvalidValue { path: String::new(), message: e.to_string() }],
+    })?;
+
+    // 読み込んだ内容と書き出した内容を比べ、型にないフィールドを検出する
+    let mut issues = Vec::new();
+    if let Ok(known) = serde_json::to_value(&config) {
+        find_unknown_fields(&data, &known, "", &mut issues);
+    }
+    issues.extend(config.validate());
+    if !issues.is_empty() {
+        return Err(ConfigError::Invalid { issues });
+    }
+
+    Ok(LoadedConfig { config, source_version })
+}
+
+/// エンベロープを解いて現在のバージョンまで移行（エンベロープがなければバージョン0）
+pub fn migrate_value<M>(value: JsonValue, kind: &str, version: u32, migrate: M) -> Result<(JsonValue, u32), ConfigError>
+where
+    M: Fn(u32, JsonValue) -> Result<JsonValue, String>,
+{
+    let (mut data, source_version) = match split_envelope(value) {
+        Ok(envelope) => {
+            if envelope.kind != kind {
+                return Err(ConfigError::KindMismatch { expected: kind.to_string(), found: envelope.kind });
+            }
+            (envelope.data, envelope.version)
+        },
+        Err(legacy) => (legacy, 0),
+    };
+
+    if source_version > version {
+        return Err(ConfigError::UnsupportedVersion { found: source_version, supported: version });
+    }
+    for from in source_version..version {
+        data = migrate(from, data).map_err(|message| ConfigError::Migration { from, message })?;
+    }
+    Ok((data, source_version))
+}
+
+/// エンベロープであれば分解し、そうでなければ元の値を返す
+fn split_envelope(value: JsonValue) -> Result<ConfigEnvelope, JsonValue> {
+    let is_envelope = value.as_object().is_some_and(|object| {
+        object.contains_key("kind") && object.contains_key("version") && object.contains_key("data")
+    });
+    if !is_envelope {
+        return Err(value);
+    }
+    serde_json::from_value(value.clone()).map_err(|_| value)
+}
+
+/// 型にないフィールドを再帰的に探す
+fn find_unknown_fields(input: &JsonValue, known: &JsonValue, path: &str, issues: &mut Vec<ConfigIssue>) {
+    let child_path = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+    match (input, known) {
+        (JsonValue::Object(input), JsonValue::Object(known)) => {
+            for (key, value) in input {
+                match known.get(key) {
+                    Some(known_value) => find_unknown_fields(value, known_value, &child_path(key), issues),
+                    None => issues.push(ConfigIssue::UnknownField { path: child_path(key) }),
+                }
+            }
+        },
+        (JsonValue::Array(input), JsonValue::Array(known)) => {
+            for (index, (value, known_value)) in input.iter().zip(known).enumerate() {
+                find_unknown_fields(value, known_value, &format!("{}[{}]", path, index), issues);
+            }
+        },
+        _ => {},
+    }
+}
+
+/// オブジェクトに項目がなければ既定値を設定（移行処理用）
+pub fn insert_default(data: &mut JsonValue, key: &str, default: JsonValue) -> Result<(), String> {
+    let object: &mut Map<String, JsonValue> = data.as_object_mut()
+        .ok_or_else(|| "Config must be a JSON object".to_string())?;
+    object.entry(key.to_string()).or_insert(default);
+    Ok(())
+}
+
+/// 問題の一覧を1行にまとめる
+fn format_issues(issues: &[ConfigIssue]) -> String {
+    issues.iter()
+        .map(|issue| issue.to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::resource_manager::ResourceConfig;
+
+    #[test]
+    fn test_legacy_config_is_migrated() {
+        let legacy = r#"{"id": "photos", "name": "Photos", "filters": {"include": ["~/Pictures"], "exclude": []}}"#;
+        let loaded = from_json::<ResourceConfig>(legacy).unwrap();
+        assert_eq!(loaded.source_version, 0);
+        assert_eq!(loaded.config.id, "photos");
+
+        // 書き出した内容は現在のバージョンのエンベロープになる
+        let saved = to_json(&loaded.config).unwrap();
+        let envelope: ConfigEnvelope = serde_json::from_str(&saved).unwrap();
+        assert_eq!(envelope.kind, ResourceConfig::KIND);
+        assert_eq!(envelope.version, ResourceConfig::VERSION);
+        assert_eq!(from_json::<ResourceConfig>(&saved).unwrap().source_version, ResourceConfig::VERSION);
+    }
+
+    #[test]
+    fn test_structured_errors() {
+        let unknown = r#"{"id": "a", "name": "A", "filters": {"include": ["/a"], "exclude": [], "extra": 1}, "sort": "name"}"#;
+        match from_json::<ResourceConfig>(unknown) {
+            Err(ConfigError::Invalid { issues }) => assert_eq!(issues, vec![
+                ConfigIssue::UnknownField { path: "filters.extra".to_string() },
+                ConfigIssue::UnknownField { path: "sort".to_string() },
+            ]),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let empty = r#"{"id": "a", "name": "", "filters": {"include": [], "exclude": []}}"#;
+        match from_json::<ResourceConfig>(empty) {
+            Err(ConfigError::Invalid { issues }) => assert_eq!(issues, vec![
+                ConfigIssue::EmptyField { path: "name".to_string() },
+                ConfigIssue::EmptyIncludeList { path: "filters.include".to_string() },
+            ]),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let newer = format!(r#"{{"kind": "{}", "version": 99, "data": {{}}}}"#, ResourceConfig::KIND);
+        assert_eq!(
+            from_json::<ResourceConfig>(&newer).unwrap_err(),
+            ConfigError::UnsupportedVersion { found: 99, supported: ResourceConfig::VERSION },
+        );
+
+        let other_kind = r#"{"kind": "plugin:findme", "version": 1, "data": {}}"#;
+        assert!(matches!(from_json::<ResourceConfig>(other_kind), Err(ConfigError::KindMismatch { .. })));
+        assert!(matches!(from_json::<ResourceConfig>("{ broken"), Err(ConfigError::Syntax { line: 1, .. })));
+    }
+}
@@ -0,0 +1,160 @@
+// core/duplicates.rs
+// 重複検出 - ファイル内容のハッシュ（BLAKE3）で完全に同一の画像をまとめる
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use serde::Serialize;
+
+use crate::core::archive;
+use crate::core::image_collection::ImageWarning;
+
+/// ハッシュ計算時の読み込み単位
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// ファイル（またはアーカイブ内のエントリ）の内容のハッシュを計算（16進文字列）
+pub fn hash_path(path: &str) -> Result<String, String> {
+    if archive::split_entry_path(path).is_some() {
+        let bytes = archive::read_entry_path(path)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        return Ok(blake3::hash(&bytes).to_hex().to_string());
+    }
+
+    let mut file = File::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+    loop {
+        let read = file.read(&mut buffer)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// 内容が同一の画像のグループ
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DuplicateGroup {
+    /// 内容のハッシュ
+    pub hash: String,
+    /// 1ファイルあたりのサイズ（バイト）
+    pub file_size: u64,
+    /// 画像のパス（コレクション内の順）
+    pub paths: Vec<String>,
+    /// 1つだけ残した場合に空くサイズ（バイト）
+    pub wasted_bytes: u64,
+}
+
+/// 重複検出の結果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DuplicateReport {
+    /// 重複グループ（空くサイズの大きい順）
+    pub groups: Vec<DuplicateGroup>,
+    /// ハッシュを求めた画像の数
+    pub hashed: usize,
+    /// すべてのグループで空くサイズの合計（バイト）
+    pub wasted_bytes: u64,
+    /// ハッシュを計算できなかった画像
+    pub warnings: Vec<ImageWarning>,
+}
+
+/// 同一内容の画像をまとめる
+///
+/// サイズが他と一致しない画像は重複し得ないため、サイズが同じ画像だけハッシュを求める。
+/// `hash` は画像のハッシュを返す関数（キャッシュを使う場合はここで引く）、
+/// `progress` は1件ごとに（完了数, 対象数, パス）で呼ばれる。
+pub fn find_duplicates<H, P>(files: &[(String, u64)], mut hash: H, mut progress: P) -> DuplicateReport
+where
+    H: FnMut(&str) -> Result<String, String>,
+    P: FnMut(usize, usize, &str),
+{
+    let mut by_size: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (position, (_, size)) in files.iter().enumerate() {
+        by_size.entry(*size).or_default().push(position);
+    }
+    let mut candidates: Vec<usize> = by_size.into_values()
+        .filter(|positions| positions.len() > 1)
+        .flatten()
+        .collect();
+    candidates.sort_unstable();
+
+    let mut report = DuplicateReport::default();
+    let mut by_hash: HashMap<String, Vec<usize>> = HashMap::new();
+    let total = candidates.len();
+    for (completed, position) in candidates.into_iter().enumerate() {
+        let path = &files[position].0;
+        match hash(path) {
+            Ok(digest) => {
+                by_hash.entry(digest).or_default().push(position);
+                report.hashed += 1;
+            },
+            Err(message) => report.warnings.push(ImageWarning { path: path.clone(), message }),
+        }
+        progress(completed + 1, total, path);
+    }
+
+    report.groups = by_hash.into_iter()
+        .filter(|(_, positions)| positions.len() > 1)
+        .map(|(hash, positions)| {
+            let file_size = files[positions[0]].1;
+            DuplicateGroup {
+                hash,
+                file_size,
+                wasted_bytes: file_size * (positions.len() as u64 - 1),
+                paths: positions.into_iter().map(|position| files[position].0.clone()).collect(),
+            }
+        })
+        .collect();
+    report.groups.sort_by(|a, b| b.wasted_bytes.cmp(&a.wasted_bytes).then_with(|| a.paths.cmp(&b.paths)));
+    report.wasted_bytes = report.groups.iter().map(|group| group.wasted_bytes).sum();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_find_duplicates_hashes_only_size_collisions() {
+        let dir = std::env::temp_dir().join(format!("image-viewer-duplicates-{}", std::process::id()));
+        fs::create_dir_all(dir.join("copy")).unwrap();
+        let write = |name: &str, bytes: &[u8]| {
+            let path = dir.join(name);
+            fs::write(&path, bytes).unwrap();
+            (path.to_string_lossy().into_owned(), bytes.len() as u64)
+        };
+        let files = vec![
+            write("a.jpg", b"same-content"),
+            write("copy/a.jpg", b"same-content"),
+            write("b.jpg", b"diff-content"),
+            write("c.jpg", b"unique"),
+        ];
+
+        let mut hashed = Vec::new();
+        let mut last_progress = (0, 0);
+        let report = find_duplicates(
+            &files,
+            |path| {
+                hashed.push(path.to_string());
+                hash_path(path)
+            },
+            |completed, total, _| last_progress = (completed, total),
+        );
+
+        // サイズが一意の c.jpg はハッシュを求めない
+        assert_eq!(hashed.len(), 3);
+        assert_eq!(last_progress, (3, 3));
+        assert_eq!(report.hashed, 3);
+        assert_eq!(report.groups.len(), 1);
+        assert_eq!(report.groups[0].paths, vec![files[0].0.clone(), files[1].0.clone()]);
+        assert_eq!(report.groups[0].wasted_bytes, 12);
+        assert_eq!(report.wasted_bytes, 12);
+        assert_eq!(report.groups[0].hash, blake3::hash(b"same-content").to_hex().to_string());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
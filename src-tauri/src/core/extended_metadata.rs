@@ -0,0 +1,804 @@
+// core/extended_metadata.rs
+// 拡張メタデータの抽出 - JPEG/PNG/WebP/TIFF（RAW含む）からEXIF・IPTC・XMPを読み取り、1つの構造体にまとめる
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use exif::{Context, Exif, In, Reader, Tag, Value};
+use quick_xml::events::{BytesStart, Event};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::core::archive::{self, ArchiveError};
+use crate::core::image_format::{self, ImageFormat};
+use crate::core::orientation;
+
+/// TIFFのXMPタグ（XMLPacket）
+const TAG_XMP: Tag = Tag(Context::Tiff, 700);
+
+/// TIFFのIPTCタグ（IPTC-NAA）
+const TAG_IPTC: Tag = Tag(Context::Tiff, 33723);
+
+/// JPEGのAPP1セグメントに格納されたEXIFの識別子
+const EXIF_SIGNATURE: &[u8] = b"Exif\0\0";
+
+/// JPEGのAPP1セグメントに格納されたXMPの識別子
+const XMP_SIGNATURE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// JPEGのAPP13セグメント（Photoshop画像リソース）の識別子
+const PHOTOSHOP_SIGNATURE: &[u8] = b"Photoshop 3.0\0";
+
+/// IPTC-NAAレコードを格納する画像リソースのID
+const IPTC_RESOURCE_ID: u16 = 0x0404;
+
+/// PNGのiTXtチャンクに格納されたXMPのキーワード
+const PNG_XMP_KEYWORD: &[u8] = b"XML:com.adobe.xmp";
+
+/// 一覧用に向きと撮影位置を読み取る際、TIFF系の画像とアーカイブ内のエントリで読み込む先頭部分のサイズ
+/// （JPEGのEXIFは先頭付近の64KB以下のAPP1セグメントにあり、TIFF系のRAWも多くはGPS IFDを先頭付近に置く）
+const HEADER_LIMIT: u64 = 256 * 1024;
+
+/// メタデータとして読み込むブロックの最大サイズ（壊れたファイルへの対策）
+const MAX_BLOCK_SIZE: u64 = 16 * 1024 * 1024;
+
+/// 拡張メタデータ読み込みのエラー型
+#[derive(Error, Debug)]
+pub enum MetadataError {
+    #[error("Failed to read image metadata: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Archive(#[from] ArchiveError),
+
+    #[error("Invalid EXIF data: {0}")]
+    Exif(String),
+}
+
+/// カメラとレンズ
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraInfo {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub lens_make: Option<String>,
+    pub lens_model: Option<String>,
+}
+
+/// 撮影設定
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExposureInfo {
+    /// 露出時間（秒）
+    pub exposure_time: Option<f64>,
+    /// F値
+    pub f_number: Option<f64>,
+    /// ISO感度
+    pub iso: Option<u32>,
+    /// 焦点距離（mm）
+    pub focal_length: Option<f64>,
+    /// 35mm判換算の焦点距離（mm）
+    pub focal_length_35mm: Option<u32>,
+    /// 露出補正（EV）
+    pub exposure_bias: Option<f64>,
+    /// フラッシュが発光したかどうか
+    pub flash_fired: Option<bool>,
+}
+
+/// 撮影位置（十進の度、南緯・西経は負）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GpsPosition {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// 高度（m、海面下は負）
+    #[serde(default)]
+    pub altitude: Option<f64>,
+}
+
+/// 一覧の読み込み時に使うEXIFの項目
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HeaderExif {
+    /// EXIFの向き（1〜8）
+    pub orientation: Option<u8>,
+    pub gps: Option<GpsPosition>,
+}
+
+/// 拡張メタデータ
+///
+/// 同じ項目が複数の形式にある場合は XMP > IPTC > EXIF の順に優先する。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExtendedMetadata {
+    pub camera: Option<CameraInfo>,
+    pub exposure: Option<ExposureInfo>,
+    /// 撮影日時（ISO-8601形式、タイムゾーンが記録されていればオフセット付き）
+    pub date_taken: Option<String>,
+    /// EXIFの向き（1〜8）
+    pub orientation: Option<u16>,
+    pub gps: Option<GpsPosition>,
+    pub title: Option<String>,
+    pub caption: Option<String>,
+    pub keywords: Vec<String>,
+    pub creator: Option<String>,
+    pub copyright: Option<String>,
+    /// 評価（XMPの xmp:Rating、-1は却下）
+    pub rating: Option<i8>,
+}
+
+impl ExtendedMetadata {
+    /// メタデータが1つもないかどうか
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// 別の形式から得た値で未設定の項目を補う（キーワードは重複を除いて追加する）
+    fn merge_fallback(&mut self, other: TextFields) {
+        self.title = self.title.take().or(other.title);
+        self.caption = self.caption.take().or(other.caption);
+        self.creator = self.creator.take().or(other.creator);
+        self.copyright = self.copyright.take().or(other.copyright);
+        self.rating = self.rating.or(other.rating);
+        for keyword in other.keywords {
+            if !self.keywords.contains(&keyword) {
+                self.keywords.push(keyword);
+            }
+        }
+    }
+}
+
+/// 画像から取り出したXMPパケットとIPTC-NAAレコード
+type MetadataBlocks = (Option<Vec<u8>>, Option<Vec<u8>>);
+
+/// IPTCとXMPから得るテキスト項目
+#[derive(Debug, Default)]
+struct TextFields {
+    title: Option<String>,
+    caption: Option<String>,
+    keywords: Vec<String>,
+    creator: Option<String>,
+    copyright: Option<String>,
+    rating: Option<i8>,
+}
+
+/// 画像ファイル（またはアーカイブ内のエントリ）の拡張メタデータを読み込む
+pub fn read_path(path: &str) -> Result<ExtendedMetadata, MetadataError> {
+    if archive::split_entry_path(path).is_some() {
+        return read_extended_metadata(&mut Cursor::new(archive::read_entry_path(path)?));
+    }
+    read_extended_metadata(&mut BufReader::new(File::open(Path::new(path))?))
+}
+
+/// 拡張メタデータを読み込む
+///
+/// メタデータを持たない画像や対応していない形式は空の `ExtendedMetadata` を返す。
+pub fn read_extended_metadata<R: BufRead + Seek>(reader: &mut R) -> Result<ExtendedMetadata, MetadataError> {
+    let format = image_format::sniff_reader(&mut *reader)?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    let exif = match Reader::new().read_from_container(&mut *reader) {
+        Ok(exif) => Some(exif),
+        Err(exif::Error::NotFound(_)) | Err(exif::Error::InvalidFormat(_)) => None,
+        Err(exif::Error::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => None,
+        Err(e) => return Err(MetadataError::Exif(e.to_string())),
+    };
+    reader.seek(SeekFrom::Start(0))?;
+
+    let (xmp, iptc) = match format {
+        Some(ImageFormat::Jpeg) => read_jpeg_blocks(reader)?,
+        Some(ImageFormat::Png) => (read_png_xmp(reader)?, None),
+        Some(ImageFormat::Webp) => (read_webp_xmp(reader)?, None),
+        Some(ImageFormat::Tiff | ImageFormat::Raw) => exif.as_ref().map_or((None, None), tiff_blocks),
+        _ => (None, None),
+    };
+
+    let mut metadata = exif.as_ref().map(from_exif).unwrap_or_default();
+    let exif_text = TextFields {
+        title: metadata.title.take(),
+        creator: metadata.creator.take(),
+        copyright: metadata.copyright.take(),
+        ..TextFields::default()
+    };
+    // 優先度の高い順に補う
+    metadata.merge_fallback(xmp.as_deref().map(parse_xmp).unwrap_or_default());
+    metadata.merge_fallback(iptc.as_deref().map(parse_iptc).unwrap_or_default());
+    metadata.merge_fallback(exif_text);
+    Ok(metadata)
+}
+
+/// EXIFから拡張メタデータを作成
+fn from_exif(exif: &Exif) -> ExtendedMetadata {
+    let field = |tag: Tag| exif.get_field(tag, In::PRIMARY).map(|field| &field.value);
+    let text = |tag: Tag| field(tag).and_then(ascii_value);
+    let float = |tag: Tag| field(tag).and_then(rational_value);
+    let uint = |tag: Tag| field(tag).and_then(|value| value.get_uint(0));
+
+    let camera = CameraInfo {
+        make: text(Tag::Make),
+        model: text(Tag::Model),
+        lens_make: text(Tag::LensMake),
+        lens_model: text(Tag::LensModel),
+    };
+    let exposure = ExposureInfo {
+        exposure_time: float(Tag::ExposureTime),
+        f_number: float(Tag::FNumber),
+        iso: uint(Tag::PhotographicSensitivity),
+        focal_length: float(Tag::FocalLength),
+        focal_length_35mm: uint(Tag::FocalLengthIn35mmFilm).filter(|&length| length > 0),
+        exposure_bias: float(Tag::ExposureBiasValue),
+        flash_fired: uint(Tag::Flash).map(|flash| flash & 1 == 1),
+    };
+
+    let date_taken = text(Tag::DateTimeOriginal)
+        .or_else(|| text(Tag::DateTime))
+        .and_then(|date| {
+            let offset = text(Tag::OffsetTimeOriginal).or_else(|| text(Tag::OffsetTime));
+            format_exif_date(&date, offset.as_deref())
+        });
+
+    ExtendedMetadata {
+        camera: Some(camera).filter(|camera| *camera != CameraInfo::default()),
+        exposure: Some(exposure).filter(|exposure| *exposure != ExposureInfo::default()),
+        date_taken,
+        orientation: uint(Tag::Orientation).filter(|value| (1..=8).contains(value)).map(|value| value as u16),
+        gps: gps_position(exif),
+        title: text(Tag::ImageDescription),
+        caption: None,
+        keywords: Vec::new(),
+        creator: text(Tag::Artist),
+        copyright: text(Tag::Copyright),
+        rating: None,
+    }
+}
+
+/// EXIFのGPS IFDから撮影位置を取得（度分秒と方位を符号付きの十進に変換する）
+fn gps_position(exif: &Exif) -> Option<GpsPosition> {
+    let field = |tag: Tag| exif.get_field(tag, In::PRIMARY).map(|field| &field.value);
+    let coordinate = |tag: Tag, reference: Tag, negative: &str| {
+        let degrees = match field(tag)? {
+            Value::Rational(parts) if parts.len() >= 3 => {
+                parts[0].to_f64() + parts[1].to_f64() / 60.0 + parts[2].to_f64() / 3600.0
+            },
+            _ => return None,
+        };
+        let negative = field(reference).and_then(ascii_value).is_some_and(|value| value.eq_ignore_ascii_case(negative));
+        Some(if negative { -degrees } else { degrees }).filter(|value| value.is_finite())
+    };
+
+    let latitude = coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef, "S").filter(|value| value.abs() <= 90.0)?;
+    let longitude = coordinate(Tag::GPSLongitude, Tag::GPSLongitudeRef, "W").filter(|value| value.abs() <= 180.0)?;
+    // 測位できなかったカメラは 0, 0 を記録することがある
+    if latitude == 0.0 && longitude == 0.0 {
+        return None;
+    }
+    let below_sea_level = field(Tag::GPSAltitudeRef).and_then(|value| value.get_uint(0)) == Some(1);
+    let altitude = field(Tag::GPSAltitude)
+        .and_then(rational_value)
+        .map(|altitude| if below_sea_level { -altitude } else { altitude });
+    Some(GpsPosition { latitude, longitude, altitude })
+}
+
+/// 画像ファイル（またはアーカイブ内のエントリ）の向きと撮影位置を読み取る
+///
+/// 一覧の読み込み時に使うため、ファイルを1度だけ開いてEXIFを1度だけ解析する（読めなければ空）。
+pub fn read_header_exif(path: &str) -> HeaderExif {
+    let result = match archive::split_entry_path(path) {
+        Some((archive_path, entry)) => archive::read_entry_prefix(Path::new(archive_path), entry, HEADER_LIMIT)
+            .map_err(MetadataError::from)
+            .and_then(|bytes| read_header_exif_from(&mut Cursor::new(bytes))),
+        None => File::open(path)
+            .map_err(MetadataError::from)
+            .and_then(|file| read_header_exif_from(&mut BufReader::new(file))),
+    };
+    result.unwrap_or_else(|e| {
+        log::debug!("No EXIF read from {}: {}", path, e);
+        HeaderExif::default()
+    })
+}
+
+/// 向きと撮影位置を読み取る
+///
+/// JPEGはEXIFのAPP1セグメントだけを、TIFF系は先頭部分だけを読み込む。
+/// TIFF系の向きは先頭部分が途中で切れていても読めるよう IFD0 から直接読む。
+pub fn read_header_exif_from<R: BufRead + Seek>(reader: &mut R) -> Result<HeaderExif, MetadataError> {
+    let format = image_format::sniff_reader(&mut *reader)?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    let (exif, ifd0_orientation) = match format {
+        Some(ImageFormat::Jpeg) => match read_jpeg_exif(reader)? {
+            Some(tiff) => (Reader::new().read_raw(tiff), None),
+            None => return Ok(HeaderExif::default()),
+        },
+        Some(ImageFormat::Tiff | ImageFormat::Raw) => {
+            let mut prefix = Vec::new();
+            (&mut *reader).take(HEADER_LIMIT).read_to_end(&mut prefix)?;
+            let orientation = orientation::read_orientation(&mut Cursor::new(&prefix)).ok().flatten();
+            (Reader::new().read_raw(prefix), orientation)
+        },
+        Some(ImageFormat::Png | ImageFormat::Webp) => (Reader::new().read_from_container(reader), None),
+        _ => return Ok(HeaderExif::default()),
+    };
+
+    Ok(match exif {
+        Ok(exif) => HeaderExif {
+            orientation: ifd0_orientation.or_else(|| orientation::exif_orientation(&exif)),
+            gps: gps_position(&exif),
+        },
+        Err(_) => HeaderExif { orientation: ifd0_orientation, gps: None },
+    })
+}
+
+/// ASCII型の値を文字列として取得（空の値は None）
+fn ascii_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Ascii(parts) => parts.first()
+            .map(|bytes| String::from_utf8_lossy(bytes).trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_string())
+            .filter(|text| !text.is_empty()),
+        _ => None,
+    }
+}
+
+/// 有理数型の値を実数として取得
+fn rational_value(value: &Value) -> Option<f64> {
+    let number = match value {
+        Value::Rational(parts) => parts.first()?.to_f64(),
+        Value::SRational(parts) => parts.first()?.to_f64(),
+        _ => return None,
+    };
+    Some(number).filter(|number| number.is_finite())
+}
+
+/// EXIFの日時（"YYYY:MM:DD HH:MM:SS"）をISO-8601形式に変換
+fn format_exif_date(date: &str, offset: Option<&str>) -> Option<String> {
+    let bytes = date.as_bytes();
+    if bytes.len() < 19 || bytes[4] != b':' || bytes[7] != b':' || bytes[13] != b':' || bytes[16] != b':' {
+        return None;
+    }
+    let digits = |range: std::ops::Range<usize>| date.get(range).filter(|part| part.bytes().all(|b| b.is_ascii_digit()));
+    let (year, month, day) = (digits(0..4)?, digits(5..7)?, digits(8..10)?);
+    let (hour, minute, second) = (digits(11..13)?, digits(14..16)?, digits(17..19)?);
+    // 日時が不明な場合はゼロで埋められている
+    if year == "0000" {
+        return None;
+    }
+
+    let offset = offset.filter(|offset| {
+        let offset = offset.as_bytes();
+        offset.len() == 6 && matches!(offset[0], b'+' | b'-') && offset[3] == b':'
+    });
+    Some(format!("{}-{}-{}T{}:{}:{}{}", year, month, day, hour, minute, second, offset.unwrap_or("")))
+}
+
+/// TIFFのタグからXMPとIPTCを取得
+fn tiff_blocks(exif: &Exif) -> MetadataBlocks {
+    let bytes = |tag: Tag| {
+        let field = exif.get_field(tag, In::PRIMARY)?;
+        match &field.value {
+            Value::Byte(bytes) | Value::Undefined(bytes, _) => Some(bytes.clone()),
+            // IPTCはLONG型で記録されることが多い
+            Value::Long(values) => Some(values.iter()
+                .flat_map(|value| if exif.little_endian() { value.to_le_bytes() } else { value.to_be_bytes() })
+                .collect()),
+            _ => None,
+        }
+    };
+    // TIFFにはPhotoshop画像リソースではなくIPTC-NAAレコードがそのまま格納される
+    (bytes(TAG_XMP), bytes(TAG_IPTC))
+}
+
+/// 指定サイズのブロックを読み込む（大きすぎるものは読み飛ばす）
+fn read_block<R: Read + Seek>(reader: &mut R, size: u64) -> Result<Option<Vec<u8>>, MetadataError> {
+    if size > MAX_BLOCK_SIZE {
+        reader.seek(SeekFrom::Current(size as i64))?;
+        return Ok(None);
+    }
+    let mut bytes = vec![0u8; size as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(Some(bytes))
+}
+
+/// JPEGの次のセグメントへ進む
+///
+/// マーカーとペイロードの長さを返し、リーダーはペイロードの先頭に置かれる。
+/// 画像データの開始や終端に達した場合は None。
+fn next_jpeg_segment<R: Read + Seek>(reader: &mut R) -> Result<Option<(u8, u64)>, MetadataError> {
+    loop {
+        let mut marker = [0u8; 2];
+        if reader.read_exact(&mut marker).is_err() || marker[0] != 0xFF {
+            return Ok(None);
+        }
+        match marker[1] {
+            // 埋め草
+            0xFF => {
+                reader.seek(SeekFrom::Current(-1))?;
+                continue;
+            },
+            // 長さを持たないマーカー
+            0x01 | 0xD0..=0xD7 => continue,
+            // 画像データの開始以降にメタデータはない
+            0xD9 | 0xDA => return Ok(None),
+            _ => {},
+        }
+
+        let mut length = [0u8; 2];
+        reader.read_exact(&mut length)?;
+        return Ok(Some((marker[1], u16::from_be_bytes(length).saturating_sub(2) as u64)));
+    }
+}
+
+/// JPEGのAPP1セグメントからEXIF（TIFF構造）を取得
+fn read_jpeg_exif<R: Read + Seek>(reader: &mut R) -> Result<Option<Vec<u8>>, MetadataError> {
+    reader.seek(SeekFrom::Start(2))?;
+    while let Some((marker, length)) = next_jpeg_segment(reader)? {
+        if marker != 0xE1 {
+            reader.seek(SeekFrom::Current(length as i64))?;
+            continue;
+        }
+        if let Some(tiff) = read_block(reader, length)?.and_then(|payload| payload.strip_prefix(EXIF_SIGNATURE).map(<[u8]>::to_vec)) {
+            return Ok(Some(tiff));
+        }
+    }
+    Ok(None)
+}
+
+/// JPEGのAPPセグメントからXMPとIPTC-NAAレコードを取得
+fn read_jpeg_blocks<R: Read + Seek>(reader: &mut R) -> Result<MetadataBlocks, MetadataError> {
+    let mut xmp = None;
+    let mut resources = None;
+    reader.seek(SeekFrom::Start(2))?;
+
+    while let Some((marker, length)) = next_jpeg_segment(reader)? {
+        match marker {
+            0xE1 if xmp.is_none() => {
+                if let Some(payload) = read_block(reader, length)? {
+                    xmp = payload.strip_prefix(XMP_SIGNATURE).map(<[u8]>::to_vec);
+                }
+            },
+            0xED => {
+                // 大きなリソースは複数のAPP13セグメントに分割される
+                if let Some(payload) = read_block(reader, length)? {
+                    if let Some(part) = payload.strip_prefix(PHOTOSHOP_SIGNATURE) {
+                        resources.get_or_insert_with(Vec::new).extend_from_slice(part);
+                    }
+                }
+            },
+            _ => {
+                reader.seek(SeekFrom::Current(length as i64))?;
+            },
+        }
+    }
+    let iptc = resources.as_deref().and_then(parse_photoshop_resources).map(<[u8]>::to_vec);
+    Ok((xmp, iptc))
+}
+
+/// PNGのiTXtチャンクからXMPを取得
+fn read_png_xmp<R: Read + Seek>(reader: &mut R) -> Result<Option<Vec<u8>>, MetadataError> {
+    reader.seek(SeekFrom::Start(8))?;
+    loop {
+        let mut header = [0u8; 8];
+        if reader.read_exact(&mut header).is_err() {
+            return Ok(None);
+        }
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+        match &header[4..8] {
+            b"IEND" => return Ok(None),
+            b"iTXt" => {
+                if let Some(data) = read_block(reader, length)? {
+                    // キーワード\0 圧縮フラグ 圧縮方式 言語\0 翻訳キーワード\0 本文
+                    if let Some(text) = data.strip_prefix(PNG_XMP_KEYWORD).and_then(|rest| rest.strip_prefix(b"\0\0")) {
+                        let text = text.get(1..).unwrap_or_default();
+                        let mut parts = text.splitn(3, |&b| b == 0);
+                        if let (Some(_), Some(_), Some(body)) = (parts.next(), parts.next(), parts.next()) {
+                            return Ok(Some(body.to_vec()));
+                        }
+                    }
+                }
+                reader.seek(SeekFrom::Current(4))?;
+            },
+            _ => {
+                reader.seek(SeekFrom::Current(length as i64 + 4))?;
+            },
+        }
+    }
+}
+
+/// WebPの "XMP " チャンクからXMPを取得
+fn read_webp_xmp<R: Read + Seek>(reader: &mut R) -> Result<Option<Vec<u8>>, MetadataError> {
+    reader.seek(SeekFrom::Start(12))?;
+    loop {
+        let mut header = [0u8; 8];
+        if reader.read_exact(&mut header).is_err() {
+            return Ok(None);
+        }
+        let length = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as u64;
+        if &header[0..4] == b"XMP " {
+            return read_block(reader, length);
+        }
+        // チャンクは偶数バイトに揃えられる
+        reader.seek(SeekFrom::Current((length + (length & 1)) as i64))?;
+    }
+}
+
+/// Photoshop画像リソースからIPTC-NAAレコードを取り出す
+fn parse_photoshop_resources(resources: &[u8]) -> Option<&[u8]> {
+    let mut position = 0;
+    while position + 8 <= resources.len() {
+        if &resources[position..position + 4] != b"8BIM" {
+            return None;
+        }
+        let id = u16::from_be_bytes([resources[position + 4], resources[position + 5]]);
+        // 名前はPascal文字列（長さのバイトを含めて偶数バイトに揃えられる）
+        let name_length = *resources.get(position + 6)? as usize;
+        let mut offset = position + 6 + name_length + 1;
+        offset += offset & 1;
+        let size = u32::from_be_bytes(resources.get(offset..offset + 4)?.try_into().ok()?) as usize;
+        let data = resources.get(offset + 4..offset + 4 + size)?;
+        if id == IPTC_RESOURCE_ID {
+            return Some(data);
+        }
+        position = offset + 4 + size + (size & 1);
+    }
+    None
+}
+
+/// IPTC-NAAレコードからテキスト項目を取り出す（アプリケーションレコード 2 のみ扱う）
+fn parse_iptc(records: &[u8]) -> TextFields {
+    let mut fields = TextFields::default();
+    let mut position = 0;
+    while position + 5 <= records.len() && records[position] == 0x1C {
+        let record = records[position + 1];
+        let dataset = records[position + 2];
+        let size = u16::from_be_bytes([records[position + 3], records[position + 4]]);
+        // 拡張長のデータセットは画像データなどのため打ち切る
+        if size & 0x8000 != 0 {
+            break;
+        }
+        let Some(data) = records.get(position + 5..position + 5 + size as usize) else { break };
+        position += 5 + size as usize;
+        if record != 2 {
+            continue;
+        }
+
+        let value = String::from_utf8_lossy(data).trim().to_string();
+        if value.is_empty() {
+            continue;
+        }
+        match dataset {
+            5 => { fields.title.get_or_insert(value); },
+            25 if !fields.keywords.contains(&value) => fields.keywords.push(value),
+            80 => { fields.creator.get_or_insert(value); },
+            116 => { fields.copyright.get_or_insert(value); },
+            120 => { fields.caption.get_or_insert(value); },
+            _ => {},
+        }
+    }
+    fields
+}
+
+/// XMPのプロパティ
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum XmpProperty {
+    Title,
+    Description,
+    Subject,
+    Creator,
+    Rights,
+    Rating,
+}
+
+impl XmpProperty {
+    /// 要素名・属性名からプロパティを判定（名前空間は慣例の接頭辞で判定する）
+    fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"dc:title" => Some(Self::Title),
+            b"dc:description" => Some(Self::Description),
+            b"dc:subject" => Some(Self::Subject),
+            b"dc:creator" => Some(Self::Creator),
+            b"dc:rights" => Some(Self::Rights),
+            b"xmp:Rating" | b"xap:Rating" => Some(Self::Rating),
+            _ => None,
+        }
+    }
+}
+
+/// XMPパケットからテキスト項目を取り出す
+///
+/// 言語別の代替値（rdf:Alt）は最初の値を使い、キーワード（rdf:Bag）はすべて集める。
+fn parse_xmp(packet: &[u8]) -> TextFields {
+    let mut fields = TextFields::default();
+    let text = String::from_utf8_lossy(packet);
+    let mut reader = quick_xml::Reader::from_str(&text);
+    // 開いている要素ごとの該当プロパティ
+    let mut stack: Vec<Option<XmpProperty>> = Vec::new();
+    let current = |stack: &[Option<XmpProperty>]| stack.iter().rev().find_map(|property| *property);
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(element)) => {
+                set_xmp_attributes(&mut fields, &element);
+                stack.push(XmpProperty::from_name(element.name().as_ref()));
+            },
+            Ok(Event::Empty(element)) => set_xmp_attributes(&mut fields, &element),
+            Ok(Event::End(_)) => {
+                stack.pop();
+            },
+            Ok(Event::Text(content)) => {
+                if let (Some(property), Ok(value)) = (current(&stack), content.unescape()) {
+                    set_xmp_property(&mut fields, property, &value);
+                }
+            },
+            Ok(Event::CData(content)) => {
+                if let Some(property) = current(&stack) {
+                    set_xmp_property(&mut fields, property, &String::from_utf8_lossy(&content));
+                }
+            },
+            Ok(Event::Eof) => break,
+            Ok(_) => {},
+            Err(e) => {
+                log::debug!("Stopping at malformed XMP packet: {}", e);
+                break;
+            },
+        }
+    }
+    fields
+}
+
+/// 属性として記録されたプロパティを取り出す（rdf:Description の省略形式）
+fn set_xmp_attributes(fields: &mut TextFields, element: &BytesStart) {
+    for attribute in element.attributes().flatten() {
+        if let Some(property) = XmpProperty::from_name(attribute.key.as_ref()) {
+            if let Ok(value) = attribute.unescape_value() {
+                set_xmp_property(fields, property, &value);
+            }
+        }
+    }
+}
+
+/// プロパティの値を設定（最初に見つかった値を使い、キーワードは集める）
+fn set_xmp_property(fields: &mut TextFields, property: XmpProperty, value: &str) {
+    let value = value.trim().to_string();
+    if value.is_empty() {
+        return;
+    }
+    match property {
+        XmpProperty::Title => { fields.title.get_or_insert(value); },
+        XmpProperty::Description => { fields.caption.get_or_insert(value); },
+        XmpProperty::Subject if !fields.keywords.contains(&value) => fields.keywords.push(value),
+        XmpProperty::Creator => { fields.creator.get_or_insert(value); },
+        XmpProperty::Rights => { fields.copyright.get_or_insert(value); },
+        XmpProperty::Rating if fields.rating.is_none() => {
+            fields.rating = value.parse::<f64>().ok()
+                .filter(|rating| (-1.0..=5.0).contains(rating))
+                .map(|rating| rating.round() as i8);
+        },
+        _ => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::experimental::Writer;
+    use exif::{Field, Rational};
+    use image::codecs::jpeg::JpegEncoder;
+    use image::ExtendedColorType;
+
+    /// JPEGのセグメントを作成
+    fn segment(marker: u8, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0xFF, marker];
+        bytes.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    /// EXIF・XMP・IPTCを埋め込んだJPEGを作成
+    fn sample_jpeg() -> Vec<u8> {
+        let ascii = |text: &str| Value::Ascii(vec![text.as_bytes().to_vec()]);
+        let dms = |degrees: u32, minutes: u32, seconds: u32| Value::Rational(vec![
+            Rational { num: degrees, denom: 1 },
+            Rational { num: minutes, denom: 1 },
+            Rational { num: seconds, denom: 1 },
+        ]);
+        let fields = [
+            Field { tag: Tag::Make, ifd_num: In::PRIMARY, value: ascii("Canon") },
+            Field { tag: Tag::Model, ifd_num: In::PRIMARY, value: ascii("EOS R5") },
+            Field { tag: Tag::Orientation, ifd_num: In::PRIMARY, value: Value::Short(vec![6]) },
+            Field { tag: Tag::DateTimeOriginal, ifd_num: In::PRIMARY, value: ascii("2024:06:01 10:20:30") },
+            Field { tag: Tag::OffsetTimeOriginal, ifd_num: In::PRIMARY, value: ascii("+09:00") },
+            Field { tag: Tag::ExposureTime, ifd_num: In::PRIMARY, value: Value::Rational(vec![Rational { num: 1, denom: 250 }]) },
+            Field { tag: Tag::PhotographicSensitivity, ifd_num: In::PRIMARY, value: Value::Short(vec![400]) },
+            Field { tag: Tag::GPSLatitudeRef, ifd_num: In::PRIMARY, value: ascii("N") },
+            Field { tag: Tag::GPSLatitude, ifd_num: In::PRIMARY, value: dms(35, 39, 36) },
+            Field { tag: Tag::GPSLongitudeRef, ifd_num: In::PRIMARY, value: ascii("W") },
+            Field { tag: Tag::GPSLongitude, ifd_num: In::PRIMARY, value: dms(139, 45, 0) },
+            Field { tag: Tag::ImageDescription, ifd_num: In::PRIMARY, value: ascii("EXIF title") },
+        ];
+        let mut writer = Writer::new();
+        for field in &fields {
+            writer.push_field(field);
+        }
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        let exif = [&b"Exif\0\0"[..], tiff.get_ref()].concat();
+
+        let xmp = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+            <rdf:Description xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:xmp="http://ns.adobe.com/xap/1.0/" xmp:Rating="4">
+              <dc:title><rdf:Alt><rdf:li xml:lang="x-default">Harbour at dawn</rdf:li></rdf:Alt></dc:title>
+              <dc:subject><rdf:Bag><rdf:li>harbour</rdf:li><rdf:li>sunrise</rdf:li></rdf:Bag></dc:subject>
+            </rdf:Description></rdf:RDF></x:xmpmeta>"#;
+
+        let dataset = |number: u8, value: &str| {
+            [&[0x1C, 2, number][..], &(value.len() as u16).to_be_bytes(), value.as_bytes()].concat()
+        };
+        let iptc = [
+            dataset(5, "IPTC title"),
+            dataset(25, "sunrise"),
+            dataset(25, "boats"),
+            dataset(80, "A. Photographer"),
+            dataset(120, "Boats moored in the harbour"),
+        ].concat();
+        let mut resources = PHOTOSHOP_SIGNATURE.to_vec();
+        resources.extend_from_slice(b"8BIM\x04\x04\0\0");
+        resources.extend_from_slice(&(iptc.len() as u32).to_be_bytes());
+        resources.extend_from_slice(&iptc);
+        resources.extend(std::iter::repeat_n(0, iptc.len() & 1));
+
+        let mut image = Vec::new();
+        JpegEncoder::new(&mut image).encode(&[128u8; 8 * 8 * 3], 8, 8, ExtendedColorType::Rgb8).unwrap();
+
+        let mut jpeg = vec![0xFF, 0xD8];
+        jpeg.extend(segment(0xE1, &exif));
+        jpeg.extend(segment(0xE1, &[XMP_SIGNATURE, &xmp[..]].concat()));
+        jpeg.extend(segment(0xED, &resources));
+        jpeg.extend_from_slice(&image[2..]);
+        jpeg
+    }
+
+    #[test]
+    fn test_reads_exif_iptc_and_xmp() {
+        let metadata = read_extended_metadata(&mut Cursor::new(sample_jpeg())).unwrap();
+
+        let camera = metadata.camera.as_ref().unwrap();
+        assert_eq!(camera.make.as_deref(), Some("Canon"));
+        assert_eq!(camera.model.as_deref(), Some("EOS R5"));
+        let exposure = metadata.exposure.as_ref().unwrap();
+        assert_eq!(exposure.exposure_time, Some(0.004));
+        assert_eq!(exposure.iso, Some(400));
+        assert_eq!(metadata.orientation, Some(6));
+        assert_eq!(metadata.date_taken.as_deref(), Some("2024-06-01T10:20:30+09:00"));
+
+        // 度分秒は符号付きの十進に変換する
+        let gps = metadata.gps.unwrap();
+        assert!((gps.latitude - 35.66).abs() < 1e-9);
+        assert!((gps.longitude + 139.75).abs() < 1e-9);
+
+        // XMP > IPTC > EXIF の順に優先し、キーワードは重複を除いて統合する
+        assert_eq!(metadata.title.as_deref(), Some("Harbour at dawn"));
+        assert_eq!(metadata.caption.as_deref(), Some("Boats moored in the harbour"));
+        assert_eq!(metadata.creator.as_deref(), Some("A. Photographer"));
+        assert_eq!(metadata.keywords, vec!["harbour", "sunrise", "boats"]);
+        assert_eq!(metadata.rating, Some(4));
+
+        // メタデータのない画像は空になる
+        let mut plain = Vec::new();
+        JpegEncoder::new(&mut plain).encode(&[0u8; 3], 1, 1, ExtendedColorType::Rgb8).unwrap();
+        assert!(read_extended_metadata(&mut Cursor::new(plain)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_header_exif_reads_orientation_and_gps_from_app1() {
+        let jpeg = sample_jpeg();
+        let header = read_header_exif_from(&mut Cursor::new(&jpeg)).unwrap();
+        assert_eq!(header.orientation, Some(6));
+        let gps = header.gps.unwrap();
+        assert!((gps.latitude - 35.66).abs() < 1e-9);
+        assert!((gps.longitude + 139.75).abs() < 1e-9);
+
+        // 画像データが壊れていてもEXIFのセグメントだけで読める
+        let app1_end = 2 + 4 + u16::from_be_bytes([jpeg[4], jpeg[5]]) as usize - 2;
+        let truncated = [&jpeg[..app1_end], &[0xFF, 0xDA][..]].concat();
+        assert_eq!(read_header_exif_from(&mut Cursor::new(truncated)).unwrap(), header);
+
+        let mut plain = Vec::new();
+        JpegEncoder::new(&mut plain).encode(&[0u8; 3], 1, 1, ExtendedColorType::Rgb8).unwrap();
+        assert_eq!(read_header_exif_from(&mut Cursor::new(plain)).unwrap(), HeaderExif::default());
+    }
+}
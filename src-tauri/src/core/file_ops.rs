@@ -0,0 +1,413 @@
+// core/file_ops.rs
+// ファイル操作 - 重複画像の整理（1つだけ残して削除・移動）を、実行前に確認できる形で行う
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+
+use crate::core::archive;
+use crate::core::duplicates::{self, DuplicateGroup};
+
+/// 重複グループから残す画像の選び方
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeepStrategy {
+    /// コレクション内で最初の画像
+    #[default]
+    First,
+    /// 更新日時が最も古い画像
+    Oldest,
+    /// 更新日時が最も新しい画像
+    Newest,
+    /// パスが最も短い画像
+    ShortestPath,
+}
+
+/// 残さない画像の扱い
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Disposal {
+    /// 削除する
+    Delete,
+    /// 指定したディレクトリへ移動する
+    MoveTo { directory: String },
+}
+
+/// 画像に対する操作
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FileAction {
+    Keep,
+    Delete,
+    Move { destination: String },
+}
+
+/// 操作の状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationStatus {
+    /// 予定（ドライランでは実行しない）
+    Planned,
+    /// 実行済み
+    Done,
+    /// 安全のため実行しなかった
+    Skipped,
+    /// 実行に失敗した
+    Failed,
+}
+
+/// 1つの画像に対する操作
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileOperation {
+    pub path: String,
+    pub action: FileAction,
+    /// ファイルサイズ（バイト）
+    pub size: u64,
+    pub status: OperationStatus,
+    /// 実行しなかった理由やエラー
+    pub message: Option<String>,
+}
+
+/// ファイル操作の結果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FileOpsReport {
+    /// ドライラン（何も変更していない）かどうか
+    pub dry_run: bool,
+    pub operations: Vec<FileOperation>,
+    /// 空く（ドライランでは空く予定の）サイズ（バイト）
+    pub reclaimed_bytes: u64,
+}
+
+impl FileOpsReport {
+    /// 実際に削除・移動されたパス
+    pub fn removed_paths(&self) -> Vec<String> {
+        self.operations.iter()
+            .filter(|op| op.status == OperationStatus::Done && op.action != FileAction::Keep)
+            .map(|op| op.path.clone())
+            .collect()
+    }
+}
+
+/// 各重複グループで1つだけ残し、残りを削除または移動する
+///
+/// 検出後にファイルが変更されている場合に備えて、操作前に内容のハッシュを確かめる。
+/// 残す画像の内容が変わっていればそのグループは何もしない。
+/// アーカイブ内の画像は変更できないため残す。
+/// シンボリックリンクやハードリンク、重なったルートで同じファイルが別のパスとして
+/// 含まれている場合は、実体を消さないようにそのパスも残す。
+/// シンボリックリンク自体は削除・移動せず、リンクが切れないようにリンク先も残す。
+/// 空くサイズには、ほかにハードリンクが残っていて実際には空かないファイルを含めない。
+pub fn keep_one(groups: &[DuplicateGroup], strategy: KeepStrategy, disposal: &Disposal, dry_run: bool) -> FileOpsReport {
+    let mut report = FileOpsReport { dry_run, ..FileOpsReport::default() };
+    let mut destinations = HashSet::new();
+
+    for group in groups {
+        let Some(keep) = choose_keep(&group.paths, strategy) else { continue };
+        let unchanged = |path: &str| duplicates::hash_path(path).is_ok_and(|hash| hash == group.hash);
+        let keep_verified = unchanged(keep);
+        // 操作するファイルの実体（残す画像と、シンボリックリンクのリンク先を含む）
+        let mut handled: HashSet<FileIdentity> = group.paths.iter()
+            .filter(|path| *path == keep || is_symlink(path))
+            .flat_map(|path| [file_identity(path), target_identity(path)])
+            .flatten()
+            .collect();
+
+        for path in &group.paths {
+            let mut operation = FileOperation {
+                path: path.clone(),
+                action: FileAction::Keep,
+                size: group.file_size,
+                status: if dry_run { OperationStatus::Planned } else { OperationStatus::Done },
+                message: None,
+            };
+            if path != keep {
+                operation.action = match disposal {
+                    Disposal::Delete => FileAction::Delete,
+                    Disposal::MoveTo { directory } => FileAction::Move {
+                        destination: unique_destination(Path::new(directory), path, &destinations)
+                            .to_string_lossy()
+                            .into_owned(),
+                    },
+                };
+                let identity = file_identity(path);
+                let skip_reason = if !keep_verified {
+                    Some(format!("Kept file changed since the scan: {}", keep))
+                } else if archive::split_entry_path(path).is_some() {
+                    Some("Images inside archives cannot be modified".to_string())
+                } else if identity.is_none() || !unchanged(path) {
+                    Some("File changed since the scan".to_string())
+                } else if is_symlink(path) {
+                    Some("Symbolic links are kept together with the linked file".to_string())
+                } else if identity.as_ref().is_some_and(|identity| handled.contains(identity)) {
+                    Some("Same file as another path in the group (symlink or hardlink)".to_string())
+                } else {
+                    None
+                };
+
+                if let Some(reason) = skip_reason {
+                    operation.status = OperationStatus::Skipped;
+                    operation.message = Some(reason);
+                } else {
+                    handled.extend(identity);
+                    if let FileAction::Move { destination } = &operation.action {
+                        destinations.insert(PathBuf::from(destination));
+                    }
+                    let reclaimable = is_last_link(path);
+                    if !dry_run {
+                        if let Err(e) = apply(path, &operation.action) {
+                            operation.status = OperationStatus::Failed;
+                            let verb = if matches!(operation.action, FileAction::Delete) { "delete" } else { "move" };
+                            operation.message = Some(format!("Failed to {} {}: {}", verb, path, e));
+                        }
+                    }
+                    if reclaimable && operation.status != OperationStatus::Failed {
+                        report.reclaimed_bytes += group.file_size;
+                    }
+                }
+            }
+            report.operations.push(operation);
+        }
+    }
+    report
+}
+
+/// 残す画像を選ぶ（アーカイブ内の画像は削除できないため、あればその中から選ぶ）
+fn choose_keep(paths: &[String], strategy: KeepStrategy) -> Option<&str> {
+    let archived: Vec<&String> = paths.iter()
+        .filter(|path| archive::split_entry_path(path).is_some())
+        .collect();
+    let candidates = if archived.is_empty() { paths.iter().collect() } else { archived };
+    let modified = |path: &str| fs::metadata(archive::physical_path(path))
+        .and_then(|metadata| metadata.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+
+    let keep = match strategy {
+        KeepStrategy::First => candidates.first().copied(),
+        KeepStrategy::Oldest => candidates.into_iter().min_by_key(|path| modified(path)),
+        KeepStrategy::Newest => candidates.into_iter().max_by_key(|path| modified(path)),
+        KeepStrategy::ShortestPath => candidates.into_iter().min_by_key(|path| path.len()),
+    };
+    keep.map(String::as_str)
+}
+
+/// ファイルの実体を識別する値（デバイス番号, iノード番号）
+#[cfg(unix)]
+type FileIdentity = (u64, u64);
+
+/// ファイルの実体を識別する値（リンクを解決した絶対パス）
+#[cfg(not(unix))]
+type FileIdentity = PathBuf;
+
+/// パスそのものの実体を取得（途中のディレクトリのリンクは解決するが、末尾のシンボリックリンクは解決しない）
+///
+/// 削除・移動の対象になるのはこの実体。ハードリンクは同じ値になる。
+/// アーカイブ内の画像や存在しないファイルは None。
+#[cfg(unix)]
+fn file_identity(path: &str) -> Option<FileIdentity> {
+    use std::os::unix::fs::MetadataExt;
+    if archive::split_entry_path(path).is_some() {
+        return None;
+    }
+    fs::symlink_metadata(path).ok().map(|metadata| (metadata.dev(), metadata.ino()))
+}
+
+/// パスそのものの実体を取得（末尾のシンボリックリンクは解決しない）
+///
+/// アーカイブ内の画像や存在しないファイルは None。
+#[cfg(not(unix))]
+fn file_identity(path: &str) -> Option<FileIdentity> {
+    if archive::split_entry_path(path).is_some() {
+        return None;
+    }
+    let path = Path::new(path);
+    Some(fs::canonicalize(path.parent()?).ok()?.join(path.file_name()?))
+}
+
+/// シンボリックリンクを解決した実体を取得
+#[cfg(unix)]
+fn target_identity(path: &str) -> Option<FileIdentity> {
+    use std::os::unix::fs::MetadataExt;
+    if archive::split_entry_path(path).is_some() {
+        return None;
+    }
+    fs::metadata(path).ok().map(|metadata| (metadata.dev(), metadata.ino()))
+}
+
+/// シンボリックリンクを解決した実体を取得
+#[cfg(not(unix))]
+fn target_identity(path: &str) -> Option<FileIdentity> {
+    if archive::split_entry_path(path).is_some() {
+        return None;
+    }
+    fs::canonicalize(path).ok()
+}
+
+/// パスがシンボリックリンクかどうか
+fn is_symlink(path: &str) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink())
+}
+
+/// 削除・移動すると実際に容量が空くか（ほかのハードリンクが残らないか）
+#[cfg(unix)]
+fn is_last_link(path: &str) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.nlink() <= 1)
+}
+
+/// 削除・移動すると実際に容量が空くか
+#[cfg(not(unix))]
+fn is_last_link(_path: &str) -> bool {
+    true
+}
+
+/// 移動先のパス（同名のファイルがあれば「名前 (n).拡張子」とする）
+fn unique_destination(directory: &Path, path: &str, reserved: &HashSet<PathBuf>) -> PathBuf {
+    let source = Path::new(path);
+    let file_name = source.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let stem = source.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let extension = source.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+
+    let mut candidate = directory.join(&file_name);
+    let mut counter = 1;
+    while candidate.exists() || reserved.contains(&candidate) {
+        candidate = directory.join(format!("{} ({}){}", stem, counter, extension));
+        counter += 1;
+    }
+    candidate
+}
+
+/// 操作を実行
+fn apply(path: &str, action: &FileAction) -> io::Result<()> {
+    match action {
+        FileAction::Keep => Ok(()),
+        FileAction::Delete => fs::remove_file(path),
+        FileAction::Move { destination } => {
+            let destination = Path::new(destination);
+            if let Some(parent) = destination.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            // 別のファイルシステムへはリネームできないため、コピーしてから削除する
+            match fs::rename(path, destination) {
+                Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                    fs::copy(path, destination)?;
+                    fs::remove_file(path)
+                },
+                result => result,
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keep_one_dry_run_and_apply() {
+        let dir = std::env::temp_dir().join(format!("image-viewer-file-ops-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested/deeper")).unwrap();
+        let paths: Vec<String> = ["nested/deeper/a.jpg", "a.jpg", "nested/a.jpg"].iter()
+            .map(|name| {
+                let path = dir.join(name);
+                fs::write(&path, b"duplicate").unwrap();
+                path.to_string_lossy().into_owned()
+            })
+            .collect();
+        let group = DuplicateGroup {
+            hash: duplicates::hash_path(&paths[0]).unwrap(),
+            file_size: 9,
+            paths: paths.clone(),
+            wasted_bytes: 18,
+        };
+
+        // ドライランでは何も変更しない
+        let planned = keep_one(std::slice::from_ref(&group), KeepStrategy::ShortestPath, &Disposal::Delete, true);
+        assert_eq!(planned.reclaimed_bytes, 18);
+        assert!(planned.removed_paths().is_empty());
+        assert_eq!(planned.operations[1].action, FileAction::Keep);
+        assert!(planned.operations.iter().all(|op| op.status == OperationStatus::Planned));
+        assert!(paths.iter().all(|path| Path::new(path).exists()));
+
+        // 検出後に変更されたファイルは残す
+        fs::write(&paths[2], b"edited!!!").unwrap();
+        let trash = dir.join("trash");
+        let disposal = Disposal::MoveTo { directory: trash.to_string_lossy().into_owned() };
+        let applied = keep_one(&[group], KeepStrategy::ShortestPath, &disposal, false);
+        assert_eq!(applied.removed_paths(), vec![paths[0].clone()]);
+        assert_eq!(applied.operations[2].status, OperationStatus::Skipped);
+        assert_eq!(applied.reclaimed_bytes, 9);
+        assert!(!Path::new(&paths[0]).exists());
+        assert!(Path::new(&paths[1]).exists() && Path::new(&paths[2]).exists());
+        assert!(trash.join("a.jpg").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_keep_one_never_removes_linked_aliases() {
+        let dir = std::env::temp_dir().join(format!("image-viewer-file-ops-links-{}", std::process::id()));
+        fs::create_dir_all(dir.join("photos")).unwrap();
+        let original = dir.join("photos/a.jpg");
+        fs::write(&original, b"duplicate").unwrap();
+        fs::write(dir.join("photos/copy.jpg"), b"duplicate").unwrap();
+        // シンボリックリンクしたディレクトリと、ハードリンク経由で同じファイルが見える
+        std::os::unix::fs::symlink(dir.join("photos"), dir.join("linked")).unwrap();
+        fs::hard_link(&original, dir.join("hardlink.jpg")).unwrap();
+
+        let paths: Vec<String> = ["photos/a.jpg", "linked/a.jpg", "hardlink.jpg", "photos/copy.jpg"].iter()
+            .map(|name| dir.join(name).to_string_lossy().into_owned())
+            .collect();
+        let group = DuplicateGroup {
+            hash: duplicates::hash_path(&paths[0]).unwrap(),
+            file_size: 9,
+            paths: paths.clone(),
+            wasted_bytes: 27,
+        };
+        let report = keep_one(&[group], KeepStrategy::First, &Disposal::Delete, false);
+
+        assert_eq!(report.removed_paths(), vec![paths[3].clone()]);
+        assert_eq!(report.operations[1].status, OperationStatus::Skipped);
+        assert_eq!(report.operations[2].status, OperationStatus::Skipped);
+        assert_eq!(report.reclaimed_bytes, 9);
+        assert_eq!(fs::read(&original).unwrap(), b"duplicate");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_keep_one_keeps_symlink_listed_before_its_target() {
+        let dir = std::env::temp_dir().join(format!("image-viewer-file-ops-symlink-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("copy.jpg"), b"duplicate").unwrap();
+        fs::write(dir.join("a.jpg"), b"duplicate").unwrap();
+        fs::write(dir.join("b.jpg"), b"duplicate").unwrap();
+        std::os::unix::fs::symlink(dir.join("a.jpg"), dir.join("link.jpg")).unwrap();
+        // グループ外のハードリンクが残るファイルは削除しても容量が空かない
+        fs::hard_link(dir.join("b.jpg"), dir.join("outside.jpg")).unwrap();
+
+        let paths: Vec<String> = ["copy.jpg", "link.jpg", "a.jpg", "b.jpg"].iter()
+            .map(|name| dir.join(name).to_string_lossy().into_owned())
+            .collect();
+        let group = DuplicateGroup {
+            hash: duplicates::hash_path(&paths[0]).unwrap(),
+            file_size: 9,
+            paths: paths.clone(),
+            wasted_bytes: 27,
+        };
+        let report = keep_one(&[group], KeepStrategy::First, &Disposal::Delete, false);
+
+        // リンクもリンク先も残し、リンクが切れないようにする
+        assert_eq!(report.operations[1].status, OperationStatus::Skipped);
+        assert_eq!(report.operations[2].status, OperationStatus::Skipped);
+        assert_eq!(fs::read(dir.join("link.jpg")).unwrap(), b"duplicate");
+        assert_eq!(report.removed_paths(), vec![paths[3].clone()]);
+        assert_eq!(report.reclaimed_bytes, 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
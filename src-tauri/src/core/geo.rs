@@ -0,0 +1,332 @@
+// core/geo.rs
+// 位置情報 - 撮影位置による絞り込み（範囲・半径）、地図表示用のクラスタリング、同梱データによるオフラインの逆ジオコーディング
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+use crate::core::extended_metadata::GpsPosition;
+
+/// 地球の平均半径（km）
+const EARTH_RADIUS_KM: f64 = 6371.0088;
+
+/// 緯度1度あたりの距離（km）
+const KM_PER_DEGREE: f64 = 111.32;
+
+/// クラスタリングのセルの一辺の既定値（km）
+pub const DEFAULT_CLUSTER_CELL_KM: f64 = 25.0;
+
+/// 最寄りの地名を探す際に最初に調べる範囲（km、見つからなければ広げていく）
+const INITIAL_SEARCH_KM: f64 = 100.0;
+
+/// 地球上の2点間の最大距離（km、半周）
+const MAX_DISTANCE_KM: f64 = std::f64::consts::PI * EARTH_RADIUS_KM;
+
+/// 同梱の地名データ（name,country,latitude,longitude）
+///
+/// 日本の都道府県庁所在地など約70件と、世界の主要都市約180件だけを含む。
+/// 日本以外では最寄りの地名が数百km離れていることも多いため、結果の距離を見て表示を決める。
+/// より詳しい地名が必要な場合は、アプリのデータディレクトリに同じ形式の places.csv
+/// （GeoNames の cities15000 などから作成したもの）を置くと同梱データの代わりに使われる。
+const BUNDLED_PLACES: &str = include_str!("../../data/places.csv");
+
+/// 2点間の大圏距離（km、ハーバーサイン公式）
+pub fn distance_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+}
+
+/// 緯度経度の範囲（west > east の場合は日付変更線をまたぐ範囲として扱う）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoBounds {
+    pub south: f64,
+    pub west: f64,
+    pub north: f64,
+    pub east: f64,
+}
+
+impl GeoBounds {
+    /// 位置が範囲に含まれるかどうか
+    pub fn contains(&self, position: &GpsPosition) -> bool {
+        if position.latitude < self.south || position.latitude > self.north {
+            return false;
+        }
+        if self.west <= self.east {
+            (self.west..=self.east).contains(&position.longitude)
+        } else {
+            position.longitude >= self.west || position.longitude <= self.east
+        }
+    }
+
+    /// 1点だけを含む範囲
+    fn from_position(position: &GpsPosition) -> Self {
+        Self {
+            south: position.latitude,
+            west: position.longitude,
+            north: position.latitude,
+            east: position.longitude,
+        }
+    }
+
+    /// 位置を含むよう範囲を広げる
+    fn extend(&mut self, position: &GpsPosition) {
+        self.south = self.south.min(position.latitude);
+        self.north = self.north.max(position.latitude);
+        self.west = self.west.min(position.longitude);
+        self.east = self.east.max(position.longitude);
+    }
+}
+
+/// 中心からの半径による範囲
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoRadius {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// 半径（km）
+    pub radius_km: f64,
+}
+
+impl GeoRadius {
+    /// 位置が範囲に含まれるかどうか
+    pub fn contains(&self, position: &GpsPosition) -> bool {
+        distance_km((self.latitude, self.longitude), (position.latitude, position.longitude)) <= self.radius_km
+    }
+}
+
+/// 地名
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Place {
+    pub name: String,
+    /// ISO 3166-1 alpha-2 の国コード
+    pub country: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// 逆ジオコーディングの結果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlaceMatch {
+    pub name: String,
+    pub country: String,
+    /// 地名の代表点からの距離（km）。地名データの密度によっては大きく離れることがある
+    pub distance_km: f64,
+}
+
+/// 地名データ（オフラインの逆ジオコーディングに使う）
+#[derive(Debug, Clone, Default)]
+pub struct PlaceIndex {
+    /// 緯度順に並べた地名
+    places: Vec<Place>,
+}
+
+impl PlaceIndex {
+    /// 同梱の地名データを読み込む
+    pub fn bundled() -> Self {
+        Self::parse(BUNDLED_PLACES)
+    }
+
+    /// 地名データのファイルを読み込む（同梱データと同じCSV形式）
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read place data {}: {}", path.display(), e))?;
+        let index = Self::parse(&text);
+        if index.is_empty() {
+            return Err(format!("No places found in {}", path.display()));
+        }
+        Ok(index)
+    }
+
+    /// CSV（name,country,latitude,longitude）を解析（解析できない行は読み飛ばす）
+    pub fn parse(text: &str) -> Self {
+        let mut places: Vec<Place> = text.lines()
+            .filter_map(|line| {
+                // 地名にカンマを含む場合に備えて後ろから分割する
+                let mut fields = line.rsplitn(4, ',');
+                let longitude = fields.next()?.trim().parse::<f64>().ok()?;
+                let latitude = fields.next()?.trim().parse::<f64>().ok()?;
+                let country = fields.next()?.trim().to_string();
+                let name = fields.next()?.trim().trim_matches('"').to_string();
+                let valid = (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude);
+                (valid && !name.is_empty()).then_some(Place { name, country, latitude, longitude })
+            })
+            .collect();
+        places.sort_by(|a, b| a.latitude.total_cmp(&b.latitude));
+        Self { places }
+    }
+
+    /// 地名の数
+    pub fn len(&self) -> usize {
+        self.places.len()
+    }
+
+    /// 地名がないかどうか
+    pub fn is_empty(&self) -> bool {
+        self.places.is_empty()
+    }
+
+    /// 最も近い地名を距離とともに探す（地名データが空の場合のみ None）
+    pub fn nearest(&self, latitude: f64, longitude: f64) -> Option<PlaceMatch> {
+        // 近い範囲から探し、見つからなければ範囲を広げる（範囲内の最寄りは全体でも最寄り）
+        let mut search_km = INITIAL_SEARCH_KM;
+        loop {
+            if let Some(place) = self.nearest_within(latitude, longitude, search_km) {
+                return Some(place);
+            }
+            if search_km >= MAX_DISTANCE_KM {
+                return None;
+            }
+            search_km = (search_km * 4.0).min(MAX_DISTANCE_KM);
+        }
+    }
+
+    /// 最も近い地名を探す（max_distance_km より遠い地名しかなければ None）
+    pub fn nearest_within(&self, latitude: f64, longitude: f64, max_distance_km: f64) -> Option<PlaceMatch> {
+        // 緯度の差だけで範囲外になる地名は距離を計算しない
+        let latitude_window = max_distance_km / KM_PER_DEGREE;
+        let start = self.places.partition_point(|place| place.latitude < latitude - latitude_window);
+        self.places[start..].iter()
+            .take_while(|place| place.latitude <= latitude + latitude_window)
+            .map(|place| (place, distance_km((latitude, longitude), (place.latitude, place.longitude))))
+            .filter(|(_, distance)| *distance <= max_distance_km)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(place, distance)| PlaceMatch {
+                name: place.name.clone(),
+                country: place.country.clone(),
+                distance_km: distance,
+            })
+    }
+}
+
+/// 地図表示用の位置グループ
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LocationCluster {
+    /// 重心の緯度
+    pub latitude: f64,
+    /// 重心の経度
+    pub longitude: f64,
+    /// 画像数
+    pub count: usize,
+    /// 含まれる画像の範囲
+    pub bounds: GeoBounds,
+    /// 画像のパス
+    pub paths: Vec<String>,
+    /// 重心に最も近い地名
+    pub place: Option<PlaceMatch>,
+}
+
+/// 位置をグリッドでまとめる（セルの一辺はおよそ cell_km、画像数の多い順）
+///
+/// 経度方向のセル幅は緯度に応じて広げ、高緯度でもセルの大きさがおよそ揃うようにする。
+pub fn cluster_positions<'a, I>(positions: I, cell_km: f64) -> Vec<LocationCluster>
+where
+    I: IntoIterator<Item = (&'a str, GpsPosition)>,
+{
+    let cell_degrees = (cell_km / KM_PER_DEGREE).clamp(1e-6, 180.0);
+    let mut cells: HashMap<(i64, i64), Vec<(&str, GpsPosition)>> = HashMap::new();
+    for (path, position) in positions {
+        let row = (position.latitude / cell_degrees).floor() as i64;
+        let row_center = (row as f64 + 0.5) * cell_degrees;
+        let width = (cell_degrees / row_center.to_radians().cos().max(0.01)).min(360.0);
+        let column = (position.longitude / width).floor() as i64;
+        cells.entry((row, column)).or_default().push((path, position));
+    }
+
+    let mut clusters: Vec<LocationCluster> = cells.into_values()
+        .map(|members| {
+            let count = members.len();
+            let mut bounds = GeoBounds::from_position(&members[0].1);
+            for (_, position) in &members[1..] {
+                bounds.extend(position);
+            }
+            let (latitude, longitude) = centroid(members.iter().map(|(_, position)| position));
+            LocationCluster {
+                latitude,
+                longitude,
+                count,
+                bounds,
+                paths: members.iter().map(|(path, _)| path.to_string()).collect(),
+                place: None,
+            }
+        })
+        .collect();
+    clusters.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.paths.cmp(&b.paths)));
+    clusters
+}
+
+/// 位置の重心（緯度, 経度）
+///
+/// 経度をそのまま平均すると日付変更線をまたぐ位置（179° と -179°）が0°付近になるため、
+/// 単位球上のベクトルの平均から求める。
+fn centroid<'a>(positions: impl Iterator<Item = &'a GpsPosition>) -> (f64, f64) {
+    let (mut x, mut y, mut z) = (0.0, 0.0, 0.0);
+    for position in positions {
+        let (latitude, longitude) = (position.latitude.to_radians(), position.longitude.to_radians());
+        x += latitude.cos() * longitude.cos();
+        y += latitude.cos() * longitude.sin();
+        z += latitude.sin();
+    }
+    let latitude = z.atan2(x.hypot(y)).to_degrees();
+    let longitude = y.atan2(x).to_degrees();
+    (latitude, longitude)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(latitude: f64, longitude: f64) -> GpsPosition {
+        GpsPosition { latitude, longitude, altitude: None }
+    }
+
+    #[test]
+    fn test_filters_clusters_and_places() {
+        // 東京駅〜大阪駅はおよそ400km
+        let tokyo = position(35.6812, 139.7671);
+        let osaka = position(34.7025, 135.4959);
+        let distance = distance_km((tokyo.latitude, tokyo.longitude), (osaka.latitude, osaka.longitude));
+        assert!((distance - 403.0).abs() < 5.0, "distance: {}", distance);
+
+        let radius = GeoRadius { latitude: 35.68, longitude: 139.76, radius_km: 10.0 };
+        assert!(radius.contains(&tokyo));
+        assert!(!radius.contains(&osaka));
+
+        // 日付変更線をまたぐ範囲
+        let pacific = GeoBounds { south: -30.0, west: 170.0, north: 30.0, east: -170.0 };
+        assert!(pacific.contains(&position(-18.1, 178.4)));
+        assert!(pacific.contains(&position(0.0, -175.0)));
+        assert!(!pacific.contains(&tokyo));
+
+        let clusters = cluster_positions(
+            vec![("a.jpg", tokyo), ("b.jpg", position(35.69, 139.70)), ("c.jpg", osaka)],
+            50.0,
+        );
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].count, 2);
+        assert_eq!(clusters[0].paths, vec!["a.jpg", "b.jpg"]);
+
+        // 同梱データで最寄りの地名を引く（遠く離れていても距離とともに返す）
+        let places = PlaceIndex::bundled();
+        assert!(places.len() > 100);
+        let nearest = places.nearest(osaka.latitude, osaka.longitude).unwrap();
+        assert_eq!((nearest.name.as_str(), nearest.country.as_str()), ("Osaka", "JP"));
+        assert!(nearest.distance_km < 5.0);
+        assert_eq!(places.nearest_within(-60.0, -140.0, 100.0), None);
+        let remote = places.nearest(-60.0, -140.0).unwrap();
+        assert!(remote.distance_km > 100.0);
+        assert!(PlaceIndex::default().nearest(0.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn test_centroid_across_antimeridian() {
+        let (latitude, longitude) = centroid([position(-17.0, 179.0), position(-17.0, -179.0)].iter());
+        assert!((latitude + 17.0).abs() < 0.01, "latitude: {}", latitude);
+        assert!((longitude.abs() - 180.0).abs() < 1e-6, "longitude: {}", longitude);
+
+        let (latitude, longitude) = centroid([position(35.0, 139.0), position(36.0, 140.0)].iter());
+        assert!((latitude - 35.5).abs() < 0.01 && (longitude - 139.5).abs() < 0.01);
+    }
+}
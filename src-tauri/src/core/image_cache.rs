@@ -0,0 +1,280 @@
+// core/image_cache.rs
+// 画像データのキャッシュ - バイト予算付きのLRUキャッシュ
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use serde::Serialize;
+
+use crate::core::image_collection::ImageData;
+
+/// デフォルトのキャッシュ予算（バイト）
+pub const DEFAULT_CACHE_BUDGET_BYTES: usize = 64 * 1024 * 1024;
+
+/// キャッシュの統計情報
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImageCacheStats {
+    /// キャッシュヒット数
+    pub hits: u64,
+    /// キャッシュミス数
+    pub misses: u64,
+    /// 予算超過により追い出されたエントリ数
+    pub evictions: u64,
+    /// 現在のエントリ数
+    pub entries: usize,
+    /// 現在の使用量（バイト）
+    pub used_bytes: usize,
+    /// キャッシュ予算（バイト）
+    pub budget_bytes: usize,
+}
+
+/// キャッシュエントリ
+#[derive(Debug)]
+struct CacheEntry {
+    /// キャッシュされた画像データ
+    data: ImageData,
+    /// 推定サイズ（バイト）
+    size: usize,
+    /// 最終アクセス時刻（論理時刻）
+    last_used: u64,
+}
+
+/// キャッシュの内部状態
+#[derive(Debug, Default)]
+struct CacheState {
+    /// パス -> エントリ
+    entries: HashMap<String, CacheEntry>,
+    /// 最終アクセス時刻 -> パス（古い順に追い出すための索引）
+    lru_index: BTreeMap<u64, String>,
+    /// 論理時刻
+    clock: u64,
+    /// 使用量（バイト）
+    used_bytes: usize,
+    /// 予算（バイト）
+    budget_bytes: usize,
+    /// 統計カウンター
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+impl CacheState {
+    /// 次の論理時刻を取得
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// 予算内に収まるまで最も古いエントリから追い出す
+    fn evict_to_budget(&mut self) {
+        while self.used_bytes > self.budget_bytes {
+            let oldest = match self.lru_index.pop_first() {
+                Some((_, path)) => path,
+                None => break,
+            };
+            if let Some(entry) = self.entries.remove(&oldest) {
+                self.used_bytes -= entry.size;
+                self.evictions += 1;
+            }
+        }
+    }
+}
+
+/// バイト予算付きLRU画像キャッシュ
+///
+/// パスをキーとするため、filter/sortで派生したコレクション間で共有できる。
+#[derive(Debug)]
+pub struct ImageCache {
+    state: Mutex<CacheState>,
+}
+
+impl Default for ImageCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_BUDGET_BYTES)
+    }
+}
+
+impl ImageCache {
+    /// 指定した予算で新しいImageCacheインスタンスを作成
+    pub fn new(budget_bytes: usize) -> Self {
+        Self {
+            state: Mutex::new(CacheState {
+                budget_bytes,
+                ..CacheState::default()
+            }),
+        }
+    }
+
+    /// パスに対応する画像データを取得（ヒット時はLRU順を更新）
+    pub fn get(&self, path: &str) -> Option<ImageData> {
+        let mut state = self.state.lock().ok()?;
+        let now = state.tick();
+
+        let previous = match state.entries.get_mut(path) {
+            Some(entry) => {
+                let previous = entry.last_used;
+                entry.last_used = now;
+                Some((previous, entry.data.clone()))
+            },
+            None => None,
+        };
+
+        match previous {
+            Some((previous, data)) => {
+                state.lru_index.remove(&previous);
+                state.lru_index.insert(now, path.to_string());
+                state.hits += 1;
+                Some(data)
+            },
+            None => {
+                state.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// 画像データをキャッシュに追加（予算を超える単体データはキャッシュしない）
+    pub fn insert(&self, path: &str, data: ImageData) {
+        let size = data.estimated_size();
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+
+        if let Some(old) = state.entries.remove(path) {
+            state.lru_index.remove(&old.last_used);
+            state.used_bytes -= old.size;
+        }
+
+        if size > state.budget_bytes {
+            return;
+        }
+
+        let now = state.tick();
+        state.entries.insert(path.to_string(), CacheEntry {
+            data,
+            size,
+            last_used: now,
+        });
+        state.lru_index.insert(now, path.to_string());
+        state.used_bytes += size;
+        state.evict_to_budget();
+    }
+
+    /// 指定したパスのエントリを削除（ファイルが変更・削除された場合に使用）
+    pub fn remove(&self, path: &str) {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(old) = state.entries.remove(path) {
+                state.lru_index.remove(&old.last_used);
+                state.used_bytes -= old.size;
+            }
+        }
+    }
+
+    /// キャッシュ予算を変更（超過分は即座に追い出す）
+    pub fn set_budget(&self, budget_bytes: usize) {
+        if let Ok(mut state) = self.state.lock() {
+            state.budget_bytes = budget_bytes;
+            state.evict_to_budget();
+        }
+    }
+
+    /// すべてのエントリを削除（統計カウンターは保持）
+    pub fn clear(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.entries.clear();
+            state.lru_index.clear();
+            state.used_bytes = 0;
+        }
+    }
+
+    /// 統計情報を取得
+    pub fn stats(&self) -> ImageCacheStats {
+        match self.state.lock() {
+            Ok(state) => ImageCacheStats {
+                hits: state.hits,
+                misses: state.misses,
+                evictions: state.evictions,
+                entries: state.entries.len(),
+                used_bytes: state.used_bytes,
+                budget_bytes: state.budget_bytes,
+            },
+            Err(_) => ImageCacheStats::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::image_collection::ImageMetadata;
+    use crate::core::orientation::Rotation;
+
+    fn create_image_data(path: &str) -> ImageData {
+        ImageData {
+            url: format!("imgview://localhost/{}", path),
+            file_name: path.to_string(),
+            metadata: ImageMetadata {
+                path: path.to_string(),
+                file_name: path.to_string(),
+                file_size: 1024,
+                format: None,
+                dimensions: None,
+                orientation: None,
+                rotation: Rotation::None,
+                display_dimensions: None,
+                gps: None,
+                palette: None,
+                date_created: None,
+                date_modified: None,
+                companions: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_hit_and_miss_counters() {
+        let cache = ImageCache::default();
+        assert!(cache.get("a.jpg").is_none());
+
+        cache.insert("a.jpg", create_image_data("a.jpg"));
+        assert!(cache.get("a.jpg").is_some());
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.entries, 1);
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let entry_size = create_image_data("a.jpg").estimated_size();
+        let cache = ImageCache::new(entry_size * 2);
+
+        cache.insert("a.jpg", create_image_data("a.jpg"));
+        cache.insert("b.jpg", create_image_data("b.jpg"));
+        // aを参照してbを最も古いエントリにする
+        assert!(cache.get("a.jpg").is_some());
+        cache.insert("c.jpg", create_image_data("c.jpg"));
+
+        assert!(cache.get("a.jpg").is_some());
+        assert!(cache.get("b.jpg").is_none());
+        assert!(cache.get("c.jpg").is_some());
+
+        let stats = cache.stats();
+        assert_eq!(stats.evictions, 1);
+        assert!(stats.used_bytes <= stats.budget_bytes);
+    }
+
+    #[test]
+    fn test_set_budget_shrinks_cache() {
+        let cache = ImageCache::default();
+        cache.insert("a.jpg", create_image_data("a.jpg"));
+        cache.insert("b.jpg", create_image_data("b.jpg"));
+
+        cache.set_budget(0);
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 0);
+        assert_eq!(stats.used_bytes, 0);
+        assert_eq!(stats.evictions, 2);
+    }
+}
@@ -1,11 +1,19 @@
-use std::fs;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use serde::{Serialize, Deserialize};
-use base64::{Engine as _, engine::general_purpose};
 use rand::seq::SliceRandom;
 use rand::thread_rng;
 
+use crate::core::archive;
+use crate::core::color_palette::{Color, ColorFilter, ColorPalette};
+use crate::core::extended_metadata::GpsPosition;
+use crate::core::geo::{self, GeoBounds, GeoRadius, LocationCluster};
+use crate::core::image_format::ImageFormat;
+use crate::core::image_cache::{ImageCache, ImageCacheStats};
+use crate::core::image_protocol;
+use crate::core::orientation::{self, Rotation};
+
 /// 画像メタデータ構造体
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImageMetadata {
@@ -15,46 +23,325 @@ pub struct ImageMetadata {
     pub file_name: String,
     /// ファイルサイズ（バイト）
     pub file_size: u64,
-    /// 画像の寸法（幅 x 高さ）- オプショナル
+    /// ファイルの内容から判定した形式 - オプショナル
+    #[serde(default)]
+    pub format: Option<ImageFormat>,
+    /// 画像の寸法（幅 x 高さ、ファイルに格納された向きのまま）- オプショナル
     pub dimensions: Option<(u32, u32)>,
+    /// EXIFの向き（1〜8）- オプショナル
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<u8>,
+    /// ユーザーが指定した回転（インデックスに保存される）
+    #[serde(default, skip_serializing_if = "Rotation::is_none")]
+    pub rotation: Rotation,
+    /// 表示時の寸法（EXIFの向きと回転を適用した後の幅 x 高さ）- オプショナル
+    #[serde(default)]
+    pub display_dimensions: Option<(u32, u32)>,
+    /// 撮影位置（EXIFのGPS情報）- オプショナル
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gps: Option<GpsPosition>,
+    /// 代表色と平均輝度（色の分析を行った画像のみ）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub palette: Option<ColorPalette>,
     /// 作成日時 - オプショナル
     pub date_created: Option<String>,
     /// 更新日時 - オプショナル
     pub date_modified: Option<String>,
+    /// 同じエントリにまとめた画像（RAW+JPEGのRAW側など）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub companions: Vec<ImageMetadata>,
+}
+
+impl ImageMetadata {
+    /// 形式（内容から判定できていなければ拡張子から判定）
+    pub fn effective_format(&self) -> Option<ImageFormat> {
+        self.format.or_else(|| ImageFormat::from_file_name(&self.file_name))
+    }
+
+    /// 回転を設定し、表示時の寸法を更新
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.rotation = rotation;
+        self.display_dimensions = self.dimensions
+            .map(|dimensions| orientation::display_dimensions(dimensions, self.orientation, rotation));
+    }
+
+    /// RAW+JPEGの組を判定するキー（ディレクトリと、拡張子を除いたファイル名の小文字）
+    fn pair_key(&self) -> String {
+        let directory = Path::new(&self.path).parent().map_or("", |parent| parent.to_str().unwrap_or(""));
+        let stem = Path::new(&self.file_name).file_stem().and_then(|stem| stem.to_str()).unwrap_or("");
+        format!("{}\0{}", directory, stem.to_lowercase())
+    }
 }
 
-/// 画像データ構造体（Base64エンコード済み）
+/// 画像データ構造体（カスタムURIスキーム経由で配信）
 #[derive(Debug, Clone, Serialize)]
 pub struct ImageData {
-    /// Base64エンコードされた画像データ
-    pub base64: String,
+    /// 画像を配信するURL（imgview://）
+    pub url: String,
     /// ファイル名
     pub file_name: String,
     /// メタデータ
     pub metadata: ImageMetadata,
 }
 
+impl ImageData {
+    /// キャッシュ予算の計算に用いる推定メモリ使用量（バイト）
+    pub fn estimated_size(&self) -> usize {
+        let metadata = &self.metadata;
+        std::mem::size_of::<Self>()
+            + self.url.len()
+            + self.file_name.len()
+            + metadata.path.len()
+            + metadata.file_name.len()
+            + metadata.date_created.as_ref().map_or(0, |d| d.len())
+            + metadata.date_modified.as_ref().map_or(0, |d| d.len())
+    }
+}
+
+/// 画像読み込み時の警告（ファイル単位）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageWarning {
+    /// 対象ファイルのパス
+    pub path: String,
+    /// 警告内容
+    pub message: String,
+}
+
+/// メタデータに対するフィルタ条件（指定された条件すべてに一致する画像を残す）
+///
+/// 未知のフィールドは綴りの誤りで条件が無視されないようにエラーとする。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImageFilter {
+    /// ファイル名に含まれる文字列（大文字小文字を区別しない）
+    pub name_contains: Option<String>,
+    /// 許可する拡張子（ドットなし、大文字小文字を区別しない）
+    pub extensions: Option<Vec<String>>,
+    /// 最小ファイルサイズ（バイト）
+    pub min_file_size: Option<u64>,
+    /// 最大ファイルサイズ（バイト）
+    pub max_file_size: Option<u64>,
+    /// 最小の幅
+    pub min_width: Option<u32>,
+    /// 最小の高さ
+    pub min_height: Option<u32>,
+    /// この日時以降に更新された画像（ISO-8601）
+    pub modified_after: Option<String>,
+    /// この日時より前に更新された画像（ISO-8601）
+    pub modified_before: Option<String>,
+    /// 撮影位置の有無
+    pub has_location: Option<bool>,
+    /// 撮影位置がこの範囲に含まれる画像
+    pub within_bounds: Option<GeoBounds>,
+    /// 撮影位置がこの半径に含まれる画像
+    pub within_radius: Option<GeoRadius>,
+    /// 指定した色が多く占める画像
+    pub color: Option<ColorFilter>,
+    /// 最小の平均輝度（0〜1）
+    pub min_brightness: Option<f32>,
+    /// 最大の平均輝度（0〜1）
+    pub max_brightness: Option<f32>,
+}
+
+impl ImageFilter {
+    /// メタデータが条件に一致するかを判定
+    pub fn matches(&self, metadata: &ImageMetadata) -> bool {
+        if let Some(needle) = &self.name_contains {
+            if !metadata.file_name.to_lowercase().contains(&needle.to_lowercase()) {
+                return false;
+            }
+        }
+        if let Some(extensions) = &self.extensions {
+            let extension = Path::new(&metadata.file_name)
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("");
+            if !extensions.iter().any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(extension)) {
+                return false;
+            }
+        }
+        if self.min_file_size.is_some_and(|min| metadata.file_size < min) {
+            return false;
+        }
+        if self.max_file_size.is_some_and(|max| metadata.file_size > max) {
+            return false;
+        }
+        if self.min_width.is_some() || self.min_height.is_some() {
+            // 寸法が不明な画像は寸法条件に一致しない
+            let (width, height) = match metadata.dimensions {
+                Some(dimensions) => dimensions,
+                None => return false,
+            };
+            if self.min_width.is_some_and(|min| width < min) || self.min_height.is_some_and(|min| height < min) {
+                return false;
+            }
+        }
+        if self.modified_after.is_some() || self.modified_before.is_some() {
+            // ISO-8601（UTC）は文字列比較で時系列順になる
+            let modified = match &metadata.date_modified {
+                Some(modified) => modified.as_str(),
+                None => return false,
+            };
+            if self.modified_after.as_deref().is_some_and(|after| modified < after) {
+                return false;
+            }
+            if self.modified_before.as_deref().is_some_and(|before| modified >= before) {
+                return false;
+            }
+        }
+        if self.has_location.is_some_and(|has_location| has_location != metadata.gps.is_some()) {
+            return false;
+        }
+        if self.within_bounds.is_some() || self.within_radius.is_some() {
+            // 撮影位置が不明な画像は位置条件に一致しない
+            let gps = match &metadata.gps {
+                Some(gps) => gps,
+                None => return false,
+            };
+            if self.within_bounds.is_some_and(|bounds| !bounds.contains(gps))
+                || self.within_radius.is_some_and(|radius| !radius.contains(gps))
+            {
+                return false;
+            }
+        }
+        if self.color.is_some() || self.min_brightness.is_some() || self.max_brightness.is_some() {
+            // 色を分析していない画像は色の条件に一致しない
+            let palette = match &metadata.palette {
+                Some(palette) => palette,
+                None => return false,
+            };
+            if self.color.is_some_and(|color| !color.matches(palette))
+                || self.min_brightness.is_some_and(|min| palette.brightness < min)
+                || self.max_brightness.is_some_and(|max| palette.brightness > max)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// ソートキー
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageSortKey {
+    FileName,
+    Path,
+    FileSize,
+    DateCreated,
+    DateModified,
+    Width,
+    Height,
+    PixelCount,
+    /// 色相（無彩色の画像は末尾）
+    Hue,
+    /// 平均輝度
+    Brightness,
+    /// `color` で指定した色への近さ
+    ColorDistance,
+}
+
+/// ソート条件
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ImageSort {
+    /// ソートキー
+    pub key: ImageSortKey,
+    /// 降順にするかどうか
+    #[serde(default)]
+    pub descending: bool,
+    /// ColorDistance で基準にする色
+    #[serde(default)]
+    pub color: Option<Color>,
+}
+
+impl ImageSort {
+    /// 2つのメタデータを比較（値が不明なものは昇順・降順どちらでも末尾に置く）
+    pub fn compare(&self, a: &ImageMetadata, b: &ImageMetadata) -> std::cmp::Ordering {
+        use std::cmp::Ordering;
+
+        fn compare_optional<T: Ord>(a: Option<T>, b: Option<T>, descending: bool) -> Ordering {
+            match (a, b) {
+                (Some(a), Some(b)) if descending => b.cmp(&a),
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+        }
+
+        // 非負の浮動小数点数はビット表現の大小が値の大小と一致する
+        let float_key = |value: Option<f32>| value.map(|value| value.max(0.0).to_bits());
+        let palette_key = |metadata: &ImageMetadata, key: fn(&ColorPalette) -> Option<f32>| {
+            float_key(metadata.palette.as_ref().and_then(key))
+        };
+
+        let descending = self.descending;
+        match self.key {
+            ImageSortKey::FileName => compare_optional(Some(&a.file_name), Some(&b.file_name), descending),
+            ImageSortKey::Path => compare_optional(Some(&a.path), Some(&b.path), descending),
+            ImageSortKey::FileSize => compare_optional(Some(a.file_size), Some(b.file_size), descending),
+            ImageSortKey::DateCreated => compare_optional(a.date_created.as_ref(), b.date_created.as_ref(), descending),
+            ImageSortKey::DateModified => compare_optional(a.date_modified.as_ref(), b.date_modified.as_ref(), descending),
+            ImageSortKey::Width => compare_optional(a.dimensions.map(|d| d.0), b.dimensions.map(|d| d.0), descending),
+            ImageSortKey::Height => compare_optional(a.dimensions.map(|d| d.1), b.dimensions.map(|d| d.1), descending),
+            ImageSortKey::PixelCount => compare_optional(
+                a.dimensions.map(|(w, h)| w as u64 * h as u64),
+                b.dimensions.map(|(w, h)| w as u64 * h as u64),
+                descending,
+            ),
+            ImageSortKey::Hue => compare_optional(palette_key(a, ColorPalette::hue), palette_key(b, ColorPalette::hue), descending),
+            ImageSortKey::Brightness => compare_optional(
+                palette_key(a, |palette| Some(palette.brightness)),
+                palette_key(b, |palette| Some(palette.brightness)),
+                descending,
+            ),
+            ImageSortKey::ColorDistance => {
+                let distance = |metadata: &ImageMetadata| float_key(self.color
+                    .and_then(|color| metadata.palette.as_ref()?.distance_to(color)));
+                compare_optional(distance(a), distance(b), descending)
+            },
+        }
+    }
+}
+
 /// 画像コレクション構造体
 #[derive(Debug, Clone, Serialize)]
 pub struct ImageCollection {
     /// 画像メタデータのリスト
     metadata_list: Vec<ImageMetadata>,
-    /// キャッシュされた画像データ
+    /// 読み込み時に発生したファイル単位の警告
+    warnings: Vec<ImageWarning>,
+    /// キャッシュされた画像データ（派生コレクションと共有）
     #[serde(skip)]
-    image_cache: Arc<Mutex<Vec<Option<ImageData>>>>,
+    image_cache: Arc<ImageCache>,
 }
 
 impl ImageCollection {
     /// 新しい ImageCollection インスタンスを作成
     pub fn new(metadata_list: Vec<ImageMetadata>) -> Self {
-        let cache_size = metadata_list.len();
-        let image_cache = Arc::new(Mutex::new(vec![None; cache_size]));
-        
+        Self::with_cache(metadata_list, Arc::new(ImageCache::default()))
+    }
+
+    /// 既存のキャッシュを共有する ImageCollection インスタンスを作成
+    pub fn with_cache(metadata_list: Vec<ImageMetadata>, image_cache: Arc<ImageCache>) -> Self {
         Self {
             metadata_list,
+            warnings: Vec::new(),
             image_cache,
         }
     }
+
+    /// 警告付きで新しい ImageCollection インスタンスを作成
+    pub fn with_warnings(metadata_list: Vec<ImageMetadata>, warnings: Vec<ImageWarning>) -> Self {
+        let mut collection = Self::new(metadata_list);
+        collection.warnings = warnings;
+        collection
+    }
+    
+    /// 読み込み時の警告を取得
+    pub fn get_warnings(&self) -> &[ImageWarning] {
+        &self.warnings
+    }
     
     /// すべての画像メタデータを取得
     pub fn get_all_metadata(&self) -> Vec<ImageMetadata> {
@@ -76,6 +363,15 @@ impl ImageCollection {
         self.metadata_list.get(index).cloned()
     }
     
+    /// 指定範囲のメタデータを取得（ページング用）
+    pub fn get_metadata_page(&self, offset: usize, limit: usize) -> Vec<ImageMetadata> {
+        self.metadata_list.iter()
+            .skip(offset)
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+    
     /// パスで特定の画像のメタデータを取得
     pub fn get_metadata_by_path(&self, path: &str) -> Option<ImageMetadata> {
         self.metadata_list.iter()
@@ -86,49 +382,43 @@ impl ImageCollection {
     /// インデックスで特定の画像を読み込み
     pub fn load_image_at(&self, index: usize) -> Result<ImageData, String> {
         // 範囲チェック
-        if index >= self.metadata_list.len() {
-            return Err(format!("Index out of bounds: {}", index));
-        }
+        let metadata = self.metadata_list.get(index)
+            .ok_or_else(|| format!("Index out of bounds: {}", index))?;
         
         // キャッシュチェック
-        if let Ok(mut cache) = self.image_cache.lock() {
-            if let Some(Some(image_data)) = cache.get(index) {
-                return Ok(image_data.clone());
-            }
-            
-            // キャッシュにない場合は読み込み
-            let metadata = &self.metadata_list[index];
-            let image_data = self.load_image_from_path(&metadata.path)?;
-            
-            // キャッシュに保存
-            if let Some(slot) = cache.get_mut(index) {
-                *slot = Some(image_data.clone());
-            }
-            
-            Ok(image_data)
-        } else {
-            Err("Failed to access image cache".to_string())
+        if let Some(image_data) = self.image_cache.get(&metadata.path) {
+            return Ok(image_data);
         }
+        
+        // キャッシュにない場合は読み込み
+        let image_data = self.load_image(metadata)?;
+        self.image_cache.insert(&metadata.path, image_data.clone());
+        
+        Ok(image_data)
     }
     
-    /// パスから画像を読み込み
-    fn load_image_from_path(&self, path: &str) -> Result<ImageData, String> {
-        let path_obj = Path::new(path);
-        
-        let metadata = self.get_metadata_by_path(path)
-            .ok_or_else(|| format!("Metadata not found for path: {}", path))?;
+    /// インデックスで特定の画像の内容を読み込み（アーカイブ内の画像はそのエントリだけを展開する）
+    pub fn read_image_bytes_at(&self, index: usize) -> Result<Vec<u8>, String> {
+        let metadata = self.metadata_list.get(index)
+            .ok_or_else(|| format!("Index out of bounds: {}", index))?;
         
-        match fs::read(path_obj) {
-            Ok(bytes) => {
-                let base64 = general_purpose::STANDARD.encode(&bytes);
-                Ok(ImageData {
-                    base64,
-                    file_name: metadata.file_name.clone(),
-                    metadata: metadata.clone(),
-                })
-            },
-            Err(e) => Err(format!("Failed to read file: {}", e))
+        if archive::split_entry_path(&metadata.path).is_some() {
+            return archive::read_entry_path(&metadata.path).map_err(|e| e.to_string());
         }
+        std::fs::read(&metadata.path).map_err(|e| format!("Failed to read image: {}", e))
+    }
+    
+    /// メタデータから画像を読み込み
+    fn load_image(&self, metadata: &ImageMetadata) -> Result<ImageData, String> {
+        if !archive::physical_path(&metadata.path).is_file() {
+            return Err(format!("File not found: {}", metadata.path));
+        }
+        
+        Ok(ImageData {
+            url: image_protocol::image_url(&metadata.path),
+            file_name: metadata.file_name.clone(),
+            metadata: metadata.clone(),
+        })
     }
     
     /// 指定された数のランダムな画像を取得
@@ -165,7 +455,7 @@ impl ImageCollection {
             .filter(predicate)
             .collect();
         
-        Self::new(filtered_metadata)
+        Self::with_cache(filtered_metadata, Arc::clone(&self.image_cache))
     }
     
     /// 比較関数に基づいてソートされた新しいコレクションを作成
@@ -176,16 +466,126 @@ impl ImageCollection {
         let mut sorted_metadata = self.metadata_list.clone();
         sorted_metadata.sort_by(|a, b| compare_fn(a, b));
         
-        Self::new(sorted_metadata)
+        Self::with_cache(sorted_metadata, Arc::clone(&self.image_cache))
     }
     
-    /// キャッシュをクリア
-    pub fn clear_cache(&self) {
-        if let Ok(mut cache) = self.image_cache.lock() {
-            for slot in cache.iter_mut() {
-                *slot = None;
+    /// 同じディレクトリにある同名（拡張子を除く）のRAWと表示用画像を1つのエントリにまとめた新しいコレクションを作成
+    ///
+    /// 表示用画像（JPEGを優先）を代表とし、RAWはその `companions` に入れる。
+    /// 対になる表示用画像のないRAWはそのまま残す。
+    pub fn group_raw_pairs(&self) -> Self {
+        let flat = self.ungroup_pairs().metadata_list;
+        let is_raw = |metadata: &ImageMetadata| metadata.effective_format() == Some(ImageFormat::Raw);
+        let is_jpeg = |metadata: &ImageMetadata| metadata.effective_format() == Some(ImageFormat::Jpeg);
+
+        // 組ごとの代表（JPEGがあればJPEG、なければ最初の表示用画像）
+        let mut primaries: HashMap<String, usize> = HashMap::new();
+        for (index, metadata) in flat.iter().enumerate().filter(|(_, metadata)| !is_raw(metadata)) {
+            let key = metadata.pair_key();
+            let replace = primaries.get(&key).is_none_or(|&current| !is_jpeg(&flat[current]) && is_jpeg(metadata));
+            if replace {
+                primaries.insert(key, index);
             }
         }
+
+        let mut companions: HashMap<usize, Vec<ImageMetadata>> = HashMap::new();
+        let mut grouped = HashSet::new();
+        for (index, metadata) in flat.iter().enumerate().filter(|(_, metadata)| is_raw(metadata)) {
+            if let Some(&primary) = primaries.get(&metadata.pair_key()) {
+                companions.entry(primary).or_default().push(metadata.clone());
+                grouped.insert(index);
+            }
+        }
+
+        let metadata_list = flat.into_iter()
+            .enumerate()
+            .filter(|(index, _)| !grouped.contains(index))
+            .map(|(index, mut metadata)| {
+                metadata.companions = companions.remove(&index).unwrap_or_default();
+                metadata
+            })
+            .collect();
+        Self {
+            metadata_list,
+            warnings: self.warnings.clone(),
+            image_cache: Arc::clone(&self.image_cache),
+        }
+    }
+
+    /// まとめた画像を元のエントリに戻した新しいコレクションを作成（代表の直後に並べる）
+    pub fn ungroup_pairs(&self) -> Self {
+        let mut metadata_list = Vec::with_capacity(self.metadata_list.len());
+        for metadata in &self.metadata_list {
+            let mut metadata = metadata.clone();
+            let companions = std::mem::take(&mut metadata.companions);
+            metadata_list.push(metadata);
+            metadata_list.extend(companions);
+        }
+        Self {
+            metadata_list,
+            warnings: self.warnings.clone(),
+            image_cache: Arc::clone(&self.image_cache),
+        }
+    }
+
+    /// 追加・変更されたメタデータと削除されたパスを反映した新しいコレクションを作成
+    ///
+    /// 既存の画像は元の位置で置き換え、新しい画像は末尾に追加する。
+    /// キャッシュは共有したまま、変更されたパスのエントリのみ破棄する。
+    pub fn apply_changes(&self, upserts: &[ImageMetadata], new_warnings: &[ImageWarning], removed: &[String]) -> Self {
+        let removed: HashSet<&str> = removed.iter().map(String::as_str).collect();
+        let mut upserts_by_path: HashMap<&str, &ImageMetadata> = upserts.iter()
+            .map(|metadata| (metadata.path.as_str(), metadata))
+            .collect();
+
+        for path in removed.iter().chain(upserts_by_path.keys()) {
+            self.image_cache.remove(path);
+        }
+
+        let mut metadata_list: Vec<ImageMetadata> = self.metadata_list.iter()
+            .filter(|metadata| !removed.contains(metadata.path.as_str()))
+            .map(|metadata| match upserts_by_path.remove(metadata.path.as_str()) {
+                Some(updated) => updated.clone(),
+                None => metadata.clone(),
+            })
+            .collect();
+        // 残ったものは新規追加（入力の順序を保つ）
+        metadata_list.extend(upserts.iter()
+            .filter(|metadata| upserts_by_path.contains_key(metadata.path.as_str()))
+            .cloned());
+
+        // 変更されたパスの警告は読み込み直した結果で置き換える
+        let mut warnings: Vec<ImageWarning> = self.warnings.iter()
+            .filter(|warning| !removed.contains(warning.path.as_str()))
+            .filter(|warning| !upserts.iter().any(|metadata| metadata.path == warning.path))
+            .cloned()
+            .collect();
+        warnings.extend(new_warnings.iter().cloned());
+
+        Self {
+            metadata_list,
+            warnings,
+            image_cache: Arc::clone(&self.image_cache),
+        }
+    }
+
+    /// キャッシュをクリア（共有している派生コレクションにも反映される）
+    pub fn clear_cache(&self) {
+        self.image_cache.clear();
+    }
+
+    /// キャッシュ予算（バイト）を変更
+    pub fn set_cache_budget(&self, budget_bytes: usize) {
+        self.image_cache.set_budget(budget_bytes);
+    }
+
+    /// 撮影位置のある画像を位置ごとにまとめる（セルの一辺はおよそ cell_km）
+    pub fn location_clusters(&self, cell_km: f64) -> Vec<LocationCluster> {
+        geo::cluster_positions(
+            self.metadata_list.iter()
+                .filter_map(|metadata| metadata.gps.map(|gps| (metadata.path.as_str(), gps))),
+            cell_km,
+        )
     }
 
     /// コレクションのダイジェスト情報を取得
@@ -195,6 +595,7 @@ impl ImageCollection {
             total_size_bytes: self.metadata_list.iter()
                 .map(|meta| meta.file_size)
                 .sum(),
+            cache: self.image_cache.stats(),
         }
     }
 }
@@ -206,6 +607,8 @@ pub struct ImageCollectionDigest {
     pub total_images: usize,
     /// 総サイズ（バイト）
     pub total_size_bytes: u64,
+    /// 画像キャッシュの統計情報
+    pub cache: ImageCacheStats,
 }
 
 #[cfg(test)]
@@ -219,17 +622,31 @@ mod tests {
                 path: "/path/to/image1.jpg".to_string(),
                 file_name: "image1.jpg".to_string(),
                 file_size: 1024,
+                format: None,
                 dimensions: Some((800, 600)),
+                orientation: None,
+                rotation: Rotation::None,
+                display_dimensions: Some((800, 600)),
+                gps: None,
+                palette: None,
                 date_created: None,
                 date_modified: None,
+                companions: Vec::new(),
             },
             ImageMetadata {
                 path: "/path/to/image2.png".to_string(),
                 file_name: "image2.png".to_string(),
                 file_size: 2048,
+                format: None,
                 dimensions: Some((1024, 768)),
+                orientation: None,
+                rotation: Rotation::None,
+                display_dimensions: Some((1024, 768)),
+                gps: None,
+                palette: None,
                 date_created: None,
                 date_modified: None,
+                companions: Vec::new(),
             },
         ];
         
@@ -245,9 +662,16 @@ mod tests {
                 path: "/path/to/image1.jpg".to_string(),
                 file_name: "image1.jpg".to_string(),
                 file_size: 1024,
+                format: None,
                 dimensions: Some((800, 600)),
+                orientation: None,
+                rotation: Rotation::None,
+                display_dimensions: Some((800, 600)),
+                gps: None,
+                palette: None,
                 date_created: None,
                 date_modified: None,
+                companions: Vec::new(),
             },
         ];
         
@@ -270,25 +694,46 @@ mod tests {
                 path: "/path/to/image1.jpg".to_string(),
                 file_name: "image1.jpg".to_string(),
                 file_size: 1024,
+                format: None,
                 dimensions: Some((800, 600)),
+                orientation: None,
+                rotation: Rotation::None,
+                display_dimensions: Some((800, 600)),
+                gps: None,
+                palette: None,
                 date_created: None,
                 date_modified: None,
+                companions: Vec::new(),
             },
             ImageMetadata {
                 path: "/path/to/image2.png".to_string(),
                 file_name: "image2.png".to_string(),
                 file_size: 2048,
+                format: None,
                 dimensions: Some((1024, 768)),
+                orientation: None,
+                rotation: Rotation::None,
+                display_dimensions: Some((1024, 768)),
+                gps: None,
+                palette: None,
                 date_created: None,
                 date_modified: None,
+                companions: Vec::new(),
             },
             ImageMetadata {
                 path: "/path/to/image3.gif".to_string(),
                 file_name: "image3.gif".to_string(),
                 file_size: 512,
+                format: None,
                 dimensions: Some((400, 300)),
+                orientation: None,
+                rotation: Rotation::None,
+                display_dimensions: Some((400, 300)),
+                gps: None,
+                palette: None,
                 date_created: None,
                 date_modified: None,
+                companions: Vec::new(),
             },
         ];
         
@@ -304,4 +749,213 @@ mod tests {
         assert_eq!(sorted_by_size.get_metadata_at(0).unwrap().file_size, 512);
         assert_eq!(sorted_by_size.get_metadata_at(2).unwrap().file_size, 2048);
     }
+
+    #[test]
+    fn test_derived_collections_share_cache() {
+        let path = std::env::temp_dir().join(format!("image-viewer-cache-test-{}.jpg", std::process::id()));
+        std::fs::write(&path, [0xFF, 0xD8, 0xFF, 0xD9]).unwrap();
+        let path_str = path.to_str().unwrap().to_string();
+
+        let metadata = vec![
+            ImageMetadata {
+                path: path_str.clone(),
+                file_name: "cached.jpg".to_string(),
+                file_size: 4,
+                format: None,
+                dimensions: None,
+                orientation: None,
+                rotation: Rotation::None,
+                display_dimensions: None,
+                gps: None,
+                palette: None,
+                date_created: None,
+                date_modified: None,
+                companions: Vec::new(),
+            },
+        ];
+
+        let collection = ImageCollection::new(metadata);
+        assert!(collection.load_image_at(0).is_ok());
+
+        // 派生コレクションは親のキャッシュにヒットする
+        let derived = collection.sort(|a, b| a.path.cmp(&b.path));
+        let image = derived.load_image_at(0).unwrap();
+        assert_eq!(image.metadata.path, path_str);
+
+        let digest = collection.get_digest();
+        assert_eq!(digest.cache.misses, 1);
+        assert_eq!(digest.cache.hits, 1);
+        assert_eq!(digest.cache.entries, 1);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_image_filter_and_sort() {
+        let metadata = vec![
+            ImageMetadata {
+                path: "/path/to/Beach.JPG".to_string(),
+                file_name: "Beach.JPG".to_string(),
+                file_size: 4096,
+                format: None,
+                dimensions: Some((1920, 1080)),
+                orientation: None,
+                rotation: Rotation::None,
+                display_dimensions: Some((1920, 1080)),
+                gps: None,
+                palette: None,
+                date_created: None,
+                date_modified: Some("2024-06-01T10:00:00Z".to_string()),
+                companions: Vec::new(),
+            },
+            ImageMetadata {
+                path: "/path/to/beach_small.png".to_string(),
+                file_name: "beach_small.png".to_string(),
+                file_size: 512,
+                format: None,
+                dimensions: Some((320, 240)),
+                orientation: None,
+                rotation: Rotation::None,
+                display_dimensions: Some((320, 240)),
+                gps: None,
+                palette: None,
+                date_created: None,
+                date_modified: Some("2023-01-01T00:00:00Z".to_string()),
+                companions: Vec::new(),
+            },
+            ImageMetadata {
+                path: "/path/to/unknown.jpg".to_string(),
+                file_name: "unknown.jpg".to_string(),
+                file_size: 2048,
+                format: None,
+                dimensions: None,
+                orientation: None,
+                rotation: Rotation::None,
+                display_dimensions: None,
+                gps: None,
+                palette: None,
+                date_created: None,
+                date_modified: None,
+                companions: Vec::new(),
+            },
+        ];
+
+        let filter = ImageFilter {
+            name_contains: Some("beach".to_string()),
+            extensions: Some(vec!["jpg".to_string()]),
+            ..ImageFilter::default()
+        };
+        assert!(filter.matches(&metadata[0]));
+        assert!(!filter.matches(&metadata[1]));
+
+        let filter = ImageFilter {
+            min_width: Some(1000),
+            ..ImageFilter::default()
+        };
+        assert!(filter.matches(&metadata[0]));
+        assert!(!filter.matches(&metadata[2]));
+
+        let filter = ImageFilter {
+            modified_after: Some("2024-01-01T00:00:00Z".to_string()),
+            ..ImageFilter::default()
+        };
+        assert!(filter.matches(&metadata[0]));
+        assert!(!filter.matches(&metadata[1]));
+
+        // 撮影位置が不明な画像は位置条件に一致しない
+        let mut located = metadata[0].clone();
+        located.gps = Some(GpsPosition { latitude: 35.0116, longitude: 135.7681, altitude: None });
+        let filter = ImageFilter {
+            within_radius: Some(GeoRadius { latitude: 35.0, longitude: 135.75, radius_km: 5.0 }),
+            ..ImageFilter::default()
+        };
+        assert!(filter.matches(&located));
+        assert!(!filter.matches(&metadata[0]));
+
+        // 寸法が不明な画像は降順でも末尾に置かれる
+        let collection = ImageCollection::new(metadata);
+        let sort = ImageSort { key: ImageSortKey::PixelCount, descending: true, color: None };
+        let sorted = collection.sort(|a, b| sort.compare(a, b));
+        let names: Vec<String> = sorted.get_all_metadata().into_iter().map(|m| m.file_name).collect();
+        assert_eq!(names, vec!["Beach.JPG", "beach_small.png", "unknown.jpg"]);
+    }
+
+    #[test]
+    fn test_image_filter_deserializes_frontend_shape() {
+        // フロントエンドの ImageFilter（ImageManager.ts）と同じ形
+        let filter: ImageFilter = serde_json::from_value(serde_json::json!({
+            "name_contains": "beach",
+            "extensions": ["jpg"],
+            "min_file_size": 1024,
+            "max_file_size": 8192,
+            "min_width": 1000,
+            "min_height": 600,
+            "modified_after": "2024-01-01T00:00:00Z",
+            "modified_before": "2025-01-01T00:00:00Z",
+            "has_location": true,
+            "within_bounds": { "south": 34.0, "west": 135.0, "north": 36.0, "east": 136.0 },
+            "within_radius": { "latitude": 35.0, "longitude": 135.75, "radius_km": 5.0 },
+            "color": { "color": "#0000ff", "min_coverage": 0.5, "tolerance": 100.0 },
+            "min_brightness": 0.2,
+            "max_brightness": 0.8,
+        })).unwrap();
+        assert_eq!(filter.name_contains.as_deref(), Some("beach"));
+        assert_eq!(filter.min_file_size, Some(1024));
+        assert_eq!(filter.has_location, Some(true));
+        assert_eq!(filter.within_radius.map(|radius| radius.radius_km), Some(5.0));
+        assert_eq!(filter.color.and_then(|color| color.min_coverage), Some(0.5));
+        assert_eq!(filter.max_brightness, Some(0.8));
+
+        // 綴りの誤り（camelCaseなど）は無視せずエラーにする
+        assert!(serde_json::from_value::<ImageFilter>(serde_json::json!({ "nameContains": "beach" })).is_err());
+        assert!(serde_json::from_value::<ImageFilter>(serde_json::json!({
+            "color": { "color": "#0000ff", "minCoverage": 0.5 },
+        })).is_err());
+    }
+
+    #[test]
+    fn test_group_raw_pairs() {
+        let image = |path: &str| ImageMetadata {
+            path: path.to_string(),
+            file_name: path.rsplit('/').next().unwrap().to_string(),
+            file_size: 0,
+            format: None,
+            dimensions: None,
+            orientation: None,
+            rotation: Rotation::None,
+            display_dimensions: None,
+            gps: None,
+            palette: None,
+            date_created: None,
+            date_modified: None,
+            companions: Vec::new(),
+        };
+        let collection = ImageCollection::new(vec![
+            image("/photos/DSC_0001.NEF"),
+            image("/photos/DSC_0001.jpg"),
+            image("/photos/DSC_0002.nef"),
+            image("/photos/other/DSC_0002.jpg"),
+            image("/photos/DSC_0003.png"),
+            image("/photos/DSC_0003.JPG"),
+            image("/photos/DSC_0003.dng"),
+        ]);
+
+        let grouped = collection.group_raw_pairs();
+        let entries: Vec<(String, Vec<String>)> = grouped.get_all_metadata().into_iter()
+            .map(|m| (m.file_name, m.companions.into_iter().map(|c| c.file_name).collect()))
+            .collect();
+        assert_eq!(entries, vec![
+            ("DSC_0001.jpg".to_string(), vec!["DSC_0001.NEF".to_string()]),
+            // ディレクトリが異なれば組にしない
+            ("DSC_0002.nef".to_string(), vec![]),
+            ("DSC_0002.jpg".to_string(), vec![]),
+            ("DSC_0003.png".to_string(), vec![]),
+            // 代表はJPEGを優先する
+            ("DSC_0003.JPG".to_string(), vec!["DSC_0003.dng".to_string()]),
+        ]);
+
+        // 元に戻すとすべてのファイルが個別のエントリになる
+        assert_eq!(grouped.ungroup_pairs().len(), 7);
+        assert_eq!(grouped.group_raw_pairs().len(), 5);
+    }
 }
@@ -0,0 +1,380 @@
+// core/image_format.rs
+// 画像形式の判定 - 拡張子ではなくファイル先頭のシグネチャ（マジックバイト）から形式を判定する
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+/// 形式の判定に必要な先頭バイト数（ISOBMFFの互換ブランドを含む）
+pub const SIGNATURE_LEN: usize = 32;
+
+/// JPEG XLコンテナのシグネチャ
+const JXL_CONTAINER_SIGNATURE: [u8; 12] = [0, 0, 0, 0x0C, b'J', b'X', b'L', b' ', 0x0D, 0x0A, 0x87, 0x0A];
+
+/// JPEG XLコードストリームのシグネチャ（2バイトしかないため単独では判定に使わない）
+const JXL_CODESTREAM_SIGNATURE: [u8; 2] = [0xFF, 0x0A];
+
+/// BMPのDIBヘッダーとして有効なサイズ（CORE・INFO・V2・V3・V4・V5）
+const BMP_DIB_HEADER_SIZES: [u32; 6] = [12, 40, 52, 56, 108, 124];
+
+/// 画像形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+    Gif,
+    Bmp,
+    Webp,
+    Tiff,
+    Avif,
+    Heic,
+    Jxl,
+    /// カメラのRAW（CR2/NEF/ARW/DNG）。埋め込みJPEGプレビューを表示に使う
+    Raw,
+}
+
+impl ImageFormat {
+    /// 対応しているすべての形式
+    pub const ALL: [ImageFormat; 10] = [
+        ImageFormat::Jpeg,
+        ImageFormat::Png,
+        ImageFormat::Gif,
+        ImageFormat::Bmp,
+        ImageFormat::Webp,
+        ImageFormat::Tiff,
+        ImageFormat::Avif,
+        ImageFormat::Heic,
+        ImageFormat::Jxl,
+        ImageFormat::Raw,
+    ];
+
+    /// 先頭バイトのシグネチャから形式を判定
+    ///
+    /// 拡張子を確認しなければ区別できないJPEG XLのコードストリームは判定しない（`detect_with_name` を使う）。
+    pub fn detect(header: &[u8]) -> Option<Self> {
+        if header.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(Self::Jpeg)
+        } else if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else if header.starts_with(b"GIF87a") || header.starts_with(b"GIF89a") {
+            Some(Self::Gif)
+        } else if is_bmp_header(header) {
+            Some(Self::Bmp)
+        } else if header.len() >= 12 && &header[0..4] == b"RIFF" && &header[8..12] == b"WEBP" {
+            Some(Self::Webp)
+        } else if header.starts_with(b"II*\0") && header.get(8..10) == Some(b"CR") {
+            // CR2はTIFFヘッダーの直後に独自のシグネチャを持つ
+            Some(Self::Raw)
+        } else if header.starts_with(b"II*\0") || header.starts_with(b"MM\0*") {
+            Some(Self::Tiff)
+        } else if header.starts_with(&JXL_CONTAINER_SIGNATURE) {
+            Some(Self::Jxl)
+        } else if header.len() >= 12 && &header[4..8] == b"ftyp" {
+            detect_heif_brand(header)
+        } else {
+            None
+        }
+    }
+
+    /// ファイル名を考慮して先頭バイトから形式を判定
+    ///
+    /// 先頭2バイトだけのJPEG XLコードストリームは、拡張子が `.jxl` の場合のみJPEG XLとみなす。
+    pub fn detect_with_name(header: &[u8], file_name: &str) -> Option<Self> {
+        Self::detect(header)
+            .map(|format| format.refine_with_name(file_name))
+            .or_else(|| {
+                (header.starts_with(&JXL_CODESTREAM_SIGNATURE) && Self::from_file_name(file_name) == Some(Self::Jxl))
+                    .then_some(Self::Jxl)
+            })
+    }
+
+    /// ファイル名を考慮して判定結果を補正
+    ///
+    /// NEF・ARW・DNGなどのRAWは内容だけではTIFFと区別できないため、拡張子がRAWであればRAWとみなす。
+    pub fn refine_with_name(self, file_name: &str) -> Self {
+        match (self, Self::from_file_name(file_name)) {
+            (Self::Tiff, Some(Self::Raw)) => Self::Raw,
+            _ => self,
+        }
+    }
+
+    /// 拡張子（`.` なし、大文字小文字を区別しない）から形式を判定
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        let lower_case = extension.to_lowercase();
+        Self::ALL.into_iter().find(|format| format.extensions().contains(&lower_case.as_str()))
+    }
+
+    /// ファイル名の拡張子から形式を判定
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let (_, extension) = file_name.rsplit_once('.')?;
+        Self::from_extension(extension)
+    }
+
+    /// 形式に対応する拡張子
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Self::Jpeg => &["jpg", "jpeg", "jpe", "jfif"],
+            Self::Png => &["png"],
+            Self::Gif => &["gif"],
+            Self::Bmp => &["bmp", "dib"],
+            Self::Webp => &["webp"],
+            Self::Tiff => &["tif", "tiff"],
+            Self::Avif => &["avif"],
+            Self::Heic => &["heic", "heif", "hif"],
+            Self::Jxl => &["jxl"],
+            Self::Raw => &["cr2", "nef", "nrw", "arw", "dng"],
+        }
+    }
+
+    /// MIMEタイプ
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::Gif => "image/gif",
+            Self::Bmp => "image/bmp",
+            Self::Webp => "image/webp",
+            Self::Tiff => "image/tiff",
+            Self::Avif => "image/avif",
+            Self::Heic => "image/heic",
+            Self::Jxl => "image/jxl",
+            Self::Raw => "image/x-raw",
+        }
+    }
+
+    /// 表示用の名前
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Jpeg => "JPEG",
+            Self::Png => "PNG",
+            Self::Gif => "GIF",
+            Self::Bmp => "BMP",
+            Self::Webp => "WebP",
+            Self::Tiff => "TIFF",
+            Self::Avif => "AVIF",
+            Self::Heic => "HEIC",
+            Self::Jxl => "JPEG XL",
+            Self::Raw => "RAW",
+        }
+    }
+
+    /// webviewがそのまま表示できる形式かどうか（それ以外は変換してから配信する）
+    pub fn is_web_displayable(self) -> bool {
+        matches!(self, Self::Jpeg | Self::Png | Self::Gif | Self::Bmp | Self::Webp | Self::Avif)
+    }
+
+    /// このビルドでピクセルをデコードできる形式かどうか（サムネイル生成と変換に必要）
+    ///
+    /// AVIF・HEIC・JPEG XLのデコーダーはネイティブライブラリを必要とするため組み込んでいない。
+    /// RAWはセンサーデータではなく埋め込みJPEGプレビューをデコードする。
+    pub fn is_decodable(self) -> bool {
+        matches!(self, Self::Jpeg | Self::Png | Self::Gif | Self::Bmp | Self::Webp | Self::Tiff | Self::Raw)
+    }
+
+    /// デコードできない形式の説明
+    pub fn unsupported_message(self) -> String {
+        format!("{} images cannot be decoded in this build", self.name())
+    }
+}
+
+/// 形式ごとの対応状況
+#[derive(Debug, Clone, Serialize)]
+pub struct FormatSupport {
+    /// 形式
+    pub format: ImageFormat,
+    /// 表示用の名前
+    pub name: &'static str,
+    /// 対応する拡張子
+    pub extensions: &'static [&'static str],
+    /// MIMEタイプ
+    pub mime_type: &'static str,
+    /// ピクセルをデコードできるかどうか
+    pub decodable: bool,
+    /// webviewがそのまま表示できるかどうか
+    pub web_displayable: bool,
+}
+
+/// すべての形式の対応状況を取得
+pub fn supported_formats() -> Vec<FormatSupport> {
+    ImageFormat::ALL.into_iter()
+        .map(|format| FormatSupport {
+            format,
+            name: format.name(),
+            extensions: format.extensions(),
+            mime_type: format.mime_type(),
+            decodable: format.is_decodable(),
+            web_displayable: format.is_web_displayable(),
+        })
+        .collect()
+}
+
+/// BMPのファイルヘッダーかどうか
+///
+/// `BM` だけではテキストなどと区別できないため、予約領域が0であることとDIBヘッダーのサイズも確認する。
+fn is_bmp_header(header: &[u8]) -> bool {
+    header.starts_with(b"BM")
+        && header.get(6..10) == Some(&[0u8; 4][..])
+        && header.get(14..18)
+            .is_some_and(|size| BMP_DIB_HEADER_SIZES.contains(&u32::from_le_bytes([size[0], size[1], size[2], size[3]])))
+}
+
+/// ISOBMFF（HEIF系）のftypボックスのブランドからAVIFとHEICを判別
+///
+/// 主ブランドが汎用の `mif1` / `msf1` の場合は互換ブランドを確認する。
+fn detect_heif_brand(header: &[u8]) -> Option<ImageFormat> {
+    let box_end = header.get(0..4)
+        .map(|size| u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize)
+        .map_or(header.len(), |size| size.min(header.len()));
+    let compatible = header.get(16..box_end).unwrap_or(&[]);
+    let brands = std::iter::once(&header[8..12]).chain(compatible.chunks_exact(4));
+
+    let mut generic = false;
+    for brand in brands {
+        match brand {
+            b"avif" | b"avis" => return Some(ImageFormat::Avif),
+            b"heic" | b"heix" | b"heim" | b"heis" | b"hevc" | b"hevx" => return Some(ImageFormat::Heic),
+            b"mif1" | b"msf1" => generic = true,
+            _ => {},
+        }
+    }
+    generic.then_some(ImageFormat::Heic)
+}
+
+/// 形式の絞り込み条件（include が空の場合はすべての形式が対象）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormatFilter {
+    /// 対象とする形式
+    pub include: Vec<ImageFormat>,
+    /// 除外する形式
+    pub exclude: Vec<ImageFormat>,
+}
+
+impl FormatFilter {
+    /// 条件が指定されていないかどうか
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// 形式が条件を満たすかどうか
+    pub fn accepts(&self, format: ImageFormat) -> bool {
+        (self.include.is_empty() || self.include.contains(&format)) && !self.exclude.contains(&format)
+    }
+}
+
+/// 拡張子から画像ファイルの候補かを判定（内容は確認しない）
+pub fn is_image_file(file_name: &str) -> bool {
+    ImageFormat::from_file_name(file_name).is_some()
+}
+
+/// 任意のリーダーの先頭を読み込んで形式を判定
+pub fn sniff_reader<R: Read>(reader: R) -> std::io::Result<Option<ImageFormat>> {
+    let mut header = Vec::with_capacity(SIGNATURE_LEN);
+    reader.take(SIGNATURE_LEN as u64).read_to_end(&mut header)?;
+    Ok(ImageFormat::detect(&header))
+}
+
+/// ファイルの先頭を読み込んで形式を判定（ファイル名による補正を含む）
+pub fn sniff_file(path: &Path) -> std::io::Result<Option<ImageFormat>> {
+    let mut header = Vec::with_capacity(SIGNATURE_LEN);
+    File::open(path)?.take(SIGNATURE_LEN as u64).read_to_end(&mut header)?;
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+    Ok(ImageFormat::detect_with_name(&header, file_name))
+}
+
+/// ファイルの画像形式を判定
+///
+/// 拡張子が画像形式でないファイルは内容から判定する（`photo.JPG.part` なども見つかる）。
+/// 拡張子が画像形式のファイルは `verify` が true の場合のみ内容を確認し、
+/// 内容から判定できない場合は拡張子の形式とみなす（壊れた画像は読み込み時に警告として報告する）。
+pub fn file_format(path: &Path, verify: bool) -> Option<ImageFormat> {
+    let by_extension = path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(ImageFormat::from_file_name);
+    if by_extension.is_some() && !verify {
+        return by_extension;
+    }
+    sniff_file(path).ok().flatten().or(by_extension)
+}
+
+/// 拡張子と内容の形式が一致しない場合の説明
+pub fn mismatch_message(file_name: &str, detected: ImageFormat) -> Option<String> {
+    if ImageFormat::from_file_name(file_name) == Some(detected.refine_with_name(file_name)) {
+        return None;
+    }
+    let extension = file_name.rsplit_once('.').map_or("", |(_, extension)| extension);
+    Some(format!("Content is {} but extension is '.{}'", detected.name(), extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_and_extension() {
+        assert_eq!(ImageFormat::detect(&[0xFF, 0xD8, 0xFF, 0xE0]), Some(ImageFormat::Jpeg));
+        assert_eq!(ImageFormat::detect(b"\x89PNG\r\n\x1a\n\0\0"), Some(ImageFormat::Png));
+        assert_eq!(ImageFormat::detect(b"RIFF\0\0\0\0WEBPVP8 "), Some(ImageFormat::Webp));
+        assert_eq!(ImageFormat::detect(b"plain text"), None);
+        // BMPは予約領域とDIBヘッダーのサイズも確認する
+        let mut bmp = b"BM\x46\0\0\0\0\0\0\0\x36\0\0\0".to_vec();
+        bmp.extend(40u32.to_le_bytes());
+        assert_eq!(ImageFormat::detect(&bmp), Some(ImageFormat::Bmp));
+        assert_eq!(ImageFormat::detect(b"BMW notes: oil change due at 60000 km"), None);
+        bmp[14] = 41;
+        assert_eq!(ImageFormat::detect(&bmp), None);
+        assert_eq!(ImageFormat::detect(b"MM\0*\0\0\0\x08"), Some(ImageFormat::Tiff));
+        // JPEG XLのコードストリームは拡張子が `.jxl` の場合のみ認める
+        assert_eq!(ImageFormat::detect(&[0xFF, 0x0A, 0xFA]), None);
+        assert_eq!(ImageFormat::detect_with_name(&[0xFF, 0x0A, 0xFA], "image.jxl"), Some(ImageFormat::Jxl));
+        assert_eq!(ImageFormat::detect_with_name(&[0xFF, 0x0A, 0xFA], "data.bin"), None);
+        assert_eq!(ImageFormat::detect(b"\0\0\0\x18ftypheic\0\0\0\0mif1heic"), Some(ImageFormat::Heic));
+        // 主ブランドが汎用でも互換ブランドでAVIFと判別する
+        assert_eq!(ImageFormat::detect(b"\0\0\0\x1cftypmif1\0\0\0\0mif1avifmiaf"), Some(ImageFormat::Avif));
+        assert_eq!(ImageFormat::detect(b"\0\0\0\x14ftypisom\0\0\0\0isom"), None);
+
+        assert_eq!(ImageFormat::from_file_name("photo.JPG"), Some(ImageFormat::Jpeg));
+        assert_eq!(ImageFormat::from_file_name("photo.JPG.part"), None);
+        assert!(!is_image_file("notes.txt"));
+        assert_eq!(ImageFormat::from_file_name("IMG_0001.HEIC"), Some(ImageFormat::Heic));
+        assert!(ImageFormat::Tiff.is_decodable() && !ImageFormat::Tiff.is_web_displayable());
+
+        // RAWはCR2のみ内容から判別でき、他は拡張子で補正する
+        assert_eq!(ImageFormat::detect(b"II*\0\x10\0\0\0CR\x02\0"), Some(ImageFormat::Raw));
+        assert_eq!(ImageFormat::Tiff.refine_with_name("DSC_0001.NEF"), ImageFormat::Raw);
+        assert_eq!(ImageFormat::Tiff.refine_with_name("scan.tif"), ImageFormat::Tiff);
+        assert_eq!(mismatch_message("DSC_0001.NEF", ImageFormat::Tiff), None);
+
+        assert_eq!(mismatch_message("a.jpeg", ImageFormat::Jpeg), None);
+        assert_eq!(mismatch_message("a.png", ImageFormat::Jpeg), Some("Content is JPEG but extension is '.png'".to_string()));
+    }
+
+    #[test]
+    fn test_file_format_sniffs_content() {
+        let dir = std::env::temp_dir().join(format!("image-viewer-format-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("photo.JPG.part"), [0xFF, 0xD8, 0xFF, 0xE0, 0, 0]).unwrap();
+        std::fs::write(dir.join("renamed.png"), [0xFF, 0xD8, 0xFF, 0xE0, 0, 0]).unwrap();
+        std::fs::write(dir.join("notes.txt"), b"plain text").unwrap();
+        std::fs::write(dir.join("bmw.txt"), b"BMW notes: oil change due at 60000 km").unwrap();
+        std::fs::write(dir.join("stream.log"), [0xFF, 0x0A, 0x07, 0x0E, 0, 0]).unwrap();
+        std::fs::write(dir.join("stream.jxl"), [0xFF, 0x0A, 0x07, 0x0E, 0, 0]).unwrap();
+
+        assert_eq!(file_format(&dir.join("photo.JPG.part"), false), Some(ImageFormat::Jpeg));
+        assert_eq!(file_format(&dir.join("renamed.png"), false), Some(ImageFormat::Png));
+        assert_eq!(file_format(&dir.join("renamed.png"), true), Some(ImageFormat::Jpeg));
+        assert_eq!(file_format(&dir.join("notes.txt"), false), None);
+        // 弱いシグネチャに偶然一致するだけの画像でないファイルは対象にしない
+        assert_eq!(file_format(&dir.join("bmw.txt"), false), None);
+        assert_eq!(file_format(&dir.join("stream.log"), false), None);
+        assert_eq!(file_format(&dir.join("stream.jxl"), true), Some(ImageFormat::Jxl));
+
+        let filter = FormatFilter { include: vec![], exclude: vec![ImageFormat::Gif] };
+        assert!(filter.accepts(ImageFormat::Jpeg));
+        assert!(!filter.accepts(ImageFormat::Gif));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,692 @@
+// core/image_index.rs
+// 永続画像インデックス - 解決済みパスとメタデータをアプリデータディレクトリに保存し、再起動後も再利用する
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use serde::{Deserialize, Serialize};
+
+use crate::core::archive;
+use crate::core::color_palette::ColorPalette;
+use crate::core::extended_metadata::ExtendedMetadata;
+use crate::core::image_collection::ImageMetadata;
+use crate::core::orientation::Rotation;
+use crate::core::perceptual_hash::PerceptualHash;
+use crate::core::resource_scanner::{self, DirectorySnapshot};
+use crate::utils::natural_cmp;
+
+/// インデックスファイルの形式バージョン
+///
+/// フィールドの追加は `#[serde(default)]` で互換性を保つ。互換性のない変更を加えたときや、
+/// 記録済みの画像からも新しい項目を読み直す必要があるときに上げる
+/// （異なるバージョンのファイルは破棄して作り直す）。
+pub const INDEX_VERSION: u32 = 3;
+
+/// 回転の指定ファイルの形式バージョン
+///
+/// 回転はユーザーが指定したデータのため、インデックスとは別のファイルに保存し、
+/// インデックスを作り直しても失わないようにする。
+const ROTATIONS_VERSION: u32 = 1;
+
+/// `save_if_due` で保存する最小間隔（一覧の読み込みなどで何度も呼ばれても書き込みはまとめる）
+pub const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// ファイルの状態（サイズと更新日時が一致する間はメタデータを再利用する）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    /// ファイルサイズ（バイト）
+    pub size: u64,
+    /// 更新日時
+    pub modified: Option<SystemTime>,
+}
+
+impl FileStamp {
+    /// ファイルの現在の状態を取得（アーカイブ内のエントリはアーカイブ自体の状態を使う）
+    pub fn read(path: &Path) -> Option<Self> {
+        let path = path.to_str().map_or(path, archive::physical_path);
+        let metadata = fs::metadata(path).ok()?;
+        if !metadata.is_file() {
+            return None;
+        }
+        Some(Self {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+/// インデックスに保存された画像
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedImage {
+    /// 記録時のファイルの状態
+    pub stamp: FileStamp,
+    /// 画像メタデータ
+    pub metadata: ImageMetadata,
+    /// 読み込み時の警告
+    #[serde(default)]
+    pub warning: Option<String>,
+    /// 拡張メタデータ（EXIF/IPTC/XMP、初めて要求されたときに抽出する）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extended: Option<ExtendedMetadata>,
+    /// 内容のハッシュ（BLAKE3、重複検出で初めて必要になったときに計算する）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// 知覚ハッシュ（回転を適用したサムネイルから計算し、回転を変更したら破棄する）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub perceptual_hash: Option<PerceptualHash>,
+}
+
+/// インデックスファイルの内容
+#[derive(Debug, Deserialize)]
+struct IndexFile {
+    /// パス -> 画像
+    #[serde(default)]
+    images: HashMap<String, IndexedImage>,
+    /// フィルタ設定のハッシュ -> 走査スナップショット
+    #[serde(default)]
+    snapshots: HashMap<u64, DirectorySnapshot>,
+}
+
+/// 保存用のインデックスファイルの内容（内部状態を借用する）
+#[derive(Serialize)]
+struct IndexFileRef<'a> {
+    version: u32,
+    images: &'a HashMap<String, IndexedImage>,
+    snapshots: HashMap<u64, &'a DirectorySnapshot>,
+}
+
+/// 回転の指定ファイルの内容
+#[derive(Debug, Serialize, Deserialize)]
+struct RotationsFile {
+    version: u32,
+    /// パス -> ユーザーが指定した回転
+    #[serde(default)]
+    rotations: HashMap<String, Rotation>,
+}
+
+/// インデックスの内部状態
+#[derive(Debug, Default)]
+struct IndexState {
+    /// パス -> 画像（保存中もロックを外せるよう共有し、変更時に必要であれば複製する）
+    images: Arc<HashMap<String, IndexedImage>>,
+    /// フィルタ設定のハッシュ -> 走査スナップショット
+    snapshots: HashMap<u64, Arc<DirectorySnapshot>>,
+    /// パス -> ユーザーが指定した回転（画像の記録とは別に保存する）
+    rotations: HashMap<String, Rotation>,
+    /// 保存していない変更があるかどうか
+    dirty: bool,
+    /// 保存していない回転の変更があるかどうか
+    rotations_dirty: bool,
+    /// 最後に保存した時刻
+    last_saved: Option<Instant>,
+}
+
+/// 永続画像インデックス
+#[derive(Debug)]
+pub struct ImageIndex {
+    /// インデックスファイルのパス
+    path: PathBuf,
+    state: Mutex<IndexState>,
+    /// 保存を1つずつ行うためのロック（書き込み中も `state` は他の操作に使える）
+    save_lock: Mutex<()>,
+}
+
+impl ImageIndex {
+    /// インデックスファイルを開く
+    ///
+    /// ファイルが存在しない場合は空のインデックスを作成する。
+    /// 壊れている場合や形式バージョンが異なる場合は破棄して作り直す。
+    /// 回転の指定は別のファイル（`rotations_path`）から読み込むため、作り直しても失われない。
+    pub fn open(path: PathBuf) -> Self {
+        let index_bytes = fs::read(&path);
+        let mut state = match &index_bytes {
+            Ok(bytes) => match parse_index(bytes) {
+                Ok(file) => IndexState {
+                    images: Arc::new(file.images),
+                    snapshots: file.snapshots.into_iter()
+                        .map(|(hash, snapshot)| (hash, Arc::new(snapshot)))
+                        .collect(),
+                    ..IndexState::default()
+                },
+                Err(e) => {
+                    log::warn!("Rebuilding image index {}: {}", path.display(), e);
+                    IndexState { dirty: true, ..IndexState::default() }
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => IndexState::default(),
+            Err(e) => {
+                log::warn!("Failed to read image index {}: {}", path.display(), e);
+                IndexState { dirty: true, ..IndexState::default() }
+            }
+        };
+
+        let rotations_path = rotations_path(&path);
+        match fs::read(&rotations_path) {
+            Ok(bytes) => match serde_json::from_slice::<RotationsFile>(&bytes) {
+                Ok(file) => state.rotations = file.rotations,
+                Err(e) => {
+                    // 読めないファイルは上書きせずに退避する
+                    log::warn!("Failed to parse image rotations {}: {}", rotations_path.display(), e);
+                    if let Err(e) = fs::rename(&rotations_path, rotations_path.with_extension("json.corrupt")) {
+                        log::warn!("Failed to back up image rotations: {}", e);
+                    }
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                // 回転をインデックスに保存していた形式からの移行（バージョンが異なるインデックスからも取り出す）
+                if let Ok(bytes) = &index_bytes {
+                    state.rotations = salvage_rotations(bytes);
+                    state.rotations_dirty = !state.rotations.is_empty();
+                }
+            },
+            Err(e) => log::warn!("Failed to read image rotations {}: {}", rotations_path.display(), e),
+        }
+
+        // 画像の記録は回転の指定に合わせる
+        let IndexState { images, rotations, dirty, .. } = &mut state;
+        for (image_path, image) in Arc::make_mut(images).iter_mut() {
+            let rotation = rotations.get(image_path).copied().unwrap_or_default();
+            if image.metadata.rotation != rotation {
+                image.metadata.set_rotation(rotation);
+                image.perceptual_hash = None;
+                *dirty = true;
+            }
+        }
+
+        Self {
+            path,
+            state: Mutex::new(state),
+            save_lock: Mutex::new(()),
+        }
+    }
+
+    /// インデックスファイルのパスを取得
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 記録された画像数を取得
+    pub fn len(&self) -> usize {
+        self.state.lock().map(|state| state.images.len()).unwrap_or(0)
+    }
+
+    /// 記録された画像がないかどうか
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// ファイルの状態が一致する場合に記録済みの画像を取得
+    pub fn lookup(&self, path: &str, stamp: &FileStamp) -> Option<IndexedImage> {
+        let state = self.state.lock().ok()?;
+        state.images.get(path)
+            .filter(|image| image.stamp == *stamp)
+            .cloned()
+    }
+
+    /// 画像を記録（ユーザーが指定した回転はファイルが変更されても引き継ぐ）
+    pub fn upsert(&self, mut image: IndexedImage) {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(rotation) = state.rotations.get(&image.metadata.path).copied() {
+                image.metadata.set_rotation(rotation);
+            }
+            Arc::make_mut(&mut state.images).insert(image.metadata.path.clone(), image);
+            state.dirty = true;
+        }
+    }
+
+    /// 記録済みの画像に拡張メタデータを追加（ファイルの状態が一致しない場合は false）
+    pub fn set_extended(&self, path: &str, stamp: &FileStamp, extended: ExtendedMetadata) -> bool {
+        let Ok(mut state) = self.state.lock() else { return false };
+        match Arc::make_mut(&mut state.images).get_mut(path).filter(|image| image.stamp == *stamp) {
+            Some(image) => {
+                image.extended = Some(extended);
+                state.dirty = true;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// 記録済みの画像に内容のハッシュを追加（ファイルの状態が一致しない場合は false）
+    pub fn set_content_hash(&self, path: &str, stamp: &FileStamp, hash: String) -> bool {
+        let Ok(mut state) = self.state.lock() else { return false };
+        match Arc::make_mut(&mut state.images).get_mut(path).filter(|image| image.stamp == *stamp) {
+            Some(image) => {
+                image.content_hash = Some(hash);
+                state.dirty = true;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// 記録済みの画像に知覚ハッシュを追加（ファイルの状態が一致しない場合は false）
+    pub fn set_perceptual_hash(&self, path: &str, stamp: &FileStamp, hash: PerceptualHash) -> bool {
+        let Ok(mut state) = self.state.lock() else { return false };
+        match Arc::make_mut(&mut state.images).get_mut(path).filter(|image| image.stamp == *stamp) {
+            Some(image) => {
+                image.perceptual_hash = Some(hash);
+                state.dirty = true;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// 記録済みの画像に代表色を追加（ファイルの状態が一致しない場合は false）
+    pub fn set_palette(&self, path: &str, stamp: &FileStamp, palette: ColorPalette) -> bool {
+        let Ok(mut state) = self.state.lock() else { return false };
+        match Arc::make_mut(&mut state.images).get_mut(path).filter(|image| image.stamp == *stamp) {
+            Some(image) => {
+                image.metadata.palette = Some(palette);
+                state.dirty = true;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// 知覚ハッシュを計算済みのすべての画像（パス, ハッシュ）
+    pub fn perceptual_hashes(&self) -> Vec<(String, PerceptualHash)> {
+        let Ok(state) = self.state.lock() else { return Vec::new() };
+        let mut hashes: Vec<(String, PerceptualHash)> = state.images.iter()
+            .filter_map(|(path, image)| image.perceptual_hash.map(|hash| (path.clone(), hash)))
+            .collect();
+        hashes.sort_by(|a, b| natural_cmp(&a.0, &b.0));
+        hashes
+    }
+
+    /// ユーザーが指定した回転を取得（記録がなければ回転なし）
+    pub fn rotation(&self, path: &str) -> Rotation {
+        self.state.lock().ok()
+            .and_then(|state| state.rotations.get(path).copied())
+            .unwrap_or_default()
+    }
+
+    /// 記録済みの画像に回転を設定し、更新後のメタデータを返す（記録がなければ None）
+    pub fn set_rotation(&self, path: &str, rotation: Rotation) -> Option<ImageMetadata> {
+        let mut state = self.state.lock().ok()?;
+        let image = Arc::make_mut(&mut state.images).get_mut(path)?;
+        image.metadata.set_rotation(rotation);
+        image.perceptual_hash = None;
+        let metadata = image.metadata.clone();
+        if rotation.is_none() {
+            state.rotations.remove(path);
+        } else {
+            state.rotations.insert(path.to_string(), rotation);
+        }
+        state.dirty = true;
+        state.rotations_dirty = true;
+        Some(metadata)
+    }
+
+    /// 画像の記録を削除（パス配下の画像やアーカイブ内の画像もすべて削除する）
+    ///
+    /// 回転の指定はユーザーのデータのため残す（取り外したドライブが戻ったときなどに再び使う）。
+    pub fn remove(&self, path: &str) {
+        if let Ok(mut state) = self.state.lock() {
+            let prefix = Path::new(path);
+            let before = state.images.len();
+            Arc::make_mut(&mut state.images).retain(|image_path, _| !archive::physical_path(image_path).starts_with(prefix));
+            if state.images.len() != before {
+                state.dirty = true;
+            }
+        }
+    }
+
+    /// 走査スナップショットを取得
+    pub fn snapshots(&self) -> HashMap<u64, Arc<DirectorySnapshot>> {
+        self.state.lock()
+            .map(|state| state.snapshots.clone())
+            .unwrap_or_default()
+    }
+
+    /// 走査スナップショットを記録（走査の古いものから `MAX_SNAPSHOTS` を超えた分を削除する）
+    pub fn set_snapshot(&self, filter_hash: u64, snapshot: Arc<DirectorySnapshot>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.snapshots.insert(filter_hash, snapshot);
+            resource_scanner::evict_snapshots(&mut state.snapshots, resource_scanner::MAX_SNAPSHOTS);
+            state.dirty = true;
+        }
+    }
+
+    /// 走査スナップショットを削除
+    pub fn remove_snapshot(&self, filter_hash: u64) {
+        if let Ok(mut state) = self.state.lock() {
+            if state.snapshots.remove(&filter_hash).is_some() {
+                state.dirty = true;
+            }
+        }
+    }
+
+    /// すべての記録を削除（回転の指定は残す）
+    pub fn clear(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.images = Arc::default();
+            state.snapshots.clear();
+            state.dirty = true;
+        }
+    }
+
+    /// 変更があればインデックスファイルと回転の指定ファイルに保存（一時ファイル経由で置き換え）
+    ///
+    /// ロック中は保存する内容を取り出すだけにし、シリアライズと書き込みはロックを外して行う。
+    pub fn save(&self) -> Result<(), String> {
+        self.save_parts(true)
+    }
+
+    /// 前回の保存から `SAVE_INTERVAL` 以上経っている場合のみ保存
+    ///
+    /// 保存しなかった変更は、次の呼び出しか自動保存（`spawn_autosave`）で保存される。
+    pub fn save_if_due(&self) -> Result<(), String> {
+        let due = self.state.lock()
+            .map(|state| state.last_saved.is_none_or(|saved| saved.elapsed() >= SAVE_INTERVAL))
+            .map_err(|e| format!("Failed to lock image index: {}", e))?;
+        if due {
+            self.save()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// 回転の指定ファイルだけを保存（ユーザーの操作はすぐに保存し、インデックスは後でまとめて保存する）
+    pub fn save_rotations(&self) -> Result<(), String> {
+        self.save_parts(false)
+    }
+
+    /// 一定間隔で未保存の変更を保存するスレッドを開始（インデックスが破棄されると終了する）
+    pub fn spawn_autosave(index: &Arc<Self>, interval: Duration) {
+        let index = Arc::downgrade(index);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            let Some(index) = index.upgrade() else { break };
+            if let Err(e) = index.save() {
+                log::warn!("Failed to save image index {}: {}", index.path.display(), e);
+            }
+        });
+    }
+
+    /// 回転の指定と（`include_index` の場合は）インデックスの変更を保存
+    fn save_parts(&self, include_index: bool) -> Result<(), String> {
+        let _saving = self.save_lock.lock().map_err(|e| {
+            format!("Failed to lock image index: {}", e)
+        })?;
+        let (rotations, index) = {
+            let mut state = self.state.lock().map_err(|e| {
+                format!("Failed to lock image index: {}", e)
+            })?;
+            let rotations = state.rotations_dirty.then(|| state.rotations.clone());
+            let index = (include_index && state.dirty)
+                .then(|| (Arc::clone(&state.images), state.snapshots.clone()));
+            state.rotations_dirty = false;
+            if index.is_some() {
+                state.dirty = false;
+                state.last_saved = Some(Instant::now());
+            }
+            (rotations, index)
+        };
+
+        if let Some(rotations) = rotations {
+            let file = RotationsFile { version: ROTATIONS_VERSION, rotations };
+            let result = serde_json::to_vec_pretty(&file)
+                .map_err(|e| format!("Failed to serialize image rotations: {}", e))
+                .and_then(|json| write_atomic(&rotations_path(&self.path), &json)
+                    .map_err(|e| format!("Failed to write image rotations: {}", e)));
+            if let Err(e) = result {
+                self.mark_unsaved(true, index.is_some());
+                return Err(e);
+            }
+        }
+
+        if let Some((images, snapshots)) = index {
+            let file = IndexFileRef {
+                version: INDEX_VERSION,
+                images: &images,
+                snapshots: snapshots.iter()
+                    .map(|(hash, snapshot)| (*hash, snapshot.as_ref()))
+                    .collect(),
+            };
+            let result = serde_json::to_vec(&file)
+                .map_err(|e| format!("Failed to serialize image index: {}", e))
+                .and_then(|json| write_atomic(&self.path, &json)
+                    .map_err(|e| format!("Failed to write image index: {}", e)));
+            if let Err(e) = result {
+                self.mark_unsaved(false, true);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// 保存に失敗した変更を未保存に戻す
+    fn mark_unsaved(&self, rotations: bool, index: bool) {
+        if let Ok(mut state) = self.state.lock() {
+            state.rotations_dirty |= rotations;
+            state.dirty |= index;
+        }
+    }
+}
+
+/// 回転の指定ファイルのパス（インデックスファイルと同じディレクトリ）
+fn rotations_path(index_path: &Path) -> PathBuf {
+    index_path.with_extension("rotations.json")
+}
+
+/// 一時ファイルに書き込んでから置き換える
+fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    fs::write(&temp_path, bytes)?;
+    fs::rename(&temp_path, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp_path);
+    })
+}
+
+/// 回転をインデックスに保存していた形式のファイルから回転を取り出す（バージョンは問わない）
+fn salvage_rotations(bytes: &[u8]) -> HashMap<String, Rotation> {
+    #[derive(Deserialize)]
+    struct LegacyMetadata {
+        #[serde(default)]
+        rotation: Rotation,
+    }
+    #[derive(Deserialize)]
+    struct LegacyImage {
+        metadata: LegacyMetadata,
+    }
+    #[derive(Deserialize)]
+    struct LegacyIndex {
+        #[serde(default)]
+        images: HashMap<String, LegacyImage>,
+    }
+
+    serde_json::from_slice::<LegacyIndex>(bytes)
+        .map(|index| {
+            index.images.into_iter()
+                .filter(|(_, image)| !image.metadata.rotation.is_none())
+                .map(|(path, image)| (path, image.metadata.rotation))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// インデックスファイルを解析（バージョンが異なる場合はエラー）
+fn parse_index(bytes: &[u8]) -> Result<IndexFile, String> {
+    // 本体を解析する前にバージョンだけを確認する
+    #[derive(Deserialize)]
+    struct VersionOnly {
+        version: u32,
+    }
+
+    let header: VersionOnly = serde_json::from_slice(bytes)
+        .map_err(|e| format!("Invalid index file: {}", e))?;
+    if header.version != INDEX_VERSION {
+        return Err(format!("Unsupported index version: {} (expected {})", header.version, INDEX_VERSION));
+    }
+
+    serde_json::from_slice(bytes)
+        .map_err(|e| format!("Invalid index file: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("image-viewer-index-{}-{}", name, std::process::id())).join("index.json")
+    }
+
+    fn create_image(path: &str, size: u64) -> IndexedImage {
+        IndexedImage {
+            stamp: FileStamp { size, modified: Some(SystemTime::UNIX_EPOCH) },
+            metadata: ImageMetadata {
+                path: path.to_string(),
+                file_name: path.rsplit('/').next().unwrap_or(path).to_string(),
+                file_size: size,
+                format: None,
+                dimensions: Some((640, 480)),
+                orientation: None,
+                rotation: Rotation::None,
+                display_dimensions: Some((640, 480)),
+                gps: None,
+                palette: None,
+                date_created: None,
+                date_modified: None,
+                companions: Vec::new(),
+            },
+            warning: None,
+            extended: None,
+            content_hash: None,
+            perceptual_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_save_and_reopen() {
+        let path = index_path("reopen");
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+
+        let index = ImageIndex::open(path.clone());
+        assert!(index.is_empty());
+        index.upsert(create_image("/photos/a.jpg", 100));
+        index.upsert(create_image("/photos/sub/b.jpg", 200));
+        index.set_snapshot(42, Arc::new(DirectorySnapshot::default()));
+        index.save().unwrap();
+
+        let reopened = ImageIndex::open(path.clone());
+        assert_eq!(reopened.len(), 2);
+        assert!(reopened.snapshots().contains_key(&42));
+
+        // 状態が一致する場合のみ記録を返す
+        let stamp = FileStamp { size: 100, modified: Some(SystemTime::UNIX_EPOCH) };
+        assert!(reopened.lookup("/photos/a.jpg", &stamp).is_some());
+        let changed = FileStamp { size: 101, ..stamp };
+        assert!(reopened.lookup("/photos/a.jpg", &changed).is_none());
+
+        // 拡張メタデータは状態が一致する記録にだけ追加する
+        let extended = ExtendedMetadata { orientation: Some(6), ..ExtendedMetadata::default() };
+        assert!(!reopened.set_extended("/photos/a.jpg", &changed, extended.clone()));
+        assert!(reopened.set_extended("/photos/a.jpg", &stamp, extended.clone()));
+        assert_eq!(reopened.lookup("/photos/a.jpg", &stamp).unwrap().extended, Some(extended));
+        assert!(!reopened.set_content_hash("/photos/a.jpg", &changed, "ab".to_string()));
+        assert!(reopened.set_content_hash("/photos/a.jpg", &stamp, "ab".to_string()));
+        assert_eq!(reopened.lookup("/photos/a.jpg", &stamp).unwrap().content_hash.as_deref(), Some("ab"));
+
+        // 回転は表示時の寸法に反映され、ファイルが変更されて記録し直しても引き継ぐ
+        let rotated = reopened.set_rotation("/photos/a.jpg", Rotation::Clockwise90).unwrap();
+        assert_eq!(rotated.display_dimensions, Some((480, 640)));
+        reopened.upsert(create_image("/photos/a.jpg", 101));
+        assert_eq!(reopened.rotation("/photos/a.jpg"), Rotation::Clockwise90);
+        assert_eq!(reopened.lookup("/photos/a.jpg", &changed).unwrap().metadata.display_dimensions, Some((480, 640)));
+
+        // ディレクトリ単位で削除できる
+        reopened.remove("/photos/sub");
+        assert_eq!(reopened.len(), 1);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_frequent_saves_are_batched() {
+        let path = index_path("batched");
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+
+        let index = ImageIndex::open(path.clone());
+        index.upsert(create_image("/photos/a.jpg", 100));
+        index.save_if_due().unwrap();
+        assert_eq!(ImageIndex::open(path.clone()).len(), 1);
+
+        // 前回の保存から間もない場合は書き込まず、明示的な保存で書き込む
+        index.upsert(create_image("/photos/b.jpg", 100));
+        index.save_if_due().unwrap();
+        assert_eq!(ImageIndex::open(path.clone()).len(), 1);
+        index.save().unwrap();
+        assert_eq!(ImageIndex::open(path.clone()).len(), 2);
+
+        // 回転の指定はインデックスとは別にすぐ保存できる
+        index.set_rotation("/photos/a.jpg", Rotation::Clockwise180).unwrap();
+        index.save_rotations().unwrap();
+        assert_eq!(ImageIndex::open(path.clone()).rotation("/photos/a.jpg"), Rotation::Clockwise180);
+        assert!(!path.with_file_name("index.json.tmp").exists());
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_rebuild_on_corruption_or_version_mismatch() {
+        let path = index_path("corrupt");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        fs::write(&path, b"{ not json").unwrap();
+        let index = ImageIndex::open(path.clone());
+        assert!(index.is_empty());
+        // 作り直したインデックスは次の保存で置き換えられる
+        index.save().unwrap();
+        let saved: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(saved["version"], INDEX_VERSION);
+
+        fs::write(&path, format!(r#"{{"version": {}, "images": {{}}}}"#, INDEX_VERSION + 1)).unwrap();
+        assert!(ImageIndex::open(path.clone()).is_empty());
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_rotations_survive_index_rebuild() {
+        let path = index_path("rotations");
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        // 回転をインデックスに保存していた古いバージョンのファイルから移行する
+        let mut legacy = create_image("/photos/a.jpg", 100);
+        legacy.metadata.set_rotation(Rotation::Clockwise90);
+        let legacy_file = serde_json::json!({ "version": 2, "images": { "/photos/a.jpg": legacy } });
+        fs::write(&path, serde_json::to_vec(&legacy_file).unwrap()).unwrap();
+        let index = ImageIndex::open(path.clone());
+        assert!(index.is_empty());
+        assert_eq!(index.rotation("/photos/a.jpg"), Rotation::Clockwise90);
+        index.save().unwrap();
+        assert!(rotations_path(&path).exists());
+
+        // インデックスの形式バージョンが上がって作り直されても回転は残る
+        fs::write(&path, format!(r#"{{"version": {}, "images": {{}}}}"#, INDEX_VERSION + 1)).unwrap();
+        let rebuilt = ImageIndex::open(path.clone());
+        assert!(rebuilt.is_empty());
+        assert_eq!(rebuilt.rotation("/photos/a.jpg"), Rotation::Clockwise90);
+        rebuilt.upsert(create_image("/photos/a.jpg", 100));
+        let stamp = FileStamp { size: 100, modified: Some(SystemTime::UNIX_EPOCH) };
+        assert_eq!(rebuilt.lookup("/photos/a.jpg", &stamp).unwrap().metadata.display_dimensions, Some((480, 640)));
+
+        // 回転を戻すと指定ファイルからも取り除く
+        rebuilt.set_rotation("/photos/a.jpg", Rotation::None).unwrap();
+        rebuilt.save().unwrap();
+        assert_eq!(ImageIndex::open(path.clone()).rotation("/photos/a.jpg"), Rotation::None);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,623 @@
+// core/image_probe.rs
+// 画像ヘッダーのプローブ - ピクセルをデコードせずに寸法とファイル日時を取得する
+
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+use crate::core::image_format::ImageFormat;
+
+/// ヘッダープローブのエラー型
+#[derive(Error, Debug)]
+pub enum ProbeError {
+    #[error("Failed to read file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Header is truncated")]
+    Truncated,
+
+    #[error("Unrecognized image signature")]
+    UnknownFormat,
+
+    #[error("Malformed {0} header: {1}")]
+    Malformed(&'static str, String),
+}
+
+/// 1ファイル分のプローブ結果
+#[derive(Debug)]
+pub struct ProbeResult {
+    /// シグネチャから判定した形式（判定できない場合は None）
+    pub format: Option<ImageFormat>,
+    /// 画像の寸法（幅, 高さ）。ヘッダーが読めない場合はエラー
+    pub dimensions: Result<(u32, u32), ProbeError>,
+    /// 作成日時（ISO-8601, UTC）
+    pub date_created: Option<String>,
+    /// 更新日時（ISO-8601, UTC）
+    pub date_modified: Option<String>,
+}
+
+/// JPEGのフレームヘッダー（SOFセグメント）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JpegFrame {
+    pub width: u32,
+    pub height: u32,
+    /// 可逆圧縮（SOF3/7/11/15）かどうか。RAWのセンサーデータに使われる
+    pub lossless: bool,
+}
+
+/// ヘッダー判定に必要な先頭バイト数
+const SIGNATURE_LEN: usize = 32;
+
+/// ファイルの寸法と日時をまとめて取得
+pub fn probe_image(path: &Path) -> ProbeResult {
+    let (date_created, date_modified) = match fs::metadata(path) {
+        Ok(metadata) => file_times(&metadata),
+        Err(_) => (None, None),
+    };
+
+    let (format, dimensions) = match File::open(path) {
+        Ok(file) => read_header(BufReader::new(file), &file_name(path)),
+        Err(e) => (None, Err(e.into())),
+    };
+    ProbeResult {
+        format,
+        dimensions,
+        date_created,
+        date_modified,
+    }
+}
+
+/// ファイルヘッダーから画像の寸法を取得
+pub fn probe_dimensions(path: &Path) -> Result<(u32, u32), ProbeError> {
+    let file = File::open(path)?;
+    read_header(BufReader::new(file), &file_name(path)).1
+}
+
+/// 任意のリーダーから画像の寸法を取得
+pub fn read_dimensions<R: Read>(reader: R) -> Result<(u32, u32), ProbeError> {
+    read_header(reader, "").1
+}
+
+/// 任意のリーダーから画像の形式と寸法を取得（形式が判定できても寸法が読めない場合がある）
+///
+/// `file_name` は拡張子による形式の補正に使う（不明な場合は空文字列）。
+pub fn read_header<R: Read>(mut reader: R, file_name: &str) -> (Option<ImageFormat>, Result<(u32, u32), ProbeError>) {
+    let mut header = [0u8; SIGNATURE_LEN];
+    let len = match read_up_to(&mut reader, &mut header) {
+        Ok(len) => len,
+        Err(e) => return (None, Err(e)),
+    };
+    let header = &header[..len];
+
+    let format = ImageFormat::detect_with_name(header, file_name);
+    let dimensions = match format {
+        // JPEGはSOFセグメントまでマーカーを辿る必要がある
+        Some(ImageFormat::Jpeg) => read_jpeg_frame(header.chain(reader)).map(|frame| (frame.width, frame.height)),
+        Some(ImageFormat::Png) => png_dimensions(header),
+        Some(ImageFormat::Gif) => gif_dimensions(header),
+        Some(ImageFormat::Bmp) => bmp_dimensions(header),
+        Some(ImageFormat::Webp) => webp_dimensions(header),
+        // TIFF・HEIF・JPEG XLは先頭以降のIFDやボックスを辿る（RAWはTIFFの寸法。プレビューの寸法はrawモジュールで取得する）
+        Some(ImageFormat::Tiff | ImageFormat::Raw) => tiff_dimensions(header.chain(reader)),
+        Some(ImageFormat::Avif | ImageFormat::Heic) => heif_dimensions(header.chain(reader)),
+        Some(ImageFormat::Jxl) => jxl_dimensions(header.chain(reader)),
+        None if header.len() < 12 => Err(ProbeError::Truncated),
+        None => Err(ProbeError::UnknownFormat),
+    };
+    (format, dimensions)
+}
+
+/// 形式の補正に使うファイル名
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+/// バッファが埋まるかEOFに達するまで読み込む
+fn read_up_to<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize, ProbeError> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(filled)
+}
+
+/// バッファを埋めるまで読み込む（足りない場合は切り詰めとして扱う）
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<(), ProbeError> {
+    if read_up_to(reader, buf)? < buf.len() {
+        return Err(ProbeError::Truncated);
+    }
+    Ok(())
+}
+
+fn u16_le(bytes: &[u8], offset: usize) -> Option<u16> {
+    bytes.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn u24_le(bytes: &[u8], offset: usize) -> Option<u32> {
+    bytes.get(offset..offset + 3).map(|b| u32::from_le_bytes([b[0], b[1], b[2], 0]))
+}
+
+fn u32_be(bytes: &[u8], offset: usize) -> Option<u32> {
+    bytes.get(offset..offset + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn u32_le(bytes: &[u8], offset: usize) -> Option<u32> {
+    bytes.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// 1バイト読み込み（EOFは切り詰めとして扱う）
+fn read_byte<R: Read>(reader: &mut R) -> Result<u8, ProbeError> {
+    let mut byte = [0u8; 1];
+    if read_up_to(reader, &mut byte)? == 0 {
+        return Err(ProbeError::Truncated);
+    }
+    Ok(byte[0])
+}
+
+/// 指定バイト数を読み飛ばす
+fn skip_bytes<R: Read>(reader: &mut R, count: u64) -> Result<(), ProbeError> {
+    let skipped = std::io::copy(&mut reader.take(count), &mut std::io::sink())?;
+    if skipped < count {
+        return Err(ProbeError::Truncated);
+    }
+    Ok(())
+}
+
+/// JPEG: SOFnセグメントからフレームヘッダーを取得
+pub fn read_jpeg_frame<R: Read>(mut reader: R) -> Result<JpegFrame, ProbeError> {
+    // SOIマーカーを読み飛ばす
+    skip_bytes(&mut reader, 2)?;
+
+    loop {
+        // マーカーの前の0xFFパディングを読み飛ばす
+        let mut byte = read_byte(&mut reader)?;
+        if byte != 0xFF {
+            return Err(ProbeError::Malformed("JPEG", format!("expected marker, found 0x{:02X}", byte)));
+        }
+        while byte == 0xFF {
+            byte = read_byte(&mut reader)?;
+        }
+        let marker = byte;
+
+        // 長さを持たないマーカー（RSTn, TEM, SOI）
+        if (0xD0..=0xD8).contains(&marker) || marker == 0x01 {
+            continue;
+        }
+        if marker == 0xD9 || marker == 0xDA {
+            return Err(ProbeError::Malformed("JPEG", "no SOF segment before image data".to_string()));
+        }
+
+        let mut len_buf = [0u8; 2];
+        if read_up_to(&mut reader, &mut len_buf)? < 2 {
+            return Err(ProbeError::Truncated);
+        }
+        let segment_len = u16::from_be_bytes(len_buf);
+        if segment_len < 2 {
+            return Err(ProbeError::Malformed("JPEG", format!("invalid segment length {}", segment_len)));
+        }
+
+        // SOF0-SOF15（DHT, JPG, DACを除く）
+        let is_sof = matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+        if is_sof {
+            let mut sof = [0u8; 5];
+            if read_up_to(&mut reader, &mut sof)? < sof.len() {
+                return Err(ProbeError::Truncated);
+            }
+            let height = u16::from_be_bytes([sof[1], sof[2]]) as u32;
+            let width = u16::from_be_bytes([sof[3], sof[4]]) as u32;
+            if width == 0 || height == 0 {
+                return Err(ProbeError::Malformed("JPEG", "zero dimension in SOF".to_string()));
+            }
+            let lossless = matches!(marker, 0xC3 | 0xC7 | 0xCB | 0xCF);
+            return Ok(JpegFrame { width, height, lossless });
+        }
+
+        skip_bytes(&mut reader, (segment_len - 2) as u64)?;
+    }
+}
+
+/// PNG: IHDRチャンクから寸法を取得
+fn png_dimensions(header: &[u8]) -> Result<(u32, u32), ProbeError> {
+    if header.len() < 24 {
+        return Err(ProbeError::Truncated);
+    }
+    if &header[12..16] != b"IHDR" {
+        return Err(ProbeError::Malformed("PNG", "first chunk is not IHDR".to_string()));
+    }
+    let width = u32_be(header, 16).ok_or(ProbeError::Truncated)?;
+    let height = u32_be(header, 20).ok_or(ProbeError::Truncated)?;
+    Ok((width, height))
+}
+
+/// GIF: 論理スクリーン記述子から寸法を取得
+fn gif_dimensions(header: &[u8]) -> Result<(u32, u32), ProbeError> {
+    let width = u16_le(header, 6).ok_or(ProbeError::Truncated)?;
+    let height = u16_le(header, 8).ok_or(ProbeError::Truncated)?;
+    Ok((width as u32, height as u32))
+}
+
+/// BMP: DIBヘッダーから寸法を取得
+fn bmp_dimensions(header: &[u8]) -> Result<(u32, u32), ProbeError> {
+    let dib_size = u32_le(header, 14).ok_or(ProbeError::Truncated)?;
+
+    if dib_size == 12 {
+        // BITMAPCOREHEADER（OS/2形式）
+        let width = u16_le(header, 18).ok_or(ProbeError::Truncated)?;
+        let height = u16_le(header, 20).ok_or(ProbeError::Truncated)?;
+        return Ok((width as u32, height as u32));
+    }
+    if dib_size < 40 {
+        return Err(ProbeError::Malformed("BMP", format!("unsupported DIB header size {}", dib_size)));
+    }
+
+    let width = u32_le(header, 18).ok_or(ProbeError::Truncated)? as i32;
+    // 高さが負の場合はトップダウン形式
+    let height = u32_le(header, 22).ok_or(ProbeError::Truncated)? as i32;
+    Ok((width.unsigned_abs(), height.unsigned_abs()))
+}
+
+/// WebP: VP8 / VP8L / VP8X チャンクから寸法を取得
+fn webp_dimensions(header: &[u8]) -> Result<(u32, u32), ProbeError> {
+    let chunk = header.get(12..16).ok_or(ProbeError::Truncated)?;
+
+    match chunk {
+        b"VP8 " => {
+            // 3バイトのフレームタグの後にスタートコードが続く
+            let start_code = header.get(23..26).ok_or(ProbeError::Truncated)?;
+            if start_code != [0x9D, 0x01, 0x2A] {
+                return Err(ProbeError::Malformed("WebP", "missing VP8 start code".to_string()));
+            }
+            let width = u16_le(header, 26).ok_or(ProbeError::Truncated)? & 0x3FFF;
+            let height = u16_le(header, 28).ok_or(ProbeError::Truncated)? & 0x3FFF;
+            Ok((width as u32, height as u32))
+        },
+        b"VP8L" => {
+            if header.get(20) != Some(&0x2F) {
+                return Err(ProbeError::Malformed("WebP", "missing VP8L signature".to_string()));
+            }
+            let bits = u32_le(header, 21).ok_or(ProbeError::Truncated)?;
+            let width = (bits & 0x3FFF) + 1;
+            let height = ((bits >> 14) & 0x3FFF) + 1;
+            Ok((width, height))
+        },
+        b"VP8X" => {
+            let width = u24_le(header, 24).ok_or(ProbeError::Truncated)? + 1;
+            let height = u24_le(header, 27).ok_or(ProbeError::Truncated)? + 1;
+            Ok((width, height))
+        },
+        _ => Err(ProbeError::Malformed("WebP", format!("unknown chunk {:?}", String::from_utf8_lossy(chunk)))),
+    }
+}
+
+/// TIFF: 最初のIFDのImageWidth / ImageLengthタグから寸法を取得
+fn tiff_dimensions<R: Read>(mut reader: R) -> Result<(u32, u32), ProbeError> {
+    let mut header = [0u8; 8];
+    read_full(&mut reader, &mut header)?;
+    let big_endian = &header[0..2] == b"MM";
+    let read_u16 = |b: &[u8]| if big_endian { u16::from_be_bytes([b[0], b[1]]) } else { u16::from_le_bytes([b[0], b[1]]) };
+    let read_u32 = |b: &[u8]| if big_endian {
+        u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+    } else {
+        u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+    };
+
+    let ifd_offset = read_u32(&header[4..8]) as u64;
+    if ifd_offset < 8 {
+        return Err(ProbeError::Malformed("TIFF", format!("invalid IFD offset {}", ifd_offset)));
+    }
+    skip_bytes(&mut reader, ifd_offset - 8)?;
+
+    let mut count = [0u8; 2];
+    read_full(&mut reader, &mut count)?;
+    let (mut width, mut height) = (None, None);
+    for _ in 0..read_u16(&count) {
+        let mut entry = [0u8; 12];
+        read_full(&mut reader, &mut entry)?;
+        // SHORT(3) または LONG(4) の値はエントリ内に直接格納される
+        let value = match read_u16(&entry[2..4]) {
+            3 => read_u16(&entry[8..10]) as u32,
+            4 => read_u32(&entry[8..12]),
+            _ => continue,
+        };
+        match read_u16(&entry[0..2]) {
+            256 => width = Some(value),
+            257 => height = Some(value),
+            _ => {},
+        }
+        if let (Some(width), Some(height)) = (width, height) {
+            return Ok((width, height));
+        }
+    }
+    Err(ProbeError::Malformed("TIFF", "missing ImageWidth or ImageLength tag".to_string()))
+}
+
+/// HEIF/AVIF: meta > iprp > ipco 内のispeプロパティから寸法を取得
+///
+/// サムネイルやタイルにもispeが付くため、最も大きいものを主画像の寸法とみなす。
+fn heif_dimensions<R: Read>(mut reader: R) -> Result<(u32, u32), ProbeError> {
+    let meta_len = find_box(&mut reader, b"meta")?;
+    let mut meta = reader.take(meta_len);
+    // metaはFullBoxのためversionとflagsを読み飛ばす
+    skip_bytes(&mut meta, 4)?;
+    let iprp_len = find_box(&mut meta, b"iprp")?;
+    let mut iprp = meta.take(iprp_len);
+    let ipco_len = find_box(&mut iprp, b"ipco")?;
+    let mut ipco = iprp.take(ipco_len);
+
+    let mut largest: Option<(u32, u32)> = None;
+    while let Some((kind, len)) = read_box_header(&mut ipco)? {
+        if &kind != b"ispe" || len < 12 {
+            skip_bytes(&mut ipco, len)?;
+            continue;
+        }
+        let mut ispe = [0u8; 12];
+        read_full(&mut ipco, &mut ispe)?;
+        let width = u32_be(&ispe, 4).ok_or(ProbeError::Truncated)?;
+        let height = u32_be(&ispe, 8).ok_or(ProbeError::Truncated)?;
+        let area = |(w, h): (u32, u32)| w as u64 * h as u64;
+        if largest.is_none_or(|current| area((width, height)) > area(current)) {
+            largest = Some((width, height));
+        }
+        skip_bytes(&mut ipco, len - 12)?;
+    }
+    largest.ok_or_else(|| ProbeError::Malformed("HEIF", "no ispe property".to_string()))
+}
+
+/// ISOBMFFのボックスヘッダーを読み込み、種類とペイロード長を返す（EOFでは None）
+fn read_box_header<R: Read>(reader: &mut R) -> Result<Option<([u8; 4], u64)>, ProbeError> {
+    let mut header = [0u8; 8];
+    match read_up_to(reader, &mut header)? {
+        0 => return Ok(None),
+        8 => {},
+        _ => return Err(ProbeError::Truncated),
+    }
+    let kind = [header[4], header[5], header[6], header[7]];
+    let invalid_size = || ProbeError::Malformed("ISOBMFF", format!("invalid size of {} box", String::from_utf8_lossy(&kind)));
+    let len = match u32_be(&header, 0).unwrap_or(0) {
+        // ファイル末尾まで続くボックス
+        0 => u64::MAX,
+        // 64ビットのサイズが続く
+        1 => {
+            let mut large = [0u8; 8];
+            read_full(reader, &mut large)?;
+            u64::from_be_bytes(large).checked_sub(16).ok_or_else(invalid_size)?
+        },
+        size => (size as u64).checked_sub(8).ok_or_else(invalid_size)?,
+    };
+    Ok(Some((kind, len)))
+}
+
+/// 現在の階層から指定した種類のボックスを探し、ペイロード長を返す
+fn find_box<R: Read>(reader: &mut R, wanted: &[u8; 4]) -> Result<u64, ProbeError> {
+    while let Some((kind, len)) = read_box_header(reader)? {
+        if &kind == wanted {
+            return Ok(len);
+        }
+        skip_bytes(reader, len)?;
+    }
+    Err(ProbeError::Malformed("ISOBMFF", format!("missing {} box", String::from_utf8_lossy(wanted))))
+}
+
+/// JPEG XL: コードストリームのSizeHeaderから寸法を取得（コンテナ形式はjxlc / jxlpボックスを探す）
+fn jxl_dimensions<R: Read>(mut reader: R) -> Result<(u32, u32), ProbeError> {
+    let mut signature = [0u8; 2];
+    read_full(&mut reader, &mut signature)?;
+    if signature != [0xFF, 0x0A] {
+        // コンテナ形式: 12バイトのシグネチャボックスの残りを読み飛ばす
+        skip_bytes(&mut reader, 10)?;
+        loop {
+            let (kind, len) = read_box_header(&mut reader)?
+                .ok_or_else(|| ProbeError::Malformed("JPEG XL", "missing codestream box".to_string()))?;
+            match &kind {
+                b"jxlc" => break,
+                // 部分コードストリームは4バイトの連番の後に続く
+                b"jxlp" => {
+                    skip_bytes(&mut reader, 4)?;
+                    break;
+                },
+                _ => skip_bytes(&mut reader, len)?,
+            }
+        }
+        read_full(&mut reader, &mut signature)?;
+        if signature != [0xFF, 0x0A] {
+            return Err(ProbeError::Malformed("JPEG XL", "missing codestream signature".to_string()));
+        }
+    }
+
+    // SizeHeaderは最大でも9バイトに収まる
+    let mut size_header = [0u8; 9];
+    let len = read_up_to(&mut reader, &mut size_header)?;
+    jxl_size_header(&size_header[..len])
+}
+
+/// JPEG XLのSizeHeaderを解析
+fn jxl_size_header(bytes: &[u8]) -> Result<(u32, u32), ProbeError> {
+    /// 縦横比の指定（1〜7）に対応する 幅:高さ
+    const RATIOS: [(u64, u64); 7] = [(1, 1), (12, 10), (4, 3), (3, 2), (16, 9), (5, 4), (2, 1)];
+
+    let mut bits = BitReader { bytes, position: 0 };
+    let small = bits.read(1)? == 1;
+    let read_size = |bits: &mut BitReader| -> Result<u32, ProbeError> {
+        if small {
+            return Ok((bits.read(5)? + 1) * 8);
+        }
+        let count = [9, 13, 18, 30][bits.read(2)? as usize];
+        Ok(bits.read(count)? + 1)
+    };
+
+    let height = read_size(&mut bits)?;
+    let width = match bits.read(3)? {
+        0 => read_size(&mut bits)?,
+        ratio => {
+            let (numerator, denominator) = RATIOS[ratio as usize - 1];
+            (height as u64 * numerator / denominator) as u32
+        },
+    };
+    Ok((width, height))
+}
+
+/// 下位ビットから順に読むビットリーダー
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    /// 指定ビット数（32以下）を読み込む
+    fn read(&mut self, count: usize) -> Result<u32, ProbeError> {
+        let mut value = 0u32;
+        for bit in 0..count {
+            let byte = *self.bytes.get(self.position / 8).ok_or(ProbeError::Truncated)?;
+            value |= (((byte >> (self.position % 8)) & 1) as u32) << bit;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+}
+
+/// ファイルメタデータから作成日時と更新日時を取得
+pub fn file_times(metadata: &fs::Metadata) -> (Option<String>, Option<String>) {
+    let created = metadata.created().ok().map(format_iso8601);
+    let modified = metadata.modified().ok().map(format_iso8601);
+    (created, modified)
+}
+
+/// SystemTimeをISO-8601形式（UTC）の文字列に変換
+pub fn format_iso8601(time: SystemTime) -> String {
+    let secs = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs_f64().ceil() as i64),
+    };
+
+    let days = secs.div_euclid(86_400);
+    let secs_of_day = secs.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        (secs_of_day % 3600) / 60,
+        secs_of_day % 60
+    )
+}
+
+/// 1970-01-01からの日数をグレゴリオ暦の年月日に変換
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_png_and_gif_headers() {
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        png.extend_from_slice(&640u32.to_be_bytes());
+        png.extend_from_slice(&480u32.to_be_bytes());
+        assert_eq!(read_dimensions(&png[..]).unwrap(), (640, 480));
+
+        let gif = b"GIF89a\x20\x03\x58\x02";
+        assert_eq!(read_dimensions(&gif[..]).unwrap(), (800, 600));
+    }
+
+    #[test]
+    fn test_jpeg_skips_segments_before_sof() {
+        let mut jpeg = vec![0xFF, 0xD8];
+        // APP1セグメント（内容はダミー）
+        jpeg.extend_from_slice(&[0xFF, 0xE1, 0x00, 0x06, 1, 2, 3, 4]);
+        // SOF0: 長さ, 精度, 高さ=300, 幅=400
+        jpeg.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08, 0x01, 0x2C, 0x01, 0x90]);
+        assert_eq!(read_dimensions(&jpeg[..]).unwrap(), (400, 300));
+    }
+
+    #[test]
+    fn test_webp_variants() {
+        let mut vp8x = b"RIFF\0\0\0\0WEBPVP8X\0\0\0\0\0\0\0\0".to_vec();
+        vp8x.extend_from_slice(&[0x7F, 0x07, 0x00, 0x37, 0x04, 0x00]);
+        assert_eq!(read_dimensions(&vp8x[..]).unwrap(), (1920, 1080));
+
+        let mut vp8l = b"RIFF\0\0\0\0WEBPVP8L\0\0\0\0\x2F".to_vec();
+        let bits: u32 = 99 | (49 << 14);
+        vp8l.extend_from_slice(&bits.to_le_bytes());
+        assert_eq!(read_dimensions(&vp8l[..]).unwrap(), (100, 50));
+    }
+
+    #[test]
+    fn test_tiff_heif_and_jxl_headers() {
+        // リトルエンディアンTIFF: ImageWidth(SHORT)=640, ImageLength(LONG)=480
+        let mut tiff = b"II*\0\x08\0\0\0\x02\0".to_vec();
+        tiff.extend_from_slice(&[0x00, 0x01, 0x03, 0x00, 1, 0, 0, 0, 0x80, 0x02, 0, 0]);
+        tiff.extend_from_slice(&[0x01, 0x01, 0x04, 0x00, 1, 0, 0, 0, 0xE0, 0x01, 0, 0]);
+        assert_eq!(read_dimensions(&tiff[..]).unwrap(), (640, 480));
+
+        // HEIC: ftyp, meta > (hdlr, iprp > ipco > ispe x2)。大きい方のispeを使う
+        fn isobmff_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+            let mut bytes = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+            bytes.extend_from_slice(kind);
+            bytes.extend_from_slice(payload);
+            bytes
+        }
+        let ispe = |width: u32, height: u32| {
+            let mut payload = vec![0u8; 4];
+            payload.extend_from_slice(&width.to_be_bytes());
+            payload.extend_from_slice(&height.to_be_bytes());
+            isobmff_box(b"ispe", &payload)
+        };
+        let ipco = isobmff_box(b"ipco", &[ispe(320, 240), ispe(4032, 3024)].concat());
+        let mut meta = vec![0u8; 4];
+        meta.extend(isobmff_box(b"hdlr", b"\0\0\0\0\0\0\0\0pict"));
+        meta.extend(isobmff_box(b"iprp", &ipco));
+        let heic = [isobmff_box(b"ftyp", b"heic\0\0\0\0mif1heic"), isobmff_box(b"meta", &meta)].concat();
+        let (format, dimensions) = read_header(&heic[..], "");
+        assert_eq!(format, Some(ImageFormat::Heic));
+        assert_eq!(dimensions.unwrap(), (4032, 3024));
+
+        // JPEG XL: 8の倍数の高さ32、縦横比なし、幅64
+        let jxl = [0xFF, 0x0A, 0x07, 0x0E];
+        assert_eq!(read_header(&jxl[..], "image.jxl").1.unwrap(), (64, 32));
+        // コードストリームは拡張子が `.jxl` でなければ判定しない
+        assert!(matches!(read_dimensions(&jxl[..]), Err(ProbeError::Truncated)));
+        // コンテナ形式でも同じコードストリームを読む
+        let mut container = vec![0, 0, 0, 0x0C, b'J', b'X', b'L', b' ', 0x0D, 0x0A, 0x87, 0x0A];
+        container.extend(isobmff_box(b"ftyp", b"jxl \0\0\0\0jxl "));
+        container.extend(isobmff_box(b"jxlc", &jxl));
+        assert_eq!(read_dimensions(&container[..]).unwrap(), (64, 32));
+    }
+
+    #[test]
+    fn test_truncated_and_unknown_headers() {
+        let truncated_jpeg = [0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, 0x4A];
+        assert!(matches!(read_dimensions(&truncated_jpeg[..]), Err(ProbeError::Truncated)));
+
+        let text = b"this is definitely not an image file";
+        assert!(matches!(read_dimensions(&text[..]), Err(ProbeError::UnknownFormat)));
+    }
+
+    #[test]
+    fn test_format_iso8601() {
+        assert_eq!(format_iso8601(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(format_iso8601(time), "2024-02-29T12:34:56Z");
+    }
+}
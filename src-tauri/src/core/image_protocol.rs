@@ -0,0 +1,322 @@
+// core/image_protocol.rs
+// 画像配信用カスタムURIスキーム（imgview://）の実装
+
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use tauri::http::{header, Request, Response, StatusCode};
+
+use crate::core::archive;
+use crate::core::image_format::ImageFormat;
+use crate::core::resource_manager::ResourceManager;
+use crate::core::thumbnail::{ThumbnailFormat, ThumbnailService};
+use crate::core::transcode::{self, TranscodeError, TranscodeService};
+
+/// 登録するURIスキーム名
+pub const SCHEME: &str = "imgview";
+
+/// Content-Type判定に読み込む先頭バイト数
+const SNIFF_LEN: usize = 16;
+
+/// 画像ファイルのパスから配信用URLを生成
+pub fn image_url(path: &str) -> String {
+    // Windows/Androidのwebviewはカスタムスキームを http://<scheme>.localhost として扱う
+    #[cfg(any(windows, target_os = "android"))]
+    let base = format!("http://{}.localhost/", SCHEME);
+    #[cfg(not(any(windows, target_os = "android")))]
+    let base = format!("{}://localhost/", SCHEME);
+
+    format!("{}{}", base, percent_encode(path))
+}
+
+/// サムネイル配信用URLを生成（?thumbnail=<サイズ>&format=<形式>）
+pub fn thumbnail_url(path: &str, size: u32, format: ThumbnailFormat) -> String {
+    format!("{}?thumbnail={}&format={}", image_url(path), size, format.extension())
+}
+
+/// URIスキームへのリクエストを処理してレスポンスを生成
+///
+/// webviewが表示できない形式は変換済みのファイルを配信し、このビルドでデコードできない形式は
+/// 壊れた画像を返す代わりに 415 Unsupported Media Type で理由を返す。
+pub fn handle_request(
+    resource_manager: &ResourceManager,
+    thumbnails: &ThumbnailService,
+    transcoder: &TranscodeService,
+    request: &Request<Vec<u8>>,
+) -> Response<Vec<u8>> {
+    let method = request.method().as_str();
+    if method != "GET" && method != "HEAD" {
+        return error_response(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed");
+    }
+
+    let path = match percent_decode(request.uri().path().trim_start_matches('/')) {
+        Some(path) if !path.is_empty() => path,
+        _ => return error_response(StatusCode::BAD_REQUEST, "Invalid image path"),
+    };
+
+    // 解決済みのリソース設定に含まれるパスのみを配信する
+    if !resource_manager.is_resolved_path(&path) {
+        log::warn!("Rejected request for unresolved path: {}", path);
+        return error_response(StatusCode::FORBIDDEN, "Path is not part of a resolved resource");
+    }
+
+    let range = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok());
+
+    // サムネイル要求の場合はキャッシュ済みサムネイルを配信する
+    let thumbnail = query_param(request.uri().query(), "thumbnail").and_then(|size| size.parse::<u32>().ok());
+    let file_path = match thumbnail {
+        Some(size) => {
+            let format = query_param(request.uri().query(), "format")
+                .and_then(ThumbnailFormat::parse)
+                .unwrap_or_default();
+            match thumbnails.get_or_create(&path, size, format) {
+                Ok(info) => info.cache_path,
+                Err(e) => {
+                    log::warn!("Failed to create thumbnail for {}: {}", path, e);
+                    if transcode::source_format(&path).is_some_and(|format| !format.is_decodable()) {
+                        return error_response(StatusCode::UNSUPPORTED_MEDIA_TYPE, &e);
+                    }
+                    return error_response(StatusCode::UNPROCESSABLE_ENTITY, "Failed to create thumbnail");
+                }
+            }
+        },
+        None => match transcoder.displayable_path(&path) {
+            Ok(Some(transcoded)) => transcoded.to_string_lossy().to_string(),
+            Ok(None) => path.clone(),
+            Err(e @ TranscodeError::Unsupported(_)) => {
+                return error_response(StatusCode::UNSUPPORTED_MEDIA_TYPE, &e.to_string());
+            },
+            Err(e) => {
+                log::warn!("Failed to transcode {}: {}", path, e);
+                return error_response(StatusCode::UNPROCESSABLE_ENTITY, "Failed to transcode image");
+            },
+        },
+    };
+
+    match serve_file(Path::new(&file_path), range, method == "HEAD") {
+        Ok(response) => response,
+        Err(e) => {
+            log::warn!("Failed to serve {}: {}", path, e);
+            error_response(StatusCode::NOT_FOUND, "Failed to read image")
+        }
+    }
+}
+
+/// ファイル（またはその一部）を読み込んでレスポンスを構築
+///
+/// アーカイブ内のエントリはそのエントリだけを展開して配信する。
+fn serve_file(path: &Path, range: Option<&str>, head_only: bool) -> Result<Response<Vec<u8>>, String> {
+    if let Some(virtual_path) = path.to_str().filter(|p| archive::split_entry_path(p).is_some()) {
+        let bytes = archive::read_entry_path(virtual_path).map_err(|e| e.to_string())?;
+        let len = bytes.len() as u64;
+        return serve_reader(Cursor::new(bytes), len, range, head_only);
+    }
+
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let file_len = file
+        .metadata()
+        .map_err(|e| format!("Failed to read metadata: {}", e))?
+        .len();
+    serve_reader(file, file_len, range, head_only)
+}
+
+/// 読み込み元（またはその一部）からレスポンスを構築
+fn serve_reader<R: Read + Seek>(mut file: R, file_len: u64, range: Option<&str>, head_only: bool) -> Result<Response<Vec<u8>>, String> {
+    let mut signature = Vec::with_capacity(SNIFF_LEN);
+    (&mut file)
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut signature)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let content_type = sniff_content_type(&signature);
+
+    let byte_range = match range.map(|r| parse_range(r, file_len)) {
+        Some(Err(())) => {
+            return Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", file_len))
+                .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+                .body(Vec::new())
+                .map_err(|e| e.to_string());
+        },
+        Some(Ok(range)) => range,
+        None => None,
+    };
+
+    let (start, end) = byte_range.unwrap_or((0, file_len.saturating_sub(1)));
+    let length = if file_len == 0 { 0 } else { end - start + 1 };
+
+    let mut body = Vec::new();
+    if !head_only && length > 0 {
+        file.seek(SeekFrom::Start(start))
+            .map_err(|e| format!("Failed to seek file: {}", e))?;
+        body.reserve(length as usize);
+        (&mut file)
+            .take(length)
+            .read_to_end(&mut body)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+    }
+
+    let mut builder = Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_LENGTH, length.to_string())
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+
+    builder = if byte_range.is_some() {
+        builder
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, file_len))
+    } else {
+        builder.status(StatusCode::OK)
+    };
+
+    builder.body(body).map_err(|e| e.to_string())
+}
+
+/// クエリ文字列から指定キーの値を取得
+fn query_param<'a>(query: Option<&'a str>, key: &str) -> Option<&'a str> {
+    query?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v)
+}
+
+/// エラーレスポンスを生成
+fn error_response(status: StatusCode, message: &str) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain")
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(message.as_bytes().to_vec())
+        .unwrap_or_default()
+}
+
+/// 先頭バイトのシグネチャからContent-Typeを判定
+pub fn sniff_content_type(bytes: &[u8]) -> &'static str {
+    ImageFormat::detect(bytes).map_or("application/octet-stream", ImageFormat::mime_type)
+}
+
+/// Rangeヘッダーを解析して (開始, 終了) の閉区間を返す
+///
+/// 単一範囲のみに対応し、複数範囲や解釈できない指定は無視して全体を返す（Ok(None)）。
+/// 範囲がファイル外の場合は Err(()) を返す。
+fn parse_range(value: &str, file_len: u64) -> Result<Option<(u64, u64)>, ()> {
+    let spec = match value.trim().strip_prefix("bytes=") {
+        Some(spec) if !spec.contains(',') => spec.trim(),
+        _ => return Ok(None),
+    };
+
+    let (start, end) = match spec.split_once('-') {
+        Some(parts) => parts,
+        None => return Ok(None),
+    };
+
+    let range = if start.is_empty() {
+        // 末尾からのサフィックス指定（bytes=-500）
+        let suffix: u64 = match end.parse() {
+            Ok(suffix) => suffix,
+            Err(_) => return Ok(None),
+        };
+        if suffix == 0 || file_len == 0 {
+            return Err(());
+        }
+        (file_len.saturating_sub(suffix), file_len - 1)
+    } else {
+        let start: u64 = match start.parse() {
+            Ok(start) => start,
+            Err(_) => return Ok(None),
+        };
+        let end = if end.is_empty() {
+            file_len.saturating_sub(1)
+        } else {
+            match end.parse::<u64>() {
+                Ok(end) => end.min(file_len.saturating_sub(1)),
+                Err(_) => return Ok(None),
+            }
+        };
+        if start >= file_len || start > end {
+            return Err(());
+        }
+        (start, end)
+    };
+
+    Ok(Some(range))
+}
+
+/// URLパス用にパーセントエンコード（非予約文字以外をすべてエンコード）
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len() * 3);
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// パーセントエンコードされた文字列をデコード
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_round_trip() {
+        let path = "/home/user/写真/summer 2024/IMG_0001.JPG";
+        let encoded = percent_encode(path);
+        assert!(!encoded.contains('/'));
+        assert!(!encoded.contains(' '));
+        assert_eq!(percent_decode(&encoded).unwrap(), path);
+        assert!(image_url(path).ends_with(&encoded));
+    }
+
+    #[test]
+    fn test_query_param() {
+        let url = thumbnail_url("/a/b.jpg", 128, ThumbnailFormat::Webp);
+        let query = url.split_once('?').map(|(_, q)| q);
+        assert_eq!(query_param(query, "thumbnail"), Some("128"));
+        assert_eq!(query_param(query, "format"), Some("webp"));
+        assert_eq!(query_param(query, "missing"), None);
+        assert_eq!(query_param(None, "thumbnail"), None);
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Ok(Some((0, 99))));
+        assert_eq!(parse_range("bytes=900-", 1000), Ok(Some((900, 999))));
+        assert_eq!(parse_range("bytes=-100", 1000), Ok(Some((900, 999))));
+        assert_eq!(parse_range("bytes=500-5000", 1000), Ok(Some((500, 999))));
+        assert_eq!(parse_range("bytes=1000-", 1000), Err(()));
+        assert_eq!(parse_range("bytes=0-1,5-9", 1000), Ok(None));
+        assert_eq!(parse_range("items=0-1", 1000), Ok(None));
+    }
+
+    #[test]
+    fn test_sniff_content_type() {
+        assert_eq!(sniff_content_type(&[0xFF, 0xD8, 0xFF, 0xE0]), "image/jpeg");
+        assert_eq!(sniff_content_type(b"\x89PNG\r\n\x1a\n\0\0"), "image/png");
+        assert_eq!(sniff_content_type(b"RIFF\0\0\0\0WEBPVP8 "), "image/webp");
+        assert_eq!(sniff_content_type(b"plain text"), "application/octet-stream");
+    }
+}
@@ -1,18 +1,43 @@
 // core/mod.rs
 // コアモジュールのエントリポイント
 
+pub mod archive;
 pub mod resource_manager;
+pub mod resource_scanner;
+pub mod resource_watcher;
 pub mod image_collection;
+pub mod collection_registry;
+pub mod color_palette;
+pub mod config_library;
+pub mod config_schema;
+pub mod duplicates;
+pub mod extended_metadata;
+pub mod file_ops;
+pub mod geo;
+pub mod image_cache;
+pub mod image_format;
+pub mod image_index;
+pub mod image_probe;
+pub mod image_protocol;
+pub mod path_expansion;
+pub mod path_pattern;
+pub mod perceptual_hash;
+pub mod orientation;
 pub mod plugin_manager;
 pub mod event_bus;
 pub mod plugin_context;
+pub mod raw;
+pub mod thumbnail;
+pub mod transcode;
 
 // コアモジュールを一括でエクスポート
 pub use resource_manager::ResourceManager;
-pub use image_collection::{ImageCollection, ImageData, ImageMetadata};
+pub use image_collection::{ImageCollection, ImageData, ImageMetadata, ImageWarning};
+pub use collection_registry::CollectionRegistry;
 pub use plugin_manager::PluginManager;
 pub use event_bus::EventBus;
 pub use plugin_context::PluginContext;
+pub use thumbnail::ThumbnailService;
 
 /// コアシステムの初期化
 pub fn initialize() -> Result<(), String> {
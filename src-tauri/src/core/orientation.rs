@@ -0,0 +1,215 @@
+// core/orientation.rs
+// 画像の向き - EXIFの Orientation とユーザーが指定した回転を、表示用の寸法とデコード後の画像に適用する
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use exif::{Exif, In, Tag};
+use image::metadata::Orientation;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::core::archive;
+use crate::core::image_format::{self, ImageFormat};
+
+/// アーカイブ内のエントリから向きを読み取る際に展開する先頭部分のサイズ
+/// （JPEGのEXIFは先頭付近の64KB以下のAPP1セグメントにある）
+const ENTRY_HEADER_LIMIT: u64 = 256 * 1024;
+
+/// TIFFの Orientation タグ
+const TAG_ORIENTATION: u16 = 274;
+
+/// ユーザーが指定する回転（時計回り、EXIFの向きを適用した後に回転する）
+///
+/// JSONでは角度（0, 90, 180, 270）として扱う。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(try_from = "u16", into = "u16")]
+pub enum Rotation {
+    #[default]
+    None,
+    Clockwise90,
+    Clockwise180,
+    Clockwise270,
+}
+
+impl Rotation {
+    /// 角度から回転を作成（90の倍数以外は None、負の角度は反時計回りとして扱う）
+    pub fn from_degrees(degrees: i32) -> Option<Self> {
+        if degrees % 90 != 0 {
+            return None;
+        }
+        Some(match degrees.rem_euclid(360) {
+            90 => Rotation::Clockwise90,
+            180 => Rotation::Clockwise180,
+            270 => Rotation::Clockwise270,
+            _ => Rotation::None,
+        })
+    }
+
+    /// 時計回りの角度
+    pub fn degrees(self) -> u16 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Clockwise90 => 90,
+            Rotation::Clockwise180 => 180,
+            Rotation::Clockwise270 => 270,
+        }
+    }
+
+    /// 回転しないかどうか
+    pub fn is_none(&self) -> bool {
+        *self == Rotation::None
+    }
+
+    /// 幅と高さが入れ替わるかどうか
+    pub fn swaps_axes(self) -> bool {
+        matches!(self, Rotation::Clockwise90 | Rotation::Clockwise270)
+    }
+}
+
+impl TryFrom<u16> for Rotation {
+    type Error = String;
+
+    fn try_from(degrees: u16) -> Result<Self, Self::Error> {
+        match degrees {
+            0 | 90 | 180 | 270 => Ok(Self::from_degrees(degrees as i32).unwrap_or_default()),
+            _ => Err(format!("Rotation must be 0, 90, 180 or 270 degrees: {}", degrees)),
+        }
+    }
+}
+
+impl From<Rotation> for u16 {
+    fn from(rotation: Rotation) -> Self {
+        rotation.degrees()
+    }
+}
+
+/// 表示時の寸法（EXIFの向きと回転を適用した後の幅, 高さ）
+pub fn display_dimensions(stored: (u32, u32), orientation: Option<u8>, rotation: Rotation) -> (u32, u32) {
+    // 向き 5〜8 は90度回転を含む
+    let exif_swaps = matches!(orientation, Some(5..=8));
+    if exif_swaps != rotation.swaps_axes() {
+        (stored.1, stored.0)
+    } else {
+        stored
+    }
+}
+
+/// デコードした画像にEXIFの向きと回転を適用
+pub fn normalize(mut image: DynamicImage, orientation: Option<u8>, rotation: Rotation) -> DynamicImage {
+    if let Some(orientation) = orientation.and_then(Orientation::from_exif) {
+        image.apply_orientation(orientation);
+    }
+    let rotation = match rotation {
+        Rotation::None => return image,
+        Rotation::Clockwise90 => Orientation::Rotate90,
+        Rotation::Clockwise180 => Orientation::Rotate180,
+        Rotation::Clockwise270 => Orientation::Rotate270,
+    };
+    image.apply_orientation(rotation);
+    image
+}
+
+/// 画像ファイル（またはアーカイブ内のエントリ）のEXIFの向きを読み取る（記録がなければ None）
+pub fn read_exif_orientation(path: &str) -> Option<u8> {
+    let result = match archive::split_entry_path(path) {
+        Some((archive_path, entry)) => archive::read_entry_prefix(Path::new(archive_path), entry, ENTRY_HEADER_LIMIT)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| read_orientation(&mut Cursor::new(bytes)).map_err(|e| e.to_string())),
+        None => File::open(path)
+            .and_then(|file| read_orientation(&mut BufReader::new(file)))
+            .map_err(|e| e.to_string()),
+    };
+    result.unwrap_or_else(|e| {
+        log::debug!("Failed to read orientation of {}: {}", path, e);
+        None
+    })
+}
+
+/// EXIFの向きを読み取る
+///
+/// TIFFとRAWはファイル全体を読み込まないよう IFD0 のタグだけを読む。
+pub fn read_orientation<R: BufRead + Seek>(reader: &mut R) -> std::io::Result<Option<u8>> {
+    let format = image_format::sniff_reader(&mut *reader)?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    let orientation = match format {
+        Some(ImageFormat::Tiff | ImageFormat::Raw) => read_ifd0_orientation(reader)?,
+        Some(ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::Webp) => {
+            match exif::Reader::new().read_from_container(reader) {
+                Ok(exif) => exif_orientation(&exif).map(u16::from),
+                Err(_) => None,
+            }
+        },
+        _ => None,
+    };
+    Ok(orientation.filter(|value| (1..=8).contains(value)).map(|value| value as u8))
+}
+
+/// 解析済みのEXIFから向きを取得（1〜8以外は None）
+pub fn exif_orientation(exif: &Exif) -> Option<u8> {
+    exif.get_field(Tag::Orientation, In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        .filter(|value| (1..=8).contains(value))
+        .map(|value| value as u8)
+}
+
+/// TIFFの IFD0 から Orientation タグを読む
+fn read_ifd0_orientation<R: Read + Seek>(reader: &mut R) -> std::io::Result<Option<u16>> {
+    let mut header = [0u8; 8];
+    reader.read_exact(&mut header)?;
+    let big_endian = header[0] == b'M';
+    let u16_at = |bytes: &[u8]| {
+        let bytes = [bytes[0], bytes[1]];
+        if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) }
+    };
+    let ifd_offset = {
+        let bytes = [header[4], header[5], header[6], header[7]];
+        if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) }
+    };
+
+    reader.seek(SeekFrom::Start(ifd_offset as u64))?;
+    let mut count = [0u8; 2];
+    reader.read_exact(&mut count)?;
+    for _ in 0..u16_at(&count) {
+        let mut entry = [0u8; 12];
+        reader.read_exact(&mut entry)?;
+        // SHORT型の値はエントリ内に格納される
+        if u16_at(&entry[0..2]) == TAG_ORIENTATION && u16_at(&entry[2..4]) == 3 {
+            return Ok(Some(u16_at(&entry[8..10])));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_rotation_and_orientation() {
+        assert_eq!(Rotation::from_degrees(-90), Some(Rotation::Clockwise270));
+        assert_eq!(Rotation::from_degrees(450), Some(Rotation::Clockwise90));
+        assert_eq!(Rotation::from_degrees(45), None);
+        assert_eq!(serde_json::to_string(&Rotation::Clockwise180).unwrap(), "180");
+        assert!(serde_json::from_str::<Rotation>("45").is_err());
+
+        // EXIFの90度回転とユーザーの90度回転は打ち消し合って元の縦横になる
+        assert_eq!(display_dimensions((40, 20), Some(6), Rotation::None), (20, 40));
+        assert_eq!(display_dimensions((40, 20), Some(6), Rotation::Clockwise90), (40, 20));
+        assert_eq!(display_dimensions((40, 20), None, Rotation::Clockwise270), (20, 40));
+
+        // 左上の画素が向き 6（時計回りに90度）で右上に移る
+        let mut source = RgbImage::from_pixel(4, 2, Rgb([0, 0, 0]));
+        source.put_pixel(0, 0, Rgb([255, 0, 0]));
+        let normalized = normalize(DynamicImage::ImageRgb8(source), Some(6), Rotation::None).to_rgb8();
+        assert_eq!(normalized.dimensions(), (2, 4));
+        assert_eq!(normalized.get_pixel(1, 0), &Rgb([255, 0, 0]));
+
+        // TIFFの IFD0 から向きを読む
+        let mut tiff = b"II*\0\x08\0\0\0\x01\0".to_vec();
+        tiff.extend_from_slice(&[0x12, 0x01, 3, 0, 1, 0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(read_orientation(&mut Cursor::new(tiff)).unwrap(), Some(8));
+    }
+}
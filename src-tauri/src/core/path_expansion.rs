@@ -0,0 +1,172 @@
+// core/path_expansion.rs
+// リソースフィルタのパス展開 - ~、環境変数、既知フォルダトークンを実パスに置き換える
+
+use std::env;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// パス展開エラー
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PathExpansionError {
+    #[error("Home directory is not available")]
+    NoHomeDirectory,
+    #[error("Environment variable is not set: {0}")]
+    UndefinedVariable(String),
+    #[error("Unknown folder token: {{{0}}}")]
+    UnknownToken(String),
+    #[error("Folder is not available on this platform: {{{0}}}")]
+    UnavailableFolder(String),
+    #[error("Unterminated variable reference: {0}")]
+    Unterminated(String),
+}
+
+/// 利用できる既知フォルダトークン
+pub const FOLDER_TOKENS: [&str; 8] = [
+    "home", "pictures", "documents", "desktop", "downloads", "videos", "music", "temp",
+];
+
+/// パスを展開（`~`、`$VAR`、`${VAR}`、`{pictures}` などの既知フォルダ）
+pub fn expand_path(input: &str) -> Result<String, PathExpansionError> {
+    expand_with(input, |name| env::var(name).ok(), known_folder)
+}
+
+/// 既知フォルダトークンに対応するディレクトリを取得
+///
+/// トークンが未知の場合は `None`、プラットフォームで利用できない場合は `Some(None)` を返す。
+fn known_folder(token: &str) -> Option<Option<PathBuf>> {
+    let dir = match token {
+        "home" => dirs::home_dir(),
+        "pictures" => dirs::picture_dir(),
+        "documents" => dirs::document_dir(),
+        "desktop" => dirs::desktop_dir(),
+        "downloads" => dirs::download_dir(),
+        "videos" => dirs::video_dir(),
+        "music" => dirs::audio_dir(),
+        "temp" => Some(env::temp_dir()),
+        _ => return None,
+    };
+    Some(dir)
+}
+
+/// 変数・フォルダの解決方法を指定してパスを展開
+fn expand_with<V, F>(input: &str, lookup_var: V, lookup_folder: F) -> Result<String, PathExpansionError>
+where
+    V: Fn(&str) -> Option<String>,
+    F: Fn(&str) -> Option<Option<PathBuf>>,
+{
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    // 先頭の ~ はホームディレクトリ（~user 形式は対象外）
+    if let Some(after) = rest.strip_prefix('~') {
+        if after.is_empty() || after.starts_with('/') || after.starts_with('\\') {
+            let home = lookup_folder("home")
+                .flatten()
+                .ok_or(PathExpansionError::NoHomeDirectory)?;
+            output.push_str(&home.to_string_lossy());
+            rest = after;
+        }
+    }
+
+    while let Some(index) = rest.find(['$', '{']) {
+        output.push_str(&rest[..index]);
+        let tail = &rest[index..];
+
+        if let Some(braced) = tail.strip_prefix("${") {
+            let end = braced
+                .find('}')
+                .ok_or_else(|| PathExpansionError::Unterminated(tail.to_string()))?;
+            let name = &braced[..end];
+            let value = lookup_var(name).ok_or_else(|| PathExpansionError::UndefinedVariable(name.to_string()))?;
+            output.push_str(&value);
+            rest = &braced[end + 1..];
+        } else if let Some(after) = tail.strip_prefix('$') {
+            let len = identifier_len(after);
+            if len == 0 {
+                // 変数名が続かない $ はそのまま残す
+                output.push('$');
+                rest = after;
+                continue;
+            }
+            let name = &after[..len];
+            let value = lookup_var(name).ok_or_else(|| PathExpansionError::UndefinedVariable(name.to_string()))?;
+            output.push_str(&value);
+            rest = &after[len..];
+        } else {
+            let after = &tail[1..];
+            let len = identifier_len(after);
+            if len == 0 || !after[len..].starts_with('}') {
+                // {a,b} のような識別子以外の波括弧はそのまま残す
+                output.push('{');
+                rest = after;
+                continue;
+            }
+            let token = &after[..len];
+            let folder = lookup_folder(&token.to_lowercase())
+                .ok_or_else(|| PathExpansionError::UnknownToken(token.to_string()))?
+                .ok_or_else(|| PathExpansionError::UnavailableFolder(token.to_string()))?;
+            output.push_str(&folder.to_string_lossy());
+            rest = &after[len + 1..];
+        }
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// 先頭から続く識別子（英数字とアンダースコア）の長さ
+fn identifier_len(value: &str) -> usize {
+    value
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(value.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(input: &str) -> Result<String, PathExpansionError> {
+        expand_with(
+            input,
+            |name| match name {
+                "HOME" => Some("/home/user".to_string()),
+                "PHOTOS" => Some("/mnt/photos".to_string()),
+                _ => None,
+            },
+            |token| match token {
+                "home" => Some(Some(PathBuf::from("/home/user"))),
+                "pictures" => Some(Some(PathBuf::from("/home/user/Pictures"))),
+                "videos" => Some(None),
+                _ => None,
+            },
+        )
+    }
+
+    #[test]
+    fn test_expand_home_and_variables() {
+        assert_eq!(expand("~/Pictures").unwrap(), "/home/user/Pictures");
+        assert_eq!(expand("~").unwrap(), "/home/user");
+        assert_eq!(expand("$HOME/a").unwrap(), "/home/user/a");
+        assert_eq!(expand("${PHOTOS}/2024").unwrap(), "/mnt/photos/2024");
+        assert_eq!(expand("/plain/path").unwrap(), "/plain/path");
+        // ~user 形式や途中の ~ は展開しない
+        assert_eq!(expand("~other/a").unwrap(), "~other/a");
+        assert_eq!(expand("/a/~b").unwrap(), "/a/~b");
+        assert_eq!(expand("/a/$/b").unwrap(), "/a/$/b");
+    }
+
+    #[test]
+    fn test_expand_folder_tokens() {
+        assert_eq!(expand("{pictures}/Trips").unwrap(), "/home/user/Pictures/Trips");
+        assert_eq!(expand("{Pictures}").unwrap(), "/home/user/Pictures");
+        assert_eq!(expand("/a/*.{jpg,png}").unwrap(), "/a/*.{jpg,png}");
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        assert_eq!(expand("$MISSING/a"), Err(PathExpansionError::UndefinedVariable("MISSING".to_string())));
+        assert_eq!(expand("${HOME"), Err(PathExpansionError::Unterminated("${HOME".to_string())));
+        assert_eq!(expand("{nowhere}/a"), Err(PathExpansionError::UnknownToken("nowhere".to_string())));
+        assert_eq!(expand("{videos}"), Err(PathExpansionError::UnavailableFolder("videos".to_string())));
+    }
+}
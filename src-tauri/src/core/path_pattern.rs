@@ -0,0 +1,547 @@
+// core/path_pattern.rs
+// リソースフィルタのパスパターン - グロブとgitignore形式の除外ルール
+//
+// `[`・`*`・`?` を文字として指定するには `[[]`・`[*]`・`[?]` のように文字クラスで囲む。
+// ただし設定のエントリがそのままディスク上に存在する絶対パスであれば、エスケープしなくても
+// 文字どおりのパスとして扱う（`/photos/Photos [2019]` など）。
+
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// パターン解析エラー
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PatternError {
+    #[error("Pattern is empty")]
+    Empty,
+    #[error("Unclosed character class in pattern: {0}")]
+    UnclosedClass(String),
+    #[error("Include pattern must be an absolute path: {0}")]
+    RelativeInclude(String),
+}
+
+/// パターンの基準位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Anchor {
+    /// 絶対パスとして照合
+    Absolute,
+    /// 走査ルートからの相対パスとして照合
+    Relative,
+    /// 区切りを含まないパターン - 任意の階層の名前と照合
+    Name,
+}
+
+/// パスの構成要素ごとのパターン
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// `**` - 0個以上の構成要素に一致
+    AnyDepth,
+    /// 1つの構成要素に一致するグロブ
+    Glob(Vec<char>),
+}
+
+/// パスパターン（`**`、`*`、`?`、`[a-z]` に対応し、構成要素単位で照合する）
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    /// 元のパターン文字列
+    source: String,
+    /// 基準位置
+    anchor: Anchor,
+    /// 構成要素ごとのパターン
+    segments: Vec<Segment>,
+    /// 末尾が `/` の場合はディレクトリのみに一致
+    dir_only: bool,
+}
+
+impl PathPattern {
+    /// パターン文字列を解析
+    pub fn parse(pattern: &str) -> Result<Self, PatternError> {
+        let trimmed = pattern.trim();
+        if trimmed.is_empty() {
+            return Err(PatternError::Empty);
+        }
+
+        let dir_only = trimmed.len() > 1 && (trimmed.ends_with('/') || trimmed.ends_with('\\'));
+        let is_absolute = is_absolute_pattern(trimmed);
+        let components = split_components(trimmed);
+        if components.is_empty() {
+            // ルートディレクトリそのもの
+            return Ok(Self {
+                source: pattern.to_string(),
+                anchor: Anchor::Absolute,
+                segments: Vec::new(),
+                dir_only,
+            });
+        }
+
+        let anchor = if is_absolute {
+            Anchor::Absolute
+        } else if components.len() == 1 && !trimmed.starts_with("./") {
+            Anchor::Name
+        } else {
+            Anchor::Relative
+        };
+
+        let mut segments = Vec::with_capacity(components.len());
+        for component in components {
+            if component == "." && anchor == Anchor::Relative {
+                continue;
+            }
+            let segment = if component == "**" {
+                Segment::AnyDepth
+            } else {
+                let chars: Vec<char> = component.chars().collect();
+                validate_classes(&chars).map_err(|_| PatternError::UnclosedClass(pattern.to_string()))?;
+                Segment::Glob(chars)
+            };
+            // 連続する ** は1つにまとめる
+            if segment == Segment::AnyDepth && segments.last() == Some(&Segment::AnyDepth) {
+                continue;
+            }
+            segments.push(segment);
+        }
+
+        Ok(Self {
+            source: pattern.to_string(),
+            anchor,
+            segments,
+            dir_only,
+        })
+    }
+
+    /// 設定のエントリを解析（ディスク上にそのまま存在する絶対パスは文字どおりのパスとして扱う）
+    pub fn parse_entry(entry: &str) -> Result<Self, PatternError> {
+        if !is_literal_path(entry) {
+            return Self::parse(entry);
+        }
+        let mut pattern = Self::parse(&escape(entry))?;
+        pattern.source = entry.to_string();
+        Ok(pattern)
+    }
+
+    /// ワイルドカードを含むかどうか
+    pub fn has_wildcards(&self) -> bool {
+        self.segments.iter().any(|segment| match segment {
+            Segment::AnyDepth => true,
+            Segment::Glob(chars) => has_wildcard(chars),
+        })
+    }
+
+    /// 対象パスに一致するかを判定（相対パターンは root からの相対パスで照合）
+    pub fn matches(&self, path: &Path, root: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+
+        let path_str = path.to_string_lossy();
+        let components = split_components(&path_str);
+
+        match self.anchor {
+            Anchor::Absolute => match_segments(&self.segments, &components),
+            Anchor::Name => match (self.segments.first(), components.last()) {
+                (Some(Segment::Glob(glob)), Some(name)) => match_glob(glob, &name.chars().collect::<Vec<_>>()),
+                (Some(Segment::AnyDepth), _) => true,
+                _ => false,
+            },
+            Anchor::Relative => {
+                let root_str = root.to_string_lossy();
+                let root_components = split_components(&root_str);
+                if components.len() < root_components.len()
+                    || components[..root_components.len()] != root_components[..]
+                {
+                    return false;
+                }
+                match_segments(&self.segments, &components[root_components.len()..])
+            },
+        }
+    }
+
+    /// インクルードパターンを走査の起点ディレクトリと残りのパターンに分割
+    ///
+    /// ワイルドカードを含まない場合は `None` を返す（従来どおりのパス指定）。
+    pub fn split_include(&self) -> Result<Option<(PathBuf, PathPattern)>, PatternError> {
+        if !self.has_wildcards() {
+            return Ok(None);
+        }
+        if self.anchor != Anchor::Absolute {
+            return Err(PatternError::RelativeInclude(self.source.clone()));
+        }
+
+        let literal_len = self.segments.iter()
+            .position(|segment| match segment {
+                Segment::AnyDepth => true,
+                Segment::Glob(chars) => has_wildcard(chars),
+            })
+            .unwrap_or(self.segments.len());
+
+        let mut base = root_prefix(&self.source);
+        for segment in &self.segments[..literal_len] {
+            if let Segment::Glob(chars) = segment {
+                let component: String = chars.iter().collect();
+                if base.as_os_str().is_empty() {
+                    // ドライブレター（C:）の直後には区切りが必要
+                    base.push(format!("{}{}", component, std::path::MAIN_SEPARATOR));
+                } else {
+                    base.push(component);
+                }
+            }
+        }
+
+        let rest = PathPattern {
+            source: self.source.clone(),
+            anchor: Anchor::Relative,
+            segments: self.segments[literal_len..].to_vec(),
+            dir_only: self.dir_only,
+        };
+        Ok(Some((base, rest)))
+    }
+
+    /// 相対パターンが一致しうる最大の深さ（`**` を含む場合は `None`）
+    pub fn max_depth(&self) -> Option<usize> {
+        if self.segments.contains(&Segment::AnyDepth) {
+            None
+        } else {
+            Some(self.segments.len())
+        }
+    }
+}
+
+/// 除外ルール
+#[derive(Debug, Clone)]
+struct ExcludeRule {
+    /// パターン
+    pattern: PathPattern,
+    /// `!` で始まる再包含ルールかどうか
+    negated: bool,
+}
+
+/// gitignore形式の除外ルール集合（後に書かれたルールが優先される）
+#[derive(Debug, Clone, Default)]
+pub struct ExcludeRules {
+    rules: Vec<ExcludeRule>,
+}
+
+impl ExcludeRules {
+    /// 空のルール集合を作成
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ルールを追加（`!` で始まるエントリは再包含ルール）
+    pub fn push(&mut self, entry: &str) -> Result<(), PatternError> {
+        let (negated, pattern) = match entry.trim_start().strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, entry),
+        };
+        self.rules.push(ExcludeRule {
+            pattern: PathPattern::parse_entry(pattern)?,
+            negated,
+        });
+        Ok(())
+    }
+
+    /// 除外パターンを末尾に追加（他のルールより優先される）
+    pub fn push_pattern(&mut self, pattern: PathPattern) {
+        self.rules.push(ExcludeRule {
+            pattern,
+            negated: false,
+        });
+    }
+
+    /// パス自身が除外されるかを判定（最後に一致したルールで決まる）
+    pub fn is_excluded(&self, path: &Path, root: &Path, is_dir: bool) -> bool {
+        self.rules.iter()
+            .rev()
+            .find(|rule| rule.pattern.matches(path, root, is_dir))
+            .is_some_and(|rule| !rule.negated)
+    }
+
+    /// パスまたはその親ディレクトリが除外されるかを判定
+    ///
+    /// gitignoreと同様に、除外されたディレクトリ配下のパスは再包含できない。
+    pub fn is_excluded_with_ancestors(&self, path: &Path, root: &Path, is_dir: bool) -> bool {
+        if self.rules.is_empty() {
+            return false;
+        }
+        let mut ancestors: Vec<&Path> = path.ancestors().skip(1).collect();
+        ancestors.reverse();
+        ancestors.iter().any(|ancestor| self.is_excluded(ancestor, root, true))
+            || self.is_excluded(path, root, is_dir)
+    }
+}
+
+/// エントリがディスク上にそのまま存在する絶対パスかどうか（`[` などを含んでもグロブとして扱わない）
+pub fn is_literal_path(entry: &str) -> bool {
+    let trimmed = entry.trim();
+    is_absolute_pattern(trimmed) && Path::new(trimmed).exists()
+}
+
+/// ワイルドカードの文字を文字クラスで囲み、文字どおりに一致するパターンにする（`[` → `[[]`）
+pub fn escape(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            '[' | '*' | '?' => {
+                escaped.push('[');
+                escaped.push(c);
+                escaped.push(']');
+            },
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// パターンが絶対パスかどうか（`/`、`\`、ドライブレター）
+fn is_absolute_pattern(pattern: &str) -> bool {
+    let bytes = pattern.as_bytes();
+    pattern.starts_with('/')
+        || pattern.starts_with('\\')
+        || (bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':')
+}
+
+/// 絶対パスパターンの起点（`/` またはドライブ）
+fn root_prefix(pattern: &str) -> PathBuf {
+    let bytes = pattern.as_bytes();
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        // ドライブは構成要素として base に含まれるため区切りのみ
+        PathBuf::new()
+    } else {
+        PathBuf::from(std::path::MAIN_SEPARATOR_STR)
+    }
+}
+
+/// パス文字列を構成要素に分割（`/` と `\` の両方を区切りとして扱う）
+fn split_components(path: &str) -> Vec<&str> {
+    path.split(['/', '\\'])
+        .filter(|component| !component.is_empty())
+        .collect()
+}
+
+/// 構成要素のワイルドカード有無
+fn has_wildcard(chars: &[char]) -> bool {
+    chars.iter().any(|c| matches!(c, '*' | '?' | '['))
+}
+
+/// 文字クラスが閉じているかを確認
+fn validate_classes(chars: &[char]) -> Result<(), ()> {
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '[' {
+            i = class_end(chars, i).ok_or(())?;
+        }
+        i += 1;
+    }
+    Ok(())
+}
+
+/// `[` の位置から対応する `]` の位置を取得
+fn class_end(chars: &[char], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    if i < chars.len() && (chars[i] == '!' || chars[i] == '^') {
+        i += 1;
+    }
+    // 先頭の ] は文字として扱う
+    if i < chars.len() && chars[i] == ']' {
+        i += 1;
+    }
+    while i < chars.len() {
+        if chars[i] == ']' {
+            return Some(i);
+        }
+        i += 1;
+    }
+    None
+}
+
+/// 構成要素列のパターン照合（`**` は0個以上の構成要素に一致）
+fn match_segments(segments: &[Segment], components: &[&str]) -> bool {
+    match segments.split_first() {
+        None => components.is_empty(),
+        Some((Segment::AnyDepth, rest)) => {
+            (0..=components.len()).any(|skip| match_segments(rest, &components[skip..]))
+        },
+        Some((Segment::Glob(glob), rest)) => match components.split_first() {
+            Some((component, remaining)) => {
+                match_glob(glob, &component.chars().collect::<Vec<_>>()) && match_segments(rest, remaining)
+            },
+            None => false,
+        },
+    }
+}
+
+/// 1つの構成要素に対するグロブ照合（`*`、`?`、`[...]`）
+fn match_glob(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // 直近の * の位置と、その * が吸収し始めたテキスト位置
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() {
+            match pattern[p] {
+                '*' => {
+                    star = Some((p, t));
+                    p += 1;
+                    continue;
+                },
+                '?' => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                },
+                '[' => {
+                    if let Some(end) = class_end(pattern, p) {
+                        if match_class(&pattern[p + 1..end], text[t]) {
+                            p = end + 1;
+                            t += 1;
+                            continue;
+                        }
+                    }
+                },
+                c if c == text[t] => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                },
+                _ => {},
+            }
+        }
+
+        // 不一致の場合は直前の * に1文字多く吸収させて再試行
+        match star {
+            Some((star_p, star_t)) => {
+                p = star_p + 1;
+                t = star_t + 1;
+                star = Some((star_p, star_t + 1));
+            },
+            None => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// 文字クラスの照合（`!`/`^` による否定と `a-z` の範囲に対応）
+fn match_class(class: &[char], c: char) -> bool {
+    let (negated, class) = match class.first() {
+        Some('!') | Some('^') => (true, &class[1..]),
+        _ => (false, class),
+    };
+
+    let mut matched = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            if class[i] <= c && c <= class[i + 2] {
+                matched = true;
+            }
+            i += 3;
+        } else {
+            if class[i] == c {
+                matched = true;
+            }
+            i += 1;
+        }
+    }
+    matched != negated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str, root: &str, is_dir: bool) -> bool {
+        PathPattern::parse(pattern).unwrap().matches(Path::new(path), Path::new(root), is_dir)
+    }
+
+    #[test]
+    fn test_match_glob() {
+        let glob = |p: &str, t: &str| match_glob(&p.chars().collect::<Vec<_>>(), &t.chars().collect::<Vec<_>>());
+        assert!(glob("*.jpg", "a.jpg"));
+        assert!(glob("*.tmp.jpg", "x.tmp.jpg"));
+        assert!(!glob("*.tmp.jpg", "x.jpg"));
+        assert!(glob("IMG_????.JPG", "IMG_0001.JPG"));
+        assert!(glob("[a-c]*", "beach.png"));
+        assert!(!glob("[!a-c]*", "beach.png"));
+        assert!(glob("*", ""));
+    }
+
+    #[test]
+    fn test_plain_path_matches_whole_components() {
+        // 従来の starts_with と異なり、名前の一部だけが一致するパスは対象外
+        assert!(matches("/photos/raw", "/photos/raw", "/photos", true));
+        assert!(!matches("/photos/raw", "/photos/raw-edits", "/photos", true));
+        assert!(matches("/photos/raw/", "/photos/raw", "/photos", true));
+        assert!(!matches("/photos/raw/", "/photos/raw", "/photos", false));
+    }
+
+    #[test]
+    fn test_relative_and_name_patterns() {
+        assert!(matches("**/.thumbnails", "/photos/a/b/.thumbnails", "/photos", true));
+        assert!(matches(".thumbnails", "/photos/a/.thumbnails", "/photos", true));
+        assert!(matches("*.tmp.jpg", "/photos/a/x.tmp.jpg", "/photos", false));
+        assert!(matches("a/**/*.png", "/photos/a/b/c/x.png", "/photos", false));
+        assert!(matches("a/**/*.png", "/photos/a/x.png", "/photos", false));
+        assert!(!matches("a/*.png", "/photos/a/b/x.png", "/photos", false));
+        assert!(!matches("a/*.png", "/other/a/x.png", "/photos", false));
+    }
+
+    #[test]
+    fn test_exclude_rules_with_negation() {
+        let mut rules = ExcludeRules::new();
+        rules.push("*.jpg").unwrap();
+        rules.push("!keep*.jpg").unwrap();
+        rules.push("/photos/private").unwrap();
+
+        let root = Path::new("/photos");
+        assert!(rules.is_excluded(Path::new("/photos/a.jpg"), root, false));
+        assert!(!rules.is_excluded(Path::new("/photos/keep1.jpg"), root, false));
+        assert!(!rules.is_excluded(Path::new("/photos/a.png"), root, false));
+        // 除外されたディレクトリ配下は再包含できない
+        assert!(rules.is_excluded_with_ancestors(Path::new("/photos/private/keep1.jpg"), root, false));
+    }
+
+    #[test]
+    fn test_split_include() {
+        let pattern = PathPattern::parse("/photos/**/*.jpg").unwrap();
+        let (base, rest) = pattern.split_include().unwrap().unwrap();
+        assert_eq!(base, Path::new("/photos"));
+        assert!(rest.matches(Path::new("/photos/2024/a.jpg"), &base, false));
+        assert!(!rest.matches(Path::new("/photos/2024/a.png"), &base, false));
+
+        assert!(PathPattern::parse("/photos/a").unwrap().split_include().unwrap().is_none());
+        assert_eq!(
+            PathPattern::parse("*.jpg").unwrap().split_include().unwrap_err(),
+            PatternError::RelativeInclude("*.jpg".to_string())
+        );
+        assert!(PathPattern::parse("/a/[bc").is_err());
+    }
+
+    #[test]
+    fn test_literal_path_with_brackets() {
+        let dir = std::env::temp_dir().join(format!("image-viewer-pattern-{}", std::process::id()));
+        let literal = dir.join("Photos [2019]");
+        let unclosed = dir.join("Scans [old");
+        std::fs::create_dir_all(&literal).unwrap();
+        std::fs::create_dir_all(&unclosed).unwrap();
+        let literal_str = literal.to_string_lossy().into_owned();
+        let unclosed_str = unclosed.to_string_lossy().into_owned();
+
+        // 存在するパスは文字クラスとして解釈しない
+        assert!(is_literal_path(&literal_str));
+        let pattern = PathPattern::parse_entry(&literal_str).unwrap();
+        assert!(pattern.matches(&literal, &dir, true));
+        assert!(!pattern.matches(&dir.join("Photos 2"), &dir, true));
+        assert!(PathPattern::parse_entry(&unclosed_str).is_ok());
+
+        let mut rules = ExcludeRules::new();
+        rules.push(&literal_str).unwrap();
+        assert!(rules.is_excluded_with_ancestors(&literal.join("a.jpg"), &dir, false));
+
+        // 存在しないパスでは文字クラスで囲んで文字として指定する
+        assert!(matches("/photos/Photos [[]2019]", "/photos/Photos [2019]", "/photos", true));
+        assert!(!matches("/photos/Photos [2019]", "/photos/Photos [2019]", "/photos", true));
+        assert_eq!(escape("/a/[b]*?.jpg"), "/a/[[]b][*][?].jpg");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
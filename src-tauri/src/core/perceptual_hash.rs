@@ -0,0 +1,359 @@
+// core/perceptual_hash.rs
+// 知覚ハッシュ - 縮小・再圧縮・軽い編集を経た画像どうしを見つけるための aHash/dHash/pHash と BK木
+
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GrayImage};
+use serde::{Deserialize, Serialize};
+
+/// 類似画像の検索で使う距離の既定値（64ビット中の異なるビット数）
+pub const DEFAULT_SIMILAR_DISTANCE: u32 = 12;
+
+/// ほぼ同一の画像とみなす距離の既定値
+pub const DEFAULT_NEAR_DUPLICATE_DISTANCE: u32 = 6;
+
+/// pHashで離散コサイン変換をかける縮小画像の一辺
+const DCT_SIZE: usize = 32;
+
+/// ハッシュの一辺（8x8 = 64ビット）
+const HASH_SIZE: usize = 8;
+
+/// 知覚ハッシュの種類
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashKind {
+    /// 平均輝度との比較（aHash）
+    Average,
+    /// 隣接画素の輝度差（dHash）
+    Difference,
+    /// 離散コサイン変換の低周波成分（pHash、再圧縮や色調補正に最も強い）
+    #[default]
+    Perceptual,
+}
+
+/// 画像の知覚ハッシュ
+///
+/// JavaScriptの数値では64ビット整数を表せないため、JSONでは16進文字列として扱う。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PerceptualHash {
+    #[serde(with = "hex_u64")]
+    pub average: u64,
+    #[serde(with = "hex_u64")]
+    pub difference: u64,
+    #[serde(with = "hex_u64")]
+    pub perceptual: u64,
+}
+
+impl PerceptualHash {
+    /// 画像（サムネイルで十分）からハッシュを計算
+    pub fn compute(image: &DynamicImage) -> Self {
+        let gray = image.to_luma8();
+        Self {
+            average: average_hash(&gray),
+            difference: difference_hash(&gray),
+            perceptual: dct_hash(&gray),
+        }
+    }
+
+    /// 指定した種類のハッシュ
+    pub fn get(&self, kind: HashKind) -> u64 {
+        match kind {
+            HashKind::Average => self.average,
+            HashKind::Difference => self.difference,
+            HashKind::Perceptual => self.perceptual,
+        }
+    }
+
+    /// 指定した種類のハッシュのハミング距離
+    pub fn distance(&self, other: &Self, kind: HashKind) -> u32 {
+        hamming(self.get(kind), other.get(kind))
+    }
+}
+
+/// 2つのハッシュで異なるビットの数
+pub fn hamming(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// aHash: 8x8に縮小し、各画素が平均輝度より明るいかどうか
+fn average_hash(gray: &GrayImage) -> u64 {
+    let small = imageops::resize(gray, HASH_SIZE as u32, HASH_SIZE as u32, FilterType::Triangle);
+    let mean = small.pixels().map(|pixel| pixel[0] as u32).sum::<u32>() / (HASH_SIZE * HASH_SIZE) as u32;
+    bits(small.pixels().map(|pixel| pixel[0] as u32 > mean))
+}
+
+/// dHash: 9x8に縮小し、各画素が右隣より明るいかどうか
+fn difference_hash(gray: &GrayImage) -> u64 {
+    let small = imageops::resize(gray, HASH_SIZE as u32 + 1, HASH_SIZE as u32, FilterType::Triangle);
+    bits((0..HASH_SIZE as u32).flat_map(|y| {
+        let small = &small;
+        (0..HASH_SIZE as u32).map(move |x| small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0])
+    }))
+}
+
+/// pHash: 32x32に縮小して離散コサイン変換をかけ、低周波の8x8成分が中央値より大きいかどうか
+fn dct_hash(gray: &GrayImage) -> u64 {
+    let small = imageops::resize(gray, DCT_SIZE as u32, DCT_SIZE as u32, FilterType::Triangle);
+    let pixels: Vec<f64> = small.pixels().map(|pixel| pixel[0] as f64).collect();
+
+    // 分離可能なため行ごと・列ごとに1次元の変換をかける（低周波の8成分だけ求める）
+    let cosines: Vec<f64> = (0..HASH_SIZE)
+        .flat_map(|u| (0..DCT_SIZE).map(move |x| ((2 * x + 1) as f64 * u as f64 * PI / (2 * DCT_SIZE) as f64).cos()))
+        .collect();
+    let mut rows = vec![0.0; DCT_SIZE * HASH_SIZE];
+    for y in 0..DCT_SIZE {
+        for u in 0..HASH_SIZE {
+            rows[y * HASH_SIZE + u] = (0..DCT_SIZE)
+                .map(|x| pixels[y * DCT_SIZE + x] * cosines[u * DCT_SIZE + x])
+                .sum();
+        }
+    }
+    let mut coefficients = vec![0.0; HASH_SIZE * HASH_SIZE];
+    for v in 0..HASH_SIZE {
+        for u in 0..HASH_SIZE {
+            coefficients[v * HASH_SIZE + u] = (0..DCT_SIZE)
+                .map(|y| rows[y * HASH_SIZE + u] * cosines[v * DCT_SIZE + y])
+                .sum();
+        }
+    }
+
+    // 直流成分は全体の明るさを表すだけなので中央値の計算から除く
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    bits(coefficients.iter().map(|coefficient| *coefficient > median))
+}
+
+/// 真偽値の列を上位ビットから詰める
+fn bits(values: impl Iterator<Item = bool>) -> u64 {
+    values.fold(0u64, |hash, bit| (hash << 1) | bit as u64)
+}
+
+/// ハミング距離で検索するBK木
+#[derive(Debug, Default)]
+pub struct BkTree {
+    nodes: Vec<BkNode>,
+}
+
+#[derive(Debug)]
+struct BkNode {
+    hash: u64,
+    /// 同じハッシュを持つ項目
+    items: Vec<usize>,
+    /// 距離 -> 子ノード
+    children: HashMap<u32, usize>,
+}
+
+impl BkTree {
+    /// 空の木を作成
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 項目を追加
+    pub fn insert(&mut self, hash: u64, item: usize) {
+        if self.nodes.is_empty() {
+            self.nodes.push(BkNode { hash, items: vec![item], children: HashMap::new() });
+            return;
+        }
+        let mut current = 0;
+        loop {
+            let distance = hamming(self.nodes[current].hash, hash);
+            if distance == 0 {
+                self.nodes[current].items.push(item);
+                return;
+            }
+            match self.nodes[current].children.get(&distance) {
+                Some(&child) => current = child,
+                None => {
+                    let child = self.nodes.len();
+                    self.nodes.push(BkNode { hash, items: vec![item], children: HashMap::new() });
+                    self.nodes[current].children.insert(distance, child);
+                    return;
+                },
+            }
+        }
+    }
+
+    /// 距離が max_distance 以下の項目を（項目, 距離）で返す
+    pub fn find(&self, hash: u64, max_distance: u32) -> Vec<(usize, u32)> {
+        let mut found = Vec::new();
+        let mut pending = if self.nodes.is_empty() { Vec::new() } else { vec![0] };
+        while let Some(current) = pending.pop() {
+            let node = &self.nodes[current];
+            let distance = hamming(node.hash, hash);
+            if distance <= max_distance {
+                found.extend(node.items.iter().map(|item| (*item, distance)));
+            }
+            // 三角不等式により、子との距離が範囲外の枝は調べなくてよい
+            let range = distance.saturating_sub(max_distance)..=distance + max_distance;
+            pending.extend(node.children.iter()
+                .filter(|(child_distance, _)| range.contains(child_distance))
+                .map(|(_, child)| *child));
+        }
+        found
+    }
+}
+
+/// 類似画像
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimilarImage {
+    pub path: String,
+    /// ハッシュのハミング距離（0〜64、小さいほど似ている）
+    pub distance: u32,
+}
+
+/// ほぼ同一の画像のグループ
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SimilarGroup {
+    /// 代表の画像（コレクション内で最初の画像）
+    pub representative: String,
+    /// 代表以外の画像と代表からの距離
+    pub members: Vec<SimilarImage>,
+}
+
+/// 基準のハッシュに似た画像を距離の近い順に探す（基準と同じパスは除く）
+pub fn find_similar(
+    target: (&str, &PerceptualHash),
+    candidates: &[(String, PerceptualHash)],
+    kind: HashKind,
+    max_distance: u32,
+) -> Vec<SimilarImage> {
+    let tree = build_tree(candidates, kind);
+    let mut similar: Vec<SimilarImage> = tree.find(target.1.get(kind), max_distance).into_iter()
+        .filter(|(item, _)| candidates[*item].0 != target.0)
+        .map(|(item, distance)| SimilarImage { path: candidates[item].0.clone(), distance })
+        .collect();
+    similar.sort_by(|a, b| a.distance.cmp(&b.distance).then_with(|| a.path.cmp(&b.path)));
+    similar
+}
+
+/// 距離が threshold 以下の画像どうしをまとめる（画像数の多い順）
+///
+/// 似た画像の連鎖でつながったものは同じグループになる。
+pub fn group_near_duplicates(candidates: &[(String, PerceptualHash)], kind: HashKind, threshold: u32) -> Vec<SimilarGroup> {
+    let tree = build_tree(candidates, kind);
+    let mut parents: Vec<usize> = (0..candidates.len()).collect();
+    fn root(parents: &mut [usize], mut item: usize) -> usize {
+        while parents[item] != item {
+            parents[item] = parents[parents[item]];
+            item = parents[item];
+        }
+        item
+    }
+    for (item, (_, hash)) in candidates.iter().enumerate() {
+        for (other, _) in tree.find(hash.get(kind), threshold) {
+            let (a, b) = (root(&mut parents, item), root(&mut parents, other));
+            // 代表がコレクション内で最初の画像になるよう小さい方を根にする
+            if a != b {
+                parents[a.max(b)] = a.min(b);
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for item in 0..candidates.len() {
+        let group = root(&mut parents, item);
+        groups.entry(group).or_default().push(item);
+    }
+    let mut groups: Vec<SimilarGroup> = groups.into_iter()
+        .filter(|(_, items)| items.len() > 1)
+        .map(|(representative, items)| {
+            let base = &candidates[representative].1;
+            SimilarGroup {
+                representative: candidates[representative].0.clone(),
+                members: items.into_iter()
+                    .filter(|item| *item != representative)
+                    .map(|item| SimilarImage {
+                        path: candidates[item].0.clone(),
+                        distance: base.distance(&candidates[item].1, kind),
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| b.members.len().cmp(&a.members.len()).then_with(|| a.representative.cmp(&b.representative)));
+    groups
+}
+
+/// 候補からBK木を作る（項目は候補の位置）
+fn build_tree(candidates: &[(String, PerceptualHash)], kind: HashKind) -> BkTree {
+    let mut tree = BkTree::new();
+    for (item, (_, hash)) in candidates.iter().enumerate() {
+        tree.insert(hash.get(kind), item);
+    }
+    tree
+}
+
+/// u64を16進文字列としてシリアライズする
+mod hex_u64 {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:016x}", value))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        u64::from_str_radix(&text, 16).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    /// 濃淡の異なるブロックを並べた画像（サイズを変えても同じ模様になる）
+    fn blocks(width: u32, height: u32, invert: bool) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            let block = (x * 7 / width + (y * 5 / height) * 7) as u64;
+            let mut value = (block.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 56) as u8;
+            if invert {
+                value = 255 - value;
+            }
+            Rgb([value, value / 2, 255 - value])
+        }))
+    }
+
+    #[test]
+    fn test_hashes_tree_and_grouping() {
+        let original = PerceptualHash::compute(&blocks(256, 192, false));
+        let resized = PerceptualHash::compute(&blocks(64, 48, false));
+        let inverted = PerceptualHash::compute(&blocks(256, 192, true));
+        for kind in [HashKind::Average, HashKind::Difference, HashKind::Perceptual] {
+            assert!(original.distance(&resized, kind) <= DEFAULT_NEAR_DUPLICATE_DISTANCE, "{:?}", kind);
+            assert!(original.distance(&inverted, kind) > DEFAULT_SIMILAR_DISTANCE, "{:?}", kind);
+        }
+
+        // JSONでは16進文字列になる
+        let json = serde_json::to_value(original).unwrap();
+        assert_eq!(json["perceptual"].as_str().map(str::len), Some(16));
+        assert_eq!(serde_json::from_value::<PerceptualHash>(json).unwrap(), original);
+
+        // BK木の検索は総当たりと一致する
+        let hashes: Vec<u64> = (0..200u64).map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15)).collect();
+        let mut tree = BkTree::new();
+        for (item, hash) in hashes.iter().enumerate() {
+            tree.insert(*hash, item);
+        }
+        let mut found: Vec<usize> = tree.find(hashes[0], 28).into_iter().map(|(item, _)| item).collect();
+        found.sort_unstable();
+        let expected: Vec<usize> = (0..hashes.len()).filter(|item| hamming(hashes[0], hashes[*item]) <= 28).collect();
+        assert_eq!(found, expected);
+
+        let candidates = vec![
+            ("a.jpg".to_string(), original),
+            ("b.jpg".to_string(), inverted),
+            ("a-small.jpg".to_string(), resized),
+        ];
+        let similar = find_similar(("a.jpg", &original), &candidates, HashKind::Perceptual, DEFAULT_SIMILAR_DISTANCE);
+        assert_eq!(similar.iter().map(|image| image.path.as_str()).collect::<Vec<_>>(), vec!["a-small.jpg"]);
+
+        let groups = group_near_duplicates(&candidates, HashKind::Perceptual, DEFAULT_NEAR_DUPLICATE_DISTANCE);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].representative, "a.jpg");
+        assert_eq!(groups[0].members[0].path, "a-small.jpg");
+    }
+}
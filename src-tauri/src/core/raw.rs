@@ -0,0 +1,349 @@
+// core/raw.rs
+// RAW画像（CR2/NEF/ARW/DNG）の埋め込みプレビュー抽出 - TIFF構造のIFDを辿り、最大のJPEGプレビューを探す
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use thiserror::Error;
+
+use crate::core::archive::{self, ArchiveError};
+use crate::core::image_probe;
+
+/// 辿るIFDの最大数（循環参照や壊れたファイルへの対策）
+const MAX_IFDS: usize = 64;
+
+/// SubIFDを辿る最大の深さ
+const MAX_DEPTH: usize = 4;
+
+/// 1つのタグから読み込む値の最大数
+const MAX_VALUES: u32 = 64;
+
+/// 1つのIFDに含まれるエントリの最大数（これより多いIFDは壊れているとみなす）
+const MAX_IFD_ENTRIES: u16 = 1024;
+
+/// 埋め込みプレビューの最大サイズ（壊れた長さでの巨大な確保を防ぐ）
+const MAX_PREVIEW_SIZE: u64 = 64 * 1024 * 1024;
+
+/// TIFFタグ
+const TAG_COMPRESSION: u16 = 259;
+const TAG_STRIP_OFFSETS: u16 = 273;
+const TAG_STRIP_BYTE_COUNTS: u16 = 279;
+const TAG_SUB_IFDS: u16 = 330;
+const TAG_JPEG_OFFSET: u16 = 513;
+const TAG_JPEG_LENGTH: u16 = 514;
+
+/// RAW読み込みのエラー型
+#[derive(Error, Debug)]
+pub enum RawError {
+    #[error("Failed to read RAW file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Archive(#[from] ArchiveError),
+
+    #[error("Invalid RAW file: {0}")]
+    Invalid(String),
+
+    #[error("No embedded JPEG preview found")]
+    NoPreview,
+}
+
+/// 埋め込みJPEGプレビューの位置と寸法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawPreview {
+    /// ファイル先頭からのオフセット
+    pub offset: u64,
+    /// バイト数
+    pub length: u64,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 最大の埋め込みプレビューを探す
+///
+/// IFDチェーンとSubIFDから JPEGInterchangeFormat、または JPEG圧縮の単一ストリップを候補とし、
+/// 可逆圧縮のJPEG（センサーデータ）を除いた中で最も画素数の多いものを返す。
+pub fn find_preview<R: Read + Seek>(reader: &mut R) -> Result<RawPreview, RawError> {
+    let mut header = [0u8; 8];
+    reader.seek(SeekFrom::Start(0))?;
+    reader.read_exact(&mut header)?;
+    let big_endian = match &header[0..4] {
+        b"II*\0" => false,
+        b"MM\0*" => true,
+        _ => return Err(RawError::Invalid("missing TIFF header".to_string())),
+    };
+
+    let len = reader.seek(SeekFrom::End(0))?;
+    let mut walker = IfdWalker {
+        reader: &mut *reader,
+        big_endian,
+        len,
+        visited: HashSet::new(),
+        candidates: Vec::new(),
+    };
+    let first_ifd = walker.u32(&header[4..8]) as u64;
+    walker.walk(first_ifd, 0)?;
+    let candidates = walker.candidates;
+
+    let mut largest: Option<RawPreview> = None;
+    for (offset, length) in candidates {
+        if !is_valid_range(offset, length, len) {
+            log::debug!("Skipping preview candidate outside the file: offset {}, length {}", offset, length);
+            continue;
+        }
+        reader.seek(SeekFrom::Start(offset))?;
+        let frame = match image_probe::read_jpeg_frame((&mut *reader).take(length)) {
+            Ok(frame) if !frame.lossless => frame,
+            _ => continue,
+        };
+        let area = |preview: &RawPreview| preview.width as u64 * preview.height as u64;
+        let preview = RawPreview { offset, length, width: frame.width, height: frame.height };
+        if largest.as_ref().is_none_or(|current| area(&preview) > area(current)) {
+            largest = Some(preview);
+        }
+    }
+    largest.ok_or(RawError::NoPreview)
+}
+
+/// 最大の埋め込みプレビューを読み込む
+pub fn read_preview<R: Read + Seek>(reader: &mut R) -> Result<Vec<u8>, RawError> {
+    let preview = find_preview(reader)?;
+    let len = reader.seek(SeekFrom::End(0))?;
+    if !is_valid_range(preview.offset, preview.length, len) {
+        return Err(RawError::Invalid(format!("preview range {}+{} exceeds file size {}", preview.offset, preview.length, len)));
+    }
+    reader.seek(SeekFrom::Start(preview.offset))?;
+    let mut bytes = Vec::with_capacity(preview.length as usize);
+    reader.take(preview.length).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != preview.length {
+        return Err(RawError::Invalid("truncated preview".to_string()));
+    }
+    Ok(bytes)
+}
+
+/// プレビューの範囲が空でなく、上限以下で、ファイル内に収まっているか
+fn is_valid_range(offset: u64, length: u64, len: u64) -> bool {
+    length > 0 && length <= MAX_PREVIEW_SIZE && offset.checked_add(length).is_some_and(|end| end <= len)
+}
+
+/// RAWファイル（またはアーカイブ内のエントリ）のプレビューを探す
+pub fn find_preview_path(path: &str) -> Result<RawPreview, RawError> {
+    if archive::split_entry_path(path).is_some() {
+        return find_preview(&mut Cursor::new(archive::read_entry_path(path)?));
+    }
+    find_preview(&mut BufReader::new(File::open(Path::new(path))?))
+}
+
+/// RAWファイル（またはアーカイブ内のエントリ）のプレビューを読み込む
+pub fn read_preview_path(path: &str) -> Result<Vec<u8>, RawError> {
+    if archive::split_entry_path(path).is_some() {
+        return read_preview(&mut Cursor::new(archive::read_entry_path(path)?));
+    }
+    read_preview(&mut BufReader::new(File::open(Path::new(path))?))
+}
+
+/// IFDのエントリ
+struct IfdEntry {
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    /// 値（4バイト以下の場合）または値へのオフセット
+    value: [u8; 4],
+}
+
+/// IFDを辿ってプレビューの候補を集める
+struct IfdWalker<'a, R> {
+    reader: &'a mut R,
+    big_endian: bool,
+    /// ファイルのサイズ
+    len: u64,
+    /// 読み込み済みのIFDのオフセット
+    visited: HashSet<u64>,
+    /// プレビュー候補（オフセット, バイト数）
+    candidates: Vec<(u64, u64)>,
+}
+
+impl<R: Read + Seek> IfdWalker<'_, R> {
+    /// IFDチェーンを辿る（SubIFDは再帰的に辿る）
+    fn walk(&mut self, offset: u64, depth: usize) -> Result<(), RawError> {
+        let mut next = offset;
+        while next != 0 && self.visited.len() < MAX_IFDS && self.visited.insert(next) {
+            let (entries, next_offset) = self.read_ifd(next)?;
+            let value = |tag: u16| entries.iter().find(|entry| entry.tag == tag);
+
+            if let (Some(offset), Some(length)) = (value(TAG_JPEG_OFFSET), value(TAG_JPEG_LENGTH)) {
+                let offset = self.values(offset)?.first().copied();
+                let length = self.values(length)?.first().copied();
+                if let (Some(offset), Some(length)) = (offset, length) {
+                    self.candidates.push((offset, length));
+                }
+            }
+
+            // JPEG圧縮（6: 旧形式, 7: JPEG）の単一ストリップ
+            let compression = match value(TAG_COMPRESSION) {
+                Some(entry) => self.values(entry)?.first().copied(),
+                None => None,
+            };
+            if matches!(compression, Some(6 | 7)) {
+                if let (Some(offsets), Some(lengths)) = (value(TAG_STRIP_OFFSETS), value(TAG_STRIP_BYTE_COUNTS)) {
+                    if let ([offset], [length]) = (&self.values(offsets)?[..], &self.values(lengths)?[..]) {
+                        self.candidates.push((*offset, *length));
+                    }
+                }
+            }
+
+            if depth < MAX_DEPTH {
+                if let Some(entry) = value(TAG_SUB_IFDS) {
+                    for sub_ifd in self.values(entry)? {
+                        // 壊れたSubIFDは無視して他の候補を探す
+                        if let Err(e) = self.walk(sub_ifd, depth + 1) {
+                            log::debug!("Skipping unreadable SubIFD at {}: {}", sub_ifd, e);
+                        }
+                    }
+                }
+            }
+            next = next_offset;
+        }
+        Ok(())
+    }
+
+    /// IFDのエントリと次のIFDのオフセットを読み込む
+    fn read_ifd(&mut self, offset: u64) -> Result<(Vec<IfdEntry>, u64), RawError> {
+        self.reader.seek(SeekFrom::Start(offset))?;
+        let mut count = [0u8; 2];
+        self.reader.read_exact(&mut count)?;
+        let count = self.u16(&count);
+        let end = offset + 2 + count as u64 * 12 + 4;
+        if count > MAX_IFD_ENTRIES || end > self.len {
+            return Err(RawError::Invalid(format!("IFD at {} with {} entries exceeds file size {}", offset, count, self.len)));
+        }
+
+        let mut entries = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let mut entry = [0u8; 12];
+            self.reader.read_exact(&mut entry)?;
+            entries.push(IfdEntry {
+                tag: self.u16(&entry[0..2]),
+                field_type: self.u16(&entry[2..4]),
+                count: self.u32(&entry[4..8]),
+                value: [entry[8], entry[9], entry[10], entry[11]],
+            });
+        }
+
+        let mut next = [0u8; 4];
+        self.reader.read_exact(&mut next)?;
+        Ok((entries, self.u32(&next) as u64))
+    }
+
+    /// SHORT / LONG / IFD型の値を読み込む（それ以外の型は空）
+    fn values(&mut self, entry: &IfdEntry) -> Result<Vec<u64>, RawError> {
+        let size = match entry.field_type {
+            3 => 2,
+            4 | 13 => 4,
+            _ => return Ok(Vec::new()),
+        };
+        let count = entry.count.min(MAX_VALUES) as usize;
+        let bytes = if count * size <= 4 {
+            entry.value[..count * size].to_vec()
+        } else {
+            self.reader.seek(SeekFrom::Start(self.u32(&entry.value) as u64))?;
+            let mut bytes = vec![0u8; count * size];
+            self.reader.read_exact(&mut bytes)?;
+            bytes
+        };
+
+        Ok(bytes.chunks_exact(size)
+            .map(|chunk| if size == 2 { self.u16(chunk) as u64 } else { self.u32(chunk) as u64 })
+            .collect())
+    }
+
+    fn u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        if self.big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) }
+    }
+
+    fn u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::jpeg::JpegEncoder;
+    use image::ExtendedColorType;
+
+    /// 単色のJPEGを生成
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let pixels = vec![128u8; (width * height * 3) as usize];
+        JpegEncoder::new(&mut bytes).encode(&pixels, width, height, ExtendedColorType::Rgb8).unwrap();
+        bytes
+    }
+
+    /// IFDのエントリ（リトルエンディアン、LONG型の単一値）
+    fn entry(tag: u16, value: u32) -> Vec<u8> {
+        [&tag.to_le_bytes()[..], &4u16.to_le_bytes(), &1u32.to_le_bytes(), &value.to_le_bytes()].concat()
+    }
+
+    /// IFD0に小さいプレビュー、SubIFDに大きいプレビューを持つRAWを生成
+    fn sample_raw() -> Vec<u8> {
+        let small = jpeg(16, 8);
+        let large = jpeg(64, 32);
+        // ヘッダー(8) + IFD0(2 + 12 * 3 + 4) + SubIFD(2 + 12 * 3 + 4)
+        let sub_ifd = 8 + 42;
+        let small_offset = sub_ifd + 42;
+        let large_offset = small_offset + small.len();
+
+        let mut raw = b"II*\0\x08\0\0\0".to_vec();
+        raw.extend_from_slice(&3u16.to_le_bytes());
+        raw.extend(entry(TAG_SUB_IFDS, sub_ifd as u32));
+        raw.extend(entry(TAG_JPEG_OFFSET, small_offset as u32));
+        raw.extend(entry(TAG_JPEG_LENGTH, small.len() as u32));
+        raw.extend_from_slice(&0u32.to_le_bytes());
+
+        raw.extend_from_slice(&3u16.to_le_bytes());
+        raw.extend(entry(TAG_COMPRESSION, 6));
+        raw.extend(entry(TAG_STRIP_OFFSETS, large_offset as u32));
+        raw.extend(entry(TAG_STRIP_BYTE_COUNTS, large.len() as u32));
+        raw.extend_from_slice(&0u32.to_le_bytes());
+
+        raw.extend(small);
+        raw.extend(large);
+        raw
+    }
+
+    #[test]
+    fn test_largest_preview_is_extracted() {
+        let raw = sample_raw();
+        let preview = find_preview(&mut Cursor::new(&raw)).unwrap();
+        assert_eq!((preview.width, preview.height), (64, 32));
+
+        let bytes = read_preview(&mut Cursor::new(&raw)).unwrap();
+        assert_eq!(image_probe::read_dimensions(&bytes[..]).unwrap(), (64, 32));
+
+        // プレビューのないTIFFはその旨のエラーになる
+        let empty = b"II*\0\x08\0\0\0\0\0\0\0\0\0";
+        assert!(matches!(find_preview(&mut Cursor::new(&empty[..])), Err(RawError::NoPreview)));
+    }
+
+    #[test]
+    fn test_out_of_range_lengths_are_rejected() {
+        // ファイルの外を指すプレビューの長さは確保する前に除外する
+        let mut raw = b"II*\0\x08\0\0\0".to_vec();
+        raw.extend_from_slice(&2u16.to_le_bytes());
+        raw.extend(entry(TAG_JPEG_OFFSET, 8));
+        raw.extend(entry(TAG_JPEG_LENGTH, u32::MAX));
+        raw.extend_from_slice(&0u32.to_le_bytes());
+        assert!(matches!(find_preview(&mut Cursor::new(&raw)), Err(RawError::NoPreview)));
+        assert!(matches!(read_preview(&mut Cursor::new(&raw)), Err(RawError::NoPreview)));
+
+        // ファイルに収まらないエントリ数のIFDは壊れているとみなす
+        let mut raw = b"II*\0\x08\0\0\0".to_vec();
+        raw.extend_from_slice(&u16::MAX.to_le_bytes());
+        raw.extend_from_slice(&[0u8; 16]);
+        assert!(matches!(find_preview(&mut Cursor::new(&raw)), Err(RawError::Invalid(_))));
+    }
+}
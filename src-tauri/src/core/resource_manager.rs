@@ -1,22 +1,74 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 use serde::{Serialize, Deserialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use serde_json::{json, Value as JsonValue};
 
-use crate::core::image_collection::{ImageCollection, ImageMetadata};
+use crate::core::archive;
+use crate::core::duplicates::{self, DuplicateReport};
+use crate::core::extended_metadata::{self, ExtendedMetadata};
+use crate::core::image_format::{self, FormatFilter, ImageFormat};
+use crate::core::image_collection::{ImageCollection, ImageMetadata, ImageWarning};
+use crate::core::image_index::{FileStamp, ImageIndex, IndexedImage};
+use crate::core::image_probe;
+use crate::core::orientation::{self, Rotation};
+use crate::core::perceptual_hash::PerceptualHash;
+use crate::core::raw;
+use crate::core::path_expansion;
+use crate::core::config_library::ConfigLibrary;
+use crate::core::config_schema::{self, insert_default, ConfigError, ConfigIssue, VersionedConfig};
+use crate::core::event_bus::EventBus;
+use crate::core::path_pattern::{self, ExcludeRules, PathPattern};
+use crate::core::resource_scanner::{self, DirectorySnapshot, ScanRoot};
+use crate::core::resource_watcher::WatchBatch;
+use crate::utils::{fnv1a_64, natural_cmp};
 
 /// リソースフィルタ - 対象と除外パスのセット
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// 各エントリはパスまたはグロブ（`**`、`*`、`?`、`[a-z]`）で、構成要素単位で照合する。
+/// exclude はgitignore形式で、後のエントリが優先され `!` で始まるエントリは再包含となる。
+/// そのまま存在する絶対パスは `[` などを含んでも文字どおりのパスとして扱い、
+/// 存在しないパスで `[`・`*`・`?` を文字として指定するには `[[]`・`[*]`・`[?]` と書く。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ResourceFilter {
-    /// 対象となるパスリスト (ディレクトリまたはファイル)
+    /// 対象となるパスリスト (ディレクトリ、ファイルまたは絶対パスのグロブ。`!` で始まるものは除外)
     pub include: Vec<String>,
-    /// 除外するパスリスト (ディレクトリまたはファイル)
+    /// 除外するパスリスト (ディレクトリ、ファイルまたはグロブ。区切りを含まないものは任意の階層の名前と照合)
     pub exclude: Vec<String>,
 }
 
+/// ディレクトリ走査のオプション
+///
+/// デフォルトは「深さ無制限・隠しファイル除外・シンボリックリンクを辿らない・サイズ制限なし」。
+/// 既存の設定ファイルに項目がない場合もデフォルト値で補われる。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanOptions {
+    /// 起点から辿るサブディレクトリの最大階層（0 は起点直下のファイルのみ、None は無制限）
+    pub max_depth: Option<usize>,
+    /// 隠しファイル・隠しディレクトリ（`.` で始まる名前など）を含めるかどうか
+    pub include_hidden: bool,
+    /// ディレクトリへのシンボリックリンクを辿るかどうか（辿る場合は同じディレクトリの再訪を検出して循環を防ぐ。ファイルへのリンクは常に含める）
+    pub follow_symlinks: bool,
+    /// 最小ファイルサイズ（バイト）
+    pub min_file_size: Option<u64>,
+    /// 最大ファイルサイズ（バイト）
+    pub max_file_size: Option<u64>,
+    /// 対象とする画像形式（指定した場合は拡張子ではなくファイルの内容で判定する）
+    pub formats: FormatFilter,
+}
+
+impl ScanOptions {
+    /// ファイルサイズが範囲内かどうか
+    pub(crate) fn accepts_size(&self, size: u64) -> bool {
+        self.min_file_size.is_none_or(|min| size >= min) && self.max_file_size.is_none_or(|max| size <= max)
+    }
+}
+
 /// リソース設定 - 識別子、名前、フィルタ情報を含む
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ResourceConfig {
     /// 設定の一意識別子
     pub id: String,
@@ -24,6 +76,72 @@ pub struct ResourceConfig {
     pub name: String,
     /// リソースフィルタ
     pub filters: ResourceFilter,
+    /// 走査オプション
+    #[serde(default)]
+    pub scan: ScanOptions,
+    /// ファイルシステムを監視して解決済みパスを自動更新するかどうか
+    #[serde(default)]
+    pub watch: bool,
+}
+
+impl VersionedConfig for ResourceConfig {
+    const KIND: &'static str = "resource_config";
+    const VERSION: u32 = 1;
+
+    fn migrate(from: u32, mut data: JsonValue) -> Result<JsonValue, String> {
+        match from {
+            // バージョン0: エンベロープのない設定（走査オプションと監視設定は既定値で補う）
+            0 => {
+                insert_default(&mut data, "scan", json!({}))?;
+                insert_default(&mut data, "watch", json!(false))?;
+                Ok(data)
+            },
+            _ => Err(format!("Unknown resource config version: {}", from)),
+        }
+    }
+
+    fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        if self.id.trim().is_empty() {
+            issues.push(ConfigIssue::EmptyField { path: "id".to_string() });
+        }
+        if self.name.trim().is_empty() {
+            issues.push(ConfigIssue::EmptyField { path: "name".to_string() });
+        }
+        if self.filters.include.is_empty() {
+            issues.push(ConfigIssue::EmptyIncludeList { path: "filters.include".to_string() });
+        }
+        for (kind, entries) in [("include", &self.filters.include), ("exclude", &self.filters.exclude)] {
+            for (index, entry) in entries.iter().enumerate() {
+                if entry.trim().is_empty() {
+                    issues.push(ConfigIssue::EmptyField { path: format!("filters.{}[{}]", kind, index) });
+                }
+            }
+        }
+        issues
+    }
+}
+
+/// フィルタの個別エントリで発生したエラー
+#[derive(Debug, Clone, Serialize)]
+pub struct FilterEntryError {
+    /// 元のエントリ文字列
+    pub entry: String,
+    /// エントリの種類（"include" または "exclude"）
+    pub kind: String,
+    /// エラー内容
+    pub message: String,
+}
+
+impl FilterEntryError {
+    /// 新しいFilterEntryErrorを作成
+    fn new(entry: &str, kind: &str, message: String) -> Self {
+        Self {
+            entry: entry.to_string(),
+            kind: kind.to_string(),
+            message,
+        }
+    }
 }
 
 /// パス展開結果
@@ -33,17 +151,69 @@ pub struct PathResolutionResult {
     pub paths: Vec<String>,
     /// 見つかったファイル数
     pub count: usize,
+    /// 展開できずに無視したエントリ
+    pub entry_errors: Vec<FilterEntryError>,
+    /// 走査できずにスキップしたパス
+    pub skipped: Vec<SkippedPath>,
+    /// 結果がどのように得られたか
+    pub cache_status: CacheStatus,
+    /// 読み直したディレクトリ数
+    pub dirs_rescanned: usize,
+}
+
+/// パス解決結果の取得元
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheStatus {
+    /// すべてのディレクトリを走査した
+    Fresh,
+    /// 更新されたディレクトリのみ走査し直した
+    PartiallyRescanned,
+    /// すべてのディレクトリが前回から変更されておらず、記録を再利用した
+    Cached,
+}
+
+/// パスをスキップした理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// パスが存在しない
+    NotFound,
+    /// アクセス権がない
+    PermissionDenied,
+    /// その他の読み込みエラー
+    Unreadable,
+    /// シンボリックリンクの循環により走査済みのディレクトリ
+    SymlinkCycle,
+    /// 読み込めないアーカイブ
+    InvalidArchive,
+}
+
+/// 走査中にスキップしたパス
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedPath {
+    /// スキップしたパス
+    pub path: String,
+    /// スキップした理由
+    pub reason: SkipReason,
+    /// 詳細メッセージ
+    pub message: String,
 }
 
-/// 画像ファイル拡張子判定
-fn is_image_file(file_name: &str) -> bool {
-    let lower_case = file_name.to_lowercase();
-    lower_case.ends_with(".jpg") || 
-    lower_case.ends_with(".jpeg") || 
-    lower_case.ends_with(".png") || 
-    lower_case.ends_with(".gif") || 
-    lower_case.ends_with(".bmp") || 
-    lower_case.ends_with(".webp")
+impl SkippedPath {
+    /// 入出力エラーからSkippedPathを作成
+    pub(crate) fn from_io_error(path: &Path, error: &std::io::Error) -> Self {
+        let reason = match error.kind() {
+            std::io::ErrorKind::NotFound => SkipReason::NotFound,
+            std::io::ErrorKind::PermissionDenied => SkipReason::PermissionDenied,
+            _ => SkipReason::Unreadable,
+        };
+        Self {
+            path: path.to_string_lossy().to_string(),
+            reason,
+            message: error.to_string(),
+        }
+    }
 }
 
 /// リソース管理クラス
@@ -53,6 +223,16 @@ pub struct ResourceManager {
     config_cache: Arc<Mutex<HashMap<String, ResourceConfig>>>,
     /// パス解決キャッシュ (設定ID -> 解決済みパスリスト)
     path_cache: Arc<Mutex<HashMap<String, Vec<String>>>>,
+    /// 走査スナップショット (フィルタ設定のハッシュ -> ディレクトリ単位の記録)
+    snapshot_cache: Arc<Mutex<HashMap<u64, Arc<DirectorySnapshot>>>>,
+    /// 実行中の解決処理のキャンセルフラグ (設定ID -> フラグ)
+    active_resolutions: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
+    /// 進捗イベントの発行先
+    event_bus: Option<Arc<EventBus>>,
+    /// 永続画像インデックス（再起動後もメタデータと走査結果を再利用する）
+    index: Option<Arc<ImageIndex>>,
+    /// 名前付きリソース設定のライブラリ
+    config_library: Option<Arc<ConfigLibrary>>,
 }
 
 impl ResourceManager {
@@ -61,107 +241,75 @@ impl ResourceManager {
         Self {
             config_cache: Arc::new(Mutex::new(HashMap::new())),
             path_cache: Arc::new(Mutex::new(HashMap::new())),
+            snapshot_cache: Arc::new(Mutex::new(HashMap::new())),
+            active_resolutions: Arc::new(Mutex::new(HashMap::new())),
+            event_bus: None,
+            index: None,
+            config_library: None,
         }
     }
 
-    /// 指定されたJSONパスから設定をロード
-    pub fn load_config(&self, path: &str) -> Result<ResourceConfig, String> {
-        let file_content = fs::read_to_string(path)
-            .map_err(|e| format!("Failed to read config file: {}", e))?;
-        
-        serde_json::from_str(&file_content)
-            .map_err(|e| format!("Failed to parse config JSON: {}", e))
+    /// 進捗イベントをイベントバスに発行するResourceManagerインスタンスを作成
+    pub fn with_event_bus(event_bus: Arc<EventBus>) -> Self {
+        Self {
+            event_bus: Some(event_bus),
+            ..Self::new()
+        }
     }
 
-    /// 設定をJSONファイルに保存
-    pub fn save_config(&self, config: &ResourceConfig, path: &str) -> Result<(), String> {
-        let json = serde_json::to_string_pretty(config)
-            .map_err(|e| format!("Failed to serialize config: {}", e))?;
-        
-        fs::write(path, json)
-            .map_err(|e| format!("Failed to write config file: {}", e))?;
-        
-        // キャッシュに保存
-        if let Ok(mut cache) = self.config_cache.lock() {
-            cache.insert(config.id.clone(), config.clone());
+    /// 永続画像インデックスを使用するよう設定（保存済みの走査スナップショットを読み込む）
+    pub fn with_index(self, index: Arc<ImageIndex>) -> Self {
+        if let Ok(mut cache) = self.snapshot_cache.lock() {
+            cache.extend(index.snapshots());
+        }
+        Self {
+            index: Some(index),
+            ..self
         }
-        
-        Ok(())
     }
 
-    /// 指定されたパスを展開し、すべての画像ファイルを取得
-    fn resolve_path(&self, path: &str, exclude_paths: &[String]) -> Result<Vec<String>, String> {
-        let path_obj = Path::new(path);
-        
-        if !path_obj.exists() {
-            return Err(format!("Path does not exist: {}", path));
+    /// 設定ライブラリを使用するよう設定（設定IDでの読み込みでライブラリの設定を参照する）
+    pub fn with_config_library(self, config_library: Arc<ConfigLibrary>) -> Self {
+        Self {
+            config_library: Some(config_library),
+            ..self
         }
-        
-        let mut result = Vec::new();
-        
-        // ファイルの場合は直接追加
-        if path_obj.is_file() {
-            if let Some(file_name) = path_obj.file_name() {
-                if let Some(file_name_str) = file_name.to_str() {
-                    if is_image_file(file_name_str) {
-                        result.push(path.to_string());
-                    }
-                }
-            }
-            return Ok(result);
+    }
+
+    /// 設定IDで設定を取得（ライブラリを優先し、なければ解決済みの設定から探す）
+    pub fn get_config(&self, config_id: &str) -> Option<ResourceConfig> {
+        if let Some(config) = self.config_library.as_ref().and_then(|library| library.get(config_id)) {
+            return Some(config);
         }
+        self.config_cache.lock().ok()?.get(config_id).cloned()
+    }
+
+    /// 指定されたJSONパスから設定をロード（古い形式は移行し、内容を検証する）
+    pub fn load_config(&self, path: &str) -> Result<ResourceConfig, ConfigError> {
+        let file_content = fs::read_to_string(path)
+            .map_err(|e| ConfigError::Io { message: e.to_string() })?;
         
-        // ディレクトリの場合は再帰的に探索
-        if path_obj.is_dir() {
-            self.scan_directory(path_obj, &mut result, exclude_paths)?;
+        let loaded = config_schema::from_json::<ResourceConfig>(&file_content)?;
+        if loaded.source_version < ResourceConfig::VERSION {
+            log::info!("Migrated config {} from version {}", path, loaded.source_version);
         }
-        
-        Ok(result)
+        Ok(loaded.config)
     }
-    
-    /// ディレクトリを再帰的に走査して画像ファイルを検索
-    fn scan_directory(&self, dir: &Path, result: &mut Vec<String>, exclude_paths: &[String]) -> Result<(), String> {
-        // 除外パスチェック
-        if let Some(dir_str) = dir.to_str() {
-            if exclude_paths.iter().any(|exclude| dir_str.starts_with(exclude)) {
-                return Ok(());
-            }
+
+    /// 設定をバージョン付きのJSONファイルに保存
+    pub fn save_config(&self, config: &ResourceConfig, path: &str) -> Result<(), ConfigError> {
+        let issues = config.validate();
+        if !issues.is_empty() {
+            return Err(ConfigError::Invalid { issues });
         }
+        let json = config_schema::to_json(config)?;
         
-        let entries = fs::read_dir(dir)
-            .map_err(|e| format!("Failed to read directory {}: {}", dir.display(), e))?;
+        fs::write(path, json)
+            .map_err(|e| ConfigError::Io { message: e.to_string() })?;
         
-        for entry in entries {
-            let entry = match entry {
-                Ok(entry) => entry,
-                Err(e) => {
-                    log::warn!("Failed to read directory entry: {}", e);
-                    continue;
-                }
-            };
-            
-            let path = entry.path();
-            
-            // 除外パスチェック
-            if let Some(path_str) = path.to_str() {
-                if exclude_paths.iter().any(|exclude| path_str.starts_with(exclude)) {
-                    continue;
-                }
-            }
-            
-            if path.is_dir() {
-                self.scan_directory(&path, result, exclude_paths)?;
-            } else if path.is_file() {
-                if let Some(file_name) = path.file_name() {
-                    if let Some(file_name_str) = file_name.to_str() {
-                        if is_image_file(file_name_str) {
-                            if let Some(path_str) = path.to_str() {
-                                result.push(path_str.to_string());
-                            }
-                        }
-                    }
-                }
-            }
+        // キャッシュに保存
+        if let Ok(mut cache) = self.config_cache.lock() {
+            cache.insert(config.id.clone(), config.clone());
         }
         
         Ok(())
@@ -174,90 +322,441 @@ impl ResourceManager {
             cache.insert(config.id.clone(), config.clone());
         }
         
-        // パスキャッシュをチェック
-        if let Ok(cache) = self.path_cache.lock() {
-            if let Some(paths) = cache.get(&config.id) {
-                return Ok(PathResolutionResult {
-                    paths: paths.clone(),
-                    count: paths.len(),
-                });
+        // 同じフィルタ設定で前回走査した結果があれば、更新日時を確認して再利用する
+        let filter_hash = filter_hash(&config);
+        let previous = self.snapshot_cache.lock().ok()
+            .and_then(|cache| cache.get(&filter_hash).cloned());
+        let has_previous = previous.is_some();
+        let previous = previous.unwrap_or_default();
+        
+        let mut entry_errors = Vec::new();
+        let (include_roots, exclude_rules) = build_scan_plan(&config, &mut entry_errors);
+        
+        // キャンセル用のフラグを登録
+        let cancel = Arc::new(AtomicBool::new(false));
+        if let Ok(mut active) = self.active_resolutions.lock() {
+            active.insert(config.id.clone(), Arc::clone(&cancel));
+        }
+        
+        // 走査はブロッキング処理のためワーカースレッドで並列に実行
+        let event_bus = self.event_bus.clone();
+        let config_id = config.id.clone();
+        let options = config.scan.clone();
+        let scan_cancel = Arc::clone(&cancel);
+        let outcome = tauri::async_runtime::spawn_blocking(move || {
+            resource_scanner::scan_with_snapshot(&include_roots, &exclude_rules, &options, &previous, &scan_cancel, |dirs_visited, images_found| {
+                if let Some(event_bus) = &event_bus {
+                    let _ = event_bus.publish("resource:scan_progress", json!({
+                        "config_id": config_id,
+                        "dirs_visited": dirs_visited,
+                        "images_found": images_found,
+                        "finished": false,
+                        "cancelled": false,
+                    }));
+                }
+            })
+        })
+        .await;
+        
+        // 完了したのが自分の解決処理であればフラグの登録を解除
+        if let Ok(mut active) = self.active_resolutions.lock() {
+            if active.get(&config.id).is_some_and(|flag| Arc::ptr_eq(flag, &cancel)) {
+                active.remove(&config.id);
             }
         }
         
-        let mut all_paths = Vec::new();
+        let outcome = outcome.map_err(|e| format!("Resolution task failed: {}", e))?;
+        let dirs_rescanned = outcome.dirs_visited - outcome.dirs_reused;
+        let cache_status = if !has_previous || (outcome.dirs_reused == 0 && dirs_rescanned > 0) {
+            CacheStatus::Fresh
+        } else if dirs_rescanned > 0 {
+            CacheStatus::PartiallyRescanned
+        } else {
+            CacheStatus::Cached
+        };
+        let mut all_paths = outcome.paths;
+        
+        if let Some(event_bus) = &self.event_bus {
+            let _ = event_bus.publish("resource:scan_progress", json!({
+                "config_id": config.id,
+                "dirs_visited": outcome.dirs_visited,
+                "images_found": all_paths.len(),
+                "finished": true,
+                "cancelled": outcome.cancelled,
+            }));
+        }
         
-        // include パスを処理
-        for include_path in &config.filters.include {
-            let paths = self.resolve_path(include_path, &config.filters.exclude)?;
-            all_paths.extend(paths);
+        // キャンセルされた場合は途中までの結果をキャッシュしない
+        if outcome.cancelled {
+            return Err(format!("Resolution cancelled: {}", config.id));
         }
         
-        // 重複を除去
-        all_paths.sort();
+        // 重複を除去（アーカイブのページ順が保たれるよう自然順で並べる）
+        all_paths.sort_by(|a, b| natural_cmp(a, b));
         all_paths.dedup();
         
         // キャッシュに保存
+        let snapshot = Arc::new(outcome.snapshot);
+        if let Ok(mut cache) = self.snapshot_cache.lock() {
+            cache.insert(filter_hash, Arc::clone(&snapshot));
+            resource_scanner::evict_snapshots(&mut cache, resource_scanner::MAX_SNAPSHOTS);
+        }
         if let Ok(mut cache) = self.path_cache.lock() {
             cache.insert(config.id.clone(), all_paths.clone());
         }
+        if let Some(index) = &self.index {
+            index.set_snapshot(filter_hash, snapshot);
+        }
+        self.save_index();
         
         Ok(PathResolutionResult {
             paths: all_paths.clone(),
             count: all_paths.len(),
+            entry_errors,
+            skipped: outcome.skipped,
+            cache_status,
+            dirs_rescanned,
         })
     }
 
     /// パスリストから内部で画像コレクションを作成する関数
     pub async fn internal_load_images_from_paths(&self, paths: Vec<String>) -> Result<ImageCollection, String> {
         let mut metadata_list = Vec::new();
+        let mut warnings = Vec::new();
         
         for path in paths {
-            let path_obj = PathBuf::from(&path);
-            
-            if !path_obj.exists() || !path_obj.is_file() {
-                log::warn!("Skipping invalid path: {}", path);
-                continue;
+            if let Some((metadata, warning)) = self.load_image_metadata(&path) {
+                metadata_list.push(metadata);
+                warnings.extend(warning);
             }
-            
-            let file_name = path_obj.file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("unknown")
-                .to_string();
-            
-            let file_size = match fs::metadata(&path_obj) {
-                Ok(metadata) => metadata.len(),
-                Err(_) => 0,
-            };
-            
-            metadata_list.push(ImageMetadata {
-                path: path.clone(),
-                file_name,
-                file_size,
-                dimensions: None, // 初期段階では次元情報は取得しない
-                date_created: None,
-                date_modified: None,
+        }
+        self.save_index();
+        
+        Ok(ImageCollection::with_warnings(metadata_list, warnings))
+    }
+
+    /// 画像のメタデータを取得（ファイルが変更されていなければインデックスの記録を使う）
+    pub fn load_image_metadata(&self, path: &str) -> Option<(ImageMetadata, Option<ImageWarning>)> {
+        let index = match &self.index {
+            Some(index) => index,
+            None => return read_image_metadata(path),
+        };
+        
+        let stamp = match FileStamp::read(Path::new(path)) {
+            Some(stamp) => stamp,
+            None => {
+                // 削除されたファイルは記録からも取り除く
+                index.remove(path);
+                return read_image_metadata(path);
+            }
+        };
+        
+        if let Some(indexed) = index.lookup(path, &stamp) {
+            let warning = indexed.warning.map(|message| ImageWarning {
+                path: path.to_string(),
+                message,
             });
+            return Some((indexed.metadata, warning));
         }
         
-        Ok(ImageCollection::new(metadata_list))
+        let (metadata, warning) = read_image_metadata(path)?;
+        index.upsert(IndexedImage {
+            stamp,
+            metadata: metadata.clone(),
+            warning: warning.as_ref().map(|w| w.message.clone()),
+            extended: None,
+            content_hash: None,
+            perceptual_hash: None,
+        });
+        Some((metadata, warning))
+    }
+
+    /// 画像の拡張メタデータ（EXIF/IPTC/XMP）を取得（抽出済みであればインデックスの記録を使う）
+    pub fn load_extended_metadata(&self, path: &str) -> Result<ExtendedMetadata, String> {
+        let read = |path: &str| extended_metadata::read_path(path)
+            .map_err(|e| format!("Failed to read metadata of {}: {}", path, e));
+        let (index, stamp) = match (&self.index, FileStamp::read(Path::new(path))) {
+            (Some(index), Some(stamp)) => (index, stamp),
+            _ => return read(path),
+        };
+
+        if let Some(extended) = index.lookup(path, &stamp).and_then(|indexed| indexed.extended) {
+            return Ok(extended);
+        }
+
+        let extended = read(path)?;
+        // 基本メタデータが未記録であれば先に記録する
+        if index.lookup(path, &stamp).is_none() {
+            self.load_image_metadata(path);
+        }
+        if index.set_extended(path, &stamp, extended.clone()) {
+            self.save_index();
+        }
+        Ok(extended)
+    }
+
+    /// 画像の内容のハッシュを取得（計算済みであればインデックスの記録を使う）
+    pub fn content_hash(&self, path: &str) -> Result<String, String> {
+        let (index, stamp) = match (&self.index, FileStamp::read(Path::new(path))) {
+            (Some(index), Some(stamp)) => (index, stamp),
+            _ => return duplicates::hash_path(path),
+        };
+
+        if let Some(hash) = index.lookup(path, &stamp).and_then(|indexed| indexed.content_hash) {
+            return Ok(hash);
+        }
+
+        let hash = duplicates::hash_path(path)?;
+        if index.lookup(path, &stamp).is_none() {
+            self.load_image_metadata(path);
+        }
+        index.set_content_hash(path, &stamp, hash.clone());
+        Ok(hash)
+    }
+
+    /// 知覚ハッシュを計算済みのすべての画像（パス, ハッシュ）
+    pub fn indexed_perceptual_hashes(&self) -> Vec<(String, PerceptualHash)> {
+        self.index.as_ref().map(|index| index.perceptual_hashes()).unwrap_or_default()
+    }
+
+    /// 画像の中から内容が同一のものを探す（ハッシュの計算状況をイベントで通知する）
+    ///
+    /// RAW+JPEGのペアは両方のファイルを対象とする。
+    pub fn find_duplicates(&self, collection_id: &str, images: &[ImageMetadata]) -> DuplicateReport {
+        let mut files: Vec<(String, u64)> = images.iter()
+            .flat_map(|metadata| std::iter::once(metadata).chain(&metadata.companions))
+            .map(|metadata| (metadata.path.clone(), metadata.file_size))
+            .collect();
+        let mut seen = HashSet::new();
+        files.retain(|(path, _)| seen.insert(path.clone()));
+
+        let report = duplicates::find_duplicates(
+            &files,
+            |path| self.content_hash(path),
+            |completed, total, path| {
+                if let Some(event_bus) = &self.event_bus {
+                    let _ = event_bus.publish("duplicates:progress", json!({
+                        "collection_id": collection_id,
+                        "completed": completed,
+                        "total": total,
+                        "path": path,
+                    }));
+                }
+            },
+        );
+        self.save_index();
+
+        if let Some(event_bus) = &self.event_bus {
+            let _ = event_bus.publish("duplicates:completed", json!({
+                "collection_id": collection_id,
+                "groups": report.groups.len(),
+                "wasted_bytes": report.wasted_bytes,
+            }));
+        }
+        report
+    }
+
+    /// 画像に回転を設定してインデックスに保存し、更新後のメタデータを返す
+    pub fn set_image_rotation(&self, path: &str, rotation: Rotation) -> Result<ImageMetadata, String> {
+        let index = self.index.as_ref()
+            .ok_or_else(|| "Image index is not available".to_string())?;
+        // 未記録の画像や変更された画像は先に記録し直す
+        if self.load_image_metadata(path).is_none() {
+            return Err(format!("Failed to read image metadata: {}", path));
+        }
+        let metadata = index.set_rotation(path, rotation)
+            .ok_or_else(|| format!("Image is not indexed: {}", path))?;
+        // ユーザーの指定はすぐに保存し、インデックスの変更は他の変更とまとめて保存する
+        if let Err(e) = index.save_rotations() {
+            log::warn!("Failed to save image rotations: {}", e);
+        }
+        self.save_index();
+        
+        if let Some(event_bus) = &self.event_bus {
+            let _ = event_bus.publish("image:rotation_changed", json!({
+                "path": path,
+                "rotation": rotation,
+                "display_dimensions": metadata.display_dimensions,
+            }));
+        }
+        Ok(metadata)
+    }
+
+    /// インデックスに変更があれば保存（前回の保存から間もない場合は自動保存に任せる）
+    pub fn save_index(&self) {
+        if let Some(index) = &self.index {
+            if let Err(e) = index.save_if_due() {
+                log::warn!("Failed to save image index {}: {}", index.path().display(), e);
+            }
+        }
+    }
+
+    /// インデックスの未保存の変更をすぐに保存（終了時に使う）
+    pub fn flush_index(&self) {
+        if let Some(index) = &self.index {
+            if let Err(e) = index.save() {
+                log::warn!("Failed to save image index {}: {}", index.path().display(), e);
+            }
+        }
     }
 
     /// 設定IDに基づいて内部で画像コレクションを直接ロードする関数
     pub async fn internal_load_images_from_config(&self, config_id: String) -> Result<ImageCollection, String> {
-        let config = {
-            if let Ok(cache) = self.config_cache.lock() {
-                if let Some(config) = cache.get(&config_id) {
-                    config.clone()
-                } else {
-                    return Err(format!("Config not found for ID: {}", config_id));
+        let config = self.get_config(&config_id)
+            .ok_or_else(|| format!("Config not found for ID: {}", config_id))?;
+        
+        let result = self.internal_resolve_resources(config).await?;
+        self.internal_load_images_from_paths(result.paths).await
+    }
+
+    /// 実行中の解決処理をキャンセル（該当する処理がなければ false）
+    pub fn cancel_resolution(&self, config_id: &str) -> bool {
+        match self.active_resolutions.lock() {
+            Ok(active) => match active.get(config_id) {
+                Some(flag) => {
+                    flag.store(true, Ordering::SeqCst);
+                    true
+                },
+                None => false,
+            },
+            Err(_) => false,
+        }
+    }
+
+    /// 設定の監視対象となる起点パスを取得
+    pub fn watch_roots(&self, config_id: &str) -> Option<Vec<PathBuf>> {
+        let config = self.config_cache.lock().ok()?.get(config_id).cloned()?;
+        let (roots, _) = build_scan_plan(&config, &mut Vec::new());
+        Some(roots.into_iter().map(|root| root.path).collect())
+    }
+
+    /// 監視で検出した変更を解決済みパスリストに反映し、実際に変化した画像のパスを返す
+    pub fn apply_fs_changes(&self, config_id: &str, changes: &WatchBatch) -> WatchBatch {
+        let config = match self.config_cache.lock().ok().and_then(|cache| cache.get(config_id).cloned()) {
+            Some(config) => config,
+            None => return WatchBatch::default(),
+        };
+        let (roots, exclude_rules) = build_scan_plan(&config, &mut Vec::new());
+        
+        // 追加・変更されたパスを判定（ファイルシステムへのアクセスはロックの外で行う）
+        let mut candidates: Vec<(String, bool)> = Vec::new();
+        // 変更されたアーカイブ -> 現在のエントリ（読めない・対象外になった場合は空）
+        let mut archives: Vec<(String, Vec<String>)> = Vec::new();
+        for path in changes.added.iter().chain(&changes.modified) {
+            let path_obj = Path::new(path);
+            if archive::is_archive_path(path_obj) {
+                let accepted = resource_scanner::accepts_path(&roots, &exclude_rules, &config.scan, path_obj);
+                let entries = match accepted {
+                    true => resource_scanner::archive_entry_paths(path, &config.scan).unwrap_or_default(),
+                    false => Vec::new(),
+                };
+                archives.push((path.clone(), entries));
+            } else if path_obj.is_dir() {
+                // 追加されたディレクトリは配下を走査する（変更はファイル側の通知で扱う）
+                if changes.added.contains(path) {
+                    let scan_root = [ScanRoot { path: path_obj.to_path_buf(), pattern: None }];
+                    let options = ScanOptions { max_depth: None, ..config.scan.clone() };
+                    let outcome = resource_scanner::scan(&scan_root, &ExcludeRules::new(), &options, &AtomicBool::new(false), |_, _| {});
+                    for found in outcome.paths {
+                        let accepted = resource_scanner::accepts_path(&roots, &exclude_rules, &config.scan, Path::new(&found));
+                        candidates.push((found, accepted));
+                    }
                 }
             } else {
-                return Err("Failed to access config cache".to_string());
+                let accepted = resource_scanner::accepts_path(&roots, &exclude_rules, &config.scan, path_obj);
+                candidates.push((path.clone(), accepted));
             }
+        }
+        
+        let mut applied = WatchBatch::default();
+        let mut cache = match self.path_cache.lock() {
+            Ok(cache) => cache,
+            Err(_) => return applied,
+        };
+        let paths = match cache.get_mut(config_id) {
+            Some(paths) => paths,
+            None => return applied,
         };
         
-        let result = self.internal_resolve_resources(config).await?;
-        self.internal_load_images_from_paths(result.paths).await
+        // 変更されたアーカイブからなくなったエントリ
+        for (archive_path, entries) in archives {
+            for path in paths.iter() {
+                let in_archive = archive::split_entry_path(path).is_some_and(|(a, _)| a == archive_path);
+                if in_archive && !entries.contains(path) {
+                    candidates.push((path.clone(), false));
+                }
+            }
+            candidates.extend(entries.into_iter().map(|entry| (entry, true)));
+        }
+        
+        // 削除されたパス（ディレクトリの場合は配下すべて、アーカイブの場合はエントリすべて）
+        for removed in &changes.removed {
+            if let Some(index) = &self.index {
+                index.remove(removed);
+            }
+            let removed_path = Path::new(removed);
+            paths.retain(|path| {
+                let gone = archive::physical_path(path).starts_with(removed_path);
+                if gone {
+                    applied.removed.push(path.clone());
+                }
+                !gone
+            });
+        }
+        
+        // 解決済みパスリストは自然順にソート済みのまま維持する
+        for (path, accepted) in candidates {
+            match (paths.binary_search_by(|p| natural_cmp(p, &path)), accepted) {
+                (Err(index), true) => {
+                    paths.insert(index, path.clone());
+                    applied.added.push(path);
+                },
+                (Ok(_), true) => applied.modified.push(path),
+                (Ok(index), false) => {
+                    paths.remove(index);
+                    applied.removed.push(path);
+                },
+                (Err(_), false) => {},
+            }
+        }
+        
+        applied.added.dedup();
+        applied.modified.sort();
+        applied.modified.dedup();
+        applied
+    }
+
+    /// 解決済みパスリストを設定IDで登録（ディレクトリ一覧など設定を経由しない場合に使用）
+    pub fn register_resolved_paths(&self, id: &str, mut paths: Vec<String>) {
+        paths.sort_by(|a, b| natural_cmp(a, b));
+        paths.dedup();
+        if let Ok(mut cache) = self.path_cache.lock() {
+            cache.insert(id.to_string(), paths);
+        }
+    }
+
+    /// 削除・移動した画像を解決済みパスリストとインデックスから取り除く
+    pub fn forget_paths(&self, removed: &[String]) {
+        if let Ok(mut cache) = self.path_cache.lock() {
+            for paths in cache.values_mut() {
+                paths.retain(|path| !removed.contains(path));
+            }
+        }
+        if let Some(index) = &self.index {
+            for path in removed {
+                index.remove(path);
+            }
+        }
+        self.save_index();
+    }
+
+    /// 指定パスがいずれかの解決済みリソースに含まれるかを確認
+    pub fn is_resolved_path(&self, path: &str) -> bool {
+        match self.path_cache.lock() {
+            // 解決済みパスリストは自然順にソート済み
+            Ok(cache) => cache.values().any(|paths| paths.binary_search_by(|p| natural_cmp(p, path)).is_ok()),
+            Err(_) => false,
+        }
     }
 
     /// キャッシュをクリア
@@ -265,6 +764,13 @@ impl ResourceManager {
         if let Ok(mut cache) = self.path_cache.lock() {
             cache.clear();
         }
+        if let Ok(mut cache) = self.snapshot_cache.lock() {
+            cache.clear();
+        }
+        if let Some(index) = &self.index {
+            index.clear();
+        }
+        self.flush_index();
     }
 
     /// 特定の設定IDのキャッシュをクリア
@@ -272,9 +778,205 @@ impl ResourceManager {
         if let Ok(mut cache) = self.path_cache.lock() {
             cache.remove(config_id);
         }
+        let config = self.config_cache.lock().ok().and_then(|cache| cache.get(config_id).cloned());
+        if let Some(config) = config {
+            let hash = filter_hash(&config);
+            if let Ok(mut cache) = self.snapshot_cache.lock() {
+                cache.remove(&hash);
+            }
+            if let Some(index) = &self.index {
+                index.remove_snapshot(hash);
+            }
+        }
     }
 }
 
+/// 画像ファイルのメタデータを読み込む（ヘッダーを解析できない場合は警告を添える）
+pub fn read_image_metadata(path: &str) -> Option<(ImageMetadata, Option<ImageWarning>)> {
+    if let Some((archive_path, entry)) = archive::split_entry_path(path) {
+        return read_archive_entry_metadata(path, Path::new(archive_path), entry);
+    }
+    let path_obj = Path::new(path);
+    
+    if !path_obj.is_file() {
+        log::warn!("Skipping invalid path: {}", path);
+        return None;
+    }
+    
+    let file_name = path_obj.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+        .to_string();
+    
+    let file_size = match fs::metadata(path_obj) {
+        Ok(metadata) => metadata.len(),
+        Err(_) => 0,
+    };
+    
+    // ヘッダーのみを読み込んで形式・寸法と日時を取得
+    let probe = image_probe::probe_image(path_obj);
+    let format = probe.format;
+    let (dimensions, warning) = header_warning(path, &file_name, format, probe.dimensions);
+    let header_exif = extended_metadata::read_header_exif(path);
+    let exif_orientation = header_exif.orientation;
+    
+    let metadata = ImageMetadata {
+        path: path.to_string(),
+        file_name,
+        file_size,
+        format,
+        dimensions,
+        orientation: exif_orientation,
+        rotation: Rotation::None,
+        display_dimensions: dimensions
+            .map(|dimensions| orientation::display_dimensions(dimensions, exif_orientation, Rotation::None)),
+        gps: header_exif.gps,
+        palette: None,
+        date_created: probe.date_created,
+        date_modified: probe.date_modified,
+        companions: Vec::new(),
+    };
+    Some((metadata, warning))
+}
+
+/// アーカイブ内のエントリのメタデータを読み込む（作成日時はアーカイブ自体のものを使う）
+fn read_archive_entry_metadata(path: &str, archive_path: &Path, entry: &str) -> Option<(ImageMetadata, Option<ImageWarning>)> {
+    let probe = match archive::probe_entry(archive_path, entry) {
+        Ok(probe) => probe,
+        Err(e) => {
+            log::warn!("Skipping invalid archive entry {}: {}", path, e);
+            return None;
+        }
+    };
+    
+    let file_name = entry.rsplit('/').next().unwrap_or(entry).to_string();
+    let format = probe.format;
+    let (dimensions, warning) = header_warning(path, &file_name, format, probe.dimensions);
+    let header_exif = extended_metadata::read_header_exif(path);
+    let exif_orientation = header_exif.orientation;
+    let date_created = fs::metadata(archive_path).ok()
+        .and_then(|metadata| image_probe::file_times(&metadata).0);
+    
+    let metadata = ImageMetadata {
+        path: path.to_string(),
+        file_name,
+        file_size: probe.entry.size,
+        format,
+        dimensions,
+        orientation: exif_orientation,
+        rotation: Rotation::None,
+        display_dimensions: dimensions
+            .map(|dimensions| orientation::display_dimensions(dimensions, exif_orientation, Rotation::None)),
+        gps: header_exif.gps,
+        palette: None,
+        date_created,
+        date_modified: probe.entry.modified,
+        companions: Vec::new(),
+    };
+    Some((metadata, warning))
+}
+
+/// ヘッダーの解析結果から寸法と警告を得る
+///
+/// 画像自体は除外せず、ヘッダーを解析できない場合や拡張子と内容の形式が異なる場合、
+/// このビルドでデコードできない形式の場合は警告として報告する。
+/// RAWは表示に使う埋め込みプレビューの寸法を返す。
+fn header_warning(
+    path: &str,
+    file_name: &str,
+    format: Option<ImageFormat>,
+    dimensions: Result<(u32, u32), image_probe::ProbeError>,
+) -> (Option<(u32, u32)>, Option<ImageWarning>) {
+    let mut messages = Vec::new();
+    if let Some(message) = format.and_then(|format| image_format::mismatch_message(file_name, format)) {
+        log::warn!("Image format mismatch {}: {}", path, message);
+        messages.push(message);
+    }
+    if let Some(format) = format.filter(|format| !format.is_decodable()) {
+        messages.push(format.unsupported_message());
+    }
+    let dimensions = if format == Some(ImageFormat::Raw) {
+        raw::find_preview_path(path).map(|preview| (preview.width, preview.height)).map_err(|e| e.to_string())
+    } else {
+        dimensions.map_err(|e| e.to_string())
+    };
+    let dimensions = match dimensions {
+        Ok(dimensions) => Some(dimensions),
+        Err(message) => {
+            log::warn!("Failed to probe image header {}: {}", path, message);
+            messages.push(message);
+            None
+        }
+    };
+    
+    let warning = (!messages.is_empty()).then(|| ImageWarning {
+        path: path.to_string(),
+        message: messages.join("; "),
+    });
+    (dimensions, warning)
+}
+
+/// フィルタと走査オプションのハッシュ（IDや名前が同じでも条件が変われば別のキーになる）
+fn filter_hash(config: &ResourceConfig) -> u64 {
+    let key = json!({
+        "filters": config.filters,
+        "scan": config.scan,
+    });
+    fnv1a_64(key.to_string().as_bytes())
+}
+
+/// 設定のフィルタから走査の起点と除外ルールを組み立てる（無効なエントリはエラーとして記録）
+fn build_scan_plan(config: &ResourceConfig, entry_errors: &mut Vec<FilterEntryError>) -> (Vec<ScanRoot>, ExcludeRules) {
+    // ~ や環境変数を展開（展開できないエントリは報告して無視する）
+    let exclude_paths = expand_entries(&config.filters.exclude, "exclude", entry_errors);
+    let include_paths = expand_entries(&config.filters.include, "include", entry_errors);
+    
+    // exclude はgitignore形式のルールとして扱う（! で再包含）
+    let mut exclude_rules = ExcludeRules::new();
+    for entry in &exclude_paths {
+        if let Err(e) = exclude_rules.push(entry) {
+            entry_errors.push(FilterEntryError::new(entry, "exclude", e.to_string()));
+        }
+    }
+    
+    // include はパスまたはグロブ（! で始まるものは結果から取り除く）
+    let mut include_roots = Vec::new();
+    for entry in &include_paths {
+        if let Some(negated) = entry.trim_start().strip_prefix('!') {
+            match PathPattern::parse_entry(negated) {
+                Ok(pattern) => exclude_rules.push_pattern(pattern),
+                Err(e) => entry_errors.push(FilterEntryError::new(entry, "include", e.to_string())),
+            }
+            continue;
+        }
+        if path_pattern::is_literal_path(entry) {
+            include_roots.push(ScanRoot { path: PathBuf::from(entry), pattern: None });
+            continue;
+        }
+        match PathPattern::parse(entry).and_then(|pattern| pattern.split_include()) {
+            Ok(Some((base, pattern))) => include_roots.push(ScanRoot { path: base, pattern: Some(pattern) }),
+            Ok(None) => include_roots.push(ScanRoot { path: PathBuf::from(entry), pattern: None }),
+            Err(e) => entry_errors.push(FilterEntryError::new(entry, "include", e.to_string())),
+        }
+    }
+    
+    (include_roots, exclude_rules)
+}
+
+/// フィルタのエントリを展開し、失敗したエントリはエラーとして記録
+fn expand_entries(entries: &[String], kind: &str, errors: &mut Vec<FilterEntryError>) -> Vec<String> {
+    entries.iter()
+        .filter_map(|entry| match path_expansion::expand_path(entry) {
+            Ok(expanded) => Some(expanded),
+            Err(e) => {
+                log::warn!("Failed to expand {} entry {}: {}", kind, entry, e);
+                errors.push(FilterEntryError::new(entry, kind, e.to_string()));
+                None
+            }
+        })
+        .collect()
+}
+
 // 以下、Tauriコマンド関数（実装ブロックの外に移動）
 
 /// 設定に基づいてリソースを解決するTauriコマンド
@@ -291,3 +993,232 @@ pub async fn load_images_from_paths(paths: Vec<String>, resource_manager: tauri:
 pub async fn load_images_from_config(config_id: String, resource_manager: tauri::State<'_, Arc<ResourceManager>>) -> Result<ImageCollection, String> {
     resource_manager.internal_load_images_from_config(config_id).await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_options_default_when_missing() {
+        let config: ResourceConfig = serde_json::from_str(
+            r#"{"id": "c", "name": "C", "filters": {"include": [], "exclude": []}}"#
+        ).unwrap();
+        assert_eq!(config.scan, ScanOptions::default());
+
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["scan"]["include_hidden"], false);
+        assert_eq!(json["scan"]["follow_symlinks"], false);
+    }
+
+    #[test]
+    fn test_path_resolution_result_uses_snake_case_keys() {
+        // フロントエンドの PathResolutionResult と同じキーで送る
+        let result = PathResolutionResult {
+            paths: Vec::new(),
+            count: 0,
+            entry_errors: Vec::new(),
+            skipped: Vec::new(),
+            cache_status: CacheStatus::PartiallyRescanned,
+            dirs_rescanned: 2,
+        };
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["cache_status"], "partially_rescanned");
+        assert_eq!(json["dirs_rescanned"], 2);
+        assert!(json["entry_errors"].is_array());
+    }
+
+    #[test]
+    fn test_filter_hash_ignores_id_and_name() {
+        let config = |id: &str, include: &str| ResourceConfig {
+            id: id.to_string(),
+            name: id.to_uppercase(),
+            filters: ResourceFilter {
+                include: vec![include.to_string()],
+                exclude: Vec::new(),
+            },
+            scan: ScanOptions::default(),
+            watch: false,
+        };
+        assert_eq!(filter_hash(&config("a", "/photos")), filter_hash(&config("b", "/photos")));
+        assert_ne!(filter_hash(&config("a", "/photos")), filter_hash(&config("a", "/photos/2024")));
+
+        let mut hidden = config("a", "/photos");
+        hidden.scan.include_hidden = true;
+        assert_ne!(filter_hash(&config("a", "/photos")), filter_hash(&hidden));
+    }
+
+    #[test]
+    fn test_existing_include_path_with_brackets_is_literal() {
+        let dir = std::env::temp_dir().join(format!("image-viewer-brackets-{}", std::process::id()));
+        let photos = dir.join("Photos [2019]");
+        fs::create_dir_all(&photos).unwrap();
+        let config = ResourceConfig {
+            id: "brackets".to_string(),
+            name: "Brackets".to_string(),
+            filters: ResourceFilter {
+                include: vec![photos.to_string_lossy().into_owned(), format!("{}/missing [", dir.to_string_lossy())],
+                exclude: Vec::new(),
+            },
+            scan: ScanOptions::default(),
+            watch: false,
+        };
+
+        let mut entry_errors = Vec::new();
+        let (roots, _) = build_scan_plan(&config, &mut entry_errors);
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].path, photos);
+        assert!(roots[0].pattern.is_none());
+        // 存在しないパスは従来どおりグロブとして解析される
+        assert_eq!(entry_errors.len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_metadata_is_reused_from_index() {
+        let dir = std::env::temp_dir().join(format!("image-viewer-manager-index-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let image_path = dir.join("a.jpg");
+        fs::write(&image_path, [0u8; 8]).unwrap();
+        let image_path = image_path.to_string_lossy().to_string();
+        let index_path = dir.join("index.json");
+
+        let manager = ResourceManager::new().with_index(Arc::new(ImageIndex::open(index_path.clone())));
+        let (metadata, warning) = manager.load_image_metadata(&image_path).unwrap();
+        assert_eq!(metadata.file_size, 8);
+        assert!(warning.is_some());
+        manager.save_index();
+
+        // 再起動後も記録が使われる（警告も復元される）
+        let index = Arc::new(ImageIndex::open(index_path));
+        assert_eq!(index.len(), 1);
+        let manager = ResourceManager::new().with_index(Arc::clone(&index));
+        let (_, warning) = manager.load_image_metadata(&image_path).unwrap();
+        assert!(warning.is_some());
+
+        // 削除されたファイルは記録からも取り除かれる
+        fs::remove_file(&image_path).unwrap();
+        assert!(manager.load_image_metadata(&image_path).is_none());
+        assert!(index.is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_apply_fs_changes() {
+        let dir = std::env::temp_dir().join(format!("image-viewer-fs-changes-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let path_of = |name: &str| dir.join(name).to_string_lossy().to_string();
+        for name in ["a.jpg", "b.jpg", "sub/c.png", "sub/d.jpg", "new.jpg", "notes.txt"] {
+            fs::write(dir.join(name), [0u8]).unwrap();
+        }
+
+        let manager = ResourceManager::new();
+        let config = ResourceConfig {
+            id: "watched".to_string(),
+            name: "Watched".to_string(),
+            filters: ResourceFilter {
+                include: vec![dir.to_string_lossy().to_string()],
+                exclude: vec!["*.png".to_string()],
+            },
+            scan: ScanOptions::default(),
+            watch: true,
+        };
+        manager.config_cache.lock().unwrap().insert(config.id.clone(), config);
+        manager.register_resolved_paths("watched", vec![path_of("a.jpg"), path_of("b.jpg")]);
+
+        fs::remove_file(dir.join("b.jpg")).unwrap();
+        let changes = WatchBatch {
+            added: vec![path_of("new.jpg"), path_of("notes.txt"), path_of("sub")],
+            removed: vec![path_of("b.jpg")],
+            modified: vec![path_of("a.jpg")],
+        };
+        let applied = manager.apply_fs_changes("watched", &changes);
+        // 除外パターンと画像以外のファイルは追加されない
+        assert_eq!(applied.added, vec![path_of("new.jpg"), path_of("sub/d.jpg")]);
+        assert_eq!(applied.removed, vec![path_of("b.jpg")]);
+        assert_eq!(applied.modified, vec![path_of("a.jpg")]);
+        assert!(manager.is_resolved_path(&path_of("sub/d.jpg")));
+        assert!(!manager.is_resolved_path(&path_of("b.jpg")));
+
+        // 未解決の設定には何も反映しない
+        assert!(manager.apply_fs_changes("unknown", &changes).is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_format_mismatch_is_reported() {
+        let dir = std::env::temp_dir().join(format!("image-viewer-mismatch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("really-gif.png");
+        fs::write(&path, b"GIF89a\x02\x00\x03\x00\x00\x00\x00").unwrap();
+
+        let (metadata, warning) = read_image_metadata(path.to_str().unwrap()).unwrap();
+        assert_eq!(metadata.format, Some(ImageFormat::Gif));
+        assert_eq!(metadata.dimensions, Some((2, 3)));
+        assert_eq!(warning.unwrap().message, "Content is GIF but extension is '.png'");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_archive_entries_follow_archive_changes() {
+        let dir = std::env::temp_dir().join(format!("image-viewer-archive-changes-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let book = dir.join("book.cbz");
+        let book_path = book.to_string_lossy().to_string();
+        let gif = b"GIF89a\x02\x00\x03\x00\x00\x00\x00";
+        let write_book = |names: &[&str]| {
+            let mut zip = zip::ZipWriter::new(fs::File::create(&book).unwrap());
+            for name in names {
+                zip.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+                std::io::Write::write_all(&mut zip, gif).unwrap();
+            }
+            zip.finish().unwrap();
+        };
+        write_book(&["p1.gif", "p2.gif"]);
+
+        let manager = ResourceManager::new();
+        let config = ResourceConfig {
+            id: "comics".to_string(),
+            name: "Comics".to_string(),
+            filters: ResourceFilter {
+                include: vec![dir.to_string_lossy().to_string()],
+                exclude: vec![],
+            },
+            scan: ScanOptions::default(),
+            watch: true,
+        };
+        manager.config_cache.lock().unwrap().insert(config.id.clone(), config);
+        let entries = resource_scanner::archive_entry_paths(&book_path, &ScanOptions::default()).unwrap();
+        manager.register_resolved_paths("comics", entries);
+
+        // エントリのメタデータはアーカイブを展開せずに読み込む
+        let (metadata, warning) = read_image_metadata(&archive::entry_path(&book_path, "p2.gif")).unwrap();
+        assert_eq!(metadata.file_name, "p2.gif");
+        assert_eq!(metadata.dimensions, Some((2, 3)));
+        assert!(warning.is_none());
+
+        // アーカイブの変更はエントリ単位の差分として反映する
+        write_book(&["p2.gif", "p10.gif"]);
+        let changes = WatchBatch { modified: vec![book_path.clone()], ..WatchBatch::default() };
+        let applied = manager.apply_fs_changes("comics", &changes);
+        assert_eq!(applied.added, vec![archive::entry_path(&book_path, "p10.gif")]);
+        assert_eq!(applied.removed, vec![archive::entry_path(&book_path, "p1.gif")]);
+        assert_eq!(applied.modified, vec![archive::entry_path(&book_path, "p2.gif")]);
+        assert_eq!(manager.path_cache.lock().unwrap()["comics"], vec![
+            archive::entry_path(&book_path, "p2.gif"),
+            archive::entry_path(&book_path, "p10.gif"),
+        ]);
+
+        // アーカイブの削除で全エントリが取り除かれる
+        let changes = WatchBatch { removed: vec![book_path.clone()], ..WatchBatch::default() };
+        assert_eq!(manager.apply_fs_changes("comics", &changes).removed.len(), 2);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,857 @@
+// core/resource_scanner.rs
+// ディレクトリの並列走査 - ワーカースレッドで画像ファイルを収集し、進捗通知とキャンセルに対応する
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use serde::{Deserialize, Serialize};
+
+use crate::core::archive::{self, ArchiveError};
+use crate::core::path_pattern::{ExcludeRules, PathPattern};
+use crate::core::image_format::{self, ImageFormat};
+use crate::core::resource_manager::{ScanOptions, SkipReason, SkippedPath};
+
+/// ワーカースレッド数の上限
+const MAX_WORKERS: usize = 8;
+
+/// 進捗通知の最小間隔
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// 更新日時の粒度の余裕（FATの2秒など、粒度の粗いファイルシステムでも同じ時刻の変更を見逃さないため）
+const MODIFIED_GRANULARITY: Duration = Duration::from_secs(2);
+
+/// 保持する走査スナップショットの最大数（フィルタ設定を変えるたびに増え続けないようにする）
+pub const MAX_SNAPSHOTS: usize = 16;
+
+/// 走査の起点
+#[derive(Debug, Clone)]
+pub struct ScanRoot {
+    /// 起点のパス（ファイルまたはディレクトリ）
+    pub path: PathBuf,
+    /// 起点からの相対パスに適用するパターン
+    pub pattern: Option<PathPattern>,
+}
+
+/// 走査結果
+#[derive(Debug, Default)]
+pub struct ScanOutcome {
+    /// 見つかった画像ファイルのパス（順不同。アーカイブはエントリの仮想パスに展開済み）
+    pub paths: Vec<String>,
+    /// スキップしたパス
+    pub skipped: Vec<SkippedPath>,
+    /// 走査したディレクトリ数（前回の記録を再利用したものを含む）
+    pub dirs_visited: usize,
+    /// 前回の記録を再利用したディレクトリ数
+    pub dirs_reused: usize,
+    /// 今回の走査で得たディレクトリ単位の記録
+    pub snapshot: DirectorySnapshot,
+    /// 途中でキャンセルされたかどうか
+    pub cancelled: bool,
+}
+
+/// ディレクトリ単位の走査記録
+///
+/// ディレクトリの更新日時は直下のエントリが追加・削除・改名されたときに変わるため、
+/// 更新日時が一致する間は読み直さずに再利用できる（既存ファイルの内容の変更は検出しない）。
+/// ただし更新日時が走査の開始時刻と同じ粒度の範囲にある記録は、読み込み後の同じ時刻内の変更で
+/// 更新日時が変わらない可能性があるため信用しない（gitの racily clean と同じ考え方）。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DirectoryRecord {
+    /// 走査時のディレクトリの更新日時
+    pub modified: Option<SystemTime>,
+    /// 直下で見つかった画像ファイル
+    pub files: Vec<String>,
+    /// 走査対象となる直下のサブディレクトリ
+    pub subdirs: Vec<PathBuf>,
+    /// 直下でスキップしたパス
+    pub skipped: Vec<SkippedPath>,
+}
+
+/// 走査結果のスナップショット（起点ごとのディレクトリ記録）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DirectorySnapshot {
+    /// 起点のインデックス -> ディレクトリ -> 記録
+    roots: Vec<HashMap<PathBuf, DirectoryRecord>>,
+    /// 記録を得た走査の開始時刻（記録がない古いスナップショットではすべて読み直す）
+    #[serde(default)]
+    started: Option<SystemTime>,
+}
+
+impl DirectorySnapshot {
+    /// 現在の更新日時で記録を再利用できるかどうか
+    ///
+    /// 更新日時が記録と一致し、かつ記録を得た走査の開始より粒度以上前である必要がある。
+    fn is_fresh(&self, record: &DirectoryRecord, modified: Option<SystemTime>) -> bool {
+        let (Some(modified), Some(started)) = (modified, self.started) else {
+            return false;
+        };
+        record.modified == Some(modified)
+            && modified.checked_add(MODIFIED_GRANULARITY).is_some_and(|limit| limit <= started)
+    }
+
+    /// 記録を得た走査の開始時刻
+    pub fn started(&self) -> Option<SystemTime> {
+        self.started
+    }
+
+    /// 記録を取得
+    pub fn get(&self, root: usize, dir: &Path) -> Option<&DirectoryRecord> {
+        self.roots.get(root)?.get(dir)
+    }
+
+    /// 記録を追加
+    pub fn insert(&mut self, root: usize, dir: PathBuf, record: DirectoryRecord) {
+        if self.roots.len() <= root {
+            self.roots.resize_with(root + 1, HashMap::new);
+        }
+        self.roots[root].insert(dir, record);
+    }
+
+    /// 記録されたディレクトリ数
+    pub fn len(&self) -> usize {
+        self.roots.iter().map(HashMap::len).sum()
+    }
+
+    /// 記録が空かどうか
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// ディレクトリを一意に識別するキー（Unixではデバイス番号とinode番号）
+#[cfg(unix)]
+type DirectoryKey = (u64, u64);
+#[cfg(not(unix))]
+type DirectoryKey = PathBuf;
+
+/// 走査待ちのディレクトリ
+struct WorkItem {
+    /// 起点のインデックス
+    root: usize,
+    /// 走査するディレクトリ
+    dir: PathBuf,
+    /// 起点からの階層
+    depth: usize,
+}
+
+/// 作業キュー（キューと処理中の件数）
+struct WorkQueue {
+    items: VecDeque<WorkItem>,
+    in_flight: usize,
+}
+
+/// ワーカー間で共有する走査状態
+struct SharedScan<'a> {
+    roots: &'a [ScanRoot],
+    exclude_rules: &'a ExcludeRules,
+    options: &'a ScanOptions,
+    previous: &'a DirectorySnapshot,
+    cancel: &'a AtomicBool,
+    queue: Mutex<WorkQueue>,
+    queue_changed: Condvar,
+    visited: Mutex<HashSet<DirectoryKey>>,
+    paths: Mutex<Vec<String>>,
+    skipped: Mutex<Vec<SkippedPath>>,
+    snapshot: Mutex<DirectorySnapshot>,
+    dirs_visited: AtomicUsize,
+    dirs_reused: AtomicUsize,
+    images_found: AtomicUsize,
+    last_progress: Mutex<Instant>,
+}
+
+/// 起点リストを並列に走査して画像ファイルを収集
+///
+/// `on_progress` は走査済みディレクトリ数と見つかった画像数を引数に、一定間隔で呼び出される。
+/// `cancel` が立てられると走査を打ち切り、`cancelled` を設定した結果を返す。
+pub fn scan<P>(
+    roots: &[ScanRoot],
+    exclude_rules: &ExcludeRules,
+    options: &ScanOptions,
+    cancel: &AtomicBool,
+    on_progress: P,
+) -> ScanOutcome
+where
+    P: Fn(usize, usize) + Sync,
+{
+    scan_with_snapshot(roots, exclude_rules, options, &DirectorySnapshot::default(), cancel, on_progress)
+}
+
+/// 前回のスナップショットを使って起点リストを走査
+///
+/// 更新日時が前回と一致し、前回の走査開始より十分前のディレクトリは読み直さずに記録を再利用する。
+/// スナップショットは同じ起点リスト・除外ルール・走査オプションで得たものである必要がある。
+pub fn scan_with_snapshot<P>(
+    roots: &[ScanRoot],
+    exclude_rules: &ExcludeRules,
+    options: &ScanOptions,
+    previous: &DirectorySnapshot,
+    cancel: &AtomicBool,
+    on_progress: P,
+) -> ScanOutcome
+where
+    P: Fn(usize, usize) + Sync,
+{
+    let shared = SharedScan {
+        roots,
+        exclude_rules,
+        options,
+        previous,
+        cancel,
+        queue: Mutex::new(WorkQueue { items: VecDeque::new(), in_flight: 0 }),
+        queue_changed: Condvar::new(),
+        visited: Mutex::new(HashSet::new()),
+        paths: Mutex::new(Vec::new()),
+        skipped: Mutex::new(Vec::new()),
+        snapshot: Mutex::new(DirectorySnapshot { started: Some(SystemTime::now()), ..DirectorySnapshot::default() }),
+        dirs_visited: AtomicUsize::new(0),
+        dirs_reused: AtomicUsize::new(0),
+        images_found: AtomicUsize::new(0),
+        last_progress: Mutex::new(Instant::now()),
+    };
+
+    // 起点を振り分け（ファイルは直接判定し、ディレクトリはキューに積む）
+    for (index, root) in roots.iter().enumerate() {
+        shared.add_root(index, root);
+    }
+
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .clamp(1, MAX_WORKERS);
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| shared.run_worker(&on_progress));
+        }
+    });
+
+    let dirs_visited = shared.dirs_visited.load(Ordering::SeqCst);
+    on_progress(dirs_visited, shared.images_found.load(Ordering::SeqCst));
+
+    // スナップショットにはアーカイブ自体を記録し、エントリの一覧は毎回読み直す
+    let cancelled = cancel.load(Ordering::SeqCst);
+    let mut skipped = shared.skipped.into_inner().unwrap_or_default();
+    let mut paths = shared.paths.into_inner().unwrap_or_default();
+    if !cancelled {
+        paths = expand_archives(paths, options, &mut skipped);
+    }
+
+    ScanOutcome {
+        paths,
+        skipped,
+        dirs_visited,
+        dirs_reused: shared.dirs_reused.load(Ordering::SeqCst),
+        snapshot: shared.snapshot.into_inner().unwrap_or_default(),
+        cancelled,
+    }
+}
+
+/// 走査の新しい順に `max` 件を残してスナップショットを削除
+pub fn evict_snapshots(snapshots: &mut HashMap<u64, Arc<DirectorySnapshot>>, max: usize) {
+    if snapshots.len() <= max {
+        return;
+    }
+    let mut by_age: Vec<(u64, Option<SystemTime>)> = snapshots.iter()
+        .map(|(hash, snapshot)| (*hash, snapshot.started()))
+        .collect();
+    // 開始時刻のない古い形式のものは最も古いものとして扱う
+    by_age.sort_by_key(|(_, started)| std::cmp::Reverse(*started));
+    for (hash, _) in by_age.into_iter().skip(max) {
+        snapshots.remove(&hash);
+    }
+}
+
+/// アーカイブ内の画像エントリの仮想パスを自然順で取得（サイズの条件はエントリ単位で適用する）
+pub fn archive_entry_paths(archive_path: &str, options: &ScanOptions) -> Result<Vec<String>, SkippedPath> {
+    let entries = archive::list_image_entries(Path::new(archive_path)).map_err(|e| {
+        log::warn!("Skipping archive {}: {}", archive_path, e);
+        match e {
+            ArchiveError::Io(e) => SkippedPath::from_io_error(Path::new(archive_path), &e),
+            e => SkippedPath {
+                path: archive_path.to_string(),
+                reason: SkipReason::InvalidArchive,
+                message: e.to_string(),
+            },
+        }
+    })?;
+    // エントリは展開しないと内容を確認できないため、形式は拡張子で判定する
+    Ok(entries.into_iter()
+        .filter(|entry| options.accepts_size(entry.size))
+        .filter(|entry| ImageFormat::from_file_name(&entry.name).is_some_and(|format| options.formats.accepts(format)))
+        .map(|entry| archive::entry_path(archive_path, &entry.name))
+        .collect())
+}
+
+/// ファイルの形式が走査オプションの条件を満たすかどうか
+///
+/// 拡張子が画像形式でないファイルは内容から判定する。形式の条件がある場合は拡張子が画像形式でも内容を確認する。
+fn accepts_format(path: &Path, options: &ScanOptions) -> bool {
+    image_format::file_format(path, !options.formats.is_empty())
+        .is_some_and(|format| options.formats.accepts(format))
+}
+
+/// 見つかったアーカイブをエントリの仮想パスに置き換える
+fn expand_archives(paths: Vec<String>, options: &ScanOptions, skipped: &mut Vec<SkippedPath>) -> Vec<String> {
+    let mut expanded = Vec::with_capacity(paths.len());
+    for path in paths {
+        if !archive::is_archive_path(Path::new(&path)) {
+            expanded.push(path);
+            continue;
+        }
+        match archive_entry_paths(&path, options) {
+            Ok(entries) => expanded.extend(entries),
+            Err(skip) => skipped.push(skip),
+        }
+    }
+    expanded
+}
+
+/// 単一のパスが起点リスト・除外ルール・走査オプションの条件を満たす画像ファイル（またはアーカイブ）かを判定
+///
+/// 監視による差分更新で、走査し直さずに新しいファイルを判定するために使用する。
+/// アーカイブ内のエントリはアーカイブ自体が条件を満たすかで判定する。
+pub fn accepts_path(roots: &[ScanRoot], exclude_rules: &ExcludeRules, options: &ScanOptions, path: &Path) -> bool {
+    if let Some((archive_path, entry)) = path.to_str().and_then(archive::split_entry_path) {
+        let accepted_format = ImageFormat::from_file_name(entry).is_some_and(|format| options.formats.accepts(format));
+        return accepted_format && accepts_path(roots, exclude_rules, options, Path::new(archive_path));
+    }
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return false,
+    };
+    let is_image = options.accepts_size(metadata.len()) && accepts_format(path, options);
+    if !is_image && !archive::is_archive_path(path) {
+        return false;
+    }
+
+    roots.iter().any(|root| {
+        if path == root.path {
+            return !exclude_rules.is_excluded_with_ancestors(path, path, false)
+                && root.pattern.as_ref().is_none_or(|p| p.matches(path, path, false));
+        }
+        let relative = match path.strip_prefix(&root.path) {
+            Ok(relative) => relative,
+            Err(_) => return false,
+        };
+        // 起点からのディレクトリ階層（起点直下のファイルは0）
+        let depth = relative.components().count() - 1;
+        if options.max_depth.is_some_and(|max| depth > max) {
+            return false;
+        }
+        if !options.include_hidden && relative.components().any(|c| c.as_os_str().to_string_lossy().starts_with('.')) {
+            return false;
+        }
+        !exclude_rules.is_excluded_with_ancestors(path, &root.path, false)
+            && root.pattern.as_ref().is_none_or(|p| p.matches(path, &root.path, false))
+    })
+}
+
+impl SharedScan<'_> {
+    /// 起点を追加
+    fn add_root(&self, index: usize, root: &ScanRoot) {
+        let path = root.path.as_path();
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                log::warn!("Skipping include path {}: {}", path.display(), e);
+                self.skip(SkippedPath::from_io_error(path, &e));
+                return;
+            }
+        };
+
+        // 起点自身またはその親が除外されている場合は何もしない
+        if self.exclude_rules.is_excluded_with_ancestors(path, path, metadata.is_dir()) {
+            return;
+        }
+
+        if metadata.is_file() {
+            if self.accepts_file(path, path, root.pattern.as_ref(), metadata.len()) {
+                self.found(path);
+            }
+        } else if metadata.is_dir() {
+            if let Some(key) = directory_key(path) {
+                if let Ok(mut visited) = self.visited.lock() {
+                    visited.insert(key);
+                }
+            }
+            self.push(WorkItem { root: index, dir: root.path.clone(), depth: 0 });
+        }
+    }
+
+    /// キューが空になるか、キャンセルされるまでディレクトリを処理
+    fn run_worker<P: Fn(usize, usize)>(&self, on_progress: &P) {
+        while let Some(item) = self.next_item() {
+            if !self.cancel.load(Ordering::Relaxed) {
+                self.scan_directory(&item);
+                self.dirs_visited.fetch_add(1, Ordering::Relaxed);
+                self.report_progress(on_progress);
+            }
+            self.finish_item();
+        }
+    }
+
+    /// 次のディレクトリを取得（すべての処理が終わった場合は None）
+    fn next_item(&self) -> Option<WorkItem> {
+        let mut queue = self.queue.lock().ok()?;
+        loop {
+            if let Some(item) = queue.items.pop_front() {
+                queue.in_flight += 1;
+                return Some(item);
+            }
+            if queue.in_flight == 0 {
+                return None;
+            }
+            queue = self.queue_changed.wait(queue).ok()?;
+        }
+    }
+
+    /// ディレクトリの処理完了を記録
+    fn finish_item(&self) {
+        if let Ok(mut queue) = self.queue.lock() {
+            queue.in_flight -= 1;
+            if queue.in_flight == 0 && queue.items.is_empty() {
+                self.queue_changed.notify_all();
+            }
+        }
+    }
+
+    /// ディレクトリをキューに追加
+    fn push(&self, item: WorkItem) {
+        if let Ok(mut queue) = self.queue.lock() {
+            queue.items.push_back(item);
+            self.queue_changed.notify_one();
+        }
+    }
+
+    /// 1つのディレクトリを走査（サブディレクトリはキューに追加）
+    fn scan_directory(&self, item: &WorkItem) {
+        let root = &self.roots[item.root];
+        // 読み込み中の変更を取りこぼさないよう、更新日時は読み込み前に取得する
+        let modified = fs::metadata(&item.dir).and_then(|m| m.modified()).ok();
+        if let Some(record) = self.previous.get(item.root, &item.dir) {
+            if self.previous.is_fresh(record, modified) {
+                self.reuse_record(item, record.clone());
+                return;
+            }
+        }
+
+        let entries = match fs::read_dir(&item.dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("Failed to read directory {}: {}", item.dir.display(), e);
+                self.skip(SkippedPath::from_io_error(&item.dir, &e));
+                return;
+            }
+        };
+
+        let mut record = DirectoryRecord { modified, ..DirectoryRecord::default() };
+        for entry in entries {
+            if self.cancel.load(Ordering::Relaxed) {
+                return;
+            }
+
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    log::warn!("Failed to read directory entry: {}", e);
+                    let skipped = SkippedPath::from_io_error(&item.dir, &e);
+                    record.skipped.push(skipped.clone());
+                    self.skip(skipped);
+                    continue;
+                }
+            };
+
+            let path = entry.path();
+
+            // 隠しファイルチェック
+            if !self.options.include_hidden && is_hidden(&entry) {
+                continue;
+            }
+
+            // ディレクトリへのシンボリックリンクは設定で許可された場合のみ辿る（循環を防ぐため）
+            // ファイルへのシンボリックリンクは常に一覧に含める
+            let is_symlink = entry.file_type().map(|t| t.is_symlink()).unwrap_or(false);
+            let is_dir = path.is_dir();
+            if is_symlink && is_dir && !self.options.follow_symlinks {
+                continue;
+            }
+
+            // 除外ルールチェック（除外されたディレクトリ配下は走査しない）
+            if self.exclude_rules.is_excluded(&path, &root.path, is_dir) {
+                continue;
+            }
+
+            if is_dir {
+                let depth = item.depth + 1;
+                // 最大階層を超えるディレクトリは走査しない
+                if self.options.max_depth.is_some_and(|max| depth > max) {
+                    continue;
+                }
+                // パターンが一致しうる深さを超えるディレクトリは走査しない
+                if root.pattern.as_ref().and_then(|p| p.max_depth()).is_some_and(|max| depth >= max) {
+                    continue;
+                }
+                record.subdirs.push(path.clone());
+                self.push_subdir(item, path);
+            } else if path.is_file() {
+                let needs_size = self.options.min_file_size.is_some() || self.options.max_file_size.is_some();
+                let size = if needs_size {
+                    fs::metadata(&path).map(|m| m.len()).unwrap_or(0)
+                } else {
+                    0
+                };
+                if self.accepts_file(&path, &root.path, root.pattern.as_ref(), size) {
+                    if let Some(path_str) = path.to_str() {
+                        record.files.push(path_str.to_string());
+                    }
+                    self.found(&path);
+                }
+            }
+        }
+
+        if let Ok(mut snapshot) = self.snapshot.lock() {
+            snapshot.insert(item.root, item.dir.clone(), record);
+        }
+    }
+
+    /// 前回の記録をそのまま結果に反映（サブディレクトリは改めて確認する）
+    fn reuse_record(&self, item: &WorkItem, record: DirectoryRecord) {
+        self.dirs_reused.fetch_add(1, Ordering::Relaxed);
+        for file in &record.files {
+            self.found(Path::new(file));
+        }
+        for skipped in &record.skipped {
+            self.skip(skipped.clone());
+        }
+        for subdir in &record.subdirs {
+            self.push_subdir(item, subdir.clone());
+        }
+        if let Ok(mut snapshot) = self.snapshot.lock() {
+            snapshot.insert(item.root, item.dir.clone(), record);
+        }
+    }
+
+    /// サブディレクトリをキューに追加
+    fn push_subdir(&self, item: &WorkItem, path: PathBuf) {
+        // 同じディレクトリを二度走査しない（シンボリックリンクによる循環の防止）
+        if self.options.follow_symlinks && !self.mark_visited(&path) {
+            log::warn!("Skipping already visited directory: {}", path.display());
+            self.skip(SkippedPath {
+                path: path.to_string_lossy().to_string(),
+                reason: SkipReason::SymlinkCycle,
+                message: "Directory was already scanned".to_string(),
+            });
+            return;
+        }
+        self.push(WorkItem { root: item.root, dir: path, depth: item.depth + 1 });
+    }
+
+    /// ファイルが収集対象かどうか（拡張子・サイズ・パターン）
+    ///
+    /// アーカイブはサイズを問わずに収集し、展開時にエントリ単位でサイズを判定する。
+    fn accepts_file(&self, path: &Path, root: &Path, pattern: Option<&PathPattern>, size: u64) -> bool {
+        if !pattern.is_none_or(|p| p.matches(path, root, false)) {
+            return false;
+        }
+        archive::is_archive_path(path) || (self.options.accepts_size(size) && accepts_format(path, self.options))
+    }
+
+    /// 走査済みとして記録（既に走査済みなら false）
+    fn mark_visited(&self, path: &Path) -> bool {
+        match (directory_key(path), self.visited.lock()) {
+            (Some(key), Ok(mut visited)) => visited.insert(key),
+            _ => true,
+        }
+    }
+
+    /// 見つかった画像を記録
+    fn found(&self, path: &Path) {
+        if let Some(path_str) = path.to_str() {
+            if let Ok(mut paths) = self.paths.lock() {
+                paths.push(path_str.to_string());
+                self.images_found.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// スキップしたパスを記録
+    fn skip(&self, skipped: SkippedPath) {
+        if let Ok(mut list) = self.skipped.lock() {
+            list.push(skipped);
+        }
+    }
+
+    /// 前回から一定時間経過していれば進捗を通知
+    fn report_progress<P: Fn(usize, usize)>(&self, on_progress: &P) {
+        let due = match self.last_progress.try_lock() {
+            Ok(mut last) if last.elapsed() >= PROGRESS_INTERVAL => {
+                *last = Instant::now();
+                true
+            },
+            _ => false,
+        };
+        if due {
+            on_progress(
+                self.dirs_visited.load(Ordering::Relaxed),
+                self.images_found.load(Ordering::Relaxed),
+            );
+        }
+    }
+}
+
+/// ディレクトリのキーを取得（シンボリックリンクは辿った先のもの）
+#[cfg(unix)]
+fn directory_key(path: &Path) -> Option<DirectoryKey> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|metadata| (metadata.dev(), metadata.ino()))
+}
+
+/// ディレクトリのキーを取得（正規化したパス）
+#[cfg(not(unix))]
+fn directory_key(path: &Path) -> Option<DirectoryKey> {
+    fs::canonicalize(path).ok()
+}
+
+/// 隠しファイル・隠しディレクトリかどうか
+fn is_hidden(entry: &fs::DirEntry) -> bool {
+    if entry.file_name().to_string_lossy().starts_with('.') {
+        return true;
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        if let Ok(metadata) = entry.metadata() {
+            return metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::image_format::FormatFilter;
+
+    /// テスト用のディレクトリ構成を作成
+    fn create_tree(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("image-viewer-scan-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::create_dir_all(root.join(".hidden")).unwrap();
+        fs::write(root.join("top.jpg"), [0u8; 16]).unwrap();
+        fs::write(root.join("large.png"), [0u8; 4096]).unwrap();
+        fs::write(root.join("a/mid.jpg"), [0u8; 16]).unwrap();
+        fs::write(root.join("a/b/deep.jpg"), [0u8; 16]).unwrap();
+        fs::write(root.join(".hidden/secret.jpg"), [0u8; 16]).unwrap();
+        root
+    }
+
+    fn scan_root(root: &Path, options: ScanOptions) -> ScanOutcome {
+        let roots = [ScanRoot { path: root.to_path_buf(), pattern: None }];
+        scan(&roots, &ExcludeRules::new(), &options, &AtomicBool::new(false), |_, _| {})
+    }
+
+    fn resolve(root: &Path, options: ScanOptions) -> Vec<String> {
+        let mut paths = scan_root(root, options).paths;
+        paths.sort();
+        paths.into_iter()
+            .map(|p| Path::new(&p).strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect()
+    }
+
+    #[test]
+    fn test_snapshot_reuses_unchanged_directories() {
+        let root = create_tree("snapshot");
+        let roots = [ScanRoot { path: root.clone(), pattern: None }];
+        let options = ScanOptions::default();
+        let run = |previous: &DirectorySnapshot| {
+            scan_with_snapshot(&roots, &ExcludeRules::new(), &options, previous, &AtomicBool::new(false), |_, _| {})
+        };
+
+        let first = run(&DirectorySnapshot::default());
+        assert_eq!(first.dirs_reused, 0);
+        assert_eq!(first.snapshot.len(), 3);
+
+        // 作成直後のディレクトリは更新日時が走査の開始と同じ時刻の範囲にあるため再利用しない
+        let second = run(&first.snapshot);
+        assert_eq!(second.dirs_reused, 0);
+        assert_eq!(second.paths.len(), first.paths.len());
+
+        // 十分後に走査した記録で、変更がなければすべて再利用する
+        let later = |snapshot: &DirectorySnapshot| DirectorySnapshot {
+            started: snapshot.started.map(|started| started + Duration::from_secs(60)),
+            ..snapshot.clone()
+        };
+        let third = run(&later(&second.snapshot));
+        assert_eq!(third.dirs_reused, 3);
+        assert_eq!(third.paths.len(), first.paths.len());
+
+        // 粒度の粗いファイルシステムで、走査と同じ時刻に追加されて更新日時が変わらなかった場合
+        let mut previous = later(&third.snapshot);
+        fs::write(root.join("a/new.jpg"), [0u8; 16]).unwrap();
+        let modified = fs::metadata(root.join("a")).unwrap().modified().unwrap();
+        previous.roots[0].get_mut(&root.join("a")).unwrap().modified = Some(modified);
+        previous.started = Some(modified);
+        // 十分後に走査した記録であれば信用するため、追加されたファイルは見えない
+        let trusted = run(&later(&previous));
+        assert_eq!(trusted.dirs_reused, 3);
+        assert_eq!(trusted.paths.len(), first.paths.len());
+        // 走査の開始と同じ時刻の範囲の更新日時は信用せずに読み直す
+        let fourth = run(&previous);
+        assert!(fourth.dirs_reused < 3);
+        assert_eq!(fourth.paths.len(), first.paths.len() + 1);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_evict_snapshots_keeps_most_recent() {
+        let snapshot = |seconds: Option<u64>| Arc::new(DirectorySnapshot {
+            started: seconds.map(|seconds| SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)),
+            ..DirectorySnapshot::default()
+        });
+        let mut snapshots: HashMap<u64, Arc<DirectorySnapshot>> = (0..5)
+            .map(|hash| (hash, snapshot(Some(hash * 10))))
+            .collect();
+        snapshots.insert(99, snapshot(None));
+
+        evict_snapshots(&mut snapshots, 3);
+        let mut kept: Vec<u64> = snapshots.into_keys().collect();
+        kept.sort();
+        assert_eq!(kept, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_scan_options() {
+        let root = create_tree("options");
+
+        assert_eq!(resolve(&root, ScanOptions::default()), vec!["a/b/deep.jpg", "a/mid.jpg", "large.png", "top.jpg"]);
+
+        let options = ScanOptions { max_depth: Some(1), ..ScanOptions::default() };
+        assert_eq!(resolve(&root, options), vec!["a/mid.jpg", "large.png", "top.jpg"]);
+
+        let options = ScanOptions { include_hidden: true, max_depth: Some(0), ..ScanOptions::default() };
+        assert_eq!(resolve(&root, options), vec!["large.png", "top.jpg"]);
+
+        let options = ScanOptions { include_hidden: true, max_file_size: Some(1024), ..ScanOptions::default() };
+        assert_eq!(resolve(&root, options), vec![".hidden/secret.jpg", "a/b/deep.jpg", "a/mid.jpg", "top.jpg"]);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_cycle_is_detected() {
+        let root = create_tree("symlink");
+        std::os::unix::fs::symlink(&root, root.join("a/b/loop")).unwrap();
+
+        // デフォルトではディレクトリへのシンボリックリンクを辿らない
+        assert_eq!(resolve(&root, ScanOptions::default()).len(), 4);
+
+        // 辿る場合も循環したディレクトリは一度しか走査しない
+        let options = ScanOptions { follow_symlinks: true, ..ScanOptions::default() };
+        let outcome = scan_root(&root, options);
+        assert_eq!(outcome.paths.len(), 4);
+        assert_eq!(outcome.skipped[0].reason, SkipReason::SymlinkCycle);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_files_are_listed_by_default() {
+        let root = create_tree("symlink-file");
+        std::os::unix::fs::symlink(root.join("top.jpg"), root.join("linked.jpg")).unwrap();
+
+        let paths = resolve(&root, ScanOptions::default());
+        assert!(paths.contains(&"linked.jpg".to_string()));
+        assert_eq!(paths.len(), 5);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_missing_root_is_skipped() {
+        let root = std::env::temp_dir().join(format!("image-viewer-missing-{}", std::process::id()));
+        let outcome = scan_root(&root, ScanOptions::default());
+        assert!(outcome.paths.is_empty());
+        assert_eq!(outcome.skipped.len(), 1);
+        assert_eq!(outcome.skipped[0].reason, SkipReason::NotFound);
+    }
+
+    #[test]
+    fn test_accepts_path() {
+        let root = create_tree("accepts");
+        let roots = [ScanRoot { path: root.clone(), pattern: None }];
+        let mut rules = ExcludeRules::new();
+        rules.push("b").unwrap();
+
+        let accepts = |path: &Path, options: &ScanOptions| accepts_path(&roots, &rules, options, path);
+        assert!(accepts(&root.join("a/mid.jpg"), &ScanOptions::default()));
+        assert!(!accepts(&root.join("a/b/deep.jpg"), &ScanOptions::default()));
+        assert!(!accepts(&root.join(".hidden/secret.jpg"), &ScanOptions::default()));
+        assert!(!accepts(&root.join("a/mid.jpg"), &ScanOptions { max_depth: Some(0), ..ScanOptions::default() }));
+        assert!(!accepts(&root.join("missing.jpg"), &ScanOptions::default()));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_archives_are_expanded() {
+        let root = create_tree("archive");
+        let mut zip = zip::ZipWriter::new(fs::File::create(root.join("a/book.cbz")).unwrap());
+        for name in ["p10.jpg", "p9.jpg", "readme.txt"] {
+            zip.start_file(name, zip::write::SimpleFileOptions::default()).unwrap();
+            std::io::Write::write_all(&mut zip, &[0u8; 16]).unwrap();
+        }
+        zip.finish().unwrap();
+        fs::write(root.join("broken.zip"), b"not a zip").unwrap();
+
+        let outcome = scan_root(&root, ScanOptions::default());
+        let mut entries: Vec<&String> = outcome.paths.iter().filter(|p| p.contains("book.cbz")).collect();
+        entries.sort();
+        let archive = root.join("a/book.cbz").to_string_lossy().to_string();
+        assert_eq!(entries, vec![&archive::entry_path(&archive, "p10.jpg"), &archive::entry_path(&archive, "p9.jpg")]);
+        assert!(outcome.skipped.iter().any(|s| s.reason == SkipReason::InvalidArchive));
+
+        // エントリはアーカイブ自体の条件で判定する
+        let roots = [ScanRoot { path: root.clone(), pattern: None }];
+        let accepts = |path: &str| accepts_path(&roots, &ExcludeRules::new(), &ScanOptions::default(), Path::new(path));
+        assert!(accepts(&archive::entry_path(&archive, "p9.jpg")));
+        assert!(!accepts(&archive::entry_path(&archive, "readme.txt")));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_formats_are_detected_from_content() {
+        let root = create_tree("formats");
+        fs::write(root.join("photo.JPG.part"), [0xFF, 0xD8, 0xFF, 0xE0, 0, 0]).unwrap();
+        fs::write(root.join("really-jpeg.png"), [0xFF, 0xD8, 0xFF, 0xE0, 0, 0]).unwrap();
+        fs::write(root.join("notes.txt"), b"plain text").unwrap();
+
+        let names = resolve(&root, ScanOptions { max_depth: Some(0), ..ScanOptions::default() });
+        assert_eq!(names, vec!["large.png", "photo.JPG.part", "really-jpeg.png", "top.jpg"]);
+
+        // 形式の条件がある場合は拡張子ではなく内容で判定する
+        let formats = FormatFilter { include: vec![ImageFormat::Jpeg], exclude: vec![] };
+        let options = ScanOptions { max_depth: Some(0), formats, ..ScanOptions::default() };
+        assert_eq!(resolve(&root, options), vec!["photo.JPG.part", "really-jpeg.png", "top.jpg"]);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_cancelled_scan_stops() {
+        let root = create_tree("cancel");
+        let roots = [ScanRoot { path: root.clone(), pattern: None }];
+        let outcome = scan(&roots, &ExcludeRules::new(), &ScanOptions::default(), &AtomicBool::new(true), |_, _| {});
+        assert!(outcome.cancelled);
+        assert_eq!(outcome.dirs_visited, 0);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,236 @@
+// core/resource_watcher.rs
+// リソース設定の監視 - ファイルシステムの変更通知をまとめてバッチとして通知する
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use notify::event::{EventKind, ModifyKind, RenameMode};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+
+/// 最後の変更通知からバッチを確定するまでの待ち時間
+const DEBOUNCE_DELAY: Duration = Duration::from_millis(500);
+
+/// 変更が続いていてもバッチを確定するまでの最大待ち時間
+const MAX_BATCH_DELAY: Duration = Duration::from_secs(3);
+
+/// まとめられた変更のバッチ
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct WatchBatch {
+    /// 追加されたパス
+    pub added: Vec<String>,
+    /// 削除されたパス
+    pub removed: Vec<String>,
+    /// 変更されたパス
+    pub modified: Vec<String>,
+}
+
+impl WatchBatch {
+    /// 変更がないかどうか
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// 設定ごとの監視状態
+struct ConfigWatch {
+    /// ファイルシステム監視（破棄すると通知スレッドも終了する）
+    _watcher: RecommendedWatcher,
+}
+
+/// リソース設定の監視を管理する
+#[derive(Default)]
+pub struct ResourceWatcher {
+    /// 設定ID -> 監視状態
+    watches: Mutex<HashMap<String, ConfigWatch>>,
+}
+
+impl std::fmt::Debug for ResourceWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let count = self.watches.lock().map(|w| w.len()).unwrap_or(0);
+        f.debug_struct("ResourceWatcher")
+            .field("watched_configs", &count)
+            .finish()
+    }
+}
+
+impl ResourceWatcher {
+    /// 新しいResourceWatcherインスタンスを作成
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 設定の起点パスの監視を開始（既に監視中の場合は置き換える）
+    ///
+    /// 短時間に続く変更はまとめられ、`on_batch` は専用スレッドから呼び出される。
+    pub fn watch<F>(&self, config_id: &str, roots: &[PathBuf], on_batch: F) -> Result<(), String>
+    where
+        F: Fn(WatchBatch) + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)
+            .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+
+        let mut watched = 0;
+        for root in roots {
+            let mode = if root.is_dir() { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+            match watcher.watch(root, mode) {
+                Ok(()) => watched += 1,
+                Err(e) => log::warn!("Failed to watch {}: {}", root.display(), e),
+            }
+        }
+        if watched == 0 {
+            return Err(format!("No watchable paths for config: {}", config_id));
+        }
+
+        let thread_name = format!("resource-watch-{}", config_id);
+        std::thread::Builder::new()
+            .name(thread_name)
+            .spawn(move || debounce_events(receiver, on_batch))
+            .map_err(|e| format!("Failed to start watcher thread: {}", e))?;
+
+        let mut watches = self.watches.lock().map_err(|e| {
+            format!("Failed to lock watches: {}", e)
+        })?;
+        watches.insert(config_id.to_string(), ConfigWatch { _watcher: watcher });
+        Ok(())
+    }
+
+    /// 監視を停止（監視していなかった場合は false）
+    pub fn unwatch(&self, config_id: &str) -> bool {
+        match self.watches.lock() {
+            Ok(mut watches) => watches.remove(config_id).is_some(),
+            Err(_) => false,
+        }
+    }
+
+    /// 監視中かどうか
+    pub fn is_watching(&self, config_id: &str) -> bool {
+        self.watches.lock()
+            .map(|watches| watches.contains_key(config_id))
+            .unwrap_or(false)
+    }
+}
+
+/// 変更通知を受け取り、落ち着いたところでバッチとして通知する
+fn debounce_events<F>(receiver: mpsc::Receiver<notify::Result<Event>>, on_batch: F)
+where
+    F: Fn(WatchBatch),
+{
+    // パス -> バッチ内で新規作成されたかどうか
+    let mut pending: HashMap<PathBuf, bool> = HashMap::new();
+    let mut batch_started: Option<Instant> = None;
+
+    loop {
+        let timeout = match batch_started {
+            Some(started) => DEBOUNCE_DELAY.min(MAX_BATCH_DELAY.saturating_sub(started.elapsed())),
+            None => Duration::from_secs(3600),
+        };
+
+        match receiver.recv_timeout(timeout) {
+            Ok(Ok(event)) => {
+                if record_event(&mut pending, event) && batch_started.is_none() {
+                    batch_started = Some(Instant::now());
+                }
+                // 最大待ち時間を超えたら変更が続いていても確定する
+                if batch_started.is_none_or(|started| started.elapsed() < MAX_BATCH_DELAY) {
+                    continue;
+                }
+            },
+            Ok(Err(e)) => {
+                log::warn!("File watcher error: {}", e);
+                continue;
+            },
+            Err(RecvTimeoutError::Timeout) => {},
+            // 監視が破棄された
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+
+        if pending.is_empty() {
+            batch_started = None;
+            continue;
+        }
+
+        let batch = classify(pending.drain());
+        batch_started = None;
+        if !batch.is_empty() {
+            on_batch(batch);
+        }
+    }
+}
+
+/// 変更通知を記録（記録した場合は true）
+fn record_event(pending: &mut HashMap<PathBuf, bool>, event: Event) -> bool {
+    let created = match event.kind {
+        // 読み取りのみのアクセスは無視する
+        EventKind::Access(_) => return false,
+        EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => Some(true),
+        EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => Some(false),
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+            pending.insert(event.paths[0].clone(), false);
+            pending.insert(event.paths[1].clone(), true);
+            return true;
+        },
+        _ => None,
+    };
+
+    for path in event.paths {
+        let entry = pending.entry(path).or_insert(false);
+        if let Some(created) = created {
+            *entry = created;
+        }
+    }
+    true
+}
+
+/// 記録した変更を現在のファイルシステムの状態で分類
+fn classify(changes: impl Iterator<Item = (PathBuf, bool)>) -> WatchBatch {
+    let mut batch = WatchBatch::default();
+    for (path, created) in changes {
+        let path_str = path.to_string_lossy().to_string();
+        if !path.exists() {
+            batch.removed.push(path_str);
+        } else if created {
+            batch.added.push(path_str);
+        } else {
+            batch.modified.push(path_str);
+        }
+    }
+    batch.added.sort();
+    batch.removed.sort();
+    batch.modified.sort();
+    batch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, RemoveKind};
+
+    #[test]
+    fn test_record_and_classify() {
+        let dir = std::env::temp_dir().join(format!("image-viewer-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let created = dir.join("new.jpg");
+        let changed = dir.join("changed.jpg");
+        let deleted = dir.join("deleted.jpg");
+        std::fs::write(&created, [0u8]).unwrap();
+        std::fs::write(&changed, [0u8]).unwrap();
+
+        let mut pending = HashMap::new();
+        record_event(&mut pending, Event::new(EventKind::Create(CreateKind::File)).add_path(created.clone()));
+        // 作成直後の書き込みは追加のまま
+        record_event(&mut pending, Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content))).add_path(created.clone()));
+        record_event(&mut pending, Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content))).add_path(changed.clone()));
+        record_event(&mut pending, Event::new(EventKind::Remove(RemoveKind::File)).add_path(deleted.clone()));
+
+        let batch = classify(pending.into_iter());
+        assert_eq!(batch.added, vec![created.to_string_lossy().to_string()]);
+        assert_eq!(batch.modified, vec![changed.to_string_lossy().to_string()]);
+        assert_eq!(batch.removed, vec![deleted.to_string_lossy().to_string()]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
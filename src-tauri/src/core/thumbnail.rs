@@ -0,0 +1,488 @@
+// core/thumbnail.rs
+// サムネイル生成サービス - 縮小画像を生成してディスクにキャッシュする
+
+use std::fs;
+use std::io::{BufRead, BufWriter, Cursor, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ExtendedColorType, ImageReader};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::core::archive;
+use crate::core::color_palette::{ColorPalette, DEFAULT_PALETTE_SIZE};
+use crate::core::event_bus::EventBus;
+use crate::core::image_format::{self, ImageFormat};
+use crate::core::image_collection::ImageWarning;
+use crate::core::image_index::{FileStamp, ImageIndex};
+use crate::core::image_protocol;
+use crate::core::orientation::{self, Rotation};
+use crate::core::perceptual_hash::PerceptualHash;
+use crate::core::raw;
+use crate::utils::fnv1a_64;
+
+/// サムネイルサイズのバケット（AllViewerのサムネイルサイズ50〜300pxを覆う）
+pub const THUMBNAIL_BUCKETS: [u32; 5] = [64, 128, 192, 256, 320];
+
+/// JPEGサムネイルの品質
+const JPEG_QUALITY: u8 = 85;
+
+/// 一時ファイル名の衝突を避けるためのカウンター
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// サムネイルのエンコード形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailFormat {
+    #[default]
+    Jpeg,
+    Webp,
+}
+
+impl ThumbnailFormat {
+    /// 拡張子
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "jpg",
+            ThumbnailFormat::Webp => "webp",
+        }
+    }
+
+    /// 文字列から形式を解析
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "jpg" | "jpeg" => Some(ThumbnailFormat::Jpeg),
+            "webp" => Some(ThumbnailFormat::Webp),
+            _ => None,
+        }
+    }
+}
+
+/// 生成済みサムネイルの情報
+#[derive(Debug, Clone, Serialize)]
+pub struct ThumbnailInfo {
+    /// 元画像のパス
+    pub source_path: String,
+    /// キャッシュファイルのパス
+    pub cache_path: String,
+    /// サムネイルを配信するURL（imgview://）
+    pub url: String,
+    /// サムネイルの寸法（幅, 高さ）
+    pub dimensions: (u32, u32),
+    /// 使用したサイズバケット
+    pub bucket: u32,
+    /// キャッシュから取得したかどうか
+    pub cached: bool,
+}
+
+/// 知覚ハッシュと代表色を計算するサムネイルのサイズ（pHashの縮小画像より大きい最小のバケット）
+const HASH_THUMBNAIL_SIZE: u32 = 64;
+
+/// 要求サイズに対応するバケットを取得
+pub fn bucket_for_size(size: u32) -> u32 {
+    THUMBNAIL_BUCKETS
+        .iter()
+        .copied()
+        .find(|bucket| *bucket >= size)
+        .unwrap_or(THUMBNAIL_BUCKETS[THUMBNAIL_BUCKETS.len() - 1])
+}
+
+/// サムネイル生成サービス
+#[derive(Debug)]
+pub struct ThumbnailService {
+    /// キャッシュディレクトリ
+    cache_dir: PathBuf,
+    /// 進捗イベントの発行先
+    event_bus: Arc<EventBus>,
+    /// バッチIDの採番用カウンター
+    next_batch_id: AtomicU64,
+    /// ユーザーが指定した回転を参照するインデックス
+    index: Option<Arc<ImageIndex>>,
+}
+
+impl ThumbnailService {
+    /// 新しいThumbnailServiceインスタンスを作成
+    pub fn new(cache_dir: PathBuf, event_bus: Arc<EventBus>) -> Self {
+        Self {
+            cache_dir,
+            event_bus,
+            next_batch_id: AtomicU64::new(1),
+            index: None,
+        }
+    }
+
+    /// 画像インデックスを設定（ユーザーが指定した回転をサムネイルに適用する）
+    pub fn with_index(mut self, index: Arc<ImageIndex>) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// サムネイルを取得（キャッシュになければ生成）
+    pub fn get_or_create(&self, path: &str, size: u32, format: ThumbnailFormat) -> Result<ThumbnailInfo, String> {
+        let bucket = bucket_for_size(size);
+        let rotation = self.index.as_ref().map_or(Rotation::None, |index| index.rotation(path));
+        let cache_path = self.cache_path(path, bucket, format, rotation)?;
+
+        if cache_path.is_file() {
+            if let Ok(dimensions) = crate::core::image_probe::probe_dimensions(&cache_path) {
+                return Ok(ThumbnailInfo {
+                    source_path: path.to_string(),
+                    cache_path: cache_path.to_string_lossy().to_string(),
+                    url: image_protocol::thumbnail_url(path, bucket, format),
+                    dimensions,
+                    bucket,
+                    cached: true,
+                });
+            }
+            // 読めないキャッシュは作り直す
+            log::warn!("Regenerating unreadable thumbnail cache: {}", cache_path.display());
+        }
+
+        let image = decode_oriented(Path::new(path), rotation)?;
+        let thumbnail = resize_to_bucket(&image, bucket);
+        let dimensions = (thumbnail.width(), thumbnail.height());
+
+        fs::create_dir_all(&self.cache_dir)
+            .map_err(|e| format!("Failed to create thumbnail cache directory: {}", e))?;
+        write_thumbnail(&thumbnail, &cache_path, format)?;
+
+        Ok(ThumbnailInfo {
+            source_path: path.to_string(),
+            cache_path: cache_path.to_string_lossy().to_string(),
+            url: image_protocol::thumbnail_url(path, bucket, format),
+            dimensions,
+            bucket,
+            cached: false,
+        })
+    }
+
+    /// 複数画像のサムネイルをバックグラウンドで生成し、進捗をイベントバスに発行
+    pub fn generate_batch(self: &Arc<Self>, paths: Vec<String>, size: u32, format: ThumbnailFormat) -> u64 {
+        let batch_id = self.next_batch_id.fetch_add(1, Ordering::SeqCst);
+        let service = Arc::clone(self);
+
+        std::thread::spawn(move || {
+            let total = paths.len();
+            let mut failed = 0;
+
+            for (index, path) in paths.iter().enumerate() {
+                if let Err(e) = service.get_or_create(path, size, format) {
+                    log::warn!("Failed to generate thumbnail for {}: {}", path, e);
+                    failed += 1;
+                }
+
+                let _ = service.event_bus.publish("thumbnail:batch_progress", json!({
+                    "batch_id": batch_id,
+                    "completed": index + 1,
+                    "failed": failed,
+                    "total": total,
+                    "path": path,
+                }));
+            }
+
+            let _ = service.event_bus.publish("thumbnail:batch_completed", json!({
+                "batch_id": batch_id,
+                "total": total,
+                "failed": failed,
+            }));
+        });
+
+        batch_id
+    }
+
+    /// 画像の知覚ハッシュを取得（計算済みであればインデックスの記録を使う）
+    ///
+    /// 回転を適用した最小サイズのサムネイルから計算するため、サムネイルのキャッシュも再利用される。
+    pub fn perceptual_hash(&self, path: &str) -> Result<PerceptualHash, String> {
+        let indexed = self.index.as_ref().zip(FileStamp::read(Path::new(path)));
+        if let Some(hash) = indexed.as_ref().and_then(|(index, stamp)| index.lookup(path, stamp)?.perceptual_hash) {
+            return Ok(hash);
+        }
+
+        let info = self.get_or_create(path, HASH_THUMBNAIL_SIZE, ThumbnailFormat::Jpeg)?;
+        let reader = ImageReader::open(&info.cache_path)
+            .map_err(|e| format!("Failed to open thumbnail {}: {}", info.cache_path, e))?;
+        let hash = PerceptualHash::compute(&decode_reader(reader)?);
+        if let Some((index, stamp)) = &indexed {
+            index.set_perceptual_hash(path, stamp, hash);
+        }
+        Ok(hash)
+    }
+
+    /// 複数画像の知覚ハッシュを取得し、計算状況をイベントバスに発行
+    ///
+    /// `job` は進捗イベントに含める識別子（コレクションIDなど）。
+    pub fn perceptual_hashes(&self, job: &str, paths: &[String]) -> (Vec<(String, PerceptualHash)>, Vec<ImageWarning>) {
+        let mut hashes = Vec::with_capacity(paths.len());
+        let mut warnings = Vec::new();
+        for (index, path) in paths.iter().enumerate() {
+            match self.perceptual_hash(path) {
+                Ok(hash) => hashes.push((path.clone(), hash)),
+                Err(message) => warnings.push(ImageWarning { path: path.clone(), message }),
+            }
+            let _ = self.event_bus.publish("similarity:progress", json!({
+                "job": job,
+                "completed": index + 1,
+                "failed": warnings.len(),
+                "total": paths.len(),
+                "path": path,
+            }));
+        }
+        (hashes, warnings)
+    }
+
+    /// 画像の代表色と平均輝度を取得（計算済みであればインデックスの記録を使う）
+    pub fn color_palette(&self, path: &str) -> Result<ColorPalette, String> {
+        let indexed = self.index.as_ref().zip(FileStamp::read(Path::new(path)));
+        if let Some(palette) = indexed.as_ref().and_then(|(index, stamp)| index.lookup(path, stamp)?.metadata.palette) {
+            return Ok(palette);
+        }
+
+        let info = self.get_or_create(path, HASH_THUMBNAIL_SIZE, ThumbnailFormat::Jpeg)?;
+        let reader = ImageReader::open(&info.cache_path)
+            .map_err(|e| format!("Failed to open thumbnail {}: {}", info.cache_path, e))?;
+        let palette = ColorPalette::extract(&decode_reader(reader)?, DEFAULT_PALETTE_SIZE);
+        if let Some((index, stamp)) = &indexed {
+            index.set_palette(path, stamp, palette.clone());
+        }
+        Ok(palette)
+    }
+
+    /// 複数画像の代表色を取得し、計算状況をイベントバスに発行
+    ///
+    /// `job` は進捗イベントに含める識別子（コレクションIDなど）。
+    pub fn color_palettes(&self, job: &str, paths: &[String]) -> (Vec<(String, ColorPalette)>, Vec<ImageWarning>) {
+        let mut palettes = Vec::with_capacity(paths.len());
+        let mut warnings = Vec::new();
+        for (index, path) in paths.iter().enumerate() {
+            match self.color_palette(path) {
+                Ok(palette) => palettes.push((path.clone(), palette)),
+                Err(message) => warnings.push(ImageWarning { path: path.clone(), message }),
+            }
+            let _ = self.event_bus.publish("colors:progress", json!({
+                "job": job,
+                "completed": index + 1,
+                "failed": warnings.len(),
+                "total": paths.len(),
+                "path": path,
+            }));
+        }
+        (palettes, warnings)
+    }
+
+    /// キャッシュを削除
+    pub fn clear_cache(&self) -> Result<(), String> {
+        if self.cache_dir.exists() {
+            fs::remove_dir_all(&self.cache_dir)
+                .map_err(|e| format!("Failed to clear thumbnail cache: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// パス・更新日時・ファイルサイズ・バケット・回転からキャッシュファイルのパスを決定
+    fn cache_path(&self, path: &str, bucket: u32, format: ThumbnailFormat, rotation: Rotation) -> Result<PathBuf, String> {
+        let key = format!("{}\0{}\0{}", source_key(path)?, bucket, rotation.degrees());
+        let file_name = format!("{:016x}_{}.{}", fnv1a_64(key.as_bytes()), bucket, format.extension());
+        Ok(self.cache_dir.join(file_name))
+    }
+}
+
+/// パス・更新日時・ファイルサイズからなるキャッシュキー（元画像が変われば別のキーになる）
+///
+/// アーカイブ内のエントリはアーカイブ自体の更新日時とサイズを使う。
+pub(crate) fn source_key(path: &str) -> Result<String, String> {
+    let metadata = fs::metadata(archive::physical_path(path))
+        .map_err(|e| format!("Failed to read metadata for {}: {}", path, e))?;
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_nanos())
+        .unwrap_or(0);
+    Ok(format!("{}\0{}\0{}", path, mtime, metadata.len()))
+}
+
+/// 画像ファイル（またはアーカイブ内のエントリ）をデコード
+///
+/// このビルドでデコードできない形式は、その旨のエラーを返す。
+/// RAWは埋め込みJPEGプレビューをデコードする。
+pub(crate) fn decode_image(path: &Path) -> Result<DynamicImage, String> {
+    if let Some(virtual_path) = path.to_str().filter(|p| archive::split_entry_path(p).is_some()) {
+        let bytes = archive::read_entry_path(virtual_path)
+            .map_err(|e| format!("Failed to open image: {}", e))?;
+        let entry_name = virtual_path.rsplit('/').next().unwrap_or(virtual_path);
+        let format = ImageFormat::detect_with_name(&bytes, entry_name);
+        ensure_decodable(format)?;
+        if format == Some(ImageFormat::Raw) {
+            return decode_raw_preview(virtual_path);
+        }
+        return decode_reader(ImageReader::new(Cursor::new(bytes)));
+    }
+    let format = image_format::sniff_file(path).ok().flatten();
+    ensure_decodable(format)?;
+    if format == Some(ImageFormat::Raw) {
+        return decode_raw_preview(&path.to_string_lossy());
+    }
+    decode_reader(ImageReader::open(path).map_err(|e| format!("Failed to open image: {}", e))?)
+}
+
+/// 画像をデコードし、EXIFの向きとユーザーが指定した回転を適用
+pub(crate) fn decode_oriented(path: &Path, rotation: Rotation) -> Result<DynamicImage, String> {
+    let image = decode_image(path)?;
+    let exif_orientation = path.to_str().and_then(orientation::read_exif_orientation);
+    Ok(orientation::normalize(image, exif_orientation, rotation))
+}
+
+/// RAWの埋め込みプレビューをデコード
+fn decode_raw_preview(path: &str) -> Result<DynamicImage, String> {
+    let preview = raw::read_preview_path(path).map_err(|e| format!("Failed to read RAW preview: {}", e))?;
+    decode_reader(ImageReader::new(Cursor::new(preview)))
+}
+
+/// デコードできない形式であればエラー（形式が判定できない場合はデコーダーに任せる）
+fn ensure_decodable(format: Option<ImageFormat>) -> Result<(), String> {
+    match format {
+        Some(format) if !format.is_decodable() => Err(format.unsupported_message()),
+        _ => Ok(()),
+    }
+}
+
+/// 形式を判定してデコード
+fn decode_reader<R: BufRead + Seek>(reader: ImageReader<R>) -> Result<DynamicImage, String> {
+    reader
+        .with_guessed_format()
+        .map_err(|e| format!("Failed to detect image format: {}", e))?
+        .decode()
+        .map_err(|e| format!("Failed to decode image: {}", e))
+}
+
+/// バケットの正方形に収まるよう縦横比を保って縮小（拡大はしない）
+fn resize_to_bucket(image: &DynamicImage, bucket: u32) -> DynamicImage {
+    if image.width() <= bucket && image.height() <= bucket {
+        return image.clone();
+    }
+    image.resize(bucket, bucket, FilterType::Triangle)
+}
+
+/// サムネイルをエンコードしてファイルに書き込む（一時ファイル経由で置き換え）
+fn write_thumbnail(image: &DynamicImage, cache_path: &Path, format: ThumbnailFormat) -> Result<(), String> {
+    // 同じサムネイルを同時に生成しても衝突しないよう一時ファイル名を分ける
+    let temp_id = TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let temp_path = cache_path.with_extension(format!("{}.tmp", temp_id));
+    let file = fs::File::create(&temp_path)
+        .map_err(|e| format!("Failed to create thumbnail file: {}", e))?;
+    let mut writer = BufWriter::new(file);
+
+    let result = match format {
+        ThumbnailFormat::Jpeg => {
+            // JPEGはアルファチャンネルを持たないためRGBに変換
+            let rgb = image.to_rgb8();
+            JpegEncoder::new_with_quality(&mut writer, JPEG_QUALITY).encode(
+                rgb.as_raw(),
+                rgb.width(),
+                rgb.height(),
+                ExtendedColorType::Rgb8,
+            )
+        },
+        ThumbnailFormat::Webp => {
+            let rgba = image.to_rgba8();
+            WebPEncoder::new_lossless(&mut writer).encode(
+                rgba.as_raw(),
+                rgba.width(),
+                rgba.height(),
+                ExtendedColorType::Rgba8,
+            )
+        },
+    };
+
+    let result = result
+        .map_err(|e| format!("Failed to encode thumbnail: {}", e))
+        .and_then(|_| writer.flush().map_err(|e| format!("Failed to write thumbnail: {}", e)))
+        .and_then(|_| fs::rename(&temp_path, cache_path).map_err(|e| format!("Failed to store thumbnail: {}", e)));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_bucket_for_size() {
+        assert_eq!(bucket_for_size(50), 64);
+        assert_eq!(bucket_for_size(150), 192);
+        assert_eq!(bucket_for_size(256), 256);
+        assert_eq!(bucket_for_size(1000), 320);
+    }
+
+    #[test]
+    fn test_get_or_create_uses_cache() {
+        let dir = std::env::temp_dir().join(format!("image-viewer-thumb-test-{}", std::process::id()));
+        let source = dir.join("source.png");
+        fs::create_dir_all(&dir).unwrap();
+        RgbImage::from_pixel(400, 200, Rgb([200, 30, 30])).save(&source).unwrap();
+
+        let service = ThumbnailService::new(dir.join("cache"), Arc::new(EventBus::new()));
+        let path = source.to_str().unwrap();
+
+        let first = service.get_or_create(path, 150, ThumbnailFormat::Jpeg).unwrap();
+        assert!(!first.cached);
+        assert_eq!(first.bucket, 192);
+        assert_eq!(first.dimensions, (192, 96));
+
+        let second = service.get_or_create(path, 150, ThumbnailFormat::Jpeg).unwrap();
+        assert!(second.cached);
+        assert_eq!(second.cache_path, first.cache_path);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rotation_override_is_applied() {
+        let dir = std::env::temp_dir().join(format!("image-viewer-thumb-rotation-{}", std::process::id()));
+        let source = dir.join("landscape.png");
+        fs::create_dir_all(&dir).unwrap();
+        RgbImage::from_pixel(400, 200, Rgb([30, 30, 200])).save(&source).unwrap();
+        let path = source.to_str().unwrap();
+
+        let index = Arc::new(ImageIndex::open(dir.join("index.json")));
+        let service = ThumbnailService::new(dir.join("cache"), Arc::new(EventBus::new()))
+            .with_index(Arc::clone(&index));
+        let upright = service.get_or_create(path, 150, ThumbnailFormat::Jpeg).unwrap();
+        assert_eq!(upright.dimensions, (192, 96));
+
+        // 知覚ハッシュはインデックスに記録する
+        let (metadata, _) = crate::core::resource_manager::read_image_metadata(path).unwrap();
+        index.upsert(crate::core::image_index::IndexedImage {
+            stamp: crate::core::image_index::FileStamp::read(&source).unwrap(),
+            metadata,
+            warning: None,
+            extended: None,
+            content_hash: None,
+            perceptual_hash: None,
+        });
+        let stamp = crate::core::image_index::FileStamp::read(&source).unwrap();
+        let hash = service.perceptual_hash(path).unwrap();
+        assert_eq!(index.lookup(path, &stamp).unwrap().perceptual_hash, Some(hash));
+
+        // 回転を変えると知覚ハッシュは計算し直す
+        index.set_rotation(path, Rotation::Clockwise90).unwrap();
+        assert_eq!(index.lookup(path, &stamp).unwrap().perceptual_hash, None);
+        // 回転を変えると別のキャッシュとして生成し直す
+        let rotated = service.get_or_create(path, 150, ThumbnailFormat::Jpeg).unwrap();
+        assert!(!rotated.cached);
+        assert_eq!(rotated.dimensions, (96, 192));
+        assert_ne!(rotated.cache_path, upright.cache_path);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
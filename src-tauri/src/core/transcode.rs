@@ -0,0 +1,211 @@
+// core/transcode.rs
+// 形式変換サービス - webviewが表示できない形式（TIFFなど）をPNG/JPEGに変換してディスクにキャッシュする
+
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::{DynamicImage, ExtendedColorType, ImageEncoder};
+use thiserror::Error;
+
+use crate::core::archive;
+use crate::core::image_format::{self, ImageFormat};
+use crate::core::image_index::ImageIndex;
+use crate::core::orientation::{self, Rotation};
+use crate::core::raw;
+use crate::core::thumbnail;
+use crate::utils::fnv1a_64;
+
+/// 変換後のJPEGの品質（表示用のため縮小版より高くする）
+const JPEG_QUALITY: u8 = 90;
+
+/// 一時ファイル名の衝突を避けるためのカウンター
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// 形式変換のエラー型
+#[derive(Error, Debug)]
+pub enum TranscodeError {
+    #[error("{}", .0.unsupported_message())]
+    Unsupported(ImageFormat),
+
+    #[error("{0}")]
+    Failed(String),
+}
+
+/// 形式変換サービス
+#[derive(Debug)]
+pub struct TranscodeService {
+    /// キャッシュディレクトリ
+    cache_dir: PathBuf,
+    /// ユーザーが指定した回転を参照するインデックス
+    index: Option<Arc<ImageIndex>>,
+}
+
+impl TranscodeService {
+    /// 新しいTranscodeServiceインスタンスを作成
+    pub fn new(cache_dir: PathBuf) -> Self {
+        Self { cache_dir, index: None }
+    }
+
+    /// 画像インデックスを設定（ユーザーが指定した回転を変換結果に適用する）
+    pub fn with_index(mut self, index: Arc<ImageIndex>) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// 表示用のファイルを取得
+    ///
+    /// webviewがそのまま表示できる形式で回転の指定もなければ None を返し、元のファイルを配信させる
+    /// （EXIFの向きはwebviewが適用する）。それ以外は向きと回転を適用した変換済みファイル
+    /// （キャッシュになければ変換して作成）のパスを返す。
+    pub fn displayable_path(&self, path: &str) -> Result<Option<PathBuf>, TranscodeError> {
+        let rotation = self.index.as_ref().map_or(Rotation::None, |index| index.rotation(path));
+        match source_format(path) {
+            Some(format) if !format.is_web_displayable() => self.get_or_create(path, format, rotation).map(Some),
+            Some(format) if !rotation.is_none() && format.is_decodable() => {
+                self.get_or_create(path, format, rotation).map(Some)
+            },
+            // デコードできない形式は回転せずに配信する
+            _ => Ok(None),
+        }
+    }
+
+    /// 変換済みファイルを取得（キャッシュになければ変換）
+    fn get_or_create(&self, path: &str, format: ImageFormat, rotation: Rotation) -> Result<PathBuf, TranscodeError> {
+        if !format.is_decodable() {
+            return Err(TranscodeError::Unsupported(format));
+        }
+
+        // 透過の有無で出力形式が変わるため、どちらのキャッシュも確認する
+        let base_name = self.cache_base_name(path, rotation).map_err(TranscodeError::Failed)?;
+        for extension in ["png", "jpg"] {
+            let cache_path = self.cache_dir.join(format!("{}.{}", base_name, extension));
+            if cache_path.is_file() {
+                return Ok(cache_path);
+            }
+        }
+
+        fs::create_dir_all(&self.cache_dir)
+            .map_err(|e| TranscodeError::Failed(format!("Failed to create transcode cache directory: {}", e)))?;
+
+        // RAWの埋め込みプレビューは既にJPEGのため、向きを変える必要がなければ再エンコードせずに保存する
+        let upright = || rotation.is_none() && orientation::read_exif_orientation(path).is_none_or(|value| value == 1);
+        if format == ImageFormat::Raw && upright() {
+            let preview = raw::read_preview_path(path).map_err(|e| TranscodeError::Failed(e.to_string()))?;
+            let cache_path = self.cache_dir.join(format!("{}.jpg", base_name));
+            write_atomically(&cache_path, |writer| writer.write_all(&preview).map_err(|e| e.to_string()))
+                .map_err(TranscodeError::Failed)?;
+            return Ok(cache_path);
+        }
+
+        let image = thumbnail::decode_oriented(Path::new(path), rotation).map_err(TranscodeError::Failed)?;
+        let extension = if image.color().has_alpha() { "png" } else { "jpg" };
+        let cache_path = self.cache_dir.join(format!("{}.{}", base_name, extension));
+        write_atomically(&cache_path, |writer| encode_image(&image, writer)).map_err(TranscodeError::Failed)?;
+        Ok(cache_path)
+    }
+
+    /// キャッシュを削除
+    pub fn clear_cache(&self) -> Result<(), String> {
+        if self.cache_dir.exists() {
+            fs::remove_dir_all(&self.cache_dir)
+                .map_err(|e| format!("Failed to clear transcode cache: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// キャッシュファイル名（拡張子なし）を決定
+    fn cache_base_name(&self, path: &str, rotation: Rotation) -> Result<String, String> {
+        let key = format!("{}\0{}", thumbnail::source_key(path)?, rotation.degrees());
+        Ok(format!("{:016x}", fnv1a_64(key.as_bytes())))
+    }
+}
+
+/// 元画像の形式を判定（内容から判定できなければ拡張子から判定）
+pub fn source_format(path: &str) -> Option<ImageFormat> {
+    match archive::split_entry_path(path) {
+        Some((archive_path, entry)) => archive::probe_entry(Path::new(archive_path), entry)
+            .ok()
+            .and_then(|probe| probe.format)
+            .or_else(|| ImageFormat::from_file_name(entry)),
+        None => image_format::file_format(Path::new(path), true),
+    }
+}
+
+/// 画像をエンコード（透過があればPNG、なければJPEG）
+fn encode_image(image: &DynamicImage, writer: &mut BufWriter<fs::File>) -> Result<(), String> {
+    let result = if image.color().has_alpha() {
+        let rgba = image.to_rgba8();
+        PngEncoder::new(writer).write_image(rgba.as_raw(), rgba.width(), rgba.height(), ExtendedColorType::Rgba8)
+    } else {
+        let rgb = image.to_rgb8();
+        JpegEncoder::new_with_quality(writer, JPEG_QUALITY).encode(
+            rgb.as_raw(),
+            rgb.width(),
+            rgb.height(),
+            ExtendedColorType::Rgb8,
+        )
+    };
+    result.map_err(|e| format!("Failed to encode transcoded image: {}", e))
+}
+
+/// キャッシュファイルを書き込む（一時ファイル経由で置き換え）
+fn write_atomically<F>(cache_path: &Path, write: F) -> Result<(), String>
+where
+    F: FnOnce(&mut BufWriter<fs::File>) -> Result<(), String>,
+{
+    let temp_id = TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let temp_path = cache_path.with_extension(format!("{}.tmp", temp_id));
+    let file = fs::File::create(&temp_path)
+        .map_err(|e| format!("Failed to create transcoded file: {}", e))?;
+    let mut writer = BufWriter::new(file);
+
+    let result = write(&mut writer)
+        .and_then(|_| writer.flush().map_err(|e| format!("Failed to write transcoded image: {}", e)))
+        .and_then(|_| fs::rename(&temp_path, cache_path).map_err(|e| format!("Failed to store transcoded image: {}", e)));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_transcodes_tiff_and_reports_undecodable() {
+        let dir = std::env::temp_dir().join(format!("image-viewer-transcode-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let tiff = dir.join("scan.tif");
+        RgbImage::from_pixel(40, 20, Rgb([10, 120, 200])).save(&tiff).unwrap();
+        let jpeg = dir.join("photo.jpg");
+        RgbImage::from_pixel(4, 4, Rgb([0, 0, 0])).save(&jpeg).unwrap();
+        let heic = dir.join("IMG_0001.heic");
+        fs::write(&heic, b"\0\0\0\x18ftypheic\0\0\0\0mif1heic").unwrap();
+
+        let service = TranscodeService::new(dir.join("cache"));
+
+        // TIFFは変換してキャッシュし、2回目は同じファイルを返す
+        let transcoded = service.displayable_path(tiff.to_str().unwrap()).unwrap().unwrap();
+        assert_eq!(transcoded.extension().unwrap(), "jpg");
+        assert_eq!(crate::core::image_probe::probe_dimensions(&transcoded).unwrap(), (40, 20));
+        assert_eq!(service.displayable_path(tiff.to_str().unwrap()).unwrap(), Some(transcoded));
+
+        // webviewが表示できる形式は変換しない
+        assert_eq!(service.displayable_path(jpeg.to_str().unwrap()).unwrap(), None);
+
+        // デコードできない形式はその旨を報告する
+        match service.displayable_path(heic.to_str().unwrap()) {
+            Err(TranscodeError::Unsupported(ImageFormat::Heic)) => {},
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
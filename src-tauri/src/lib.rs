@@ -1,28 +1,36 @@
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use std::sync::Arc;
-use base64::{Engine as _, engine::general_purpose};
 use serde::Serialize;
 use tauri::AppHandle;
+use tauri::Emitter;
 use tauri::Manager;
 
 // コアモジュールのエクスポート
 pub mod core;
 // プラグインシステムのエクスポート
 pub mod plugins;
-// ユーティリティ関数（将来的に実装予定）
-// pub mod utils;
+// ユーティリティ関数
+pub mod utils;
 
 // イベントバスとプラグインマネージャーのインスタンスを保持するグローバル状態
 struct AppState {
-    // event_bus: Arc<core::event_bus::EventBus>,
+    event_bus: Arc<core::event_bus::EventBus>,
     // plugin_manager: Arc<core::plugin_manager::PluginManager>,
     resource_manager: Arc<core::resource_manager::ResourceManager>,
+    resource_watcher: Arc<core::resource_watcher::ResourceWatcher>,
+    config_library: Arc<core::config_library::ConfigLibrary>,
+    collection_registry: Arc<core::collection_registry::CollectionRegistry>,
+    thumbnail_service: Arc<core::thumbnail::ThumbnailService>,
+    transcode_service: Arc<core::transcode::TranscodeService>,
+    place_index: Arc<core::geo::PlaceIndex>,
+    plugin_registry: Arc<plugins::PluginRegistry>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct ImageData {
-    base64: String,
+    url: String,
     file_name: String,
 }
 
@@ -32,17 +40,6 @@ pub struct DirectoryContent {
     current_index: usize,
 }
 
-// 対応している画像拡張子
-fn is_image_file(file_name: &str) -> bool {
-    let lower_case = file_name.to_lowercase();
-    lower_case.ends_with(".jpg") || 
-    lower_case.ends_with(".jpeg") || 
-    lower_case.ends_with(".png") || 
-    lower_case.ends_with(".gif") || 
-    lower_case.ends_with(".bmp") || 
-    lower_case.ends_with(".webp")
-}
-
 #[tauri::command]
 async fn load_image(path: String, _app: AppHandle) -> Result<ImageData, String> {
     let file_path = std::path::Path::new(&path);
@@ -53,20 +50,19 @@ async fn load_image(path: String, _app: AppHandle) -> Result<ImageData, String>
         .unwrap_or("unknown")
         .to_string();
 
-    match fs::read(file_path) {
-        Ok(bytes) => {
-            let base64 = general_purpose::STANDARD.encode(&bytes);
-            Ok(ImageData {
-                base64,
-                file_name,
-            })
-        }
-        Err(e) => Err(format!("Failed to read file: {}", e))
+    if !file_path.is_file() {
+        return Err(format!("Failed to read file: {}", path));
     }
+
+    // 画像本体はカスタムURIスキーム経由で配信する
+    Ok(ImageData {
+        url: core::image_protocol::image_url(&path),
+        file_name,
+    })
 }
 
 #[tauri::command]
-async fn get_directory_images(dir_path: String) -> Result<DirectoryContent, String> {
+async fn get_directory_images(dir_path: String, app_handle: AppHandle) -> Result<DirectoryContent, String> {
     let path = Path::new(&dir_path);
     
     if !path.exists() {
@@ -95,16 +91,10 @@ async fn get_directory_images(dir_path: String) -> Result<DirectoryContent, Stri
         
         let file_path = entry.path();
         
-        // ファイルのみを対象とし、ディレクトリは無視
-        if file_path.is_file() {
-            if let Some(file_name) = file_path.file_name() {
-                if let Some(file_name_str) = file_name.to_str() {
-                    if is_image_file(file_name_str) {
-                        if let Some(path_str) = file_path.to_str() {
-                            image_paths.push(path_str.to_string());
-                        }
-                    }
-                }
+        // ファイルのみを対象とし、ディレクトリは無視（拡張子が画像形式でないファイルは内容で判定）
+        if file_path.is_file() && core::image_format::file_format(&file_path, false).is_some() {
+            if let Some(path_str) = file_path.to_str() {
+                image_paths.push(path_str.to_string());
             }
         }
     }
@@ -112,6 +102,10 @@ async fn get_directory_images(dir_path: String) -> Result<DirectoryContent, Stri
     // ファイル名でソート
     image_paths.sort();
     
+    // 一覧したディレクトリの画像をURIスキームで配信できるよう登録
+    let state = app_handle.state::<AppState>();
+    state.resource_manager.register_resolved_paths(&format!("directory:{}", dir_path), image_paths.clone());
+    
     Ok(DirectoryContent {
         images: image_paths,
         current_index: 0,
@@ -141,8 +135,19 @@ async fn resolve_resources(
 ) -> Result<core::resource_manager::PathResolutionResult, String> {
     let state = app_handle.state::<AppState>();
     let resource_manager = &state.resource_manager;
+    let config_id = config.id.clone();
+    let watch = config.watch;
     
-    resource_manager.internal_resolve_resources(config).await
+    let result = resource_manager.internal_resolve_resources(config).await?;
+    update_watch(&state, &config_id, watch);
+    Ok(result)
+}
+
+// 実行中のリソース解決をキャンセルするコマンド
+#[tauri::command]
+async fn cancel_resolution(config_id: String, app_handle: AppHandle) -> Result<bool, String> {
+    let state = app_handle.state::<AppState>();
+    Ok(state.resource_manager.cancel_resolution(&config_id))
 }
 
 // 画像コレクション読み込みコマンド
@@ -157,32 +162,704 @@ async fn load_images_from_paths(
     resource_manager.internal_load_images_from_paths(paths).await
 }
 
+// 設定IDに基づく画像コレクション読み込みコマンド
+#[tauri::command]
+async fn load_images_from_config(
+    config_id: String,
+    app_handle: AppHandle
+) -> Result<core::image_collection::ImageCollection, String> {
+    let state = app_handle.state::<AppState>();
+    state.resource_manager.internal_load_images_from_config(config_id).await
+}
+
+// 設定ファイル読み込みコマンド（古い形式は移行し、検証エラーは構造化して返す）
+#[tauri::command]
+async fn load_config(
+    path: String,
+    app_handle: AppHandle
+) -> Result<core::resource_manager::ResourceConfig, core::config_schema::ConfigError> {
+    let state = app_handle.state::<AppState>();
+    state.resource_manager.load_config(&path)
+}
+
+// 設定ファイル保存コマンド
+#[tauri::command]
+async fn save_config(
+    config: core::resource_manager::ResourceConfig,
+    path: String,
+    app_handle: AppHandle
+) -> Result<(), core::config_schema::ConfigError> {
+    let state = app_handle.state::<AppState>();
+    state.resource_manager.save_config(&config, &path)
+}
+
+// 設定ライブラリの一覧取得コマンド
+#[tauri::command]
+async fn list_resource_configs(app_handle: AppHandle) -> Result<core::config_library::ConfigListing, String> {
+    let state = app_handle.state::<AppState>();
+    Ok(state.config_library.list())
+}
+
+// 設定ライブラリへの追加コマンド
+#[tauri::command]
+async fn create_resource_config(
+    config: core::resource_manager::ResourceConfig,
+    app_handle: AppHandle
+) -> Result<core::resource_manager::ResourceConfig, core::config_library::ConfigLibraryError> {
+    let state = app_handle.state::<AppState>();
+    state.config_library.create(config)
+}
+
+// 設定ライブラリの更新コマンド（解決済みの結果と監視は破棄する）
+#[tauri::command]
+async fn update_resource_config(
+    config: core::resource_manager::ResourceConfig,
+    app_handle: AppHandle
+) -> Result<core::resource_manager::ResourceConfig, core::config_library::ConfigLibraryError> {
+    let state = app_handle.state::<AppState>();
+    let updated = state.config_library.update(config)?;
+    state.resource_manager.clear_config_cache(&updated.id);
+    state.resource_watcher.unwatch(&updated.id);
+    Ok(updated)
+}
+
+// 設定ライブラリの複製コマンド
+#[tauri::command]
+async fn duplicate_resource_config(
+    config_id: String,
+    name: Option<String>,
+    app_handle: AppHandle
+) -> Result<core::resource_manager::ResourceConfig, core::config_library::ConfigLibraryError> {
+    let state = app_handle.state::<AppState>();
+    state.config_library.duplicate(&config_id, name)
+}
+
+// 設定ライブラリからの削除コマンド
+#[tauri::command]
+async fn delete_resource_config(config_id: String, app_handle: AppHandle) -> Result<(), core::config_library::ConfigLibraryError> {
+    let state = app_handle.state::<AppState>();
+    state.config_library.delete(&config_id)?;
+    state.resource_manager.clear_config_cache(&config_id);
+    state.resource_watcher.unwatch(&config_id);
+    Ok(())
+}
+
+// 既定の設定の変更コマンド
+#[tauri::command]
+async fn set_default_resource_config(config_id: String, app_handle: AppHandle) -> Result<(), core::config_library::ConfigLibraryError> {
+    let state = app_handle.state::<AppState>();
+    state.config_library.set_default(&config_id)
+}
+
+// プラグイン設定をバージョン付きエンベロープとして書き出すコマンド
+#[tauri::command]
+async fn export_plugin_config(plugin_id: String, app_handle: AppHandle) -> Result<core::config_schema::ConfigEnvelope, String> {
+    let state = app_handle.state::<AppState>();
+    state.plugin_registry.export_plugin_config(&plugin_id).map_err(|e| e.to_string())
+}
+
+// プラグイン設定の読み込みコマンド（古いバージョンは移行して適用し、現在のバージョンで保存する）
+#[tauri::command]
+async fn import_plugin_config(plugin_id: String, config: serde_json::Value, app_handle: AppHandle) -> Result<(), String> {
+    let state = app_handle.state::<AppState>();
+    state.plugin_registry.import_plugin_config(&plugin_id, config)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+// コレクション作成コマンド（パスリストから読み込んでレジストリに登録）
+#[tauri::command]
+async fn create_collection(
+    paths: Vec<String>,
+    app_handle: AppHandle
+) -> Result<core::collection_registry::CollectionSummary, String> {
+    let state = app_handle.state::<AppState>();
+    let collection = state.resource_manager.internal_load_images_from_paths(paths).await?;
+    let loaded_paths: Vec<String> = collection.get_all_metadata()
+        .into_iter()
+        .map(|meta| meta.path)
+        .collect();
+    
+    let summary = state.collection_registry.register(collection)?;
+    // 読み込んだ画像をURIスキームで配信できるよう登録
+    state.resource_manager.register_resolved_paths(&format!("collection:{}", summary.id), loaded_paths);
+    Ok(summary)
+}
+
+// コレクション作成コマンド（リソース設定を解決して読み込む）
+#[tauri::command]
+async fn create_collection_from_config(
+    config: core::resource_manager::ResourceConfig,
+    app_handle: AppHandle
+) -> Result<core::collection_registry::CollectionSummary, String> {
+    let state = app_handle.state::<AppState>();
+    let config_id = config.id.clone();
+    let watch = config.watch;
+    let resolution = state.resource_manager.internal_resolve_resources(config).await?;
+    let collection = state.resource_manager.internal_load_images_from_paths(resolution.paths).await?;
+    
+    let summary = state.collection_registry.register_with_source(collection, &config_id)?;
+    update_watch(&state, &config_id, watch);
+    Ok(summary)
+}
+
+// コレクション一覧取得コマンド
+#[tauri::command]
+async fn list_collections(app_handle: AppHandle) -> Result<Vec<core::collection_registry::CollectionSummary>, String> {
+    let state = app_handle.state::<AppState>();
+    state.collection_registry.list()
+}
+
+// コレクションのメタデータをページ単位で取得するコマンド
+#[tauri::command]
+async fn get_collection_page(
+    collection_id: String,
+    offset: usize,
+    limit: usize,
+    app_handle: AppHandle
+) -> Result<core::collection_registry::CollectionPage, String> {
+    let state = app_handle.state::<AppState>();
+    state.collection_registry.get_page(&collection_id, offset, limit)
+}
+
+// コレクション内の画像をインデックスで読み込むコマンド
+#[tauri::command]
+async fn load_image_at(
+    collection_id: String,
+    index: usize,
+    app_handle: AppHandle
+) -> Result<core::image_collection::ImageData, String> {
+    let state = app_handle.state::<AppState>();
+    state.collection_registry.load_image_at(&collection_id, index)
+}
+
+// コレクションからランダムな画像を取得するコマンド
+#[tauri::command]
+async fn get_random_images(
+    collection_id: String,
+    count: usize,
+    app_handle: AppHandle
+) -> Result<Vec<core::image_collection::ImageData>, String> {
+    let state = app_handle.state::<AppState>();
+    state.collection_registry.get_random_images(&collection_id, count)
+}
+
+// フィルタ・ソート（とRAW+JPEGのまとめ）を適用した子コレクションを作成するコマンド
+#[tauri::command]
+async fn derive_collection(
+    collection_id: String,
+    filter: Option<core::image_collection::ImageFilter>,
+    sort: Option<core::image_collection::ImageSort>,
+    group_raw_pairs: Option<bool>,
+    app_handle: AppHandle
+) -> Result<core::collection_registry::CollectionSummary, String> {
+    let state = app_handle.state::<AppState>();
+    state.collection_registry.derive(&collection_id, filter.as_ref(), sort.as_ref(), group_raw_pairs.unwrap_or(false))
+}
+
+// コレクション破棄コマンド
+#[tauri::command]
+async fn drop_collection(collection_id: String, app_handle: AppHandle) -> Result<(), String> {
+    let state = app_handle.state::<AppState>();
+    state.collection_registry.drop_collection(&collection_id)?;
+    state.resource_manager.clear_config_cache(&format!("collection:{}", collection_id));
+    Ok(())
+}
+
+// サムネイル取得コマンド（キャッシュになければ生成）
+#[tauri::command]
+async fn get_thumbnail(
+    path: String,
+    size: u32,
+    format: Option<core::thumbnail::ThumbnailFormat>,
+    app_handle: AppHandle
+) -> Result<core::thumbnail::ThumbnailInfo, String> {
+    let state = app_handle.state::<AppState>();
+    if !state.resource_manager.is_resolved_path(&path) {
+        return Err(format!("Path is not part of a resolved resource: {}", path));
+    }
+    
+    // デコードと縮小はブロッキング処理のため別スレッドで実行
+    let thumbnail_service = Arc::clone(&state.thumbnail_service);
+    tauri::async_runtime::spawn_blocking(move || {
+        thumbnail_service.get_or_create(&path, size, format.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| format!("Thumbnail task failed: {}", e))?
+}
+
+// サムネイル一括生成コマンド（進捗は thumbnail:batch_progress イベントで通知）
+#[tauri::command]
+async fn generate_thumbnails(
+    paths: Vec<String>,
+    size: u32,
+    format: Option<core::thumbnail::ThumbnailFormat>,
+    app_handle: AppHandle
+) -> Result<u64, String> {
+    let state = app_handle.state::<AppState>();
+    let paths: Vec<String> = paths.into_iter()
+        .filter(|path| state.resource_manager.is_resolved_path(path))
+        .collect();
+    
+    Ok(state.thumbnail_service.generate_batch(paths, size, format.unwrap_or_default()))
+}
+
+// サムネイルキャッシュ削除コマンド
+#[tauri::command]
+async fn clear_thumbnail_cache(app_handle: AppHandle) -> Result<(), String> {
+    let state = app_handle.state::<AppState>();
+    state.thumbnail_service.clear_cache()?;
+    state.transcode_service.clear_cache()
+}
+
+// 画像の拡張メタデータ（EXIF/IPTC/XMP）を取得するコマンド
+#[tauri::command]
+async fn get_image_metadata(
+    path: String,
+    app_handle: AppHandle
+) -> Result<core::extended_metadata::ExtendedMetadata, String> {
+    let state = app_handle.state::<AppState>();
+    if !state.resource_manager.is_resolved_path(&path) {
+        return Err(format!("Path is not part of a resolved resource: {}", path));
+    }
+    
+    // ファイルの読み込みと解析はブロッキング処理のため別スレッドで実行
+    let resource_manager = Arc::clone(&state.resource_manager);
+    tauri::async_runtime::spawn_blocking(move || {
+        resource_manager.load_extended_metadata(&path)
+    })
+    .await
+    .map_err(|e| format!("Metadata task failed: {}", e))?
+}
+
+// 画像の回転（時計回りの角度: 0, 90, 180, 270）を設定するコマンド
+//
+// 元のファイルは変更せず、インデックスに保存してサムネイルと表示用の変換に適用する。
+#[tauri::command]
+async fn set_image_rotation(
+    path: String,
+    rotation: core::orientation::Rotation,
+    app_handle: AppHandle
+) -> Result<core::ImageMetadata, String> {
+    let state = app_handle.state::<AppState>();
+    if !state.resource_manager.is_resolved_path(&path) {
+        return Err(format!("Path is not part of a resolved resource: {}", path));
+    }
+    
+    let resource_manager = Arc::clone(&state.resource_manager);
+    tauri::async_runtime::spawn_blocking(move || {
+        resource_manager.set_image_rotation(&path, rotation)
+    })
+    .await
+    .map_err(|e| format!("Rotation task failed: {}", e))?
+}
+
+// コレクションの画像を撮影位置ごとにまとめるコマンド（地図表示用）
+#[tauri::command]
+async fn get_location_clusters(
+    collection_id: String,
+    cell_km: Option<f64>,
+    app_handle: AppHandle
+) -> Result<Vec<core::geo::LocationCluster>, String> {
+    let state = app_handle.state::<AppState>();
+    state.collection_registry.location_clusters(
+        &collection_id,
+        cell_km.unwrap_or(core::geo::DEFAULT_CLUSTER_CELL_KM),
+        &state.place_index,
+    )
+}
+
+// 緯度経度から最寄りの地名を取得するコマンド（同梱の地名データを使い、ネットワークには接続しない）
+#[tauri::command]
+async fn reverse_geocode(
+    latitude: f64,
+    longitude: f64,
+    app_handle: AppHandle
+) -> Option<core::geo::PlaceMatch> {
+    let state = app_handle.state::<AppState>();
+    state.place_index.nearest(latitude, longitude)
+}
+
+// コレクション内の内容が同一の画像を探すコマンド（ハッシュの計算状況は duplicates:progress で通知する）
+#[tauri::command]
+async fn find_duplicates(
+    collection_id: String,
+    app_handle: AppHandle
+) -> Result<core::duplicates::DuplicateReport, String> {
+    let collection = app_handle.state::<AppState>().collection_registry.get(&collection_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        state.resource_manager.find_duplicates(&collection_id, &collection.get_all_metadata())
+    })
+    .await
+    .map_err(|e| format!("Duplicate detection task failed: {}", e))
+}
+
+// 重複画像を1つだけ残して削除・移動するコマンド（dry_run では予定する操作だけを返す）
+#[tauri::command]
+async fn keep_one_duplicate(
+    collection_id: String,
+    hashes: Option<Vec<String>>,
+    strategy: Option<core::file_ops::KeepStrategy>,
+    disposal: core::file_ops::Disposal,
+    dry_run: bool,
+    app_handle: AppHandle
+) -> Result<core::file_ops::FileOpsReport, String> {
+    let collection = app_handle.state::<AppState>().collection_registry.get(&collection_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        // ハッシュはキャッシュされているため、検出し直して現在の状態で判断する
+        let mut groups = state.resource_manager.find_duplicates(&collection_id, &collection.get_all_metadata()).groups;
+        if let Some(hashes) = &hashes {
+            groups.retain(|group| hashes.contains(&group.hash));
+        }
+        let report = core::file_ops::keep_one(&groups, strategy.unwrap_or_default(), &disposal, dry_run);
+
+        let removed = report.removed_paths();
+        if !removed.is_empty() {
+            if let Err(e) = state.collection_registry.remove_paths(&removed) {
+                log::warn!("Failed to update collections after removing duplicates: {}", e);
+            }
+            state.resource_manager.forget_paths(&removed);
+            let _ = state.event_bus.publish("resource:removed", serde_json::json!({
+                "collection_id": collection_id,
+                "paths": removed,
+            }));
+        }
+        report
+    })
+    .await
+    .map_err(|e| format!("Duplicate removal task failed: {}", e))
+}
+
+/// コレクションの画像の知覚ハッシュを取得（RAW+JPEGのペアは代表の画像のみ）
+fn collection_perceptual_hashes(state: &AppState, collection_id: &str) -> Result<Vec<(String, core::perceptual_hash::PerceptualHash)>, String> {
+    let paths: Vec<String> = state.collection_registry.get(collection_id)?
+        .get_all_metadata()
+        .into_iter()
+        .map(|metadata| metadata.path)
+        .collect();
+    let (hashes, warnings) = state.thumbnail_service.perceptual_hashes(collection_id, &paths);
+    state.resource_manager.save_index();
+    for warning in warnings {
+        log::warn!("Failed to compute perceptual hash of {}: {}", warning.path, warning.message);
+    }
+    Ok(hashes)
+}
+
+// 見た目が似た画像を距離の近い順に探すコマンド
+// （collection_id を省略した場合は知覚ハッシュを計算済みの画像から探す）
+#[tauri::command]
+async fn find_similar(
+    path: String,
+    max_distance: Option<u32>,
+    collection_id: Option<String>,
+    kind: Option<core::perceptual_hash::HashKind>,
+    app_handle: AppHandle
+) -> Result<Vec<core::perceptual_hash::SimilarImage>, String> {
+    let state = app_handle.state::<AppState>();
+    if !state.resource_manager.is_resolved_path(&path) {
+        return Err(format!("Path is not part of a resolved resource: {}", path));
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        let target = state.thumbnail_service.perceptual_hash(&path)?;
+        let candidates = match &collection_id {
+            Some(collection_id) => collection_perceptual_hashes(&state, collection_id)?,
+            None => state.resource_manager.indexed_perceptual_hashes(),
+        };
+        state.resource_manager.save_index();
+        Ok(core::perceptual_hash::find_similar(
+            (&path, &target),
+            &candidates,
+            kind.unwrap_or_default(),
+            max_distance.unwrap_or(core::perceptual_hash::DEFAULT_SIMILAR_DISTANCE),
+        ))
+    })
+    .await
+    .map_err(|e| format!("Similar image search task failed: {}", e))?
+}
+
+// コレクション内のほぼ同一の画像（縮小・再圧縮・軽い編集を経たもの）をまとめるコマンド
+#[tauri::command]
+async fn group_near_duplicates(
+    collection_id: String,
+    threshold: Option<u32>,
+    kind: Option<core::perceptual_hash::HashKind>,
+    app_handle: AppHandle
+) -> Result<Vec<core::perceptual_hash::SimilarGroup>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        let candidates = collection_perceptual_hashes(&state, &collection_id)?;
+        Ok(core::perceptual_hash::group_near_duplicates(
+            &candidates,
+            kind.unwrap_or_default(),
+            threshold.unwrap_or(core::perceptual_hash::DEFAULT_NEAR_DUPLICATE_DISTANCE),
+        ))
+    })
+    .await
+    .map_err(|e| format!("Near-duplicate grouping task failed: {}", e))?
+}
+
+// コレクションの画像の代表色と平均輝度を求め、色による絞り込み・並べ替えに使えるようにするコマンド
+//
+// 結果はインデックスに保存し、その画像を含むすべてのコレクションのメタデータに反映する。
+#[tauri::command]
+async fn analyze_colors(
+    collection_id: String,
+    app_handle: AppHandle
+) -> Result<core::color_palette::ColorAnalysis, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        let metadata = state.collection_registry.get(&collection_id)?.get_all_metadata();
+        let paths: Vec<String> = metadata.iter().map(|metadata| metadata.path.clone()).collect();
+        let (palettes, warnings) = state.thumbnail_service.color_palettes(&collection_id, &paths);
+        state.resource_manager.save_index();
+
+        let mut palettes: HashMap<String, core::color_palette::ColorPalette> = palettes.into_iter().collect();
+        let analyzed = palettes.len();
+        let updates: Vec<core::ImageMetadata> = metadata.into_iter()
+            .filter_map(|metadata| {
+                let palette = palettes.remove(&metadata.path)?;
+                Some(core::ImageMetadata { palette: Some(palette), ..metadata })
+            })
+            .collect();
+        let updated_collections = state.collection_registry.update_metadata(&updates)?;
+        Ok(core::color_palette::ColorAnalysis { analyzed, warnings, updated_collections })
+    })
+    .await
+    .map_err(|e| format!("Color analysis task failed: {}", e))?
+}
+
+// 画像形式ごとの対応状況（デコード可否、webviewでの表示可否）を取得するコマンド
+#[tauri::command]
+async fn get_supported_formats() -> Vec<core::image_format::FormatSupport> {
+    core::image_format::supported_formats()
+}
+
+/// 設定の watch に応じてファイルシステムの監視を開始・停止する
+fn update_watch(state: &AppState, config_id: &str, watch: bool) {
+    if !watch {
+        state.resource_watcher.unwatch(config_id);
+        return;
+    }
+    
+    let roots = match state.resource_manager.watch_roots(config_id) {
+        Some(roots) => roots,
+        None => return,
+    };
+    let resource_manager = Arc::clone(&state.resource_manager);
+    let collection_registry = Arc::clone(&state.collection_registry);
+    let event_bus = Arc::clone(&state.event_bus);
+    let watched_id = config_id.to_string();
+    
+    let result = state.resource_watcher.watch(config_id, &roots, move |batch| {
+        let applied = resource_manager.apply_fs_changes(&watched_id, &batch);
+        if applied.is_empty() {
+            return;
+        }
+        
+        // 追加・変更された画像のメタデータを読み込み直してコレクションに反映
+        let mut upserts = Vec::new();
+        let mut warnings = Vec::new();
+        for path in applied.added.iter().chain(&applied.modified) {
+            if let Some((metadata, warning)) = resource_manager.load_image_metadata(path) {
+                upserts.push(metadata);
+                warnings.extend(warning);
+            }
+        }
+        resource_manager.save_index();
+        if let Err(e) = collection_registry.apply_changes(&watched_id, &upserts, &warnings, &applied.removed) {
+            log::warn!("Failed to update collections for {}: {}", watched_id, e);
+        }
+        
+        for (event_type, paths) in [
+            ("resource:added", &applied.added),
+            ("resource:removed", &applied.removed),
+            ("resource:modified", &applied.modified),
+        ] {
+            if paths.is_empty() {
+                continue;
+            }
+            let _ = event_bus.publish(event_type, serde_json::json!({
+                "config_id": watched_id,
+                "paths": paths,
+            }));
+        }
+    });
+    if let Err(e) = result {
+        log::warn!("Failed to watch resources for {}: {}", config_id, e);
+    }
+}
+
+/// イベントバスのイベントをフロントエンドへ転送する
+fn forward_events_to_frontend(app_handle: &AppHandle, event_bus: &core::event_bus::EventBus, event_types: &[&str]) {
+    for event_type in event_types {
+        let app_handle = app_handle.clone();
+        let result = event_bus.subscribe(event_type, move |payload| {
+            app_handle.emit(&payload.event_type, payload.data)
+                .map_err(|e| format!("Failed to emit event to frontend: {}", e))
+        });
+        if let Err(e) = result {
+            log::warn!("Failed to forward event {}: {}", event_type, e);
+        }
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     // イベントバスの作成
-    // let event_bus = Arc::new(core::event_bus::EventBus::new());
+    let event_bus = Arc::new(core::event_bus::EventBus::new());
     
     // プラグインマネージャーの作成
     // let plugin_manager = Arc::new(core::plugin_manager::PluginManager::new(Arc::clone(&event_bus)));
     
-    // リソースマネージャーの作成
-    let resource_manager = Arc::new(core::resource_manager::ResourceManager::new());
+    // コレクションレジストリの作成
+    let collection_registry = Arc::new(core::collection_registry::CollectionRegistry::new());
+    
+    // リソース監視の作成
+    let resource_watcher = Arc::new(core::resource_watcher::ResourceWatcher::new());
 
     tauri::Builder::default()
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_opener::init())
-        .manage(AppState {
-            // event_bus,
-            // plugin_manager,
-            resource_manager,
+        .setup(move |app| {
+            // 画像インデックスはアプリのデータディレクトリに保存する
+            let index_path = app.path().app_data_dir()?.join("image_index.json");
+            let image_index = Arc::new(core::image_index::ImageIndex::open(index_path));
+            // 頻繁に呼ばれる保存はまとめ、残った変更は一定間隔で保存する（終了時にも保存する）
+            core::image_index::ImageIndex::spawn_autosave(&image_index, core::image_index::SAVE_INTERVAL);
+            
+            // サムネイルキャッシュはアプリのキャッシュディレクトリ配下に置く（回転の指定はインデックスから参照する）
+            let cache_dir = app.path().app_cache_dir()?.join("thumbnails");
+            let thumbnail_service = Arc::new(
+                core::thumbnail::ThumbnailService::new(cache_dir, Arc::clone(&event_bus))
+                    .with_index(Arc::clone(&image_index))
+            );
+            // webviewが表示できない形式の変換結果も同じキャッシュディレクトリ配下に置く
+            let transcode_dir = app.path().app_cache_dir()?.join("transcoded");
+            let transcode_service = Arc::new(
+                core::transcode::TranscodeService::new(transcode_dir)
+                    .with_index(Arc::clone(&image_index))
+            );
+            
+            // 設定ライブラリはアプリの設定ディレクトリに保存する
+            let library_path = app.path().app_config_dir()?.join("resource_configs.json");
+            let config_library = Arc::new(core::config_library::ConfigLibrary::open(library_path));
+            
+            // 逆ジオコーディング用の地名データ（データディレクトリに places.csv があれば同梱データの代わりに使う）
+            let places_path = app.path().app_data_dir()?.join("places.csv");
+            let place_index = Arc::new(if places_path.is_file() {
+                core::geo::PlaceIndex::open(&places_path).unwrap_or_else(|e| {
+                    log::warn!("Falling back to bundled place data: {}", e);
+                    core::geo::PlaceIndex::bundled()
+                })
+            } else {
+                core::geo::PlaceIndex::bundled()
+            });
+            
+            // プラグインを登録し、設定ディレクトリに保存された設定を読み込む（古いバージョンは移行し、以降の更新もここに保存する）
+            let plugin_registry = plugins::initialize(Arc::clone(&event_bus))?;
+            let plugin_config_dir = app.path().app_config_dir()?.join("plugins");
+            for e in plugin_registry.load_plugin_configs(&plugin_config_dir) {
+                log::warn!("Failed to load plugin config: {}", e);
+            }
+            
+            // リソースマネージャーの作成
+            let resource_manager = Arc::new(
+                core::resource_manager::ResourceManager::with_event_bus(Arc::clone(&event_bus))
+                    .with_index(image_index)
+                    .with_config_library(Arc::clone(&config_library))
+            );
+            
+            forward_events_to_frontend(app.handle(), &event_bus, &[
+                "thumbnail:batch_progress",
+                "thumbnail:batch_completed",
+                "resource:scan_progress",
+                "resource:added",
+                "resource:removed",
+                "resource:modified",
+                "image:rotation_changed",
+                "duplicates:progress",
+                "duplicates:completed",
+                "similarity:progress",
+                "colors:progress",
+            ]);
+            
+            app.manage(AppState {
+                event_bus: Arc::clone(&event_bus),
+                // plugin_manager,
+                resource_manager,
+                resource_watcher,
+                config_library,
+                collection_registry,
+                thumbnail_service,
+                transcode_service,
+                place_index,
+                plugin_registry,
+            });
+            Ok(())
+        })
+        .register_asynchronous_uri_scheme_protocol(core::image_protocol::SCHEME, |ctx, request, responder| {
+            // ファイル読み込みはブロッキング処理のため別スレッドで実行
+            let app_handle = ctx.app_handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                let state = app_handle.state::<AppState>();
+                responder.respond(core::image_protocol::handle_request(
+                    &state.resource_manager,
+                    &state.thumbnail_service,
+                    &state.transcode_service,
+                    &request,
+                ));
+            });
         })
         .invoke_handler(tauri::generate_handler![
             load_image,
             get_directory_images,
             load_plugin,
             resolve_resources,
+            cancel_resolution,
             load_images_from_paths,
+            load_images_from_config,
+            load_config,
+            save_config,
+            list_resource_configs,
+            create_resource_config,
+            update_resource_config,
+            duplicate_resource_config,
+            delete_resource_config,
+            set_default_resource_config,
+            export_plugin_config,
+            import_plugin_config,
+            create_collection,
+            create_collection_from_config,
+            list_collections,
+            get_collection_page,
+            load_image_at,
+            get_random_images,
+            derive_collection,
+            drop_collection,
+            get_thumbnail,
+            generate_thumbnails,
+            clear_thumbnail_cache,
+            get_supported_formats,
+            get_image_metadata,
+            set_image_rotation,
+            get_location_clusters,
+            reverse_geocode,
+            find_duplicates,
+            keep_one_duplicate,
+            find_similar,
+            group_near_duplicates,
+            analyze_colors,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            if let tauri::RunEvent::Exit = event {
+                if let Some(state) = app_handle.try_state::<AppState>() {
+                    state.resource_manager.flush_index();
+                }
+            }
+        });
 }
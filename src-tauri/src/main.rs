@@ -5,7 +5,6 @@ use std::sync::Arc;
 // use image_viewer_lib::core::{self, event_bus::EventBus, plugin_manager::PluginManager};
 use image_viewer_lib::core::{self, event_bus::EventBus, plugin_manager::PluginManager};
 // use image_viewer_lib::core::{self, event_bus::EventBus};
-use image_viewer_lib::plugins;
 
 fn main() {
     // ロガーの初期化
@@ -23,16 +22,7 @@ fn main() {
     // プラグインマネージャーの初期化
     let _plugin_manager = PluginManager::new(Arc::clone(&event_bus));
     
-    // プラグインシステムの初期化
-    match plugins::initialize(Arc::clone(&event_bus)) {
-        Ok(_registry) => {
-            log::info!("Plugin system initialized successfully");
-            
-            // 必要に応じて組み込みプラグインのロード処理をここに追加
-            
-        },
-        Err(e) => log::error!("Failed to initialize plugin system: {}", e),
-    }
+    // プラグインシステムはアプリの起動時（run の setup）に初期化し、保存された設定を読み込む
     
     // Tauriアプリケーションの起動
     image_viewer_lib::run();
@@ -5,6 +5,7 @@ use serde_json::{json, Value as JsonValue};
 use crate::core::plugin_context::PluginContext;
 use crate::plugins::plugin_trait::{Plugin, PluginDescriptor, PluginResult};
 use crate::core::resource_manager::ResourceConfig;
+use crate::core::thumbnail;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 // UIモジュールをインポート
@@ -135,6 +136,8 @@ impl AllViewerPlugin {
                 include: vec![state.current_directory.clone().unwrap_or_default()],
                 exclude: vec![],
             },
+            scan: crate::core::resource_manager::ScanOptions::default(),
+            watch: false,
         };
         state.resource_config = Some(resource_config);
 
@@ -250,6 +253,8 @@ impl Plugin for AllViewerPlugin {
         Ok(json!({
             "viewMode": state.view_mode,
             "thumbnailSize": state.thumbnail_size,
+            // サムネイル生成時に使用されるサイズバケット
+            "thumbnailBucket": thumbnail::bucket_for_size(state.thumbnail_size),
             "showLabels": state.show_labels,
             "currentDirectory": state.current_directory,
         }))
@@ -256,6 +256,7 @@ pub fn get_frontend_code() -> String {
                     <tr><th>Modified:</th><td>${image.dateModified || 'Unknown'}</td></tr>
                 </table>
             `;
+            this.loadExtendedMetadata(image, metadata.querySelector('table'));
             
             // ナビゲーションボタン
             const nav = document.createElement('div');
@@ -281,6 +282,74 @@ pub fn get_frontend_code() -> String {
             this.mainView.appendChild(detailView);
         }
         
+        // EXIF/IPTC/XMPを取得して詳細表示の表に行を追加
+        loadExtendedMetadata(image, table) {
+            const path = image.path || (image.metadata && image.metadata.path);
+            if (!window.invoke || !path) {
+                return;
+            }
+            
+            const index = this.selectedIndex;
+            window.invoke('get_image_metadata', { path }).then(extended => {
+                // 取得中に別の画像が選択された場合は表示しない
+                if (index !== this.selectedIndex || !table.isConnected) {
+                    return;
+                }
+                
+                for (const [label, value] of this.formatExtendedMetadata(extended)) {
+                    const row = table.insertRow();
+                    const th = document.createElement('th');
+                    th.textContent = `${label}:`;
+                    const td = document.createElement('td');
+                    td.textContent = value;
+                    row.appendChild(th);
+                    row.appendChild(td);
+                }
+            }).catch(error => {
+                console.warn('Failed to load image metadata:', error);
+            });
+        }
+        
+        // 拡張メタデータを表示用の [ラベル, 値] の組に変換（値のない項目は除く）
+        formatExtendedMetadata(extended) {
+            const camera = extended.camera || {};
+            const exposure = extended.exposure || {};
+            const join = (...parts) => parts.filter(part => part).join(' ');
+            
+            const settings = [];
+            if (exposure.exposure_time) {
+                settings.push(exposure.exposure_time < 1
+                    ? `1/${Math.round(1 / exposure.exposure_time)}s`
+                    : `${exposure.exposure_time}s`);
+            }
+            if (exposure.f_number) settings.push(`f/${exposure.f_number.toFixed(1)}`);
+            if (exposure.iso) settings.push(`ISO ${exposure.iso}`);
+            if (exposure.focal_length) {
+                const equivalent = exposure.focal_length_35mm ? ` (${exposure.focal_length_35mm}mm eq.)` : '';
+                settings.push(`${Math.round(exposure.focal_length)}mm${equivalent}`);
+            }
+            if (exposure.exposure_bias) settings.push(`${exposure.exposure_bias > 0 ? '+' : ''}${exposure.exposure_bias.toFixed(1)} EV`);
+            if (exposure.flash_fired) settings.push('Flash');
+            
+            const gps = extended.gps
+                ? `${extended.gps.latitude.toFixed(5)}, ${extended.gps.longitude.toFixed(5)}`
+                : null;
+            
+            return [
+                ['Title', extended.title],
+                ['Caption', extended.caption],
+                ['Taken', extended.date_taken],
+                ['Camera', join(camera.make, camera.model)],
+                ['Lens', join(camera.lens_make, camera.lens_model)],
+                ['Exposure', settings.join(', ')],
+                ['Location', gps],
+                ['Keywords', (extended.keywords || []).join(', ')],
+                ['Creator', extended.creator],
+                ['Copyright', extended.copyright],
+                ['Rating', extended.rating != null ? String(extended.rating) : null],
+            ].filter(([, value]) => value);
+        }
+        
         createThumbnail(image, index, simple = false) {
             const thumb = document.createElement('div');
             thumb.className = 'image-thumbnail';
@@ -604,6 +673,7 @@ mod tests {
         assert!(!code.is_empty());
         assert!(code.contains("AllViewerUI"));
         assert!(code.contains("renderImages"));
+        assert!(code.contains("get_image_metadata"));
     }
     
     #[test]
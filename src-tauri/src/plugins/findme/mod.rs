@@ -2,12 +2,19 @@
 
 use std::sync::{Arc, Mutex};
 use serde_json::{json, Value as JsonValue};
+use crate::core::perceptual_hash::{SimilarImage, DEFAULT_NEAR_DUPLICATE_DISTANCE};
 use crate::core::plugin_context::PluginContext;
 use crate::plugins::plugin_trait::{Plugin, PluginDescriptor, PluginResult};
 
 // UIモジュールをインポート
 pub mod ui;
 
+// おとりに使わない距離（これ以下は同じ写真とみなし、見分けられないため除く）
+const MIN_DISTRACTOR_DISTANCE: u32 = DEFAULT_NEAR_DUPLICATE_DISTANCE;
+
+// おとりの既定の数
+const DEFAULT_DISTRACTOR_COUNT: usize = 3;
+
 // プラグインの状態
 #[derive(Debug, Default)]
 struct FindMeState {
@@ -96,6 +103,26 @@ impl FindMePlugin {
     }
 }
 
+// 難易度に応じておとりの画像を選ぶ
+// similar は正解の画像からの知覚ハッシュの距離付きの候補（find_similar の結果）で、
+// 難しいほど正解に似た画像を選ぶ
+fn choose_distractors(similar: &[SimilarImage], difficulty: &str, count: usize) -> Vec<String> {
+    let mut candidates: Vec<&SimilarImage> = similar.iter()
+        .filter(|image| image.distance > MIN_DISTRACTOR_DISTANCE)
+        .collect();
+    candidates.sort_by(|a, b| a.distance.cmp(&b.distance).then_with(|| a.path.cmp(&b.path)));
+
+    let start = match difficulty {
+        "hard" => 0,
+        "medium" => candidates.len().saturating_sub(count) / 2,
+        _ => candidates.len().saturating_sub(count),
+    };
+    candidates[start..].iter()
+        .take(count)
+        .map(|image| image.path.clone())
+        .collect()
+}
+
 impl Plugin for FindMePlugin {
     fn get_id(&self) -> String {
         self.descriptor.id.clone()
@@ -220,6 +247,26 @@ impl Plugin for FindMePlugin {
                     }
                 })
             }),
+            ("choose_distractors", {
+                let state_clone = Arc::clone(&self.state);
+                Box::new(move |args: JsonValue| -> PluginResult<JsonValue> {
+                    let similar: Vec<SimilarImage> = args.get("similar")
+                        .cloned()
+                        .map(serde_json::from_value)
+                        .ok_or("No similar images provided")?
+                        .map_err(|e| format!("Invalid similar images: {}", e))?;
+                    let count = args.get("count")
+                        .and_then(|c| c.as_u64())
+                        .map_or(DEFAULT_DISTRACTOR_COUNT, |c| c as usize);
+
+                    let state = state_clone.lock().map_err(|e| {
+                        format!("Failed to lock state: {}", e)
+                    })?;
+
+                    let distractors = choose_distractors(&similar, &state.difficulty, count);
+                    Ok(json!({"success": true, "distractors": distractors}))
+                })
+            }),
         ]
     }
 
@@ -249,4 +296,18 @@ mod tests {
         assert_eq!(state.difficulty, "easy");
         assert_eq!(state.score, 0);
     }
+
+    #[test]
+    fn test_choose_distractors_by_difficulty() {
+        let similar: Vec<SimilarImage> = [(2, "same.jpg"), (9, "close.jpg"), (20, "middle.jpg"), (40, "far.jpg")]
+            .iter()
+            .map(|(distance, path)| SimilarImage { path: path.to_string(), distance: *distance })
+            .collect();
+
+        // ほぼ同一の画像は選ばない
+        assert_eq!(choose_distractors(&similar, "hard", 1), vec!["close.jpg"]);
+        assert_eq!(choose_distractors(&similar, "medium", 1), vec!["middle.jpg"]);
+        assert_eq!(choose_distractors(&similar, "easy", 1), vec!["far.jpg"]);
+        assert_eq!(choose_distractors(&similar, "hard", 5).len(), 3);
+    }
 }
@@ -55,6 +55,20 @@ pub trait Plugin: Send + Sync {
         Ok(())
     }
     
+    /// 設定の形式バージョン（形式を変えたら上げて `migrate_config` で移行する）
+    fn config_version(&self) -> u32 {
+        1
+    }
+    
+    /// `from_version` の設定を1つ上のバージョンへ移行
+    fn migrate_config(&self, from_version: u32, config: JsonValue) -> PluginResult<JsonValue> {
+        match from_version {
+            // デフォルト実装ではエンベロープのない設定（バージョン0）をそのままバージョン1とみなす
+            0 => Ok(config),
+            _ => Err(format!("Cannot migrate config from version {}", from_version)),
+        }
+    }
+    
     /// フロントエンドのUIコードを取得（オプション）
     fn get_frontend_code(&self) -> Option<String> {
         None
@@ -76,6 +90,7 @@ pub struct MockPlugin {
     descriptor: PluginDescriptor,
     initialized: bool,
     active: bool,
+    config: JsonValue,
 }
 
 #[cfg(test)]
@@ -92,6 +107,7 @@ impl MockPlugin {
             },
             initialized: false,
             active: false,
+            config: serde_json::json!({ "enabled": true }),
         }
     }
 }
@@ -124,6 +140,26 @@ impl Plugin for MockPlugin {
         Ok(())
     }
     
+    fn get_config(&self) -> PluginResult<JsonValue> {
+        Ok(self.config.clone())
+    }
+    
+    fn update_config(&mut self, config: JsonValue) -> PluginResult<()> {
+        self.config = config;
+        Ok(())
+    }
+    
+    fn migrate_config(&self, from_version: u32, mut config: JsonValue) -> PluginResult<JsonValue> {
+        match from_version {
+            // バージョン0には有効フラグがなかった
+            0 => {
+                crate::core::config_schema::insert_default(&mut config, "enabled", serde_json::json!(true))?;
+                Ok(config)
+            },
+            _ => Err(format!("Cannot migrate config from version {}", from_version)),
+        }
+    }
+    
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -1,9 +1,12 @@
 // plugins/registry.rs
 use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, RwLock};
 use thiserror::Error;
 use serde_json::Value as JsonValue;
 
+use crate::core::config_schema::{self, ConfigEnvelope, ConfigError};
 use crate::core::plugin_context::PluginContext;
 use crate::core::event_bus::EventBus;
 use crate::plugins::plugin_trait::{Plugin, PluginDescriptor};
@@ -31,6 +34,9 @@ pub enum PluginRegistryError {
     
     #[error("Plugin system error: {0}")]
     SystemError(String),
+    
+    #[error("Invalid config for plugin '{0}': {1}")]
+    InvalidConfig(String, ConfigError),
 }
 
 /// プラグインの状態
@@ -71,6 +77,8 @@ pub struct PluginRegistry {
     context: Arc<PluginContext>,
     /// ディスカバリーパス（プラグインを検索するディレクトリ）
     discovery_paths: Mutex<Vec<String>>,
+    /// 設定ファイルの保存先（設定を読み込むまでは None で、更新しても保存しない）
+    config_dir: RwLock<Option<PathBuf>>,
 }
 
 impl PluginRegistry {
@@ -86,6 +94,7 @@ impl PluginRegistry {
             event_bus,
             context,
             discovery_paths: Mutex::new(Vec::new()),
+            config_dir: RwLock::new(None),
         }
     }
     
@@ -405,19 +414,150 @@ impl PluginRegistry {
     }
     
     /// プラグインの設定を更新
+    ///
+    /// `load_plugin_configs` で設定を読み込んだ後は、更新した設定をその保存先に保存する。
     pub fn update_plugin_config(&self, plugin_id: &str, config: JsonValue) -> Result<(), PluginRegistryError> {
-        let mut plugins = self.plugins.write().map_err(|e| {
-            PluginRegistryError::SystemError(format!("Failed to lock plugins registry for writing: {}", e))
+        {
+            let mut plugins = self.plugins.write().map_err(|e| {
+                PluginRegistryError::SystemError(format!("Failed to lock plugins registry for writing: {}", e))
+            })?;
+            
+            let entry = plugins.get_mut(plugin_id).ok_or_else(|| {
+                PluginRegistryError::PluginNotFound(plugin_id.to_string())
+            })?;
+            
+            entry.plugin.update_config(config).map_err(|e| {
+                PluginRegistryError::OperationError(format!("Failed to update plugin config: {}", e))
+            })?;
+        }
+        
+        let config_dir = self.config_dir.read().map_err(|e| {
+            PluginRegistryError::SystemError(format!("Failed to lock plugin config directory: {}", e))
+        })?.clone();
+        match config_dir {
+            Some(dir) => self.save_plugin_config(plugin_id, &dir),
+            None => Ok(()),
+        }
+    }
+    
+    /// プラグインの設定をバージョン付きエンベロープとして書き出す
+    pub fn export_plugin_config(&self, plugin_id: &str) -> Result<ConfigEnvelope, PluginRegistryError> {
+        let version = self.with_plugin(plugin_id, |plugin| plugin.config_version())?;
+        Ok(ConfigEnvelope {
+            kind: plugin_config_kind(plugin_id),
+            version,
+            data: self.get_plugin_config(plugin_id)?,
+        })
+    }
+    
+    /// 書き出した設定を読み込む（古いバージョンは移行してから適用する）
+    ///
+    /// エンベロープのない設定はバージョン0として扱う。読み込んだ設定の元のバージョンを返す。
+    pub fn import_plugin_config(&self, plugin_id: &str, value: JsonValue) -> Result<u32, PluginRegistryError> {
+        let config = self.with_plugin(plugin_id, |plugin| {
+            config_schema::migrate_value(
+                value,
+                &plugin_config_kind(plugin_id),
+                plugin.config_version(),
+                |from, data| plugin.migrate_config(from, data),
+            )
         })?;
+        let (config, source_version) = config.map_err(|e| PluginRegistryError::InvalidConfig(plugin_id.to_string(), e))?;
+        self.update_plugin_config(plugin_id, config)?;
+        Ok(source_version)
+    }
+    
+    /// 保存済みの設定ファイル（`dir/<プラグインID>.json`）から各プラグインの設定を読み込む
+    ///
+    /// 古いバージョンの設定は移行してから現在のバージョンで保存し直す。
+    /// ファイルのないプラグインは既定の設定のまま。読み込めなかったプラグインのエラーを返す。
+    /// 以降の設定の更新は `dir` に保存する。
+    pub fn load_plugin_configs(&self, dir: &Path) -> Vec<PluginRegistryError> {
+        let plugin_ids = match self.get_all_plugin_ids() {
+            Ok(plugin_ids) => plugin_ids,
+            Err(e) => return vec![e],
+        };
         
-        let entry = plugins.get_mut(plugin_id).ok_or_else(|| {
-            PluginRegistryError::PluginNotFound(plugin_id.to_string())
+        let mut errors = Vec::new();
+        for plugin_id in plugin_ids {
+            let path = plugin_config_path(dir, &plugin_id);
+            let text = match fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    errors.push(PluginRegistryError::InvalidConfig(plugin_id, ConfigError::Io { message: e.to_string() }));
+                    continue;
+                }
+            };
+            let value = match serde_json::from_str(&text) {
+                Ok(value) => value,
+                Err(e) => {
+                    errors.push(PluginRegistryError::InvalidConfig(plugin_id, ConfigError::Syntax {
+                        message: e.to_string(),
+                        line: e.line(),
+                        column: e.column(),
+                    }));
+                    continue;
+                }
+            };
+            let migrated = self.import_plugin_config(&plugin_id, value)
+                .and_then(|source_version| self.with_plugin(&plugin_id, |plugin| source_version < plugin.config_version()));
+            match migrated {
+                Ok(true) => errors.extend(self.save_plugin_config(&plugin_id, dir).err()),
+                Ok(false) => {},
+                Err(e) => errors.push(e),
+            }
+        }
+        
+        match self.config_dir.write() {
+            Ok(mut config_dir) => *config_dir = Some(dir.to_path_buf()),
+            Err(e) => errors.push(PluginRegistryError::SystemError(format!("Failed to lock plugin config directory: {}", e))),
+        }
+        errors
+    }
+    
+    /// プラグインの設定をバージョン付きで `dir/<プラグインID>.json` に保存（一時ファイル経由で置き換え）
+    pub fn save_plugin_config(&self, plugin_id: &str, dir: &Path) -> Result<(), PluginRegistryError> {
+        let io_error = |e: std::io::Error| PluginRegistryError::InvalidConfig(
+            plugin_id.to_string(),
+            ConfigError::Io { message: e.to_string() },
+        );
+        let envelope = self.export_plugin_config(plugin_id)?;
+        let json = serde_json::to_string_pretty(&envelope).map_err(|e| {
+            PluginRegistryError::InvalidConfig(plugin_id.to_string(), ConfigError::Io { message: e.to_string() })
         })?;
         
-        entry.plugin.update_config(config).map_err(|e| {
-            PluginRegistryError::OperationError(format!("Failed to update plugin config: {}", e))
+        fs::create_dir_all(dir).map_err(io_error)?;
+        let path = plugin_config_path(dir, plugin_id);
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, json).map_err(io_error)?;
+        fs::rename(&temp_path, &path).map_err(|e| {
+            let _ = fs::remove_file(&temp_path);
+            io_error(e)
         })
     }
+    
+    /// 登録されたプラグインを参照して処理を行う
+    fn with_plugin<T>(&self, plugin_id: &str, f: impl FnOnce(&dyn Plugin) -> T) -> Result<T, PluginRegistryError> {
+        let plugins = self.plugins.read().map_err(|e| {
+            PluginRegistryError::SystemError(format!("Failed to lock plugins registry: {}", e))
+        })?;
+        
+        let entry = plugins.get(plugin_id).ok_or_else(|| {
+            PluginRegistryError::PluginNotFound(plugin_id.to_string())
+        })?;
+        Ok(f(entry.plugin.as_ref()))
+    }
+}
+
+/// プラグイン設定のエンベロープに記録する種類
+fn plugin_config_kind(plugin_id: &str) -> String {
+    format!("plugin:{}", plugin_id)
+}
+
+/// プラグイン設定の保存先ファイル
+fn plugin_config_path(dir: &Path, plugin_id: &str) -> std::path::PathBuf {
+    dir.join(format!("{}.json", plugin_id))
 }
 
 #[cfg(test)]
@@ -496,4 +636,100 @@ mod tests {
         let all_descs = registry.get_all_plugin_descriptors().unwrap();
         assert_eq!(all_descs.len(), 2);
     }
+    
+    #[test]
+    fn test_plugin_config_export_and_import() {
+        let event_bus = Arc::new(EventBus::new());
+        let registry = PluginRegistry::new(Arc::clone(&event_bus));
+        registry.register_plugin(Box::new(MockPlugin::new("test-plugin"))).unwrap();
+        
+        let envelope = registry.export_plugin_config("test-plugin").unwrap();
+        assert_eq!(envelope.kind, "plugin:test-plugin");
+        assert_eq!(envelope.version, 1);
+        
+        // エンベロープのない古い設定は移行してから適用される
+        registry.import_plugin_config("test-plugin", serde_json::json!({ "level": 3 })).unwrap();
+        let config = registry.get_plugin_config("test-plugin").unwrap();
+        assert_eq!(config, serde_json::json!({ "level": 3, "enabled": true }));
+        
+        // 別のプラグインの設定は読み込めない
+        let other = serde_json::json!({ "kind": "plugin:other", "version": 1, "data": {} });
+        assert!(matches!(
+            registry.import_plugin_config("test-plugin", other),
+            Err(PluginRegistryError::InvalidConfig(_, ConfigError::KindMismatch { .. }))
+        ));
+    }
+    
+    /// 移行を実装しないプラグイン
+    struct PlainPlugin;
+    
+    impl Plugin for PlainPlugin {
+        fn get_id(&self) -> String {
+            "plain".to_string()
+        }
+        
+        fn get_descriptor(&self) -> PluginDescriptor {
+            PluginDescriptor {
+                id: self.get_id(),
+                name: "Plain".to_string(),
+                version: "1.0.0".to_string(),
+                description: String::new(),
+                author: String::new(),
+            }
+        }
+        
+        fn initialize(&mut self, _context: Arc<PluginContext>) -> crate::plugins::PluginResult<()> {
+            Ok(())
+        }
+        
+        fn activate(&mut self) -> crate::plugins::PluginResult<()> {
+            Ok(())
+        }
+        
+        fn deactivate(&mut self) -> crate::plugins::PluginResult<()> {
+            Ok(())
+        }
+        
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+        
+        fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+            self
+        }
+    }
+    
+    #[test]
+    fn test_plugin_configs_are_persisted() {
+        let dir = std::env::temp_dir().join(format!("image-viewer-plugin-configs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        
+        let event_bus = Arc::new(EventBus::new());
+        let registry = PluginRegistry::new(Arc::clone(&event_bus));
+        registry.register_plugin(Box::new(MockPlugin::new("test-plugin"))).unwrap();
+        registry.register_plugin(Box::new(PlainPlugin)).unwrap();
+        
+        // 移行を実装しないプラグインでも、エンベロープのない設定はそのまま読み込める
+        assert_eq!(registry.import_plugin_config("plain", serde_json::json!({ "a": 1 })).unwrap(), 0);
+        
+        // 古い形式の設定ファイルは起動時に移行され、現在のバージョンで保存し直される
+        fs::write(dir.join("test-plugin.json"), r#"{ "level": 3 }"#).unwrap();
+        fs::write(dir.join("plain.json"), "{ broken").unwrap();
+        let errors = registry.load_plugin_configs(&dir);
+        assert!(matches!(errors.as_slice(), [PluginRegistryError::InvalidConfig(id, ConfigError::Syntax { .. })] if id == "plain"));
+        assert_eq!(registry.get_plugin_config("test-plugin").unwrap(), serde_json::json!({ "level": 3, "enabled": true }));
+        let saved: JsonValue = serde_json::from_str(&fs::read_to_string(dir.join("test-plugin.json")).unwrap()).unwrap();
+        assert_eq!(saved["kind"], "plugin:test-plugin");
+        assert_eq!(saved["data"]["enabled"], true);
+        
+        // 読み込み後の更新はそのまま保存され、別のレジストリで読み直せる
+        registry.update_plugin_config("test-plugin", serde_json::json!({ "level": 5, "enabled": false })).unwrap();
+        let reopened = PluginRegistry::new(event_bus);
+        reopened.register_plugin(Box::new(MockPlugin::new("test-plugin"))).unwrap();
+        assert!(reopened.load_plugin_configs(&dir).is_empty());
+        assert_eq!(reopened.get_plugin_config("test-plugin").unwrap(), serde_json::json!({ "level": 5, "enabled": false }));
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
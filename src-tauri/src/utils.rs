@@ -1,2 +1,62 @@
 // 汎用ユーティリティ関数の実装
 // 共通化すべき機能が見つかった時点で追加する
+
+/// FNV-1a 64bitハッシュ（キャッシュキー用、実行間で安定）
+pub fn fnv1a_64(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// 自然順の比較（連続する数字は数値として比較するため "page2" < "page10" となる）
+///
+/// 数値として等しい場合（"01" と "1"）は文字列の順で決め、異なる文字列が等しくならないようにする。
+pub fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+
+    let (mut a_rest, mut b_rest) = (a, b);
+    loop {
+        let (a_char, b_char) = match (a_rest.chars().next(), b_rest.chars().next()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_char), Some(b_char)) => (a_char, b_char),
+        };
+
+        if a_char.is_ascii_digit() && b_char.is_ascii_digit() {
+            let a_len = a_rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(a_rest.len());
+            let b_len = b_rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(b_rest.len());
+            // 先頭の0を除いた桁数、次に各桁で比較する
+            let a_digits = a_rest[..a_len].trim_start_matches('0');
+            let b_digits = b_rest[..b_len].trim_start_matches('0');
+            let ordering = a_digits.len().cmp(&b_digits.len()).then_with(|| a_digits.cmp(b_digits));
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            a_rest = &a_rest[a_len..];
+            b_rest = &b_rest[b_len..];
+        } else {
+            if a_char != b_char {
+                return a_char.cmp(&b_char);
+            }
+            a_rest = &a_rest[a_char.len_utf8()..];
+            b_rest = &b_rest[b_char.len_utf8()..];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_natural_cmp() {
+        let mut names = vec!["page10.jpg", "page2.jpg", "page1.jpg", "page02.jpg", "cover.jpg"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, vec!["cover.jpg", "page1.jpg", "page02.jpg", "page2.jpg", "page10.jpg"]);
+        assert_eq!(natural_cmp("a", "a"), std::cmp::Ordering::Equal);
+    }
+}
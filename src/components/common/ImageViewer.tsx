@@ -274,7 +274,7 @@ const ImageViewer: React.FC<ImageViewerProps> = ({
         {image && (
           <img
             ref={imageRef}
-            src={`data:image/png;base64,${image.base64}`}
+            src={image.url}
             alt={image.fileName}
             style={{
               ...styles.image,
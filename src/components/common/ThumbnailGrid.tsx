@@ -143,7 +143,7 @@ const ThumbnailGrid: React.FC<ThumbnailGridProps> = ({
       const image = images[index];
       
       // 既にImageDataの場合はそのまま使用
-      if ('base64' in image && image.base64) {
+      if ('url' in image && image.url) {
         setLoadedImages(prev => ({ ...prev, [index]: image as ImageData }));
         if (onImageLoad) onImageLoad(index, image as ImageData);
       } 
@@ -289,7 +289,7 @@ const ThumbnailGrid: React.FC<ThumbnailGridProps> = ({
                 {/* サムネイル画像 */}
                 {isLoaded && loadedImages[index] ? (
                   <img
-                    src={`data:image/png;base64,${loadedImages[index]?.base64}`}
+                    src={loadedImages[index]?.url}
                     alt={fileName}
                     style={styles.thumbnail}
                   />
@@ -494,6 +494,23 @@ export class ConfigManager {
     }
   }
 
+  /**
+   * バックエンドのプラグイン設定をバージョン付きエンベロープとして書き出す
+   * @param pluginId プラグインID
+   */
+  public async exportPluginConfig(pluginId: string): Promise<{ kind: string; version: number; data: any }> {
+    return await invoke('export_plugin_config', { pluginId });
+  }
+
+  /**
+   * 書き出したプラグイン設定をバックエンドに読み込む（古いバージョンは移行され、保存される）
+   * @param pluginId プラグインID
+   * @param config エンベロープ、またはエンベロープのない古い設定
+   */
+  public async importPluginConfig(pluginId: string, config: any): Promise<void> {
+    await invoke('import_plugin_config', { pluginId, config });
+  }
+
   /**
    * 現在の設定全体を取得
    */
@@ -2,6 +2,7 @@ import { invoke } from "@tauri-apps/api/core";
 import { exists, writeTextFile, readTextFile } from "@tauri-apps/plugin-fs";
 import { appConfigDir, join } from '@tauri-apps/api/path';
 import { mkdir } from '@tauri-apps/plugin-fs';
+import type { ImageFormat } from '../core/ImageManager';
 
 // リソースフィルターのインターフェース
 export interface ResourceFilter {
@@ -9,11 +10,110 @@ export interface ResourceFilter {
   exclude: string[];
 }
 
+// 走査オプションのインターフェース（キーはバックエンドの設定ファイルと同じ形式）
+// 省略時のデフォルト: 深さ無制限・隠しファイル除外・シンボリックリンクを辿らない・サイズ制限なし
+export interface ScanOptions {
+  max_depth?: number | null;
+  include_hidden?: boolean;
+  follow_symlinks?: boolean;
+  min_file_size?: number | null;
+  max_file_size?: number | null;
+  // 対象とする画像形式（指定した場合はファイルの内容で判定する）
+  formats?: {
+    include?: ImageFormat[];
+    exclude?: ImageFormat[];
+  };
+}
+
 // リソース設定のインターフェース
 export interface ResourceConfig {
   id: string;
   name: string;
   filters: ResourceFilter;
+  scan?: ScanOptions;
+  // ファイルシステムを監視して変更を自動反映するかどうか
+  watch?: boolean;
+}
+
+// バックエンドが管理する設定ライブラリの内容
+export interface ResourceConfigLibrary {
+  default_id?: string | null;
+  configs: ResourceConfig[];
+}
+
+// 設定の検証で見つかった問題（path は "filters.include" のような項目の位置）
+export type ConfigIssue =
+  | { kind: 'unknown_field'; path: string }
+  | { kind: 'empty_field'; path: string }
+  | { kind: 'empty_include_list'; path: string }
+  | { kind: 'duplicate_id'; path: string; id: string }
+  | { kind: 'default_not_found'; path: string; id: string }
+  | { kind: 'invalid_value'; path: string; message: string };
+
+// 設定ファイルの読み込み・保存エラー
+export type ConfigError =
+  | { kind: 'io'; message: string }
+  | { kind: 'syntax'; message: string; line: number; column: number }
+  | { kind: 'kind_mismatch'; expected: string; found: string }
+  | { kind: 'unsupported_version'; found: number; supported: number }
+  | { kind: 'migration'; from: number; message: string }
+  | { kind: 'invalid'; issues: ConfigIssue[] };
+
+// 設定ライブラリの操作エラー
+export type ConfigLibraryError =
+  | { kind: 'not_found'; id: string }
+  | { kind: 'duplicate_id'; id: string }
+  | { kind: 'invalid'; error: ConfigError }
+  | { kind: 'io'; message: string }
+  | { kind: 'read_only'; error: ConfigError };
+
+/**
+ * バックエンドから返された設定エラーを表示用の文字列に変換
+ * @param error invoke が投げたエラー
+ */
+export function describeConfigError(error: unknown): string {
+  if (typeof error !== 'object' || error === null || !('kind' in error)) {
+    return String(error);
+  }
+  const e = error as ConfigError | ConfigLibraryError;
+  switch (e.kind) {
+    case 'io':
+      return e.message;
+    case 'syntax':
+      return `JSONの構文エラー（${e.line}行 ${e.column}列）: ${e.message}`;
+    case 'kind_mismatch':
+      return `設定の種類が異なります（${e.expected} を想定、${e.found} を検出）`;
+    case 'unsupported_version':
+      return `設定のバージョン ${e.found} には対応していません（対応バージョン: ${e.supported}）`;
+    case 'migration':
+      return `バージョン ${e.from} からの移行に失敗しました: ${e.message}`;
+    case 'not_found':
+      return `設定が見つかりません: ${e.id}`;
+    case 'duplicate_id':
+      return `設定IDが重複しています: ${e.id}`;
+    case 'read_only':
+      return `設定ライブラリを読み込めなかったため変更できません: ${describeConfigError(e.error)}`;
+    case 'invalid':
+      return 'issues' in e ? e.issues.map(describeConfigIssue).join(', ') : describeConfigError(e.error);
+  }
+}
+
+// 検証で見つかった問題を表示用の文字列に変換
+function describeConfigIssue(issue: ConfigIssue): string {
+  switch (issue.kind) {
+    case 'unknown_field':
+      return `不明な項目: ${issue.path}`;
+    case 'empty_field':
+      return `空の項目: ${issue.path}`;
+    case 'empty_include_list':
+      return `インクルードパスがありません: ${issue.path}`;
+    case 'duplicate_id':
+      return `IDが重複しています（${issue.id}）: ${issue.path}`;
+    case 'default_not_found':
+      return `既定の設定が見つかりません（${issue.id}）: ${issue.path}`;
+    case 'invalid_value':
+      return `不正な値: ${issue.path} ${issue.message}`;
+  }
 }
 
 // リソース定義管理クラス
@@ -163,7 +263,18 @@ export class ResourceDefinitionManager {
     config: ResourceConfig
   ): Promise<string[]> {
     try {
-      const result: { paths: string[] } = await invoke('resolve_resources', { config });
+      const result: {
+        paths: string[];
+        skipped?: { path: string; reason: string; message: string }[];
+      } = await invoke('resolve_resources', { config });
+      
+      // 読み込めなかったフォルダがあっても解決できた分は返す
+      if (result.skipped && result.skipped.length > 0) {
+        console.warn(
+          `Resolved ${result.paths.length} images, ${result.skipped.length} paths skipped:`,
+          result.skipped
+        );
+      }
       return result.paths || [];
     } catch (error) {
       console.error('Failed to resolve resources:', error);
@@ -171,6 +282,97 @@ export class ResourceDefinitionManager {
     }
   }
 
+  /**
+   * バックエンドの設定ライブラリを取得（ローカルの設定リストも同期する）
+   * @returns 設定ライブラリの内容
+   */
+  public static async fetchResourceConfigs(): Promise<ResourceConfigLibrary> {
+    try {
+      const library = await invoke<ResourceConfigLibrary>('list_resource_configs');
+      this.resourceConfigList = [...library.configs];
+      return library;
+    } catch (error) {
+      console.error('Failed to list resource configs:', error);
+      throw new Error(`リソース設定一覧の取得に失敗しました: ${error}`);
+    }
+  }
+
+  /**
+   * 設定ライブラリに設定を追加（IDが空の場合はバックエンドで採番される）
+   * @param config 追加するリソース設定
+   * @returns 保存されたリソース設定
+   */
+  public static async storeResourceConfig(config: ResourceConfig): Promise<ResourceConfig> {
+    try {
+      const stored = await invoke<ResourceConfig>('create_resource_config', { config });
+      this.addResourceConfigToList(stored);
+      return stored;
+    } catch (error) {
+      console.error('Failed to create resource config:', error);
+      throw new Error(`リソース設定の追加に失敗しました: ${describeConfigError(error)}`);
+    }
+  }
+
+  /**
+   * 設定ライブラリの設定を更新
+   * @param config 更新後のリソース設定
+   * @returns 保存されたリソース設定
+   */
+  public static async updateStoredResourceConfig(config: ResourceConfig): Promise<ResourceConfig> {
+    try {
+      const stored = await invoke<ResourceConfig>('update_resource_config', { config });
+      this.addResourceConfigToList(stored);
+      return stored;
+    } catch (error) {
+      console.error('Failed to update resource config:', error);
+      throw new Error(`リソース設定の更新に失敗しました: ${describeConfigError(error)}`);
+    }
+  }
+
+  /**
+   * 設定ライブラリの設定を複製
+   * @param configId 複製元の設定ID
+   * @param name 複製後の名前（省略時は元の名前から付ける）
+   * @returns 複製されたリソース設定
+   */
+  public static async duplicateResourceConfig(configId: string, name?: string): Promise<ResourceConfig> {
+    try {
+      const copy = await invoke<ResourceConfig>('duplicate_resource_config', { configId, name });
+      this.addResourceConfigToList(copy);
+      return copy;
+    } catch (error) {
+      console.error('Failed to duplicate resource config:', error);
+      throw new Error(`リソース設定の複製に失敗しました: ${describeConfigError(error)}`);
+    }
+  }
+
+  /**
+   * 設定ライブラリから設定を削除
+   * @param configId 削除する設定のID
+   */
+  public static async deleteResourceConfig(configId: string): Promise<void> {
+    try {
+      await invoke<void>('delete_resource_config', { configId });
+      this.removeResourceConfigFromList(configId);
+    } catch (error) {
+      console.error('Failed to delete resource config:', error);
+      throw new Error(`リソース設定の削除に失敗しました: ${describeConfigError(error)}`);
+    }
+  }
+
+  /**
+   * 既定のリソース設定を変更
+   * @param configId 既定にする設定のID
+   */
+  public static async setDefaultResourceConfig(configId: string): Promise<void> {
+    try {
+      await invoke<void>('set_default_resource_config', { configId });
+    } catch (error) {
+      console.error('Failed to set default resource config:', error);
+      throw new Error(`既定のリソース設定の変更に失敗しました: ${describeConfigError(error)}`);
+    }
+  }
+
   /**
    * 新しいリソース設定を作成
    * @param params リソース設定のパラメータ
@@ -181,6 +383,8 @@ export class ResourceDefinitionManager {
     name?: string;
     includePaths: string[];
     excludePaths?: string[];
+    scan?: ScanOptions;
+    watch?: boolean;
   }): ResourceConfig {
     // IDとタイトルのデフォルト生成
     const defaultId = `resource_${Date.now()}`;
@@ -192,7 +396,9 @@ export class ResourceDefinitionManager {
       filters: {
         include: params.includePaths,
         exclude: params.excludePaths || []
-      }
+      },
+      scan: params.scan,
+      watch: params.watch
     };
   }
 
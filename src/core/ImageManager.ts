@@ -1,4 +1,29 @@
 import { invoke } from '@tauri-apps/api/core';
+import { describeConfigError } from '../config/ResourceDefinition';
+
+/**
+ * ファイルの内容から判定した画像形式
+ */
+export type ImageFormat = 'jpeg' | 'png' | 'gif' | 'bmp' | 'webp' | 'tiff' | 'avif' | 'heic' | 'jxl' | 'raw';
+
+/**
+ * 画像形式ごとの対応状況（get_supported_formats の結果）
+ */
+export interface FormatSupport {
+  format: ImageFormat;
+  name: string;
+  extensions: string[];
+  mime_type: string;
+  /** このビルドでデコードできるか（false の形式はサムネイルを生成できない） */
+  decodable: boolean;
+  /** webviewでそのまま表示できるか（false の形式は変換して配信される） */
+  web_displayable: boolean;
+}
+
+/**
+ * 画像の回転（時計回りの角度）
+ */
+export type ImageRotation = 0 | 90 | 180 | 270;
 
 /**
  * 画像メタデータのインターフェース
@@ -7,19 +32,73 @@ export interface ImageMetadata {
   path: string;
   fileName: string;
   fileSize: number;
+  format?: ImageFormat | null;
   dimensions?: {
     width: number;
     height: number;
   };
+  /** EXIFの向き（1〜8） */
+  orientation?: number;
+  /** ユーザーが指定した回転（時計回りの角度） */
+  rotation?: ImageRotation;
+  /** 表示時の寸法（EXIFの向きと回転を適用した後の [幅, 高さ]） */
+  display_dimensions?: [number, number] | null;
+  /** 撮影位置 */
+  gps?: GpsPosition;
+  /** 代表色と平均輝度（色の分析を行った画像のみ） */
+  palette?: ColorPalette;
   dateCreated?: string;
   dateModified?: string;
+  /** 同じエントリにまとめた画像（RAW+JPEGのRAW側など） */
+  companions?: ImageMetadata[];
+}
+
+/**
+ * 画像の拡張メタデータ（EXIF/IPTC/XMP）のインターフェース
+ */
+export interface ExtendedMetadata {
+  camera?: {
+    make?: string | null;
+    model?: string | null;
+    lens_make?: string | null;
+    lens_model?: string | null;
+  } | null;
+  exposure?: {
+    /** 露出時間（秒） */
+    exposure_time?: number | null;
+    f_number?: number | null;
+    iso?: number | null;
+    /** 焦点距離（mm） */
+    focal_length?: number | null;
+    focal_length_35mm?: number | null;
+    /** 露出補正（EV） */
+    exposure_bias?: number | null;
+    flash_fired?: boolean | null;
+  } | null;
+  /** 撮影日時（ISO-8601形式） */
+  date_taken?: string | null;
+  /** EXIFの向き（1〜8） */
+  orientation?: number | null;
+  /** 撮影位置（十進の度、南緯・西経は負） */
+  gps?: {
+    latitude: number;
+    longitude: number;
+    altitude?: number | null;
+  } | null;
+  title?: string | null;
+  caption?: string | null;
+  keywords: string[];
+  creator?: string | null;
+  copyright?: string | null;
+  /** 評価（-1〜5） */
+  rating?: number | null;
 }
 
 /**
  * 画像データのインターフェース
  */
 export interface ImageData {
-  base64: string;
+  url: string;
   fileName: string;
   metadata: ImageMetadata;
 }
@@ -47,6 +126,44 @@ export interface ResourceConfig {
   id: string;
   name: string;
   filters: ResourceFilter;
+  scan?: {
+    max_depth?: number | null;
+    include_hidden?: boolean;
+    follow_symlinks?: boolean;
+    min_file_size?: number | null;
+    max_file_size?: number | null;
+    formats?: {
+      include?: ImageFormat[];
+      exclude?: ImageFormat[];
+    };
+  };
+  watch?: boolean;
+}
+
+/**
+ * ファイルシステム監視による変更イベント（resource:added / resource:removed / resource:modified）
+ */
+export interface ResourceChangeEvent {
+  config_id: string;
+  paths: string[];
+}
+
+/**
+ * フィルタエントリのエラー（展開できず無視されたエントリ）
+ */
+export interface FilterEntryError {
+  entry: string;
+  kind: 'include' | 'exclude';
+  message: string;
+}
+
+/**
+ * 走査中にスキップしたパス
+ */
+export interface SkippedPath {
+  path: string;
+  reason: 'not_found' | 'permission_denied' | 'unreadable' | 'symlink_cycle' | 'invalid_archive';
+  message: string;
 }
 
 /**
@@ -55,6 +172,240 @@ export interface ResourceConfig {
 export interface PathResolutionResult {
   paths: string[];
   count: number;
+  entry_errors: FilterEntryError[];
+  skipped: SkippedPath[];
+  // fresh: 全走査 / partially_rescanned: 更新されたディレクトリのみ再走査 / cached: 前回の結果を再利用
+  cache_status: 'fresh' | 'partially_rescanned' | 'cached';
+  dirs_rescanned: number;
+}
+
+/**
+ * 登録済みコレクションの概要
+ */
+export interface CollectionSummary {
+  id: string;
+  parent_id?: string | null;
+  total_images: number;
+  total_size_bytes: number;
+  warning_count: number;
+}
+
+/**
+ * コレクションのメタデータページ
+ */
+export interface CollectionPage {
+  collection_id: string;
+  offset: number;
+  total: number;
+  items: ImageMetadata[];
+}
+
+/**
+ * コレクションのフィルタ条件
+ *
+ * コマンド引数の中の値はキー名が変換されないため、Rust側と同じ snake_case で指定する。
+ */
+export interface ImageFilter {
+  name_contains?: string;
+  extensions?: string[];
+  min_file_size?: number;
+  max_file_size?: number;
+  min_width?: number;
+  min_height?: number;
+  modified_after?: string;
+  modified_before?: string;
+  /** 撮影位置の有無 */
+  has_location?: boolean;
+  /** 撮影位置がこの範囲に含まれる画像 */
+  within_bounds?: GeoBounds;
+  /** 撮影位置がこの半径に含まれる画像 */
+  within_radius?: GeoRadius;
+  /** 指定した色が大部分を占める画像（色の分析を行った画像のみ） */
+  color?: ColorFilter;
+  /** 平均輝度の下限（0〜1） */
+  min_brightness?: number;
+  /** 平均輝度の上限（0〜1） */
+  max_brightness?: number;
+}
+
+/**
+ * 撮影位置（十進の度、南緯・西経は負）
+ */
+export interface GpsPosition {
+  latitude: number;
+  longitude: number;
+  /** 高度（m） */
+  altitude?: number | null;
+}
+
+/**
+ * 緯度経度の範囲（west > east の場合は日付変更線をまたぐ）
+ */
+export interface GeoBounds {
+  south: number;
+  west: number;
+  north: number;
+  east: number;
+}
+
+/**
+ * 中心からの半径による範囲
+ */
+export interface GeoRadius {
+  latitude: number;
+  longitude: number;
+  radius_km: number;
+}
+
+/**
+ * 逆ジオコーディングの結果
+ */
+export interface PlaceMatch {
+  name: string;
+  /** ISO 3166-1 alpha-2 の国コード */
+  country: string;
+  /** 地名の代表点からの距離（km）。地名データによっては大きく離れることがある */
+  distance_km: number;
+}
+
+/**
+ * 地図表示用の位置グループ
+ */
+export interface LocationCluster {
+  latitude: number;
+  longitude: number;
+  count: number;
+  bounds: GeoBounds;
+  paths: string[];
+  place?: PlaceMatch | null;
+}
+
+/**
+ * 内容が同一の画像のグループ
+ */
+export interface DuplicateGroup {
+  /** 内容のハッシュ（BLAKE3） */
+  hash: string;
+  file_size: number;
+  paths: string[];
+  /** 1つだけ残した場合に空くサイズ（バイト） */
+  wasted_bytes: number;
+}
+
+/**
+ * 重複検出の結果
+ */
+export interface DuplicateReport {
+  groups: DuplicateGroup[];
+  hashed: number;
+  wasted_bytes: number;
+  /** ハッシュを計算できなかった画像 */
+  warnings: { path: string; message: string }[];
+}
+
+/**
+ * 重複グループから残す画像の選び方
+ */
+export type KeepStrategy = 'first' | 'oldest' | 'newest' | 'shortest_path';
+
+/**
+ * 残さない画像の扱い
+ */
+export type Disposal = { type: 'delete' } | { type: 'move_to'; directory: string };
+
+/**
+ * 1つの画像に対するファイル操作
+ */
+export interface FileOperation {
+  path: string;
+  action: { type: 'keep' } | { type: 'delete' } | { type: 'move'; destination: string };
+  size: number;
+  status: 'planned' | 'done' | 'skipped' | 'failed';
+  message?: string | null;
+}
+
+/**
+ * ファイル操作の結果
+ */
+export interface FileOpsReport {
+  dry_run: boolean;
+  operations: FileOperation[];
+  reclaimed_bytes: number;
+}
+
+/**
+ * 知覚ハッシュの種類（aHash / dHash / pHash）
+ */
+export type HashKind = 'average' | 'difference' | 'perceptual';
+
+/**
+ * 類似画像
+ */
+export interface SimilarImage {
+  path: string;
+  /** ハッシュのハミング距離（0〜64、小さいほど似ている） */
+  distance: number;
+}
+
+/**
+ * ほぼ同一の画像のグループ
+ */
+export interface SimilarGroup {
+  representative: string;
+  members: SimilarImage[];
+}
+
+/**
+ * 画像の代表色の1色
+ */
+export interface PaletteColor {
+  /** "#rrggbb" */
+  color: string;
+  /** 画像に占める割合（0〜1） */
+  weight: number;
+}
+
+/**
+ * 画像の代表色と平均輝度
+ */
+export interface ColorPalette {
+  /** 割合の大きい順 */
+  colors: PaletteColor[];
+  /** 平均輝度（0〜1） */
+  brightness: number;
+}
+
+/**
+ * 色による絞り込みの条件
+ */
+export interface ColorFilter {
+  /** "#rrggbb" */
+  color: string;
+  /** 近い色が占める割合の下限（省略時は0.4） */
+  min_coverage?: number;
+  /** 近い色とみなす距離（省略時は120） */
+  tolerance?: number;
+}
+
+/**
+ * 色の分析の結果
+ */
+export interface ColorAnalysis {
+  analyzed: number;
+  warnings: { path: string; message: string }[];
+  /** 代表色を反映したコレクションのID */
+  updated_collections: string[];
+}
+
+/**
+ * コレクションのソート条件
+ */
+export interface ImageSort {
+  key: 'file_name' | 'path' | 'file_size' | 'date_created' | 'date_modified' | 'width' | 'height' | 'pixel_count'
+    | 'hue' | 'brightness' | 'color_distance';
+  descending?: boolean;
+  /** color_distance で基準にする色（"#rrggbb"） */
+  color?: string;
 }
 
 /**
@@ -96,6 +447,21 @@ export class ImageManager {
     }
   }
 
+  /**
+   * 実行中のリソース解決をキャンセル
+   * 進捗は resource:scan_progress イベントで通知される
+   * @param configId 設定ID
+   * @returns キャンセル対象の解決処理があったかどうか
+   */
+  public async cancelResolution(configId: string): Promise<boolean> {
+    try {
+      return await invoke<boolean>('cancel_resolution', { configId });
+    } catch (error) {
+      console.error(`Failed to cancel resolution for config ${configId}:`, error);
+      throw new Error(`設定 ${configId} の解決のキャンセルに失敗しました: ${error}`);
+    }
+  }
+
   /**
    * パスリストから画像を読み込む
    * @param paths 画像パスのリスト
@@ -109,7 +475,7 @@ export class ImageManager {
       
       // 実際の画像データはまだロードされていないので、メタデータのみ返す
       return collection.metadataList.map(metadata => ({
-        base64: '', // 初期値は空（実際の画像は必要に応じて後から読み込む）
+        url: '', // 初期値は空（実際の画像URLは必要に応じて後から取得する）
         fileName: metadata.fileName,
         metadata
       }));
@@ -132,7 +498,7 @@ export class ImageManager {
       
       // 実際の画像データはまだロードされていないので、メタデータのみ返す
       return collection.metadataList.map(metadata => ({
-        base64: '', // 初期値は空（実際の画像は必要に応じて後から読み込む）
+        url: '', // 初期値は空（実際の画像URLは必要に応じて後から取得する）
         fileName: metadata.fileName,
         metadata
       }));
@@ -171,6 +537,98 @@ export class ImageManager {
     }
   }
 
+  /**
+   * パスリストからコレクションを作成してバックエンドに登録
+   * @param paths 画像パスのリスト
+   * @returns 作成したコレクションの概要
+   */
+  public async createCollection(paths: string[]): Promise<CollectionSummary> {
+    try {
+      return await invoke<CollectionSummary>('create_collection', { paths });
+    } catch (error) {
+      console.error('Failed to create collection:', error);
+      throw new Error(`コレクションの作成に失敗しました: ${error}`);
+    }
+  }
+
+  /**
+   * リソース設定からコレクションを作成してバックエンドに登録
+   * @param config リソース設定
+   * @returns 作成したコレクションの概要
+   */
+  public async createCollectionFromConfig(config: ResourceConfig): Promise<CollectionSummary> {
+    try {
+      return await invoke<CollectionSummary>('create_collection_from_config', { config });
+    } catch (error) {
+      console.error(`Failed to create collection from config ${config.id}:`, error);
+      throw new Error(`設定からのコレクション作成に失敗しました: ${error}`);
+    }
+  }
+
+  /**
+   * 登録済みコレクションの一覧を取得
+   * @returns コレクションの概要リスト
+   */
+  public async listCollections(): Promise<CollectionSummary[]> {
+    try {
+      return await invoke<CollectionSummary[]>('list_collections');
+    } catch (error) {
+      console.error('Failed to list collections:', error);
+      throw new Error(`コレクション一覧の取得に失敗しました: ${error}`);
+    }
+  }
+
+  /**
+   * コレクションのメタデータをページ単位で取得
+   * @param collectionId コレクションID
+   * @param offset 開始位置
+   * @param limit 取得件数
+   * @returns メタデータのページ
+   */
+  public async getCollectionPage(collectionId: string, offset: number, limit: number): Promise<CollectionPage> {
+    try {
+      return await invoke<CollectionPage>('get_collection_page', { collectionId, offset, limit });
+    } catch (error) {
+      console.error(`Failed to get page of collection ${collectionId}:`, error);
+      throw new Error(`コレクション ${collectionId} のページ取得に失敗しました: ${error}`);
+    }
+  }
+
+  /**
+   * フィルタ・ソートを適用した子コレクションを作成
+   * @param collectionId 派生元のコレクションID
+   * @param filter フィルタ条件
+   * @param sort ソート条件
+   * @param groupRawPairs 同名のRAWとJPEGを1つのエントリにまとめるか
+   * @returns 作成したコレクションの概要
+   */
+  public async deriveCollection(
+    collectionId: string,
+    filter?: ImageFilter,
+    sort?: ImageSort,
+    groupRawPairs?: boolean
+  ): Promise<CollectionSummary> {
+    try {
+      return await invoke<CollectionSummary>('derive_collection', { collectionId, filter, sort, groupRawPairs });
+    } catch (error) {
+      console.error(`Failed to derive collection from ${collectionId}:`, error);
+      throw new Error(`コレクション ${collectionId} からの派生に失敗しました: ${error}`);
+    }
+  }
+
+  /**
+   * コレクションを破棄
+   * @param collectionId コレクションID
+   */
+  public async dropCollection(collectionId: string): Promise<void> {
+    try {
+      await invoke<void>('drop_collection', { collectionId });
+    } catch (error) {
+      console.error(`Failed to drop collection ${collectionId}:`, error);
+      throw new Error(`コレクション ${collectionId} の破棄に失敗しました: ${error}`);
+    }
+  }
+
   /**
    * JSON設定ファイルを読み込む
    * @param path 設定ファイルのパス
@@ -181,7 +639,7 @@ export class ImageManager {
       return await invoke<ResourceConfig>('load_config', { path });
     } catch (error) {
       console.error('Failed to load config:', error);
-      throw new Error(`設定ファイルの読み込みに失敗しました: ${error}`);
+      throw new Error(`設定ファイルの読み込みに失敗しました: ${describeConfigError(error)}`);
     }
   }
 
@@ -195,7 +653,7 @@ export class ImageManager {
       await invoke<void>('save_config', { config, path });
     } catch (error) {
       console.error('Failed to save config:', error);
-      throw new Error(`設定ファイルの保存に失敗しました: ${error}`);
+      throw new Error(`設定ファイルの保存に失敗しました: ${describeConfigError(error)}`);
     }
   }
 
@@ -226,6 +684,176 @@ export class ImageManager {
     }
   }
 
+  /**
+   * 画像形式ごとの対応状況を取得
+   * @returns 形式ごとのデコード可否と表示可否
+   */
+  public async getSupportedFormats(): Promise<FormatSupport[]> {
+    try {
+      return await invoke<FormatSupport[]>('get_supported_formats');
+    } catch (error) {
+      console.error('Failed to get supported formats:', error);
+      throw new Error(`対応形式の取得に失敗しました: ${error}`);
+    }
+  }
+
+  /**
+   * 画像の拡張メタデータ（EXIF/IPTC/XMP）を取得
+   * @param path 画像のパス
+   * @returns 拡張メタデータ
+   */
+  public async getImageMetadata(path: string): Promise<ExtendedMetadata> {
+    try {
+      return await invoke<ExtendedMetadata>('get_image_metadata', { path });
+    } catch (error) {
+      console.error('Failed to get image metadata:', error);
+      throw new Error(`画像メタデータの取得に失敗しました: ${error}`);
+    }
+  }
+
+  /**
+   * 画像の回転を設定（元のファイルは変更せず、サムネイルと表示に適用される）
+   * @param path 画像のパス
+   * @param rotation 時計回りの角度
+   * @returns 更新後のメタデータ
+   */
+  public async setImageRotation(path: string, rotation: ImageRotation): Promise<ImageMetadata> {
+    try {
+      return await invoke<ImageMetadata>('set_image_rotation', { path, rotation });
+    } catch (error) {
+      console.error('Failed to set image rotation:', error);
+      throw new Error(`画像の回転の設定に失敗しました: ${error}`);
+    }
+  }
+
+  /**
+   * コレクションの画像を撮影位置ごとにまとめる（地図表示用）
+   * @param collectionId コレクションID
+   * @param cellKm グループの大きさ（km、省略時は25km）
+   * @returns 画像数の多い順の位置グループ
+   */
+  public async getLocationClusters(collectionId: string, cellKm?: number): Promise<LocationCluster[]> {
+    try {
+      return await invoke<LocationCluster[]>('get_location_clusters', { collectionId, cellKm });
+    } catch (error) {
+      console.error('Failed to get location clusters:', error);
+      throw new Error(`位置グループの取得に失敗しました: ${error}`);
+    }
+  }
+
+  /**
+   * 緯度経度から最寄りの地名を取得（オフライン）
+   * @param latitude 緯度
+   * @param longitude 経度
+   * @returns 最寄りの地名と距離（同梱データは主要都市のみのため、distance_km を見て表示を決める）
+   */
+  public async reverseGeocode(latitude: number, longitude: number): Promise<PlaceMatch | null> {
+    try {
+      return await invoke<PlaceMatch | null>('reverse_geocode', { latitude, longitude });
+    } catch (error) {
+      console.error('Failed to reverse geocode:', error);
+      throw new Error(`地名の取得に失敗しました: ${error}`);
+    }
+  }
+
+  /**
+   * コレクション内の内容が同一の画像を探す（進捗は duplicates:progress イベントで通知される）
+   * @param collectionId コレクションID
+   * @returns 空くサイズの大きい順の重複グループ
+   */
+  public async findDuplicates(collectionId: string): Promise<DuplicateReport> {
+    try {
+      return await invoke<DuplicateReport>('find_duplicates', { collectionId });
+    } catch (error) {
+      console.error('Failed to find duplicates:', error);
+      throw new Error(`重複画像の検出に失敗しました: ${error}`);
+    }
+  }
+
+  /**
+   * 重複画像を1つだけ残して削除・移動する
+   * @param collectionId コレクションID
+   * @param disposal 残さない画像の扱い
+   * @param options dryRun が true の場合は予定する操作だけを返す、hashes で対象のグループを限定する
+   * @returns 操作の結果
+   */
+  public async keepOneDuplicate(
+    collectionId: string,
+    disposal: Disposal,
+    options: { dryRun: boolean; strategy?: KeepStrategy; hashes?: string[] }
+  ): Promise<FileOpsReport> {
+    try {
+      return await invoke<FileOpsReport>('keep_one_duplicate', {
+        collectionId,
+        hashes: options.hashes,
+        strategy: options.strategy,
+        disposal,
+        dryRun: options.dryRun,
+      });
+    } catch (error) {
+      console.error('Failed to remove duplicates:', error);
+      throw new Error(`重複画像の整理に失敗しました: ${error}`);
+    }
+  }
+
+  /**
+   * 見た目が似た画像を探す（ハッシュの計算状況は similarity:progress イベントで通知される）
+   * @param path 基準の画像のパス
+   * @param maxDistance 最大距離（省略時は12）
+   * @param options collectionId を省略した場合はハッシュを計算済みの画像から探す
+   * @returns 距離の近い順の類似画像
+   */
+  public async findSimilar(
+    path: string,
+    maxDistance?: number,
+    options: { collectionId?: string; kind?: HashKind } = {}
+  ): Promise<SimilarImage[]> {
+    try {
+      return await invoke<SimilarImage[]>('find_similar', {
+        path,
+        maxDistance,
+        collectionId: options.collectionId,
+        kind: options.kind,
+      });
+    } catch (error) {
+      console.error('Failed to find similar images:', error);
+      throw new Error(`類似画像の検索に失敗しました: ${error}`);
+    }
+  }
+
+  /**
+   * コレクション内のほぼ同一の画像（縮小・再圧縮・軽い編集を経たもの）をまとめる
+   * @param collectionId コレクションID
+   * @param threshold 同一とみなす最大距離（省略時は6）
+   * @param kind ハッシュの種類（省略時は pHash）
+   * @returns 画像数の多い順のグループ
+   */
+  public async groupNearDuplicates(collectionId: string, threshold?: number, kind?: HashKind): Promise<SimilarGroup[]> {
+    try {
+      return await invoke<SimilarGroup[]>('group_near_duplicates', { collectionId, threshold, kind });
+    } catch (error) {
+      console.error('Failed to group near duplicates:', error);
+      throw new Error(`類似画像のグループ化に失敗しました: ${error}`);
+    }
+  }
+
+  /**
+   * コレクションの画像の代表色と平均輝度を求める
+   *
+   * 結果はその画像を含むすべてのコレクションに反映され、色によるフィルタ・ソートに使える。
+   * 進捗は colors:progress イベントで通知される。
+   * @param collectionId コレクションID
+   * @returns 分析の結果
+   */
+  public async analyzeColors(collectionId: string): Promise<ColorAnalysis> {
+    try {
+      return await invoke<ColorAnalysis>('analyze_colors', { collectionId });
+    } catch (error) {
+      console.error('Failed to analyze colors:', error);
+      throw new Error(`色の分析に失敗しました: ${error}`);
+    }
+  }
+
   /**
    * 指定した数のランダムな画像を取得
    * @param collectionId コレクションID
@@ -5,7 +5,13 @@ declare module '@tauri-apps/api/core' {
   interface InvokeCommands {
     'resolve_resources': {
       args: { config: any };
-      return: { paths: string[]; count: number; };
+      return: {
+        paths: string[];
+        count: number;
+        skipped: { path: string; reason: string; message: string; }[];
+        cache_status: 'fresh' | 'partially_rescanned' | 'cached';
+        dirs_rescanned: number;
+      };
     },
     'load_images_from_paths': {
       args: { paths: string[] };